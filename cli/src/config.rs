@@ -0,0 +1,281 @@
+//! Connection, signer and transaction settings shared by every command.
+//!
+//! Transactions are either sent straight away or, with `--sign-only`, printed as base64 so
+//! the remaining signers can add their signatures offline (`tx sign`) before one of them
+//! broadcasts it (`tx send`).
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use clap::Args;
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSimulateTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use token_sale_client::anchor_lang::{AccountDeserialize, AnchorDeserialize};
+
+/// Global options
+#[derive(Args)]
+pub struct ConfigArgs {
+    /// RPC endpoint, or one of `localhost`, `devnet`, `mainnet-beta`
+    #[arg(
+        short = 'u',
+        long,
+        global = true,
+        env = "TOKEN_SALE_RPC_URL",
+        default_value = "localhost"
+    )]
+    pub url: String,
+
+    /// Keypair that signs and pays for transactions
+    #[arg(short = 'k', long, global = true, env = "TOKEN_SALE_KEYPAIR")]
+    pub keypair: Option<PathBuf>,
+
+    /// Act as this authority instead of the keypair, e.g. a multisig member signing offline
+    #[arg(long, global = true)]
+    pub authority: Option<Pubkey>,
+
+    /// Fee payer, when different from the authority
+    #[arg(long, global = true)]
+    pub fee_payer: Option<Pubkey>,
+
+    /// Print the (partially) signed transaction instead of sending it
+    #[arg(long, global = true)]
+    pub sign_only: bool,
+
+    /// Recent blockhash to sign against, required to build transactions without an RPC
+    #[arg(long, global = true)]
+    pub blockhash: Option<Hash>,
+}
+
+pub struct Config {
+    pub rpc: RpcClient,
+    keypair: Option<Keypair>,
+    authority: Option<Pubkey>,
+    fee_payer: Option<Pubkey>,
+    sign_only: bool,
+    blockhash: Option<Hash>,
+}
+
+impl Config {
+    pub fn new(args: ConfigArgs) -> Result<Self> {
+        let keypair_path = match args.keypair {
+            Some(path) => Some(path),
+            None => default_keypair_path(),
+        };
+        let keypair = match keypair_path {
+            Some(path) if path.exists() => Some(
+                read_keypair_file(&path)
+                    .map_err(|err| anyhow!("failed to read keypair {}: {err}", path.display()))?,
+            ),
+            // Offline signers may run without any local key
+            _ => None,
+        };
+
+        Ok(Self {
+            rpc: RpcClient::new_with_commitment(
+                resolve_url(&args.url),
+                CommitmentConfig::confirmed(),
+            ),
+            keypair,
+            authority: args.authority,
+            fee_payer: args.fee_payer,
+            sign_only: args.sign_only,
+            blockhash: args.blockhash,
+        })
+    }
+
+    /// The wallet commands act as: `--authority`, else the keypair's pubkey
+    pub fn authority(&self) -> Result<Pubkey> {
+        self.authority
+            .or_else(|| self.keypair.as_ref().map(Keypair::pubkey))
+            .ok_or_else(|| anyhow!("no keypair found; pass --keypair or --authority"))
+    }
+
+    pub fn keypair(&self) -> Result<&Keypair> {
+        self.keypair
+            .as_ref()
+            .ok_or_else(|| anyhow!("no keypair found; pass --keypair"))
+    }
+
+    /// Fetch and decode a program account
+    pub fn fetch<T: AccountDeserialize>(&self, address: &Pubkey) -> Result<T> {
+        let data = self
+            .rpc
+            .get_account_data(address)
+            .with_context(|| format!("failed to fetch account {address}"))?;
+        token_sale_client::decode(&data)
+            .map_err(|err| anyhow!("failed to decode account {address}: {err}"))
+    }
+
+    pub fn account_exists(&self, address: &Pubkey) -> Result<bool> {
+        let account = self
+            .rpc
+            .get_account_with_commitment(address, self.rpc.commitment())
+            .with_context(|| format!("failed to fetch account {address}"))?;
+        Ok(account.value.is_some())
+    }
+
+    /// Who pays transaction fees and the rent of accounts created for the authority:
+    /// `--fee-payer`, else the authority
+    pub fn fee_payer(&self) -> Result<Pubkey> {
+        match self.fee_payer {
+            Some(fee_payer) => Ok(fee_payer),
+            None => self.authority(),
+        }
+    }
+
+    /// Sign with the local keypair and send, or print the transaction for offline signers
+    pub fn process(&self, instructions: &[Instruction]) -> Result<()> {
+        let fee_payer = self.fee_payer()?;
+        let blockhash = match self.blockhash {
+            Some(blockhash) => blockhash,
+            None => self
+                .rpc
+                .get_latest_blockhash()
+                .context("failed to fetch a recent blockhash")?,
+        };
+
+        let mut transaction = Transaction::new_with_payer(instructions, Some(&fee_payer));
+        self.partial_sign(&mut transaction, blockhash)?;
+
+        if self.sign_only {
+            print_transaction(&transaction)?;
+            Ok(())
+        } else {
+            self.send(&transaction)
+        }
+    }
+
+    /// Simulate read-only instructions and decode the value `program_id` returned.
+    ///
+    /// Nothing is signed or sent; the authority only stands in as the fee payer.
+    pub fn simulate<T: AnchorDeserialize>(
+        &self,
+        program_id: &Pubkey,
+        instructions: &[Instruction],
+    ) -> Result<T> {
+        let transaction = Transaction::new_with_payer(instructions, Some(&self.authority()?));
+        let result = self
+            .rpc
+            .simulate_transaction_with_config(
+                &transaction,
+                RpcSimulateTransactionConfig {
+                    sig_verify: false,
+                    replace_recent_blockhash: true,
+                    ..RpcSimulateTransactionConfig::default()
+                },
+            )
+            .context("failed to simulate the transaction")?
+            .value;
+        if let Some(err) = result.err {
+            for line in result.logs.unwrap_or_default() {
+                eprintln!("  {line}");
+            }
+            bail!("simulation failed: {err}");
+        }
+        result
+            .return_data
+            .and_then(|return_data| token_sale_client::decode_return_data(program_id, &return_data))
+            .ok_or_else(|| anyhow!("the simulation returned no value"))
+    }
+
+    /// Add the local keypair's signature to a transaction built elsewhere
+    pub fn sign_encoded(&self, encoded: &str) -> Result<()> {
+        let mut transaction = decode_transaction(encoded)?;
+        let blockhash = transaction.message.recent_blockhash;
+        let keypair = self.keypair()?;
+        if !transaction
+            .message
+            .signer_keys()
+            .contains(&&keypair.pubkey())
+        {
+            bail!("{} is not a signer of this transaction", keypair.pubkey());
+        }
+        transaction.try_partial_sign(&[keypair], blockhash)?;
+        print_transaction(&transaction)
+    }
+
+    /// Broadcast a transaction once every signer has signed
+    pub fn send_encoded(&self, encoded: &str) -> Result<()> {
+        self.send(&decode_transaction(encoded)?)
+    }
+
+    fn partial_sign(&self, transaction: &mut Transaction, blockhash: Hash) -> Result<()> {
+        transaction.message.recent_blockhash = blockhash;
+        if let Some(keypair) = &self.keypair {
+            if transaction
+                .message
+                .signer_keys()
+                .contains(&&keypair.pubkey())
+            {
+                transaction.try_partial_sign(&[keypair], blockhash)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn send(&self, transaction: &Transaction) -> Result<()> {
+        let missing = missing_signers(transaction);
+        if !missing.is_empty() {
+            let missing: Vec<String> = missing.iter().map(Pubkey::to_string).collect();
+            bail!(
+                "missing signatures from {}; rerun with --sign-only and collect them with `tx sign`",
+                missing.join(", ")
+            );
+        }
+
+        let signature = self
+            .rpc
+            .send_and_confirm_transaction(transaction)
+            .context("transaction failed")?;
+        println!("Signature: {signature}");
+        Ok(())
+    }
+}
+
+fn default_keypair_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config/solana/id.json"))
+}
+
+fn resolve_url(url: &str) -> String {
+    match url {
+        "localhost" | "l" => "http://127.0.0.1:8899",
+        "devnet" | "d" => "https://api.devnet.solana.com",
+        "mainnet-beta" | "m" => "https://api.mainnet-beta.solana.com",
+        url => url,
+    }
+    .to_string()
+}
+
+fn missing_signers(transaction: &Transaction) -> Vec<Pubkey> {
+    let signer_keys = transaction.message.signer_keys();
+    signer_keys
+        .into_iter()
+        .zip(&transaction.signatures)
+        .filter(|(_, signature)| **signature == Default::default())
+        .map(|(key, _)| *key)
+        .collect()
+}
+
+fn print_transaction(transaction: &Transaction) -> Result<()> {
+    let encoded = BASE64_STANDARD.encode(bincode::serialize(transaction)?);
+    println!("Blockhash: {}", transaction.message.recent_blockhash);
+    for signer in missing_signers(transaction) {
+        println!("Missing signature: {signer}");
+    }
+    println!("Transaction: {encoded}");
+    Ok(())
+}
+
+fn decode_transaction(encoded: &str) -> Result<Transaction> {
+    let bytes = BASE64_STANDARD
+        .decode(encoded.trim())
+        .context("transaction is not valid base64")?;
+    bincode::deserialize(&bytes).context("failed to decode transaction")
+}
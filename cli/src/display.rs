@@ -0,0 +1,321 @@
+//! Human-readable output for decoded program accounts.
+
+use std::fmt::Display;
+
+use solana_sdk::pubkey::Pubkey;
+use token_sale_client::{escrow, multi_presale};
+
+fn field(label: &str, value: impl Display) {
+    println!("  {label:<26} {value}");
+}
+
+fn optional<T: Display>(value: &Option<T>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "none".to_string(),
+    }
+}
+
+/// Zero means "not set" for most limits and timestamps
+fn limit(value: u64) -> String {
+    if value == 0 {
+        "unlimited".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn bps(value: u16) -> String {
+    format!("{value} bps ({:.2}%)", f64::from(value) / 100.0)
+}
+
+/// A quote from either program's `quote_purchase` or `quote_exact_in`
+pub fn print_purchase_quote(
+    token_amount: u64,
+    gross_cost: u64,
+    platform_fee: u64,
+    seller_net: u64,
+    remaining_allowance: u64,
+) {
+    println!("Purchase quote");
+    field("Tokens", token_amount);
+    field("Total cost", gross_cost);
+    field("Platform fee", platform_fee);
+    field("Seller receives", seller_net);
+    field("Remaining allowance", remaining_allowance);
+}
+
+pub fn print_token_sale(address: &Pubkey, sale: &escrow::TokenSale) {
+    println!("Token sale {address}");
+    field("Seller", sale.seller);
+    field("Token mint", sale.token_mint);
+    field("Payment mint", sale.payment_mint);
+    field("Price per token", sale.price_per_token);
+    if sale.pending_price_per_token > 0 {
+        field(
+            "Announced price",
+            format!(
+                "{} from {}",
+                sale.pending_price_per_token, sale.price_change_time
+            ),
+        );
+    }
+    field(
+        "Tokens available",
+        format!("{} / {}", sale.tokens_available, sale.total_tokens),
+    );
+    field(
+        "Sale window",
+        format!("{} .. {}", sale.sale_start_time, sale.sale_end_time),
+    );
+    field(
+        "Sale duration",
+        format!(
+            "{}s .. {}s",
+            sale.min_sale_duration(),
+            sale.max_sale_duration()
+        ),
+    );
+    field("Max tokens per buyer", limit(sale.max_tokens_per_buyer));
+    field("Platform fee", bps(sale.platform_fee_bps));
+    field("Fee recipient", sale.platform_fee_recipient);
+    field("Active", sale.is_active());
+    field("Paused", sale.is_paused());
+    field("Max tokens per tx", limit(sale.max_tokens_per_tx));
+    field(
+        "Min purchase interval",
+        format!("{}s", sale.min_purchase_interval),
+    );
+    field("Max tokens per slot", limit(sale.max_tokens_per_slot));
+    if sale.is_locked() {
+        field("Tokens locked until", sale.unlock_time);
+        field("Receipt mint", sale.receipt_mint);
+    }
+    if let Some(hook_program) = sale.purchase_hook() {
+        field("Purchase hook", hook_program);
+    }
+    field("Wallet screening", sale.screens_wallets());
+    if sale.soft_close_window > 0 {
+        field(
+            "Soft close",
+            format!(
+                "+{}s in the last {}s, until {}",
+                sale.soft_close_extension, sale.soft_close_window, sale.soft_close_ceiling
+            ),
+        );
+    }
+
+    if sale.is_overflow() {
+        field(
+            "Overflow deposits",
+            format!(
+                "{} ({} claimed)",
+                sale.total_deposits, sale.deposits_claimed
+            ),
+        );
+    }
+
+    if !sale.price_tiers().is_empty() {
+        println!("  Price tiers:");
+        for tier in sale.price_tiers() {
+            println!(
+                "    from {} sold: {}",
+                tier.tokens_sold_threshold, tier.price_per_token
+            );
+        }
+    }
+
+    if sale.milestone_count > 0 {
+        println!("  Milestones:");
+        for (index, milestone) in sale.milestones().enumerate() {
+            let state = if index < sale.current_milestone as usize {
+                "released"
+            } else if index == sale.current_milestone as usize && sale.milestone_approved() {
+                "approved"
+            } else {
+                "pending"
+            };
+            println!(
+                "    #{index}: {} by {} ({state})",
+                bps(milestone.release_bps),
+                milestone.deadline
+            );
+        }
+        field("Milestone approver", optional(&sale.milestone_approver()));
+        field(
+            "Milestone votes",
+            format!("{} / {}", sale.milestone_votes, sale.milestone_vote_supply),
+        );
+        field("Milestones failed", sale.milestones_failed());
+        field(
+            "Proceeds released",
+            format!("{} / {}", sale.proceeds_released, sale.proceeds_escrowed),
+        );
+    }
+}
+
+pub fn print_lottery(lottery: &escrow::Lottery) {
+    println!("  Lottery:");
+    field("Tokens per ticket", lottery.ticket_tokens);
+    field("Tickets", lottery.tickets);
+    if lottery.has_revealer() {
+        field("Seed revealer", lottery.revealer);
+    }
+    if lottery.is_drawn() {
+        field("Winners", lottery.winners);
+    } else if lottery.draw_slot > 0 {
+        field("Draw slot", lottery.draw_slot);
+    } else {
+        field("Winners", "not drawn");
+    }
+}
+
+pub fn print_auction(auction: &escrow::Auction) {
+    println!("  Auction:");
+    field(
+        "Bid prices",
+        format!(
+            "{} .. {} in steps of {}",
+            auction.min_price,
+            auction.level_price(auction.price_levels - 1),
+            auction.tick_size
+        ),
+    );
+    field(
+        "Bids",
+        format!("{} ({} settled)", auction.bids, auction.bids_settled),
+    );
+    if auction.is_sealed() {
+        field(
+            "Sealed",
+            format!(
+                "{} collateral, {}s to reveal, {} forfeited",
+                auction.collateral,
+                auction.reveal_period,
+                bps(auction.forfeit_bps)
+            ),
+        );
+        field("Bids revealed", auction.bids_revealed);
+    }
+    if auction.is_cleared() {
+        field("Clearing price", auction.clearing_price);
+        field(
+            "Tokens sold",
+            format!(
+                "{} ({} delivered)",
+                auction.tokens_reserved, auction.tokens_filled
+            ),
+        );
+    } else {
+        field("Clearing price", "not cleared");
+    }
+}
+
+pub fn print_legacy_token_sale(address: &Pubkey, sale: &multi_presale::TokenSale) {
+    println!("Token sale {address} (multi-presale)");
+    field("Seller", sale.seller);
+    field("Token mint", sale.token_mint);
+    field("Payment mint", sale.payment_mint);
+    field("Price per token", sale.price_per_token);
+    field(
+        "Tokens available",
+        format!("{} / {}", sale.tokens_available, sale.total_tokens),
+    );
+    field(
+        "Sale window",
+        format!("{} .. {}", sale.sale_start_time, sale.sale_end_time),
+    );
+    field("Max tokens per buyer", limit(sale.max_tokens_per_buyer));
+    field("Platform fee", bps(sale.platform_fee_bps));
+    field("Fee recipient", sale.platform_fee_recipient);
+    field("Active", sale.is_active());
+    field("Paused", sale.is_paused());
+    field("Wallet screening", sale.screens_wallets());
+}
+
+pub fn print_deny_list(address: &Pubkey, authority: &Pubkey, denied_count: u64, required: bool) {
+    println!("Deny list {address}");
+    field("Compliance authority", authority);
+    field("Denied wallets", denied_count);
+    field("Required", required);
+}
+
+pub fn print_platform(address: &Pubkey, platform: &multi_presale::PlatformAccount) {
+    println!("Platform {address}");
+    field("Authority", platform.authority);
+    field("Treasury", platform.treasury);
+    field("Platform fee", bps(platform.platform_fee));
+    field("Total projects", platform.total_projects);
+    field("Paused", platform.is_paused);
+    field(
+        "Project duration",
+        format!(
+            "{}s .. {}s",
+            platform.min_project_duration, platform.max_project_duration
+        ),
+    );
+}
+
+pub fn print_project(address: &Pubkey, project: &multi_presale::ProjectAccount) {
+    println!("Project {address}");
+    field("Id", project.id);
+    field("Name", &project.name);
+    field("Creator", project.creator);
+    field("Description", &project.description);
+    field("Website", &project.website);
+    field("Logo", &project.logo_url);
+    field("Category", multi_presale::category_name(project.category));
+    field("Tags", project.tags.join(", "));
+    field(
+        "Token",
+        format!(
+            "{} ({}, {} decimals)",
+            project.token_name, project.token_symbol, project.token_decimals
+        ),
+    );
+    field("Token mint", project.token_mint);
+    field("Token vault", optional(&project.token_vault));
+    field("Status", multi_presale::status_name(project.status));
+    field(
+        "Approval",
+        multi_presale::approval_name(project.approval_status),
+    );
+    field("Approved by", optional(&project.approved_by));
+    field("Approved at", optional(&project.approved_at));
+    field("Created at", project.created_at);
+    field("Updated at", project.updated_at);
+}
+
+pub fn print_sale_round(address: &Pubkey, round: &multi_presale::SaleRound) {
+    println!("Sale round {address}");
+    field("Project id", round.project_id);
+    field("Round", round.round_number);
+    field(
+        "Sale type",
+        multi_presale::sale_type_name(round.sale_type()),
+    );
+    field("Token price", round.token_price);
+    field(
+        "Tokens sold",
+        format!("{} / {}", round.tokens_sold, round.total_tokens),
+    );
+    field(
+        "Round window",
+        format!("{} .. {}", round.start_time, round.end_time),
+    );
+    field("Min purchase", round.min_purchase);
+    field("Max tokens per buyer", limit(round.max_tokens_per_buyer));
+    field("Max total raise", limit(round.max_total_raise));
+    field("Whitelist required", round.whitelist_required());
+    field("Active", round.is_active());
+}
+
+pub fn print_platform_treasury(address: &Pubkey, treasury: &multi_presale::PlatformTreasury) {
+    println!("Platform treasury {address}");
+    field("Authority", treasury.authority);
+    field("Treasury vault", treasury.treasury_vault);
+    field("Fee", bps(treasury.fee_percentage));
+    field("Total fees collected", treasury.total_fees_collected);
+    field("Total volume", treasury.total_volume);
+    field("Total projects", treasury.total_projects);
+}
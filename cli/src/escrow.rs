@@ -0,0 +1,1439 @@
+//! Commands for the `escrow` program: token sales, swap offers and service escrows.
+
+use anyhow::Result;
+use clap::{Args, Subcommand};
+use solana_client::rpc_config::RpcProgramAccountsConfig;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use token_sale_client::decode;
+use token_sale_client::escrow::{self, args, filters, instructions, pda, Milestone, PriceTier};
+
+use crate::config::Config;
+use crate::display;
+use crate::token_account;
+
+/// A sealed bid, given again unchanged to reveal it
+#[derive(Args)]
+pub struct SealedBidArgs {
+    #[arg(long)]
+    pub sale: Pubkey,
+    /// Identifier of this bid, unique per bidder
+    #[arg(long)]
+    pub id: u64,
+    /// Tokens wanted
+    #[arg(long)]
+    pub quantity: u64,
+    /// Highest price per token, a price on the auction's ladder
+    #[arg(long)]
+    pub max_price: u64,
+    /// Hex 32-byte secret salt; keep it until the bid is revealed
+    #[arg(long, value_parser = parse_hash)]
+    pub salt: [u8; 32],
+    #[arg(long)]
+    pub payment_account: Option<Pubkey>,
+}
+
+/// Accounts for a sale's purchase hook
+#[derive(Args)]
+pub struct HookArgs {
+    /// Account the sale's purchase hook expects, in order; suffix `:w` if writable
+    #[arg(long = "hook-account", value_parser = parse_hook_account)]
+    pub hook_accounts: Vec<AccountMeta>,
+}
+
+impl HookArgs {
+    /// Append the sale's purchase hook to a purchase, if it has one
+    fn apply(&self, sale: &escrow::TokenSale, instruction: Instruction) -> Instruction {
+        match sale.purchase_hook() {
+            Some(hook_program) => {
+                instructions::with_purchase_hook(instruction, &hook_program, &self.hook_accounts)
+            }
+            None => instruction,
+        }
+    }
+}
+
+/// Bids settled per `settle_auction_bids` transaction, three accounts each
+const SETTLE_CHUNK: usize = 6;
+
+/// Arguments shared by the escrow and multi-presale `initialize_sale`
+#[derive(Args)]
+pub struct InitSaleArgs {
+    /// Mint of the token being sold
+    #[arg(long)]
+    pub token_mint: Pubkey,
+    /// Mint buyers pay with
+    #[arg(long)]
+    pub payment_mint: Pubkey,
+    /// Price per token, in payment mint base units
+    #[arg(long)]
+    pub price: u64,
+    /// Tokens moved into the sale vault
+    #[arg(long)]
+    pub total_tokens: u64,
+    /// Unix timestamp the sale opens at
+    #[arg(long)]
+    pub start: i64,
+    /// Unix timestamp the sale closes at
+    #[arg(long)]
+    pub end: i64,
+    /// Per-buyer cap (0 = no limit)
+    #[arg(long, default_value_t = 0)]
+    pub max_per_buyer: u64,
+    /// Platform fee in basis points
+    #[arg(long, default_value_t = 0)]
+    pub fee_bps: u16,
+    /// Wallet that receives platform fees
+    #[arg(long)]
+    pub fee_recipient: Pubkey,
+    /// Token account funding the sale (defaults to the seller's associated account)
+    #[arg(long)]
+    pub seller_token_account: Option<Pubkey>,
+}
+
+/// Arguments shared by the escrow and multi-presale `buy_tokens`
+#[derive(Args)]
+pub struct BuyArgs {
+    /// Sale to buy from
+    #[arg(long)]
+    pub sale: Pubkey,
+    /// Tokens to buy
+    #[arg(long)]
+    pub amount: u64,
+    /// Payment account (defaults to the buyer's associated account)
+    #[arg(long)]
+    pub payment_account: Option<Pubkey>,
+    /// Account receiving the tokens (defaults to the buyer's associated account)
+    #[arg(long)]
+    pub token_account: Option<Pubkey>,
+}
+
+/// Arguments shared by the escrow and multi-presale quote instructions
+#[derive(Args)]
+pub struct QuoteArgs {
+    /// Sale to quote
+    #[arg(long)]
+    pub sale: Pubkey,
+    /// Price buying exactly this many tokens
+    #[arg(long, required_unless_present = "payment", conflicts_with = "payment")]
+    pub amount: Option<u64>,
+    /// Find the most tokens this payment buys, fee included
+    #[arg(long)]
+    pub payment: Option<u64>,
+    /// Apply this buyer's per-buyer limit (defaults to the authority, if they have bought before)
+    #[arg(long)]
+    pub buyer: Option<Pubkey>,
+}
+
+/// Arguments shared by the escrow and multi-presale `update_sale_params`
+#[derive(Args)]
+pub struct UpdateSaleArgs {
+    #[arg(long)]
+    pub sale: Pubkey,
+    #[arg(long)]
+    pub price: Option<u64>,
+    #[arg(long)]
+    pub start: Option<i64>,
+    #[arg(long)]
+    pub end: Option<i64>,
+    #[arg(long)]
+    pub max_per_buyer: Option<u64>,
+}
+
+/// Seller commands that only take the sale address
+#[derive(Args)]
+pub struct SaleArg {
+    #[arg(long)]
+    pub sale: Pubkey,
+}
+
+/// Arguments shared by the escrow and multi-presale `set_wallet_screening`
+#[derive(Args)]
+pub struct ScreeningArgs {
+    #[arg(long)]
+    pub sale: Pubkey,
+    /// Stop screening, unless the deny list is required
+    #[arg(long)]
+    pub off: bool,
+}
+
+/// Arguments shared by the escrow and multi-presale deny list setup
+#[derive(Args)]
+pub struct DenyListArgs {
+    /// Who denies and allows wallets
+    #[arg(long)]
+    pub compliance_authority: Pubkey,
+    /// Screen every sale's buyers, so sellers cannot turn screening off
+    #[arg(long)]
+    pub required: bool,
+}
+
+/// The buyer whose limit a quote applies: `--buyer`, else the authority once they have
+/// a tracker account. `tracker` derives the buyer's tracker address.
+pub fn quote_buyer(
+    config: &Config,
+    quote: &QuoteArgs,
+    tracker: impl Fn(&Pubkey) -> Pubkey,
+) -> Result<Option<Pubkey>> {
+    if quote.buyer.is_some() {
+        return Ok(quote.buyer);
+    }
+    let Ok(authority) = config.authority() else {
+        return Ok(None);
+    };
+    Ok(config
+        .account_exists(&tracker(&authority))?
+        .then_some(authority))
+}
+
+#[derive(Subcommand)]
+pub enum SaleCommand {
+    /// Create a sale and fund its vault
+    Init(InitSaleArgs),
+    /// Create the buyer's purchase tracker (done automatically by `buy`)
+    CreateBuyer(SaleArg),
+    /// Close the buyer's tracker after the sale, refunding its rent to whoever paid it
+    CloseBuyer(SaleArg),
+    /// Buy tokens
+    Buy {
+        #[command(flatten)]
+        buy: BuyArgs,
+        #[command(flatten)]
+        hook: HookArgs,
+    },
+    /// Buy for another wallet, paying from your own account (creates its tracker and yours
+    /// if needed). The purchase restarts your own cooldown as well as the recipient's
+    BuyFor {
+        #[arg(long)]
+        sale: Pubkey,
+        /// Wallet credited with the purchase
+        #[arg(long)]
+        recipient: Pubkey,
+        /// Tokens to buy
+        #[arg(long)]
+        amount: u64,
+        /// Payment account (defaults to the payer's associated account)
+        #[arg(long)]
+        payment_account: Option<Pubkey>,
+        /// Account receiving the tokens (defaults to the recipient's associated account)
+        #[arg(long)]
+        token_account: Option<Pubkey>,
+        #[command(flatten)]
+        hook: HookArgs,
+    },
+    /// Price a purchase without buying, as `buy` would charge it
+    Quote(QuoteArgs),
+    /// Pause or unpause a sale
+    Pause(SaleArg),
+    /// Cancel a sale and return unsold tokens
+    Cancel {
+        #[arg(long)]
+        sale: Pubkey,
+        /// Account receiving the unsold tokens (defaults to the seller's associated account)
+        #[arg(long)]
+        token_account: Option<Pubkey>,
+    },
+    /// Change price, window, per-buyer cap or duration bounds before the sale starts; once
+    /// it is live, announce a price change of at most 10% that applies a day later
+    Update {
+        #[command(flatten)]
+        update: UpdateSaleArgs,
+        /// Seconds the sale must run before it may close early (0 = 24 hours)
+        #[arg(long)]
+        min_duration: Option<i64>,
+        /// Longest the sale may run once extended, in seconds (0 = 90 days)
+        #[arg(long)]
+        max_duration: Option<i64>,
+    },
+    /// Move a sale's end time later (at most 90 days from its start, unless the seller set
+    /// another maximum)
+    Extend {
+        #[arg(long)]
+        sale: Pubkey,
+        /// New end time (unix timestamp)
+        #[arg(long)]
+        end: i64,
+    },
+    /// End a sale now, once it has run its minimum duration (a day by default) or sold out
+    Close(SaleArg),
+    /// Extend a fixed-price sale when someone buys near the end, before the sale starts (all 0 = off)
+    SoftClose {
+        #[arg(long)]
+        sale: Pubkey,
+        /// Final seconds in which a purchase extends the sale
+        #[arg(long)]
+        window: i64,
+        /// Seconds each such purchase adds
+        #[arg(long)]
+        extension: i64,
+        /// Latest end time the extensions may reach (unix timestamp)
+        #[arg(long)]
+        ceiling: i64,
+    },
+    /// Set anti-bot purchase limits, omitted values are left unchanged
+    Throttles {
+        #[arg(long)]
+        sale: Pubkey,
+        #[arg(long)]
+        max_per_tx: Option<u64>,
+        /// Seconds between purchases by the same buyer
+        #[arg(long)]
+        min_interval: Option<i64>,
+        #[arg(long)]
+        max_per_slot: Option<u64>,
+    },
+    /// Hold purchased tokens until a time, paying out transferable receipts meanwhile
+    Lock {
+        #[arg(long)]
+        sale: Pubkey,
+        /// Unix timestamp from which receipts redeem for tokens
+        #[arg(long)]
+        unlock_time: i64,
+    },
+    /// Burn receipts of a locked sale for its tokens
+    Redeem {
+        #[arg(long)]
+        sale: Pubkey,
+        /// Receipts to redeem
+        #[arg(long)]
+        amount: u64,
+        /// Receipt account (defaults to the holder's associated account)
+        #[arg(long)]
+        receipt_account: Option<Pubkey>,
+        /// Account receiving the tokens (defaults to the holder's associated account)
+        #[arg(long)]
+        token_account: Option<Pubkey>,
+    },
+    /// Screen buyers against the deny list of the sale's platform (its fee recipient)
+    Screen(ScreeningArgs),
+    /// Have every purchase call an integrator program; can only be set once
+    Hook {
+        #[arg(long)]
+        sale: Pubkey,
+        /// Program invoked after each purchase
+        #[arg(long)]
+        program: Pubkey,
+    },
+    /// Replace the volume price tiers (none = flat price)
+    PriceTiers {
+        #[arg(long)]
+        sale: Pubkey,
+        /// `TOKENS_SOLD:PRICE`, repeatable, ordered by threshold
+        #[arg(long = "tier", value_parser = parse_price_tier)]
+        tiers: Vec<PriceTier>,
+    },
+    /// Escrow proceeds and release them by milestone
+    Milestones {
+        #[arg(long)]
+        sale: Pubkey,
+        /// `RELEASE_BPS:DEADLINE`, repeatable, ordered by deadline
+        #[arg(long = "milestone", value_parser = parse_milestone, required = true)]
+        milestones: Vec<Milestone>,
+        /// Who can approve milestones (none = buyer vote)
+        #[arg(long)]
+        approver: Option<Pubkey>,
+    },
+    /// Approve the current milestone as the milestone approver
+    ApproveMilestone(SaleArg),
+    /// Vote to approve the current milestone as a buyer
+    VoteMilestone(SaleArg),
+    /// Release the approved milestone's proceeds to the seller
+    ReleaseMilestone(SaleArg),
+    /// Reclaim unreleased payment after a missed milestone
+    ClaimRefund {
+        #[arg(long)]
+        sale: Pubkey,
+        #[arg(long)]
+        payment_account: Option<Pubkey>,
+    },
+    /// Allocate the sale pro rata to deposits, before it starts
+    Overflow(SaleArg),
+    /// Deposit payment into an overflow sale (creates the buyer's tracker if needed)
+    Deposit {
+        #[arg(long)]
+        sale: Pubkey,
+        /// Payment to deposit, in payment mint base units
+        #[arg(long)]
+        amount: u64,
+        #[arg(long)]
+        payment_account: Option<Pubkey>,
+    },
+    /// Claim an overflow allocation and the unspent deposit once the sale ends
+    ClaimOverflow(SaleArg),
+    /// Allocate the sale by lottery, before it starts: buyers register tickets and the
+    /// winners are drawn after it ends
+    Lottery {
+        #[arg(long)]
+        sale: Pubkey,
+        /// Tokens each winning ticket receives
+        #[arg(long)]
+        ticket_tokens: u64,
+        /// Hex sha256 of the seed the revealer will reveal (none = slot hash draw)
+        #[arg(long, value_parser = parse_hash, requires = "revealer")]
+        commitment: Option<[u8; 32]>,
+        /// Who reveals the seed
+        #[arg(long, requires = "commitment")]
+        revealer: Option<Pubkey>,
+    },
+    /// Register a lottery ticket, depositing its price
+    RegisterTicket {
+        #[arg(long)]
+        sale: Pubkey,
+        #[arg(long)]
+        payment_account: Option<Pubkey>,
+    },
+    /// Reveal the committed lottery seed and draw the winners (revealer)
+    RevealSeed {
+        #[arg(long)]
+        sale: Pubkey,
+        /// Hex seed matching the commitment
+        #[arg(long, value_parser = parse_hash)]
+        seed: [u8; 32],
+    },
+    /// Request a slot hash lottery draw, or run it once the requested slot has passed
+    Draw(SaleArg),
+    /// Claim a lottery ticket's tokens, or its refund if it lost
+    ClaimTicket(SaleArg),
+    /// Close a claimed lottery ticket, refunding its rent to whoever paid it
+    CloseTicket(SaleArg),
+    /// Sell the sale by uniform-price batch auction, before it starts
+    Auction {
+        #[arg(long)]
+        sale: Pubkey,
+        /// Lowest bid price, in payment mint base units
+        #[arg(long)]
+        min_price: u64,
+        /// Step between bid prices
+        #[arg(long, default_value_t = 0)]
+        tick_size: u64,
+        /// Bid prices on the ladder, at most 32
+        #[arg(long, default_value_t = 1)]
+        levels: u8,
+    },
+    /// Bid in an auction, depositing quantity * max price
+    Bid {
+        #[arg(long)]
+        sale: Pubkey,
+        /// Identifier of this bid, unique per bidder
+        #[arg(long)]
+        id: u64,
+        /// Tokens wanted
+        #[arg(long)]
+        quantity: u64,
+        /// Highest price per token, a price on the auction's ladder
+        #[arg(long)]
+        max_price: u64,
+        #[arg(long)]
+        payment_account: Option<Pubkey>,
+    },
+    /// Seal an auction's bids, before the sale starts: bidders commit hashes with a fixed
+    /// collateral and reveal once it ends
+    SealBids {
+        #[arg(long)]
+        sale: Pubkey,
+        /// Deposit committed with each sealed bid
+        #[arg(long)]
+        collateral: u64,
+        /// Seconds after the sale ends for revealing bids
+        #[arg(long)]
+        reveal_period: i64,
+        /// Share of the collateral an unrevealed bid forfeits, in basis points (1 to 10000)
+        #[arg(long, default_value_t = 10000)]
+        forfeit_bps: u16,
+    },
+    /// Commit a sealed bid; only its hash and the collateral go on chain
+    CommitBid(SealedBidArgs),
+    /// Reveal a sealed bid once the sale has ended, depositing the rest of its cost
+    RevealBid(SealedBidArgs),
+    /// Fix the auction's clearing price once the sale has ended
+    ClearAuction(SaleArg),
+    /// Settle every unsettled auction bid, a few per transaction
+    SettleBids(SaleArg),
+    /// Close a settled auction bid, refunding its rent to whoever paid it
+    CloseBid {
+        #[arg(long)]
+        sale: Pubkey,
+        /// Identifier of the bid
+        #[arg(long)]
+        id: u64,
+    },
+    /// Show a sale
+    Show { sale: Pubkey },
+}
+
+#[derive(Subcommand)]
+pub enum DenyListCommand {
+    /// Create the program's deny list, once at deployment, or update it (its admin)
+    Configure(DenyListArgs),
+    /// Bar a wallet from buying in screened sales (compliance authority)
+    Deny {
+        #[arg(long)]
+        wallet: Pubkey,
+    },
+    /// Lift a wallet's denial (compliance authority)
+    Allow {
+        #[arg(long)]
+        wallet: Pubkey,
+    },
+    /// Show the deny list, and whether a wallet is on it
+    Show {
+        #[arg(long)]
+        wallet: Option<Pubkey>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum OfferCommand {
+    /// Deposit token A and ask for token B in return
+    Make {
+        #[arg(long)]
+        id: u64,
+        #[arg(long)]
+        mint_a: Pubkey,
+        #[arg(long)]
+        mint_b: Pubkey,
+        #[arg(long)]
+        amount_a: u64,
+        #[arg(long)]
+        wanted_b: u64,
+        /// Only this wallet may take the offer
+        #[arg(long)]
+        taker: Option<Pubkey>,
+        /// Unix timestamp after which the offer can no longer be filled (0 = never)
+        #[arg(long, default_value_t = 0)]
+        expires_at: i64,
+        #[arg(long)]
+        partially_fillable: bool,
+    },
+    /// Take everything left in an offer
+    Take {
+        #[arg(long)]
+        maker: Pubkey,
+        #[arg(long)]
+        id: u64,
+    },
+    /// Fill part of an offer
+    Fill {
+        #[arg(long)]
+        maker: Pubkey,
+        #[arg(long)]
+        id: u64,
+        /// Token A to receive
+        #[arg(long)]
+        amount_a: u64,
+    },
+    /// Cancel an offer and reclaim the remaining token A
+    Cancel {
+        #[arg(long)]
+        id: u64,
+    },
+}
+
+/// Identifies a service escrow by its payer and id
+#[derive(Args)]
+pub struct ServiceEscrowArg {
+    /// Payer that funded the escrow (defaults to the authority)
+    #[arg(long)]
+    pub payer: Option<Pubkey>,
+    #[arg(long)]
+    pub id: u64,
+}
+
+#[derive(Subcommand)]
+pub enum ServiceCommand {
+    /// Lock payment for a service
+    Create {
+        #[arg(long)]
+        id: u64,
+        #[arg(long)]
+        payee: Pubkey,
+        #[arg(long)]
+        arbiter: Pubkey,
+        #[arg(long)]
+        mint: Pubkey,
+        #[arg(long)]
+        amount: u64,
+        /// Unix timestamp after which the payee can claim
+        #[arg(long)]
+        release_deadline: i64,
+    },
+    /// Release the payment to the payee (payer)
+    Release(ServiceEscrowArg),
+    /// Refund the payment to the payer (payee)
+    Refund(ServiceEscrowArg),
+    /// Freeze the escrow for arbitration (payer or payee)
+    Dispute(ServiceEscrowArg),
+    /// Split a disputed escrow (arbiter)
+    Resolve {
+        #[command(flatten)]
+        escrow: ServiceEscrowArg,
+        /// Payee's share in basis points
+        #[arg(long)]
+        payee_share_bps: u16,
+    },
+    /// Claim an undisputed escrow after its release deadline
+    ClaimExpired(ServiceEscrowArg),
+}
+
+fn parse_pair<A, B>(value: &str) -> Result<(A, B), String>
+where
+    A: std::str::FromStr,
+    B: std::str::FromStr,
+{
+    let (a, b) = value
+        .split_once(':')
+        .ok_or_else(|| format!("expected two values separated by ':', got {value}"))?;
+    let a = a.parse().map_err(|_| format!("invalid value {a}"))?;
+    let b = b.parse().map_err(|_| format!("invalid value {b}"))?;
+    Ok((a, b))
+}
+
+fn parse_hash(value: &str) -> Result<[u8; 32], String> {
+    let value = value.strip_prefix("0x").unwrap_or(value);
+    if value.len() != 64 || !value.is_ascii() {
+        return Err(format!("expected 64 hex characters, got {value}"));
+    }
+    let mut hash = [0; 32];
+    for (byte, pair) in hash.iter_mut().zip(value.as_bytes().chunks(2)) {
+        let pair = std::str::from_utf8(pair).unwrap_or_default();
+        *byte = u8::from_str_radix(pair, 16).map_err(|_| format!("invalid hex {pair}"))?;
+    }
+    Ok(hash)
+}
+
+fn parse_hook_account(value: &str) -> Result<AccountMeta, String> {
+    let (pubkey, writable) = match value.strip_suffix(":w") {
+        Some(pubkey) => (pubkey, true),
+        None => (value, false),
+    };
+    let pubkey = pubkey
+        .parse()
+        .map_err(|_| format!("invalid pubkey {pubkey}"))?;
+    Ok(if writable {
+        AccountMeta::new(pubkey, false)
+    } else {
+        AccountMeta::new_readonly(pubkey, false)
+    })
+}
+
+fn parse_price_tier(value: &str) -> Result<PriceTier, String> {
+    let (tokens_sold_threshold, price_per_token) = parse_pair(value)?;
+    Ok(PriceTier {
+        tokens_sold_threshold,
+        price_per_token,
+    })
+}
+
+fn parse_milestone(value: &str) -> Result<Milestone, String> {
+    let (release_bps, deadline) = parse_pair(value)?;
+    Ok(Milestone {
+        release_bps,
+        deadline,
+    })
+}
+
+pub fn run_sale(config: &Config, command: SaleCommand) -> Result<()> {
+    match command {
+        SaleCommand::Init(init) => {
+            let seller = config.authority()?;
+            let seller_token_account = init
+                .seller_token_account
+                .unwrap_or_else(|| token_account(&seller, &init.token_mint));
+            println!("Sale: {}", pda::token_sale(&seller, &init.token_mint).0);
+            config.process(&[instructions::initialize_sale(
+                &seller,
+                &init.token_mint,
+                &init.payment_mint,
+                &seller_token_account,
+                args::InitializeSale {
+                    price_per_token: init.price,
+                    total_tokens: init.total_tokens,
+                    sale_start_time: init.start,
+                    sale_end_time: init.end,
+                    max_tokens_per_buyer: init.max_per_buyer,
+                    platform_fee_bps: init.fee_bps,
+                    platform_fee_recipient: init.fee_recipient,
+                },
+            )])
+        }
+        SaleCommand::CreateBuyer(SaleArg { sale }) => {
+            config.process(&[instructions::create_buyer_account(
+                &config.authority()?,
+                &config.fee_payer()?,
+                &sale,
+            )])
+        }
+        SaleCommand::CloseBuyer(SaleArg { sale }) => {
+            let buyer = config.authority()?;
+            let tracker: escrow::BuyerAccount = config.fetch(&pda::buyer(&buyer, &sale).0)?;
+            config.process(&[instructions::close_buyer_account(
+                &buyer,
+                &tracker.rent_recipient(),
+                &sale,
+            )])
+        }
+        SaleCommand::Buy { buy, hook } => {
+            let buyer = config.authority()?;
+            let token_sale: escrow::TokenSale = config.fetch(&buy.sale)?;
+
+            let mut ixs = Vec::new();
+            if !config.account_exists(&pda::buyer(&buyer, &buy.sale).0)? {
+                ixs.push(instructions::create_buyer_account(
+                    &buyer,
+                    &config.fee_payer()?,
+                    &buy.sale,
+                ));
+            }
+            let purchase = instructions::buy_tokens(
+                &buyer,
+                &buy.sale,
+                &buy.payment_account
+                    .unwrap_or_else(|| token_account(&buyer, &token_sale.payment_mint)),
+                &token_account(&token_sale.seller, &token_sale.payment_mint),
+                &token_account(&token_sale.platform_fee_recipient, &token_sale.payment_mint),
+                &buy.token_account
+                    .unwrap_or_else(|| token_account(&buyer, &token_sale.payout_mint())),
+                token_sale.milestone_count > 0,
+                token_sale.is_locked(),
+                args::BuyTokens {
+                    token_amount: buy.amount,
+                },
+            );
+            ixs.push(hook.apply(&token_sale, purchase));
+            config.process(&ixs)
+        }
+        SaleCommand::BuyFor {
+            sale,
+            recipient,
+            amount,
+            payment_account,
+            token_account: recipient_token_account,
+            hook,
+        } => {
+            let payer = config.authority()?;
+            let token_sale: escrow::TokenSale = config.fetch(&sale)?;
+
+            let mut ixs = Vec::new();
+            if !config.account_exists(&pda::buyer(&payer, &sale).0)? {
+                ixs.push(instructions::create_buyer_account(
+                    &payer,
+                    &config.fee_payer()?,
+                    &sale,
+                ));
+            }
+            if !config.account_exists(&pda::buyer(&recipient, &sale).0)? {
+                ixs.push(instructions::create_buyer_account_for(
+                    &payer, &recipient, &sale,
+                ));
+            }
+            let purchase = instructions::buy_tokens_for(
+                &payer,
+                &recipient,
+                &sale,
+                &payment_account.unwrap_or_else(|| token_account(&payer, &token_sale.payment_mint)),
+                &token_account(&token_sale.seller, &token_sale.payment_mint),
+                &token_account(&token_sale.platform_fee_recipient, &token_sale.payment_mint),
+                &recipient_token_account
+                    .unwrap_or_else(|| token_account(&recipient, &token_sale.payout_mint())),
+                token_sale.milestone_count > 0,
+                token_sale.is_locked(),
+                args::BuyTokensFor {
+                    token_amount: amount,
+                },
+            );
+            ixs.push(hook.apply(&token_sale, purchase));
+            config.process(&ixs)
+        }
+        SaleCommand::Quote(quote) => {
+            let buyer = quote_buyer(config, &quote, |buyer| pda::buyer(buyer, &quote.sale).0)?;
+            let ix = match (quote.amount, quote.payment) {
+                (Some(token_amount), _) => instructions::quote_purchase(
+                    &quote.sale,
+                    buyer.as_ref(),
+                    args::QuotePurchase { token_amount },
+                ),
+                (None, payment) => instructions::quote_exact_in(
+                    &quote.sale,
+                    buyer.as_ref(),
+                    args::QuoteExactIn {
+                        payment: payment.unwrap_or_default(),
+                    },
+                ),
+            };
+            let result: escrow::PurchaseQuote = config.simulate(&escrow::PROGRAM_ID, &[ix])?;
+            display::print_purchase_quote(
+                result.token_amount,
+                result.gross_cost,
+                result.platform_fee,
+                result.seller_net,
+                result.remaining_allowance,
+            );
+            Ok(())
+        }
+        SaleCommand::Pause(SaleArg { sale }) => {
+            config.process(&[instructions::toggle_pause(&config.authority()?, &sale)])
+        }
+        SaleCommand::Cancel {
+            sale,
+            token_account: seller_token_account,
+        } => {
+            let seller = config.authority()?;
+            let token_sale: escrow::TokenSale = config.fetch(&sale)?;
+            let seller_token_account = seller_token_account
+                .unwrap_or_else(|| token_account(&seller, &token_sale.token_mint));
+            config.process(&[instructions::cancel_sale(
+                &seller,
+                &sale,
+                &seller_token_account,
+                token_sale.is_auction(),
+            )])
+        }
+        SaleCommand::Update {
+            update,
+            min_duration,
+            max_duration,
+        } => config.process(&[instructions::update_sale_params(
+            &config.authority()?,
+            &update.sale,
+            args::UpdateSaleParams {
+                new_price_per_token: update.price,
+                new_sale_start_time: update.start,
+                new_sale_end_time: update.end,
+                new_max_tokens_per_buyer: update.max_per_buyer,
+                new_min_sale_duration: min_duration,
+                new_max_sale_duration: max_duration,
+            },
+        )]),
+        SaleCommand::Extend { sale, end } => config.process(&[instructions::extend_sale(
+            &config.authority()?,
+            &sale,
+            args::ExtendSale {
+                new_sale_end_time: end,
+            },
+        )]),
+        SaleCommand::Close(SaleArg { sale }) => {
+            config.process(&[instructions::close_sale_early(&config.authority()?, &sale)])
+        }
+        SaleCommand::SoftClose {
+            sale,
+            window,
+            extension,
+            ceiling,
+        } => config.process(&[instructions::configure_soft_close(
+            &config.authority()?,
+            &sale,
+            args::ConfigureSoftClose {
+                window,
+                extension,
+                ceiling,
+            },
+        )]),
+        SaleCommand::Lock { sale, unlock_time } => {
+            let token_sale: escrow::TokenSale = config.fetch(&sale)?;
+            println!("Receipt mint: {}", pda::receipt_mint(&sale).0);
+            config.process(&[instructions::configure_token_lock(
+                &config.authority()?,
+                &sale,
+                &token_sale.token_mint,
+                args::ConfigureTokenLock { unlock_time },
+            )])
+        }
+        SaleCommand::Redeem {
+            sale,
+            amount,
+            receipt_account,
+            token_account: holder_token_account,
+        } => {
+            let holder = config.authority()?;
+            let token_sale: escrow::TokenSale = config.fetch(&sale)?;
+            config.process(&[instructions::redeem_receipts(
+                &holder,
+                &sale,
+                &receipt_account
+                    .unwrap_or_else(|| token_account(&holder, &token_sale.receipt_mint)),
+                &holder_token_account
+                    .unwrap_or_else(|| token_account(&holder, &token_sale.token_mint)),
+                args::RedeemReceipts { amount },
+            )])
+        }
+        SaleCommand::Screen(ScreeningArgs { sale, off }) => {
+            config.process(&[instructions::set_wallet_screening(
+                &config.authority()?,
+                &sale,
+                args::SetWalletScreening { enabled: !off },
+            )])
+        }
+        SaleCommand::Hook { sale, program } => config.process(&[instructions::set_purchase_hook(
+            &config.authority()?,
+            &sale,
+            &program,
+        )]),
+        SaleCommand::Throttles {
+            sale,
+            max_per_tx,
+            min_interval,
+            max_per_slot,
+        } => config.process(&[instructions::set_purchase_throttles(
+            &config.authority()?,
+            &sale,
+            args::SetPurchaseThrottles {
+                max_tokens_per_tx: max_per_tx,
+                min_purchase_interval: min_interval,
+                max_tokens_per_slot: max_per_slot,
+            },
+        )]),
+        SaleCommand::PriceTiers { sale, tiers } => {
+            config.process(&[instructions::set_price_tiers(
+                &config.authority()?,
+                &sale,
+                args::SetPriceTiers { price_tiers: tiers },
+            )])
+        }
+        SaleCommand::Milestones {
+            sale,
+            milestones,
+            approver,
+        } => {
+            let token_sale: escrow::TokenSale = config.fetch(&sale)?;
+            config.process(&[instructions::configure_milestones(
+                &config.authority()?,
+                &sale,
+                &token_sale.payment_mint,
+                args::ConfigureMilestones {
+                    milestones,
+                    approver,
+                },
+            )])
+        }
+        SaleCommand::ApproveMilestone(SaleArg { sale }) => {
+            config.process(&[instructions::approve_milestone(&config.authority()?, &sale)])
+        }
+        SaleCommand::VoteMilestone(SaleArg { sale }) => {
+            config.process(&[instructions::vote_milestone(&config.authority()?, &sale)])
+        }
+        SaleCommand::ReleaseMilestone(SaleArg { sale }) => {
+            let token_sale: escrow::TokenSale = config.fetch(&sale)?;
+            config.process(&[instructions::release_milestone(
+                &sale,
+                &token_account(&token_sale.seller, &token_sale.payment_mint),
+            )])
+        }
+        SaleCommand::ClaimRefund {
+            sale,
+            payment_account,
+        } => {
+            let buyer = config.authority()?;
+            let payment_account = match payment_account {
+                Some(account) => account,
+                None => {
+                    let token_sale: escrow::TokenSale = config.fetch(&sale)?;
+                    token_account(&buyer, &token_sale.payment_mint)
+                }
+            };
+            config.process(&[instructions::claim_milestone_refund(
+                &buyer,
+                &sale,
+                &payment_account,
+            )])
+        }
+        SaleCommand::Overflow(SaleArg { sale }) => {
+            let token_sale: escrow::TokenSale = config.fetch(&sale)?;
+            config.process(&[instructions::configure_overflow(
+                &config.authority()?,
+                &sale,
+                &token_sale.payment_mint,
+            )])
+        }
+        SaleCommand::Deposit {
+            sale,
+            amount,
+            payment_account,
+        } => {
+            let buyer = config.authority()?;
+            let token_sale: escrow::TokenSale = config.fetch(&sale)?;
+
+            let mut ixs = Vec::new();
+            if !config.account_exists(&pda::buyer(&buyer, &sale).0)? {
+                ixs.push(instructions::create_buyer_account(
+                    &buyer,
+                    &config.fee_payer()?,
+                    &sale,
+                ));
+            }
+            ixs.push(instructions::deposit_overflow(
+                &buyer,
+                &sale,
+                &payment_account.unwrap_or_else(|| token_account(&buyer, &token_sale.payment_mint)),
+                args::DepositOverflow { amount },
+            ));
+            config.process(&ixs)
+        }
+        SaleCommand::ClaimOverflow(SaleArg { sale }) => {
+            let buyer = config.authority()?;
+            let token_sale: escrow::TokenSale = config.fetch(&sale)?;
+            config.process(&[instructions::claim_overflow(
+                &buyer,
+                &sale,
+                &token_account(&buyer, &token_sale.payment_mint),
+                &token_account(&buyer, &token_sale.token_mint),
+                &token_account(&token_sale.seller, &token_sale.payment_mint),
+                &token_account(&token_sale.platform_fee_recipient, &token_sale.payment_mint),
+            )])
+        }
+        SaleCommand::Lottery {
+            sale,
+            ticket_tokens,
+            commitment,
+            revealer,
+        } => {
+            let token_sale: escrow::TokenSale = config.fetch(&sale)?;
+            config.process(&[instructions::configure_lottery(
+                &config.authority()?,
+                &sale,
+                &token_sale.payment_mint,
+                args::ConfigureLottery {
+                    ticket_tokens,
+                    seed_commitment: commitment,
+                    revealer,
+                },
+            )])
+        }
+        SaleCommand::RegisterTicket {
+            sale,
+            payment_account,
+        } => {
+            let buyer = config.authority()?;
+            let payment_account = match payment_account {
+                Some(account) => account,
+                None => {
+                    let token_sale: escrow::TokenSale = config.fetch(&sale)?;
+                    token_account(&buyer, &token_sale.payment_mint)
+                }
+            };
+            config.process(&[instructions::register_lottery_ticket(
+                &buyer,
+                &config.fee_payer()?,
+                &sale,
+                &payment_account,
+            )])
+        }
+        SaleCommand::RevealSeed { sale, seed } => {
+            config.process(&[instructions::reveal_lottery_seed(
+                &config.authority()?,
+                &sale,
+                args::RevealLotterySeed { seed },
+            )])
+        }
+        SaleCommand::Draw(SaleArg { sale }) => {
+            let lottery: escrow::Lottery = config.fetch(&pda::lottery(&sale).0)?;
+            let slot = config.rpc.get_slot()?;
+            let expired = slot > lottery.draw_slot + escrow::MAX_SLOT_HASH_AGE;
+            if lottery.draw_slot == 0 || expired {
+                println!(
+                    "Requesting a draw; run this again after slot {}",
+                    slot + escrow::LOTTERY_DRAW_DELAY_SLOTS
+                );
+                config.process(&[instructions::request_lottery_draw(&sale)])
+            } else {
+                config.process(&[instructions::draw_lottery(&sale)])
+            }
+        }
+        SaleCommand::ClaimTicket(SaleArg { sale }) => {
+            let buyer = config.authority()?;
+            let token_sale: escrow::TokenSale = config.fetch(&sale)?;
+            config.process(&[instructions::claim_lottery_ticket(
+                &buyer,
+                &sale,
+                &token_account(&buyer, &token_sale.payment_mint),
+                &token_account(&buyer, &token_sale.token_mint),
+                &token_account(&token_sale.seller, &token_sale.payment_mint),
+                &token_account(&token_sale.platform_fee_recipient, &token_sale.payment_mint),
+            )])
+        }
+        SaleCommand::CloseTicket(SaleArg { sale }) => {
+            let buyer = config.authority()?;
+            let ticket: escrow::LotteryTicket =
+                config.fetch(&pda::lottery_ticket(&buyer, &sale).0)?;
+            config.process(&[instructions::close_lottery_ticket(
+                &buyer,
+                &ticket.rent_payer,
+                &sale,
+            )])
+        }
+        SaleCommand::Auction {
+            sale,
+            min_price,
+            tick_size,
+            levels,
+        } => {
+            let token_sale: escrow::TokenSale = config.fetch(&sale)?;
+            config.process(&[instructions::configure_auction(
+                &config.authority()?,
+                &sale,
+                &token_sale.payment_mint,
+                args::ConfigureAuction {
+                    min_price,
+                    tick_size,
+                    price_levels: levels,
+                },
+            )])
+        }
+        SaleCommand::Bid {
+            sale,
+            id,
+            quantity,
+            max_price,
+            payment_account,
+        } => {
+            let bidder = config.authority()?;
+            let payment_account = match payment_account {
+                Some(account) => account,
+                None => {
+                    let token_sale: escrow::TokenSale = config.fetch(&sale)?;
+                    token_account(&bidder, &token_sale.payment_mint)
+                }
+            };
+            println!("Bid: {}", pda::auction_bid(&sale, &bidder, id).0);
+            config.process(&[instructions::submit_bid(
+                &bidder,
+                &config.fee_payer()?,
+                &sale,
+                &payment_account,
+                args::SubmitBid {
+                    bid_id: id,
+                    quantity,
+                    max_price,
+                },
+            )])
+        }
+        SaleCommand::SealBids {
+            sale,
+            collateral,
+            reveal_period,
+            forfeit_bps,
+        } => config.process(&[instructions::configure_sealed_bids(
+            &config.authority()?,
+            &sale,
+            args::ConfigureSealedBids {
+                collateral,
+                reveal_period,
+                forfeit_bps,
+            },
+        )]),
+        SaleCommand::CommitBid(bid) => {
+            let bidder = config.authority()?;
+            let commitment =
+                escrow::AuctionBid::commitment_of(bid.max_price, bid.quantity, &bid.salt, &bidder);
+            println!("Bid: {}", pda::auction_bid(&bid.sale, &bidder, bid.id).0);
+            config.process(&[instructions::commit_sealed_bid(
+                &bidder,
+                &config.fee_payer()?,
+                &bid.sale,
+                &bid_payment_account(config, &bidder, &bid)?,
+                args::CommitSealedBid {
+                    bid_id: bid.id,
+                    commitment,
+                },
+            )])
+        }
+        SaleCommand::RevealBid(bid) => {
+            let bidder = config.authority()?;
+            config.process(&[instructions::reveal_sealed_bid(
+                &bidder,
+                &bid.sale,
+                &bid_payment_account(config, &bidder, &bid)?,
+                args::RevealSealedBid {
+                    bid_id: bid.id,
+                    quantity: bid.quantity,
+                    max_price: bid.max_price,
+                    salt: bid.salt,
+                },
+            )])
+        }
+        SaleCommand::ClearAuction(SaleArg { sale }) => {
+            config.process(&[instructions::clear_auction(&sale)])
+        }
+        SaleCommand::SettleBids(SaleArg { sale }) => {
+            let token_sale: escrow::TokenSale = config.fetch(&sale)?;
+            let bids = config.rpc.get_program_accounts_with_config(
+                &escrow::PROGRAM_ID,
+                RpcProgramAccountsConfig {
+                    filters: Some(filters::auction_bids_by_sale(&sale)),
+                    ..Default::default()
+                },
+            )?;
+            let mut unsettled = Vec::new();
+            for (address, account) in bids {
+                let bid: escrow::AuctionBid = decode(&account.data)?;
+                if !bid.settled() {
+                    unsettled.push((
+                        address,
+                        token_account(&bid.bidder, &token_sale.payment_mint),
+                        token_account(&bid.bidder, &token_sale.token_mint),
+                    ));
+                }
+            }
+            println!("Settling {} bids", unsettled.len());
+            for chunk in unsettled.chunks(SETTLE_CHUNK) {
+                config.process(&[instructions::settle_auction_bids(
+                    &sale,
+                    &token_account(&token_sale.seller, &token_sale.payment_mint),
+                    &token_account(&token_sale.seller, &token_sale.token_mint),
+                    &token_account(&token_sale.platform_fee_recipient, &token_sale.payment_mint),
+                    chunk,
+                )])?;
+            }
+            Ok(())
+        }
+        SaleCommand::CloseBid { sale, id } => {
+            let bidder = config.authority()?;
+            let bid: escrow::AuctionBid = config.fetch(&pda::auction_bid(&sale, &bidder, id).0)?;
+            config.process(&[instructions::close_auction_bid(
+                &bidder,
+                &bid.rent_payer,
+                &sale,
+                id,
+            )])
+        }
+        SaleCommand::Show { sale } => {
+            let token_sale: escrow::TokenSale = config.fetch(&sale)?;
+            display::print_token_sale(&sale, &token_sale);
+            if token_sale.is_lottery() {
+                display::print_lottery(&config.fetch(&pda::lottery(&sale).0)?);
+            }
+            if token_sale.is_auction() {
+                display::print_auction(&config.fetch(&pda::auction(&sale).0)?);
+            }
+            Ok(())
+        }
+    }
+}
+
+fn bid_payment_account(config: &Config, bidder: &Pubkey, bid: &SealedBidArgs) -> Result<Pubkey> {
+    match bid.payment_account {
+        Some(account) => Ok(account),
+        None => {
+            let token_sale: escrow::TokenSale = config.fetch(&bid.sale)?;
+            Ok(token_account(bidder, &token_sale.payment_mint))
+        }
+    }
+}
+
+pub fn run_deny_list(config: &Config, command: DenyListCommand) -> Result<()> {
+    match command {
+        DenyListCommand::Configure(DenyListArgs {
+            compliance_authority: authority,
+            required,
+        }) => {
+            let admin = config.authority()?;
+            let ix = if config.account_exists(&pda::deny_list().0)? {
+                instructions::update_deny_list(
+                    &admin,
+                    args::UpdateDenyList {
+                        authority,
+                        required,
+                    },
+                )
+            } else {
+                instructions::create_deny_list(
+                    &admin,
+                    args::CreateDenyList {
+                        authority,
+                        required,
+                    },
+                )
+            };
+            config.process(&[ix])
+        }
+        DenyListCommand::Deny { wallet } => config.process(&[instructions::deny_wallet(
+            &config.authority()?,
+            args::DenyWallet { wallet },
+        )]),
+        DenyListCommand::Allow { wallet } => {
+            config.process(&[instructions::allow_wallet(&config.authority()?, &wallet)])
+        }
+        DenyListCommand::Show { wallet } => {
+            let (address, _) = pda::deny_list();
+            let deny_list: escrow::DenyList = config.fetch(&address)?;
+            display::print_deny_list(
+                &address,
+                &deny_list.authority,
+                deny_list.denied_count,
+                deny_list.required,
+            );
+            if let Some(wallet) = wallet {
+                let denied = config.account_exists(&pda::denied_wallet(&address, &wallet).0)?;
+                println!(
+                    "{wallet} is {}",
+                    if denied { "denied" } else { "not denied" }
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+pub fn run_offer(config: &Config, command: OfferCommand) -> Result<()> {
+    match command {
+        OfferCommand::Make {
+            id,
+            mint_a,
+            mint_b,
+            amount_a,
+            wanted_b,
+            taker,
+            expires_at,
+            partially_fillable,
+        } => {
+            let maker = config.authority()?;
+            println!("Offer: {}", pda::offer(&maker, id).0);
+            config.process(&[instructions::make_offer(
+                &maker,
+                &mint_a,
+                &mint_b,
+                &token_account(&maker, &mint_a),
+                args::MakeOffer {
+                    offer_id: id,
+                    token_a_amount: amount_a,
+                    token_b_wanted: wanted_b,
+                    taker,
+                    expires_at,
+                    partially_fillable,
+                },
+            )])
+        }
+        OfferCommand::Take { maker, id } => {
+            let taker = config.authority()?;
+            let offer: escrow::Offer = config.fetch(&pda::offer(&maker, id).0)?;
+            config.process(&[instructions::take_offer(
+                &taker,
+                &maker,
+                id,
+                &token_account(&taker, &offer.token_mint_a),
+                &token_account(&taker, &offer.token_mint_b),
+                &token_account(&maker, &offer.token_mint_b),
+            )])
+        }
+        OfferCommand::Fill {
+            maker,
+            id,
+            amount_a,
+        } => {
+            let taker = config.authority()?;
+            let offer: escrow::Offer = config.fetch(&pda::offer(&maker, id).0)?;
+            config.process(&[instructions::fill_offer(
+                &taker,
+                &maker,
+                id,
+                &token_account(&taker, &offer.token_mint_a),
+                &token_account(&taker, &offer.token_mint_b),
+                &token_account(&maker, &offer.token_mint_b),
+                args::FillOffer {
+                    token_a_fill: amount_a,
+                },
+            )])
+        }
+        OfferCommand::Cancel { id } => {
+            let maker = config.authority()?;
+            let offer: escrow::Offer = config.fetch(&pda::offer(&maker, id).0)?;
+            config.process(&[instructions::cancel_offer(
+                &maker,
+                id,
+                &token_account(&maker, &offer.token_mint_a),
+            )])
+        }
+    }
+}
+
+/// Payer, escrow state and both parties' token accounts of a service escrow
+fn service_escrow_accounts(
+    config: &Config,
+    escrow: &ServiceEscrowArg,
+) -> Result<(Pubkey, Pubkey, Pubkey)> {
+    let payer = match escrow.payer {
+        Some(payer) => payer,
+        None => config.authority()?,
+    };
+    let service_escrow: escrow::ServiceEscrow =
+        config.fetch(&pda::service_escrow(&payer, escrow.id).0)?;
+    Ok((
+        payer,
+        token_account(&payer, &service_escrow.mint),
+        token_account(&service_escrow.payee, &service_escrow.mint),
+    ))
+}
+
+pub fn run_service(config: &Config, command: ServiceCommand) -> Result<()> {
+    let authority = config.authority()?;
+    match command {
+        ServiceCommand::Create {
+            id,
+            payee,
+            arbiter,
+            mint,
+            amount,
+            release_deadline,
+        } => {
+            println!("Service escrow: {}", pda::service_escrow(&authority, id).0);
+            config.process(&[instructions::create_service_escrow(
+                &authority,
+                &payee,
+                &arbiter,
+                &mint,
+                &token_account(&authority, &mint),
+                args::CreateServiceEscrow {
+                    escrow_id: id,
+                    amount,
+                    release_deadline,
+                },
+            )])
+        }
+        ServiceCommand::Release(escrow) => {
+            let (payer, payer_account, payee_account) = service_escrow_accounts(config, &escrow)?;
+            config.process(&[instructions::release_service_escrow(
+                &payer,
+                escrow.id,
+                &payer_account,
+                &payee_account,
+            )])
+        }
+        ServiceCommand::Refund(escrow) => {
+            let (payer, payer_account, payee_account) = service_escrow_accounts(config, &escrow)?;
+            config.process(&[instructions::refund_service_escrow(
+                &authority,
+                &payer,
+                escrow.id,
+                &payer_account,
+                &payee_account,
+            )])
+        }
+        ServiceCommand::Dispute(escrow) => {
+            let payer = escrow.payer.unwrap_or(authority);
+            config.process(&[instructions::raise_dispute(&authority, &payer, escrow.id)])
+        }
+        ServiceCommand::Resolve {
+            escrow,
+            payee_share_bps,
+        } => {
+            let (payer, payer_account, payee_account) = service_escrow_accounts(config, &escrow)?;
+            config.process(&[instructions::resolve_dispute(
+                &authority,
+                &payer,
+                escrow.id,
+                &payer_account,
+                &payee_account,
+                args::ResolveDispute { payee_share_bps },
+            )])
+        }
+        ServiceCommand::ClaimExpired(escrow) => {
+            let (payer, payer_account, payee_account) = service_escrow_accounts(config, &escrow)?;
+            config.process(&[instructions::claim_expired_service_escrow(
+                &authority,
+                &payer,
+                escrow.id,
+                &payer_account,
+                &payee_account,
+            )])
+        }
+    }
+}
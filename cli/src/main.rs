@@ -0,0 +1,101 @@
+//! `token-sale`: run sales on the escrow and multi-presale programs from the command line.
+//!
+//! Every instruction of both programs has a subcommand, and the `show` subcommands
+//! pretty-print decoded account state. Token accounts default to the associated token
+//! account of the relevant wallet.
+//!
+//! Multisig members sign offline: build the transaction with `--sign-only` (and
+//! `--authority`, `--fee-payer`, `--blockhash` as needed), pass the printed transaction
+//! through `tx sign` on each signer's machine, then broadcast it with `tx send`.
+
+mod config;
+mod display;
+mod escrow;
+mod migrate;
+mod presale;
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use solana_sdk::pubkey::Pubkey;
+use token_sale_client::anchor_spl::associated_token::get_associated_token_address;
+
+use config::{Config, ConfigArgs};
+
+#[derive(Parser)]
+#[command(name = "token-sale", version, about)]
+struct Cli {
+    #[command(flatten)]
+    config: ConfigArgs,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Token sales (escrow program)
+    #[command(subcommand)]
+    Sale(escrow::SaleCommand),
+    /// Token swap offers (escrow program)
+    #[command(subcommand)]
+    Offer(escrow::OfferCommand),
+    /// Arbiter-mediated service escrows (escrow program)
+    #[command(subcommand)]
+    Service(escrow::ServiceCommand),
+    /// The deny list screening sale buyers (escrow program)
+    #[command(subcommand)]
+    DenyList(escrow::DenyListCommand),
+    /// Platform configuration and treasury (multi-presale program)
+    #[command(subcommand)]
+    Platform(presale::PlatformCommand),
+    /// Projects (multi-presale program)
+    #[command(subcommand)]
+    Project(presale::ProjectCommand),
+    /// Project sale rounds (multi-presale program)
+    #[command(subcommand)]
+    Round(presale::RoundCommand),
+    /// Sale round whitelists (multi-presale program)
+    #[command(subcommand)]
+    Whitelist(presale::WhitelistCommand),
+    /// Standalone token sales (multi-presale program)
+    #[command(subcommand)]
+    PresaleSale(presale::LegacySaleCommand),
+    /// Upgrade any program account to its current layout, paying for the extra rent
+    Migrate { account: Pubkey },
+    /// Offline signing of transactions built with `--sign-only`
+    #[command(subcommand)]
+    Tx(TxCommand),
+}
+
+#[derive(Subcommand)]
+enum TxCommand {
+    /// Add the keypair's signature and print the transaction again
+    Sign { transaction: String },
+    /// Broadcast a fully signed transaction
+    Send { transaction: String },
+}
+
+/// Associated token account of `owner` for `mint`
+pub(crate) fn token_account(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    get_associated_token_address(owner, mint)
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let config = Config::new(cli.config)?;
+
+    match cli.command {
+        Command::Sale(command) => escrow::run_sale(&config, command),
+        Command::Offer(command) => escrow::run_offer(&config, command),
+        Command::Service(command) => escrow::run_service(&config, command),
+        Command::DenyList(command) => escrow::run_deny_list(&config, command),
+        Command::Platform(command) => presale::run_platform(&config, command),
+        Command::Project(command) => presale::run_project(&config, command),
+        Command::Round(command) => presale::run_round(&config, command),
+        Command::Whitelist(command) => presale::run_whitelist(&config, command),
+        Command::PresaleSale(command) => presale::run_legacy_sale(&config, command),
+        Command::Migrate { account } => migrate::run(&config, account),
+        Command::Tx(TxCommand::Sign { transaction }) => config.sign_encoded(&transaction),
+        Command::Tx(TxCommand::Send { transaction }) => config.send_encoded(&transaction),
+    }
+}
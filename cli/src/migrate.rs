@@ -0,0 +1,115 @@
+//! `migrate`: upgrade any account of either program to its current layout.
+//!
+//! The account type is recognised from its owner and discriminator, so one command covers
+//! every migration instruction, including the conversion of Borsh sales, sale rounds and
+//! buyer trackers to their zero-copy layouts. The signer pays any extra rent the new layout
+//! needs; no other authority is required.
+
+use anyhow::{bail, Context, Result};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use token_sale_client::anchor_lang::Discriminator;
+use token_sale_client::{decode, escrow, multi_presale};
+
+use crate::config::Config;
+
+/// A recognised account and the instruction that migrates it
+struct Migration {
+    kind: &'static str,
+    build: fn(&Pubkey, &Pubkey) -> Instruction,
+    /// Set when the account already decodes at the current version
+    current_version: Option<u8>,
+}
+
+/// The first account type whose discriminator `data` starts with.
+///
+/// Expects the owning program's `Versioned` trait to be in scope.
+macro_rules! detect {
+    ($data:expr, $($account:ty => $kind:literal, $build:expr;)+) => {{
+        let data: &[u8] = $data;
+        None$(.or_else(|| {
+            data.starts_with(<$account>::DISCRIMINATOR).then(|| Migration {
+                kind: $kind,
+                build: $build,
+                current_version: decode::<$account>(data)
+                    .ok()
+                    .map(|state| state.version())
+                    .filter(|version| *version >= <$account>::VERSION),
+            })
+        }))+
+    }};
+}
+
+/// Like `detect!` for zero-copy accounts: one still under its legacy Borsh discriminator
+/// needs converting, one under the zero-copy discriminator is current once it has grown
+/// to the current size.
+///
+/// Expects the owning program's `ZeroCopyVersioned` trait to be in scope.
+macro_rules! detect_zero_copy {
+    ($data:expr, $($account:ty => $kind:literal, $build:expr;)+) => {{
+        let data: &[u8] = $data;
+        None$(.or_else(|| {
+            let current = data.starts_with(<$account>::DISCRIMINATOR);
+            let legacy = data.starts_with(<<$account as ZeroCopyVersioned>::Legacy>::DISCRIMINATOR);
+            (current || legacy).then(|| Migration {
+                kind: $kind,
+                build: $build,
+                current_version: (current && data.len() >= <$account as ZeroCopyVersioned>::SPACE)
+                    .then_some(<$account as ZeroCopyVersioned>::VERSION),
+            })
+        }))+
+    }};
+}
+
+pub fn run(config: &Config, account: Pubkey) -> Result<()> {
+    let payer = config.authority()?;
+    let state = config
+        .rpc
+        .get_account(&account)
+        .with_context(|| format!("failed to fetch account {account}"))?;
+
+    let migration = if state.owner == escrow::PROGRAM_ID {
+        use escrow::{instructions::*, Versioned, ZeroCopyVersioned};
+        detect!(&state.data,
+            escrow::Offer => "offer", migrate_offer;
+            escrow::ServiceEscrow => "service escrow", migrate_service_escrow;
+        )
+        .or_else(|| {
+            detect_zero_copy!(&state.data,
+                escrow::TokenSale => "sale", migrate_token_sale;
+                escrow::BuyerAccount => "buyer account", migrate_buyer_account;
+            )
+        })
+    } else if state.owner == multi_presale::PROGRAM_ID {
+        use multi_presale::{instructions::*, Versioned, ZeroCopyVersioned};
+        detect!(&state.data,
+            multi_presale::PlatformAccount => "platform account", migrate_platform;
+            multi_presale::ProjectAccount => "project", migrate_project;
+            multi_presale::SaleConfiguration => "sale tier", migrate_sale_configuration;
+            multi_presale::PlatformTreasury => "platform treasury", migrate_platform_treasury;
+            multi_presale::ProjectWhitelist => "whitelist", migrate_whitelist;
+        )
+        .or_else(|| {
+            detect_zero_copy!(&state.data,
+                multi_presale::SaleRound => "sale round", migrate_sale_round;
+                multi_presale::TokenSale => "presale sale", migrate_token_sale;
+                multi_presale::BuyerAccount => "presale buyer account", migrate_buyer_account;
+            )
+        })
+    } else {
+        bail!("{account} is not owned by the escrow or multi-presale program");
+    };
+
+    let Some(migration) = migration else {
+        bail!("{account} has no migration instruction");
+    };
+    if let Some(version) = migration.current_version {
+        println!(
+            "{account} ({}) is already at version {version}",
+            migration.kind
+        );
+        return Ok(());
+    }
+    println!("Migrating {} {account}", migration.kind);
+    config.process(&[(migration.build)(&payer, &account)])
+}
@@ -0,0 +1,689 @@
+//! Commands for the `multi_presale` program: platform, projects, rounds and whitelists.
+
+use anyhow::{anyhow, Result};
+use clap::{Args, Subcommand, ValueEnum};
+use solana_sdk::pubkey::Pubkey;
+use token_sale_client::anchor_spl::token::{spl_token, ID as TOKEN_PROGRAM_ID};
+use token_sale_client::multi_presale::{self, args, instructions, pda, ProjectCategory, SaleType};
+
+use crate::config::Config;
+use crate::display;
+use crate::escrow::{
+    quote_buyer, BuyArgs, DenyListArgs, InitSaleArgs, QuoteArgs, SaleArg, ScreeningArgs,
+    UpdateSaleArgs,
+};
+use crate::token_account;
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum SaleTypeArg {
+    Seed,
+    Private,
+    Public,
+}
+
+impl From<SaleTypeArg> for SaleType {
+    fn from(sale_type: SaleTypeArg) -> Self {
+        match sale_type {
+            SaleTypeArg::Seed => SaleType::Seed,
+            SaleTypeArg::Private => SaleType::Private,
+            SaleTypeArg::Public => SaleType::Public,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum CategoryArg {
+    #[value(name = "defi")]
+    DeFi,
+    Gaming,
+    #[value(name = "nft")]
+    Nft,
+    Infrastructure,
+    Social,
+    Metaverse,
+    #[value(name = "ai")]
+    Ai,
+    Other,
+}
+
+impl From<CategoryArg> for ProjectCategory {
+    fn from(category: CategoryArg) -> Self {
+        match category {
+            CategoryArg::DeFi => ProjectCategory::DeFi,
+            CategoryArg::Gaming => ProjectCategory::Gaming,
+            CategoryArg::Nft => ProjectCategory::NFT,
+            CategoryArg::Infrastructure => ProjectCategory::Infrastructure,
+            CategoryArg::Social => ProjectCategory::Social,
+            CategoryArg::Metaverse => ProjectCategory::Metaverse,
+            CategoryArg::Ai => ProjectCategory::AI,
+            CategoryArg::Other => ProjectCategory::Other,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum PlatformCommand {
+    /// Create the platform configuration (once per deployment)
+    Init {
+        /// Wallet collecting platform fees
+        #[arg(long)]
+        treasury: Pubkey,
+        #[arg(long)]
+        fee_bps: u16,
+        /// Shortest allowed sale, in seconds
+        #[arg(long)]
+        min_duration: i64,
+        /// Longest allowed sale, in seconds
+        #[arg(long)]
+        max_duration: i64,
+    },
+    /// Create the platform treasury and its fee vault
+    InitTreasury {
+        #[arg(long)]
+        payment_mint: Pubkey,
+        /// Fee in basis points, at most 1000
+        #[arg(long)]
+        fee_bps: u16,
+    },
+    /// Create or update the platform's deny list (admin)
+    DenyList(DenyListArgs),
+    /// Bar a wallet from buying in screened sales (compliance authority)
+    Deny {
+        #[arg(long)]
+        wallet: Pubkey,
+    },
+    /// Lift a wallet's denial (compliance authority)
+    Allow {
+        #[arg(long)]
+        wallet: Pubkey,
+    },
+    /// Show the platform configuration and its deny list
+    Show,
+    /// Show the platform treasury
+    Treasury,
+}
+
+/// Project commands that only take the project address
+#[derive(Args)]
+pub struct ProjectArg {
+    #[arg(long)]
+    pub project: Pubkey,
+}
+
+#[derive(Subcommand)]
+pub enum ProjectCommand {
+    /// Register a project for review
+    Create {
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        description: String,
+        #[arg(long, default_value = "")]
+        logo_url: String,
+        #[arg(long, default_value = "")]
+        website: String,
+        #[arg(long, value_enum, default_value = "other")]
+        category: CategoryArg,
+        /// Repeatable
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        #[arg(long)]
+        token_mint: Pubkey,
+        #[arg(long)]
+        token_name: String,
+        #[arg(long)]
+        token_symbol: String,
+        #[arg(long)]
+        token_decimals: u8,
+    },
+    /// Approve a project (platform admin)
+    Approve(ProjectArg),
+    /// Create the project's token vault
+    InitVault(ProjectArg),
+    /// Deposit sale tokens into the project vault, creating it if needed
+    Fund {
+        #[arg(long)]
+        project: Pubkey,
+        #[arg(long)]
+        amount: u64,
+        /// Source token account (defaults to the creator's associated account)
+        #[arg(long)]
+        token_account: Option<Pubkey>,
+    },
+    /// Apply the automatic status transition
+    Advance(ProjectArg),
+    /// Configure the project's sale tier
+    ConfigureTier {
+        #[arg(long)]
+        project: Pubkey,
+        #[arg(long)]
+        payment_mint: Pubkey,
+        #[arg(long, value_enum)]
+        sale_type: SaleTypeArg,
+        #[arg(long)]
+        price: u64,
+        #[arg(long)]
+        total_tokens: u64,
+        #[arg(long)]
+        min_purchase: u64,
+        #[arg(long)]
+        max_purchase: u64,
+        #[arg(long)]
+        start: i64,
+        #[arg(long)]
+        end: i64,
+        #[arg(long)]
+        whitelist_only: bool,
+        #[arg(long)]
+        requires_kyc: bool,
+        /// Referral reward in basis points (0 = referrals disabled)
+        #[arg(long, default_value_t = 0)]
+        referral_bps: u16,
+    },
+    /// Show a project, by address or by creator and name
+    Show {
+        #[arg(required_unless_present = "name")]
+        project: Option<Pubkey>,
+        /// Creator of the project (defaults to the authority)
+        #[arg(long, requires = "name")]
+        creator: Option<Pubkey>,
+        #[arg(long)]
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum RoundCommand {
+    /// Create a sale round
+    Create {
+        #[arg(long)]
+        project: Pubkey,
+        #[arg(long)]
+        round: u8,
+        #[arg(long, value_enum)]
+        sale_type: SaleTypeArg,
+        #[arg(long)]
+        price: u64,
+        #[arg(long)]
+        total_tokens: u64,
+        /// Per-buyer cap (0 = no limit)
+        #[arg(long, default_value_t = 0)]
+        max_per_buyer: u64,
+        #[arg(long)]
+        start: i64,
+        #[arg(long)]
+        end: i64,
+        #[arg(long)]
+        whitelist_required: bool,
+        #[arg(long, default_value_t = 0)]
+        min_purchase: u64,
+        /// Maximum raise (0 = no limit)
+        #[arg(long, default_value_t = 0)]
+        max_raise: u64,
+    },
+    /// Show a sale round
+    Show {
+        #[arg(long)]
+        project: Pubkey,
+        #[arg(long)]
+        round: u8,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum WhitelistCommand {
+    /// Create a round's whitelist with a batch of addresses
+    Add {
+        #[arg(long)]
+        project: Pubkey,
+        #[arg(long)]
+        round: u8,
+        /// Repeatable
+        #[arg(long = "address", required = true)]
+        addresses: Vec<Pubkey>,
+    },
+}
+
+/// Standalone sales run by the multi-presale program
+#[derive(Subcommand)]
+pub enum LegacySaleCommand {
+    /// Create a sale and fund its vault
+    Init(InitSaleArgs),
+    /// Create the buyer's purchase tracker (done automatically by `buy`)
+    CreateBuyer(SaleArg),
+    /// Close the buyer's tracker after the sale, refunding its rent to whoever paid it
+    CloseBuyer(SaleArg),
+    /// Buy tokens
+    Buy(BuyArgs),
+    /// Price a purchase without buying, as `buy` would charge it
+    Quote(QuoteArgs),
+    /// Pause or unpause a sale
+    Pause(SaleArg),
+    /// Cancel a sale and return unsold tokens
+    Cancel {
+        #[arg(long)]
+        sale: Pubkey,
+        #[arg(long)]
+        token_account: Option<Pubkey>,
+    },
+    /// Change price, window or per-buyer cap before the sale starts
+    Update(UpdateSaleArgs),
+    /// Screen buyers against the platform's deny list
+    Screen(ScreeningArgs),
+    /// Show a sale
+    Show { sale: Pubkey },
+}
+
+pub fn run_platform(config: &Config, command: PlatformCommand) -> Result<()> {
+    match command {
+        PlatformCommand::Init {
+            treasury,
+            fee_bps,
+            min_duration,
+            max_duration,
+        } => config.process(&[instructions::initialize_platform(
+            &config.authority()?,
+            &treasury,
+            args::InitializePlatform {
+                platform_fee: fee_bps,
+                min_project_duration: min_duration,
+                max_project_duration: max_duration,
+            },
+        )]),
+        PlatformCommand::InitTreasury {
+            payment_mint,
+            fee_bps,
+        } => config.process(&[instructions::initialize_platform_treasury(
+            &config.authority()?,
+            &payment_mint,
+            args::InitializePlatformTreasury {
+                fee_percentage: fee_bps,
+            },
+        )]),
+        PlatformCommand::DenyList(DenyListArgs {
+            compliance_authority: authority,
+            required,
+        }) => {
+            let admin = config.authority()?;
+            let ix = if config.account_exists(&pda::deny_list().0)? {
+                instructions::update_deny_list(
+                    &admin,
+                    args::UpdateDenyList {
+                        authority,
+                        required,
+                    },
+                )
+            } else {
+                instructions::create_deny_list(
+                    &admin,
+                    args::CreateDenyList {
+                        authority,
+                        required,
+                    },
+                )
+            };
+            config.process(&[ix])
+        }
+        PlatformCommand::Deny { wallet } => config.process(&[instructions::deny_wallet(
+            &config.authority()?,
+            args::DenyWallet { wallet },
+        )]),
+        PlatformCommand::Allow { wallet } => {
+            config.process(&[instructions::allow_wallet(&config.authority()?, &wallet)])
+        }
+        PlatformCommand::Show => {
+            let (platform, _) = pda::platform();
+            display::print_platform(&platform, &config.fetch(&platform)?);
+            let (address, _) = pda::deny_list();
+            if config.account_exists(&address)? {
+                let deny_list: multi_presale::DenyList = config.fetch(&address)?;
+                display::print_deny_list(
+                    &address,
+                    &deny_list.authority,
+                    deny_list.denied_count,
+                    deny_list.required,
+                );
+            }
+            Ok(())
+        }
+        PlatformCommand::Treasury => {
+            let (treasury, _) = pda::platform_treasury();
+            display::print_platform_treasury(&treasury, &config.fetch(&treasury)?);
+            Ok(())
+        }
+    }
+}
+
+pub fn run_project(config: &Config, command: ProjectCommand) -> Result<()> {
+    match command {
+        ProjectCommand::Create {
+            name,
+            description,
+            logo_url,
+            website,
+            category,
+            tags,
+            token_mint,
+            token_name,
+            token_symbol,
+            token_decimals,
+        } => {
+            let creator = config.authority()?;
+            println!("Project: {}", pda::project(&creator, &name).0);
+            config.process(&[instructions::create_project(
+                &creator,
+                &token_mint,
+                args::CreateProject {
+                    name,
+                    description,
+                    logo_url,
+                    website,
+                    category: category.into(),
+                    tags,
+                    token_name,
+                    token_symbol,
+                    token_decimals,
+                },
+            )])
+        }
+        ProjectCommand::Approve(ProjectArg { project }) => {
+            config.process(&[instructions::approve_project(
+                &config.authority()?,
+                &project,
+            )])
+        }
+        ProjectCommand::InitVault(ProjectArg { project }) => {
+            let project_account: multi_presale::ProjectAccount = config.fetch(&project)?;
+            config.process(&[instructions::initialize_project_vault(
+                &config.authority()?,
+                &project,
+                &project_account.token_mint,
+            )])
+        }
+        ProjectCommand::Fund {
+            project,
+            amount,
+            token_account: source,
+        } => {
+            let creator = config.authority()?;
+            let project_account: multi_presale::ProjectAccount = config.fetch(&project)?;
+            let token_mint = project_account.token_mint;
+
+            let mut ixs = Vec::new();
+            let vault = match project_account.token_vault {
+                Some(vault) => vault,
+                None => {
+                    ixs.push(instructions::initialize_project_vault(
+                        &creator,
+                        &project,
+                        &token_mint,
+                    ));
+                    pda::project_vault(&project, &token_mint).0
+                }
+            };
+            ixs.push(spl_token::instruction::transfer(
+                &TOKEN_PROGRAM_ID,
+                &source.unwrap_or_else(|| token_account(&creator, &token_mint)),
+                &vault,
+                &creator,
+                &[],
+                amount,
+            )?);
+            config.process(&ixs)
+        }
+        ProjectCommand::Advance(ProjectArg { project }) => {
+            config.process(&[instructions::advance_project_status(
+                &config.authority()?,
+                &project,
+            )])
+        }
+        ProjectCommand::ConfigureTier {
+            project,
+            payment_mint,
+            sale_type,
+            price,
+            total_tokens,
+            min_purchase,
+            max_purchase,
+            start,
+            end,
+            whitelist_only,
+            requires_kyc,
+            referral_bps,
+        } => config.process(&[instructions::configure_sale_tier(
+            &config.authority()?,
+            &project,
+            &payment_mint,
+            args::ConfigureSaleTier {
+                sale_type: sale_type.into(),
+                token_price: price,
+                total_tokens,
+                min_purchase,
+                max_purchase,
+                start_time: start,
+                end_time: end,
+                is_whitelist_only: whitelist_only,
+                requires_kyc,
+                referral_enabled: referral_bps > 0,
+                referral_rate: referral_bps,
+            },
+        )]),
+        ProjectCommand::Show {
+            project,
+            creator,
+            name,
+        } => {
+            let project = match (project, name) {
+                (Some(project), _) => project,
+                (None, Some(name)) => {
+                    let creator = match creator {
+                        Some(creator) => creator,
+                        None => config.authority()?,
+                    };
+                    pda::project(&creator, &name).0
+                }
+                (None, None) => return Err(anyhow!("pass a project address or --name")),
+            };
+            display::print_project(&project, &config.fetch(&project)?);
+            Ok(())
+        }
+    }
+}
+
+pub fn run_round(config: &Config, command: RoundCommand) -> Result<()> {
+    match command {
+        RoundCommand::Create {
+            project,
+            round,
+            sale_type,
+            price,
+            total_tokens,
+            max_per_buyer,
+            start,
+            end,
+            whitelist_required,
+            min_purchase,
+            max_raise,
+        } => {
+            println!("Sale round: {}", pda::sale_round(&project, round).0);
+            config.process(&[instructions::create_sale_round(
+                &config.authority()?,
+                &project,
+                args::CreateSaleRound {
+                    round_number: round,
+                    sale_type: sale_type.into(),
+                    token_price: price,
+                    total_tokens,
+                    max_tokens_per_buyer: max_per_buyer,
+                    start_time: start,
+                    end_time: end,
+                    whitelist_required,
+                    min_purchase,
+                    max_total_raise: max_raise,
+                },
+            )])
+        }
+        RoundCommand::Show { project, round } => {
+            let (sale_round, _) = pda::sale_round(&project, round);
+            display::print_sale_round(&sale_round, &config.fetch(&sale_round)?);
+            Ok(())
+        }
+    }
+}
+
+pub fn run_whitelist(config: &Config, command: WhitelistCommand) -> Result<()> {
+    match command {
+        WhitelistCommand::Add {
+            project,
+            round,
+            addresses,
+        } => config.process(&[instructions::add_to_whitelist(
+            &config.authority()?,
+            &project,
+            args::AddToWhitelist {
+                sale_round: round,
+                addresses,
+            },
+        )]),
+    }
+}
+
+pub fn run_legacy_sale(config: &Config, command: LegacySaleCommand) -> Result<()> {
+    match command {
+        LegacySaleCommand::Init(init) => {
+            let seller = config.authority()?;
+            let seller_token_account = init
+                .seller_token_account
+                .unwrap_or_else(|| token_account(&seller, &init.token_mint));
+            println!("Sale: {}", pda::token_sale(&seller, &init.token_mint).0);
+            config.process(&[instructions::initialize_sale(
+                &seller,
+                &init.token_mint,
+                &init.payment_mint,
+                &seller_token_account,
+                args::InitializeSale {
+                    price_per_token: init.price,
+                    total_tokens: init.total_tokens,
+                    sale_start_time: init.start,
+                    sale_end_time: init.end,
+                    max_tokens_per_buyer: init.max_per_buyer,
+                    platform_fee_bps: init.fee_bps,
+                    platform_fee_recipient: init.fee_recipient,
+                },
+            )])
+        }
+        LegacySaleCommand::CreateBuyer(SaleArg { sale }) => {
+            config.process(&[instructions::create_buyer_account(
+                &config.authority()?,
+                &config.fee_payer()?,
+                &sale,
+            )])
+        }
+        LegacySaleCommand::CloseBuyer(SaleArg { sale }) => {
+            let buyer = config.authority()?;
+            let tracker: multi_presale::BuyerAccount =
+                config.fetch(&pda::buyer(&buyer, &sale).0)?;
+            config.process(&[instructions::close_buyer_account(
+                &buyer,
+                &tracker.rent_recipient(),
+                &sale,
+            )])
+        }
+        LegacySaleCommand::Buy(buy) => {
+            let buyer = config.authority()?;
+            let token_sale: multi_presale::TokenSale = config.fetch(&buy.sale)?;
+
+            let mut ixs = Vec::new();
+            if !config.account_exists(&pda::buyer(&buyer, &buy.sale).0)? {
+                ixs.push(instructions::create_buyer_account(
+                    &buyer,
+                    &config.fee_payer()?,
+                    &buy.sale,
+                ));
+            }
+            ixs.push(instructions::buy_tokens(
+                &buyer,
+                &buy.sale,
+                &buy.payment_account
+                    .unwrap_or_else(|| token_account(&buyer, &token_sale.payment_mint)),
+                &token_account(&token_sale.seller, &token_sale.payment_mint),
+                &token_account(&token_sale.platform_fee_recipient, &token_sale.payment_mint),
+                &buy.token_account
+                    .unwrap_or_else(|| token_account(&buyer, &token_sale.token_mint)),
+                args::BuyTokens {
+                    token_amount: buy.amount,
+                },
+            ));
+            config.process(&ixs)
+        }
+        LegacySaleCommand::Quote(quote) => {
+            let buyer = quote_buyer(config, &quote, |buyer| pda::buyer(buyer, &quote.sale).0)?;
+            let ix = match (quote.amount, quote.payment) {
+                (Some(token_amount), _) => instructions::quote_purchase(
+                    &quote.sale,
+                    buyer.as_ref(),
+                    args::QuotePurchase { token_amount },
+                ),
+                (None, payment) => instructions::quote_exact_in(
+                    &quote.sale,
+                    buyer.as_ref(),
+                    args::QuoteExactIn {
+                        payment: payment.unwrap_or_default(),
+                    },
+                ),
+            };
+            let result: multi_presale::PurchaseQuote =
+                config.simulate(&multi_presale::PROGRAM_ID, &[ix])?;
+            display::print_purchase_quote(
+                result.token_amount,
+                result.gross_cost,
+                result.platform_fee,
+                result.seller_net,
+                result.remaining_allowance,
+            );
+            Ok(())
+        }
+        LegacySaleCommand::Screen(ScreeningArgs { sale, off }) => {
+            config.process(&[instructions::set_wallet_screening(
+                &config.authority()?,
+                &sale,
+                args::SetWalletScreening { enabled: !off },
+            )])
+        }
+        LegacySaleCommand::Pause(SaleArg { sale }) => {
+            config.process(&[instructions::toggle_pause(&config.authority()?, &sale)])
+        }
+        LegacySaleCommand::Cancel {
+            sale,
+            token_account: seller_token_account,
+        } => {
+            let seller = config.authority()?;
+            let seller_token_account = match seller_token_account {
+                Some(account) => account,
+                None => {
+                    let token_sale: multi_presale::TokenSale = config.fetch(&sale)?;
+                    token_account(&seller, &token_sale.token_mint)
+                }
+            };
+            config.process(&[instructions::cancel_sale(
+                &seller,
+                &sale,
+                &seller_token_account,
+            )])
+        }
+        LegacySaleCommand::Update(update) => config.process(&[instructions::update_sale_params(
+            &config.authority()?,
+            &update.sale,
+            args::UpdateSaleParams {
+                new_price_per_token: update.price,
+                new_sale_start_time: update.start,
+                new_sale_end_time: update.end,
+                new_max_tokens_per_buyer: update.max_per_buyer,
+            },
+        )]),
+        LegacySaleCommand::Show { sale } => {
+            display::print_legacy_token_sale(&sale, &config.fetch(&sale)?);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Events emitted by the `escrow` program.
+
+pub use escrow::{
+    AuctionBidSettled, AuctionBidSubmitted, AuctionCleared, BuyerAccountClosed, DenyListConfigured,
+    LotteryDrawn, LotteryTicketClaimed, LotteryTicketRegistered, OverflowClaimed,
+    OverflowDeposited, PriceChangeScheduled, PurchaseHookSet, PurchasedOnBehalf, ReceiptsRedeemed,
+    SaleCancelled, SaleClosedEarly, SaleExtended, SaleInitialized, SaleSoftCloseExtended,
+    SealedBidCommitted, SealedBidRevealed, TokensPurchased, WalletAllowed, WalletDenied,
+    WalletScreeningSet,
+};
+
+use crate::events::decode_as;
+
+/// A decoded `escrow` event
+pub enum Event {
+    SaleInitialized(SaleInitialized),
+    TokensPurchased(TokensPurchased),
+    SaleCancelled(SaleCancelled),
+    PriceChangeScheduled(PriceChangeScheduled),
+    SaleExtended(SaleExtended),
+    SaleSoftCloseExtended(SaleSoftCloseExtended),
+    SaleClosedEarly(SaleClosedEarly),
+    LotteryTicketRegistered(LotteryTicketRegistered),
+    LotteryDrawn(LotteryDrawn),
+    LotteryTicketClaimed(LotteryTicketClaimed),
+    OverflowDeposited(OverflowDeposited),
+    OverflowClaimed(OverflowClaimed),
+    AuctionBidSubmitted(AuctionBidSubmitted),
+    AuctionCleared(AuctionCleared),
+    AuctionBidSettled(AuctionBidSettled),
+    SealedBidCommitted(SealedBidCommitted),
+    SealedBidRevealed(SealedBidRevealed),
+    PurchasedOnBehalf(PurchasedOnBehalf),
+    BuyerAccountClosed(BuyerAccountClosed),
+    PurchaseHookSet(PurchaseHookSet),
+    ReceiptsRedeemed(ReceiptsRedeemed),
+    WalletScreeningSet(WalletScreeningSet),
+    DenyListConfigured(DenyListConfigured),
+    WalletDenied(WalletDenied),
+    WalletAllowed(WalletAllowed),
+}
+
+impl Event {
+    /// Decode an event payload (discriminator followed by the Borsh-encoded fields)
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        decode_as(data)
+            .map(Self::SaleInitialized)
+            .or_else(|| decode_as(data).map(Self::TokensPurchased))
+            .or_else(|| decode_as(data).map(Self::SaleCancelled))
+            .or_else(|| decode_as(data).map(Self::PriceChangeScheduled))
+            .or_else(|| decode_as(data).map(Self::SaleExtended))
+            .or_else(|| decode_as(data).map(Self::SaleSoftCloseExtended))
+            .or_else(|| decode_as(data).map(Self::SaleClosedEarly))
+            .or_else(|| decode_as(data).map(Self::LotteryTicketRegistered))
+            .or_else(|| decode_as(data).map(Self::LotteryDrawn))
+            .or_else(|| decode_as(data).map(Self::LotteryTicketClaimed))
+            .or_else(|| decode_as(data).map(Self::OverflowDeposited))
+            .or_else(|| decode_as(data).map(Self::OverflowClaimed))
+            .or_else(|| decode_as(data).map(Self::AuctionBidSubmitted))
+            .or_else(|| decode_as(data).map(Self::AuctionCleared))
+            .or_else(|| decode_as(data).map(Self::AuctionBidSettled))
+            .or_else(|| decode_as(data).map(Self::SealedBidCommitted))
+            .or_else(|| decode_as(data).map(Self::SealedBidRevealed))
+            .or_else(|| decode_as(data).map(Self::PurchasedOnBehalf))
+            .or_else(|| decode_as(data).map(Self::BuyerAccountClosed))
+            .or_else(|| decode_as(data).map(Self::PurchaseHookSet))
+            .or_else(|| decode_as(data).map(Self::ReceiptsRedeemed))
+            .or_else(|| decode_as(data).map(Self::WalletScreeningSet))
+            .or_else(|| decode_as(data).map(Self::DenyListConfigured))
+            .or_else(|| decode_as(data).map(Self::WalletDenied))
+            .or_else(|| decode_as(data).map(Self::WalletAllowed))
+    }
+}
@@ -0,0 +1,249 @@
+//! `getProgramAccounts` filters for `escrow` accounts.
+//!
+//! Each helper returns the discriminator filter for the account type plus a memcmp on
+//! one field, ready to pass as `RpcProgramAccountsConfig::filters`.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::Discriminator;
+
+use super::{AuctionBid, BuyerAccount, Offer, ServiceEscrow, TokenSale};
+use crate::{discriminator_filter, pubkey_filter, RpcFilterType, DISCRIMINATOR_LEN};
+
+/// Byte offsets of the filterable fields, including the 8-byte discriminator
+pub mod offsets {
+    use super::DISCRIMINATOR_LEN;
+
+    pub const TOKEN_SALE_SELLER: usize = DISCRIMINATOR_LEN;
+    pub const TOKEN_SALE_TOKEN_MINT: usize = TOKEN_SALE_SELLER + 32;
+    pub const TOKEN_SALE_PAYMENT_MINT: usize = TOKEN_SALE_TOKEN_MINT + 32;
+    pub const TOKEN_SALE_PLATFORM_FEE_RECIPIENT: usize = TOKEN_SALE_PAYMENT_MINT + 32;
+
+    pub const BUYER_ACCOUNT_BUYER: usize = DISCRIMINATOR_LEN;
+    pub const BUYER_ACCOUNT_TOKEN_SALE: usize = BUYER_ACCOUNT_BUYER + 32;
+
+    pub const AUCTION_BID_BIDDER: usize = DISCRIMINATOR_LEN;
+    pub const AUCTION_BID_TOKEN_SALE: usize = AUCTION_BID_BIDDER + 32;
+
+    pub const OFFER_MAKER: usize = DISCRIMINATOR_LEN;
+    pub const OFFER_TOKEN_MINT_A: usize = OFFER_MAKER + 32 + 8;
+    pub const OFFER_TOKEN_MINT_B: usize = OFFER_TOKEN_MINT_A + 32;
+
+    pub const SERVICE_ESCROW_PAYER: usize = DISCRIMINATOR_LEN;
+    pub const SERVICE_ESCROW_PAYEE: usize = SERVICE_ESCROW_PAYER + 32;
+    pub const SERVICE_ESCROW_ARBITER: usize = SERVICE_ESCROW_PAYEE + 32;
+}
+
+/// All token sales
+pub fn token_sales() -> Vec<RpcFilterType> {
+    vec![discriminator_filter(TokenSale::DISCRIMINATOR)]
+}
+
+/// Token sales created by `seller`
+pub fn token_sales_by_seller(seller: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(TokenSale::DISCRIMINATOR),
+        pubkey_filter(offsets::TOKEN_SALE_SELLER, seller),
+    ]
+}
+
+/// Token sales selling `token_mint`
+pub fn token_sales_by_token_mint(token_mint: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(TokenSale::DISCRIMINATOR),
+        pubkey_filter(offsets::TOKEN_SALE_TOKEN_MINT, token_mint),
+    ]
+}
+
+/// Token sales accepting `payment_mint`
+pub fn token_sales_by_payment_mint(payment_mint: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(TokenSale::DISCRIMINATOR),
+        pubkey_filter(offsets::TOKEN_SALE_PAYMENT_MINT, payment_mint),
+    ]
+}
+
+/// Token sales paying fees to `recipient`
+pub fn token_sales_by_fee_recipient(recipient: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(TokenSale::DISCRIMINATOR),
+        pubkey_filter(offsets::TOKEN_SALE_PLATFORM_FEE_RECIPIENT, recipient),
+    ]
+}
+
+/// Buyer trackers owned by `buyer`, across all sales
+pub fn buyer_accounts_by_buyer(buyer: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(BuyerAccount::DISCRIMINATOR),
+        pubkey_filter(offsets::BUYER_ACCOUNT_BUYER, buyer),
+    ]
+}
+
+/// Buyer trackers of one sale
+pub fn buyer_accounts_by_sale(token_sale: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(BuyerAccount::DISCRIMINATOR),
+        pubkey_filter(offsets::BUYER_ACCOUNT_TOKEN_SALE, token_sale),
+    ]
+}
+
+/// Auction bids placed in one sale, settled or not
+pub fn auction_bids_by_sale(token_sale: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(AuctionBid::DISCRIMINATOR),
+        pubkey_filter(offsets::AUCTION_BID_TOKEN_SALE, token_sale),
+    ]
+}
+
+/// Open swap offers made by `maker`
+pub fn offers_by_maker(maker: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(Offer::DISCRIMINATOR),
+        pubkey_filter(offsets::OFFER_MAKER, maker),
+    ]
+}
+
+/// Open swap offers exchanging `token_mint_a` for `token_mint_b`
+pub fn offers_by_pair(token_mint_a: &Pubkey, token_mint_b: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(Offer::DISCRIMINATOR),
+        pubkey_filter(offsets::OFFER_TOKEN_MINT_A, token_mint_a),
+        pubkey_filter(offsets::OFFER_TOKEN_MINT_B, token_mint_b),
+    ]
+}
+
+/// Open service escrows funded by `payer`
+pub fn service_escrows_by_payer(payer: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(ServiceEscrow::DISCRIMINATOR),
+        pubkey_filter(offsets::SERVICE_ESCROW_PAYER, payer),
+    ]
+}
+
+/// Open service escrows paying `payee`
+pub fn service_escrows_by_payee(payee: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(ServiceEscrow::DISCRIMINATOR),
+        pubkey_filter(offsets::SERVICE_ESCROW_PAYEE, payee),
+    ]
+}
+
+/// Open service escrows arbitrated by `arbiter`
+pub fn service_escrows_by_arbiter(arbiter: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(ServiceEscrow::DISCRIMINATOR),
+        pubkey_filter(offsets::SERVICE_ESCROW_ARBITER, arbiter),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::{AccountSerialize, ZeroCopy};
+
+    fn matches(filters: &[RpcFilterType], data: &[u8]) -> bool {
+        filters.iter().all(|filter| match filter {
+            RpcFilterType::Memcmp(memcmp) => memcmp.bytes_match(data),
+            _ => false,
+        })
+    }
+
+    fn serialize<T: AccountSerialize>(account: &T) -> Vec<u8> {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    fn zero_copy_bytes<T: ZeroCopy>(account: &T) -> Vec<u8> {
+        [T::DISCRIMINATOR, bytemuck::bytes_of(account)].concat()
+    }
+
+    #[test]
+    fn token_sale_offsets_match_layout() {
+        let mut sale: TokenSale = bytemuck::Zeroable::zeroed();
+        sale.seller = Pubkey::new_unique();
+        sale.token_mint = Pubkey::new_unique();
+        sale.payment_mint = Pubkey::new_unique();
+        sale.platform_fee_recipient = Pubkey::new_unique();
+        sale.milestone_approver = Pubkey::new_unique();
+        sale.price_per_token = u64::MAX;
+        let data = zero_copy_bytes(&sale);
+
+        assert!(matches(&token_sales_by_seller(&sale.seller), &data));
+        assert!(matches(&token_sales_by_token_mint(&sale.token_mint), &data));
+        assert!(matches(
+            &token_sales_by_payment_mint(&sale.payment_mint),
+            &data
+        ));
+        assert!(matches(
+            &token_sales_by_fee_recipient(&sale.platform_fee_recipient),
+            &data
+        ));
+        assert!(!matches(&token_sales_by_seller(&sale.token_mint), &data));
+    }
+
+    #[test]
+    fn buyer_account_offsets_match_layout() {
+        let mut buyer_account: BuyerAccount = bytemuck::Zeroable::zeroed();
+        buyer_account.buyer = Pubkey::new_unique();
+        buyer_account.token_sale = Pubkey::new_unique();
+        let data = zero_copy_bytes(&buyer_account);
+
+        assert!(matches(
+            &buyer_accounts_by_buyer(&buyer_account.buyer),
+            &data
+        ));
+        assert!(matches(
+            &buyer_accounts_by_sale(&buyer_account.token_sale),
+            &data
+        ));
+        assert!(!matches(&token_sales(), &data));
+    }
+
+    #[test]
+    fn auction_bid_offsets_match_layout() {
+        let mut bid: AuctionBid = bytemuck::Zeroable::zeroed();
+        bid.bidder = Pubkey::new_unique();
+        bid.token_sale = Pubkey::new_unique();
+        let data = zero_copy_bytes(&bid);
+
+        assert!(matches(&auction_bids_by_sale(&bid.token_sale), &data));
+        assert!(!matches(&auction_bids_by_sale(&bid.bidder), &data));
+    }
+
+    #[test]
+    fn offer_and_service_escrow_offsets_match_layout() {
+        let offer = Offer {
+            maker: Pubkey::new_unique(),
+            offer_id: u64::MAX,
+            token_mint_a: Pubkey::new_unique(),
+            token_mint_b: Pubkey::new_unique(),
+            ..Default::default()
+        };
+        let data = serialize(&offer);
+        assert!(matches(&offers_by_maker(&offer.maker), &data));
+        assert!(matches(
+            &offers_by_pair(&offer.token_mint_a, &offer.token_mint_b),
+            &data
+        ));
+
+        let service_escrow = ServiceEscrow {
+            payer: Pubkey::new_unique(),
+            payee: Pubkey::new_unique(),
+            arbiter: Pubkey::new_unique(),
+            ..Default::default()
+        };
+        let data = serialize(&service_escrow);
+        assert!(matches(
+            &service_escrows_by_payer(&service_escrow.payer),
+            &data
+        ));
+        assert!(matches(
+            &service_escrows_by_payee(&service_escrow.payee),
+            &data
+        ));
+        assert!(matches(
+            &service_escrows_by_arbiter(&service_escrow.arbiter),
+            &data
+        ));
+    }
+}
@@ -0,0 +1,1254 @@
+//! Instruction builders for every `escrow` handler.
+//!
+//! Builders take the accounts a caller has to choose (signers, token accounts) and derive
+//! every program address themselves. Instruction arguments are passed as the Anchor
+//! generated structs from [`args`](super::args), so they cannot drift from the program.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::sysvar;
+use anchor_lang::system_program;
+use anchor_spl::token::ID as TOKEN_PROGRAM_ID;
+use escrow::accounts;
+
+use super::{args, pda, PROGRAM_ID};
+use crate::build_instruction;
+
+/// `initialize_sale`: create a sale and move `total_tokens` into its vault
+pub fn initialize_sale(
+    seller: &Pubkey,
+    token_mint: &Pubkey,
+    payment_mint: &Pubkey,
+    seller_token_account: &Pubkey,
+    args: args::InitializeSale,
+) -> Instruction {
+    let (token_sale, _) = pda::token_sale(seller, token_mint);
+    build_instruction(
+        PROGRAM_ID,
+        accounts::InitializeSale {
+            seller: *seller,
+            token_sale,
+            token_mint: *token_mint,
+            payment_mint: *payment_mint,
+            seller_token_account: *seller_token_account,
+            token_vault: pda::token_vault(&token_sale).0,
+            token_program: TOKEN_PROGRAM_ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        args,
+    )
+}
+
+/// `create_buyer_account`: create the buyer's tracker for a sale, `rent_payer` paying
+/// the rent and getting it back on close
+pub fn create_buyer_account(
+    buyer: &Pubkey,
+    rent_payer: &Pubkey,
+    token_sale: &Pubkey,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::CreateBuyerAccount {
+            buyer: *buyer,
+            rent_payer: *rent_payer,
+            token_sale: *token_sale,
+            buyer_account: pda::buyer(buyer, token_sale).0,
+            system_program: system_program::ID,
+        },
+        args::CreateBuyerAccount {},
+    )
+}
+
+/// `create_buyer_account_for`: create `recipient`'s tracker for a sale, `payer` paying the rent
+pub fn create_buyer_account_for(
+    payer: &Pubkey,
+    recipient: &Pubkey,
+    token_sale: &Pubkey,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::CreateBuyerAccountFor {
+            payer: *payer,
+            recipient: *recipient,
+            token_sale: *token_sale,
+            buyer_account: pda::buyer(recipient, token_sale).0,
+            system_program: system_program::ID,
+        },
+        args::CreateBuyerAccountFor {},
+    )
+}
+
+/// `close_buyer_account`: close the buyer's tracker once the sale is over, refunding
+/// the rent to `rent_payer`, the account that paid it
+pub fn close_buyer_account(
+    buyer: &Pubkey,
+    rent_payer: &Pubkey,
+    token_sale: &Pubkey,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::CloseBuyerAccount {
+            buyer: *buyer,
+            rent_payer: *rent_payer,
+            token_sale: *token_sale,
+            buyer_account: pda::buyer(buyer, token_sale).0,
+        },
+        args::CloseBuyerAccount {},
+    )
+}
+
+/// `buy_tokens`: purchase from a sale.
+///
+/// `escrows_proceeds` must be true for sales with milestones configured, so the
+/// proceeds vault is passed instead of being left out. Likewise `locks_tokens` for sales
+/// with a token lock, which pay out receipts: `buyer_token_account` then holds the
+/// receipt mint.
+#[allow(clippy::too_many_arguments)]
+pub fn buy_tokens(
+    buyer: &Pubkey,
+    token_sale: &Pubkey,
+    buyer_payment_account: &Pubkey,
+    seller_payment_account: &Pubkey,
+    platform_fee_account: &Pubkey,
+    buyer_token_account: &Pubkey,
+    escrows_proceeds: bool,
+    locks_tokens: bool,
+    args: args::BuyTokens,
+) -> Instruction {
+    let deny_list = pda::deny_list().0;
+    build_instruction(
+        PROGRAM_ID,
+        accounts::BuyTokens {
+            buyer: *buyer,
+            token_sale: *token_sale,
+            buyer_account: pda::buyer(buyer, token_sale).0,
+            buyer_payment_account: *buyer_payment_account,
+            seller_payment_account: *seller_payment_account,
+            platform_fee_account: *platform_fee_account,
+            buyer_token_account: *buyer_token_account,
+            token_vault: pda::token_vault(token_sale).0,
+            proceeds_vault: escrows_proceeds.then(|| pda::proceeds_vault(token_sale).0),
+            receipt_mint: locks_tokens.then(|| pda::receipt_mint(token_sale).0),
+            deny_list,
+            denied_buyer: pda::denied_wallet(&deny_list, buyer).0,
+            token_program: TOKEN_PROGRAM_ID,
+        },
+        args,
+    )
+}
+
+/// `buy_tokens_for`: purchase from a sale for `recipient`, paid from `payer_payment_account`.
+///
+/// The purchase counts against the recipient's tracker and restarts the payer's cooldown,
+/// so both trackers have to exist. Both the payer and the recipient are screened; see
+/// [`buy_tokens`] for `escrows_proceeds` and `locks_tokens`.
+#[allow(clippy::too_many_arguments)]
+pub fn buy_tokens_for(
+    payer: &Pubkey,
+    recipient: &Pubkey,
+    token_sale: &Pubkey,
+    payer_payment_account: &Pubkey,
+    seller_payment_account: &Pubkey,
+    platform_fee_account: &Pubkey,
+    recipient_token_account: &Pubkey,
+    escrows_proceeds: bool,
+    locks_tokens: bool,
+    args: args::BuyTokensFor,
+) -> Instruction {
+    let deny_list = pda::deny_list().0;
+    build_instruction(
+        PROGRAM_ID,
+        accounts::BuyTokensFor {
+            payer: *payer,
+            recipient: *recipient,
+            token_sale: *token_sale,
+            buyer_account: pda::buyer(recipient, token_sale).0,
+            payer_account: pda::buyer(payer, token_sale).0,
+            payer_payment_account: *payer_payment_account,
+            seller_payment_account: *seller_payment_account,
+            platform_fee_account: *platform_fee_account,
+            recipient_token_account: *recipient_token_account,
+            token_vault: pda::token_vault(token_sale).0,
+            proceeds_vault: escrows_proceeds.then(|| pda::proceeds_vault(token_sale).0),
+            receipt_mint: locks_tokens.then(|| pda::receipt_mint(token_sale).0),
+            deny_list,
+            denied_payer: pda::denied_wallet(&deny_list, payer).0,
+            denied_recipient: pda::denied_wallet(&deny_list, recipient).0,
+            token_program: TOKEN_PROGRAM_ID,
+        },
+        args,
+    )
+}
+
+/// `quote_purchase`: price a purchase as `buy_tokens` would, returned as return data.
+///
+/// Pass the buyer once they have a tracker account, so their per-buyer limit applies.
+pub fn quote_purchase(
+    token_sale: &Pubkey,
+    buyer: Option<&Pubkey>,
+    args: args::QuotePurchase,
+) -> Instruction {
+    build_instruction(PROGRAM_ID, quote_accounts(token_sale, buyer), args)
+}
+
+/// `quote_exact_in`: the largest purchase a payment covers, returned as return data
+pub fn quote_exact_in(
+    token_sale: &Pubkey,
+    buyer: Option<&Pubkey>,
+    args: args::QuoteExactIn,
+) -> Instruction {
+    build_instruction(PROGRAM_ID, quote_accounts(token_sale, buyer), args)
+}
+
+fn quote_accounts(token_sale: &Pubkey, buyer: Option<&Pubkey>) -> accounts::QuotePurchase {
+    accounts::QuotePurchase {
+        token_sale: *token_sale,
+        buyer_account: buyer.map(|buyer| pda::buyer(buyer, token_sale).0),
+    }
+}
+
+/// `cancel_sale`: return unsold tokens to the seller.
+///
+/// Set `is_auction` for auction sales, whose auction has to be cleared once they end.
+pub fn cancel_sale(
+    seller: &Pubkey,
+    token_sale: &Pubkey,
+    seller_token_account: &Pubkey,
+    is_auction: bool,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::CancelSale {
+            seller: *seller,
+            token_sale: *token_sale,
+            seller_token_account: *seller_token_account,
+            token_vault: pda::token_vault(token_sale).0,
+            auction: is_auction.then(|| pda::auction(token_sale).0),
+            token_program: TOKEN_PROGRAM_ID,
+        },
+        args::CancelSale {},
+    )
+}
+
+/// `toggle_pause`: pause or unpause a sale
+pub fn toggle_pause(seller: &Pubkey, token_sale: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::TogglePause {
+            seller: *seller,
+            token_sale: *token_sale,
+        },
+        args::TogglePause {},
+    )
+}
+
+/// `update_sale_params`: change price, window, buyer limit or duration bounds before the sale starts, or
+/// announce a bounded price change while it is live
+pub fn update_sale_params(
+    seller: &Pubkey,
+    token_sale: &Pubkey,
+    args: args::UpdateSaleParams,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::UpdateSaleParams {
+            seller: *seller,
+            token_sale: *token_sale,
+        },
+        args,
+    )
+}
+
+/// `extend_sale`: move a sale's end time later, within the maximum sale duration
+pub fn extend_sale(seller: &Pubkey, token_sale: &Pubkey, args: args::ExtendSale) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::ChangeSaleWindow {
+            seller: *seller,
+            token_sale: *token_sale,
+        },
+        args,
+    )
+}
+
+/// `close_sale_early`: end a sale that has run the minimum duration or sold out
+pub fn close_sale_early(seller: &Pubkey, token_sale: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::ChangeSaleWindow {
+            seller: *seller,
+            token_sale: *token_sale,
+        },
+        args::CloseSaleEarly {},
+    )
+}
+
+/// `configure_soft_close`: extend the sale on late purchases, up to a ceiling
+pub fn configure_soft_close(
+    seller: &Pubkey,
+    token_sale: &Pubkey,
+    args: args::ConfigureSoftClose,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::ChangeSaleWindow {
+            seller: *seller,
+            token_sale: *token_sale,
+        },
+        args,
+    )
+}
+
+/// `set_purchase_throttles`: configure anti-bot throttles
+pub fn set_purchase_throttles(
+    seller: &Pubkey,
+    token_sale: &Pubkey,
+    args: args::SetPurchaseThrottles,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::SetPurchaseThrottles {
+            seller: *seller,
+            token_sale: *token_sale,
+        },
+        args,
+    )
+}
+
+/// `set_purchase_hook`: have every purchase call `hook_program`, which cannot be changed
+/// afterwards
+pub fn set_purchase_hook(
+    seller: &Pubkey,
+    token_sale: &Pubkey,
+    hook_program: &Pubkey,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::SetPurchaseHook {
+            seller: *seller,
+            token_sale: *token_sale,
+            hook_program: *hook_program,
+        },
+        args::SetPurchaseHook {},
+    )
+}
+
+/// `set_wallet_screening`: screen the sale's buyers against the deny list, or stop
+/// screening unless the list is required
+pub fn set_wallet_screening(
+    seller: &Pubkey,
+    token_sale: &Pubkey,
+    args: args::SetWalletScreening,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::SetWalletScreening {
+            seller: *seller,
+            token_sale: *token_sale,
+            deny_list: pda::deny_list().0,
+        },
+        args,
+    )
+}
+
+/// Append a sale's purchase hook to a [`buy_tokens`] or [`buy_tokens_for`] instruction:
+/// the hook program, then the accounts it expects. Signer flags in `hook_accounts` are
+/// not passed on to the hook.
+pub fn with_purchase_hook(
+    mut instruction: Instruction,
+    hook_program: &Pubkey,
+    hook_accounts: &[AccountMeta],
+) -> Instruction {
+    instruction
+        .accounts
+        .push(AccountMeta::new_readonly(*hook_program, false));
+    instruction.accounts.extend_from_slice(hook_accounts);
+    instruction
+}
+
+/// `set_price_tiers`: set volume price breakpoints before the sale starts
+pub fn set_price_tiers(
+    seller: &Pubkey,
+    token_sale: &Pubkey,
+    args: args::SetPriceTiers,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::SetPriceTiers {
+            seller: *seller,
+            token_sale: *token_sale,
+        },
+        args,
+    )
+}
+
+/// `configure_milestones`: escrow proceeds and release them by milestone
+pub fn configure_milestones(
+    seller: &Pubkey,
+    token_sale: &Pubkey,
+    payment_mint: &Pubkey,
+    args: args::ConfigureMilestones,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::ConfigureMilestones {
+            seller: *seller,
+            token_sale: *token_sale,
+            payment_mint: *payment_mint,
+            proceeds_vault: pda::proceeds_vault(token_sale).0,
+            token_program: TOKEN_PROGRAM_ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        args,
+    )
+}
+
+/// `approve_milestone`: unlock the current milestone as the designated approver
+pub fn approve_milestone(approver: &Pubkey, token_sale: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::ApproveMilestone {
+            approver: *approver,
+            token_sale: *token_sale,
+        },
+        args::ApproveMilestone {},
+    )
+}
+
+/// `vote_milestone`: vote to unlock the current milestone as a buyer
+pub fn vote_milestone(buyer: &Pubkey, token_sale: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::VoteMilestone {
+            buyer: *buyer,
+            token_sale: *token_sale,
+            buyer_account: pda::buyer(buyer, token_sale).0,
+        },
+        args::VoteMilestone {},
+    )
+}
+
+/// `release_milestone`: pay the seller an approved tranche
+pub fn release_milestone(token_sale: &Pubkey, seller_payment_account: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::ReleaseMilestone {
+            token_sale: *token_sale,
+            seller_payment_account: *seller_payment_account,
+            proceeds_vault: pda::proceeds_vault(token_sale).0,
+            token_program: TOKEN_PROGRAM_ID,
+        },
+        args::ReleaseMilestone {},
+    )
+}
+
+/// `claim_milestone_refund`: claim a refund after a missed milestone deadline
+pub fn claim_milestone_refund(
+    buyer: &Pubkey,
+    token_sale: &Pubkey,
+    buyer_payment_account: &Pubkey,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::ClaimMilestoneRefund {
+            buyer: *buyer,
+            token_sale: *token_sale,
+            buyer_account: pda::buyer(buyer, token_sale).0,
+            buyer_payment_account: *buyer_payment_account,
+            proceeds_vault: pda::proceeds_vault(token_sale).0,
+            token_program: TOKEN_PROGRAM_ID,
+        },
+        args::ClaimMilestoneRefund {},
+    )
+}
+
+/// `configure_overflow`: allocate the sale pro rata to deposits
+pub fn configure_overflow(
+    seller: &Pubkey,
+    token_sale: &Pubkey,
+    payment_mint: &Pubkey,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::ConfigureOverflow {
+            seller: *seller,
+            token_sale: *token_sale,
+            payment_mint: *payment_mint,
+            deposit_vault: pda::deposit_vault(token_sale).0,
+            token_program: TOKEN_PROGRAM_ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        args::ConfigureOverflow {},
+    )
+}
+
+/// `deposit_overflow`: deposit payment into an overflow sale
+pub fn deposit_overflow(
+    buyer: &Pubkey,
+    token_sale: &Pubkey,
+    buyer_payment_account: &Pubkey,
+    args: args::DepositOverflow,
+) -> Instruction {
+    let deny_list = pda::deny_list().0;
+    build_instruction(
+        PROGRAM_ID,
+        accounts::DepositOverflow {
+            buyer: *buyer,
+            token_sale: *token_sale,
+            buyer_account: pda::buyer(buyer, token_sale).0,
+            buyer_payment_account: *buyer_payment_account,
+            deposit_vault: pda::deposit_vault(token_sale).0,
+            deny_list,
+            denied_buyer: pda::denied_wallet(&deny_list, buyer).0,
+            token_program: TOKEN_PROGRAM_ID,
+        },
+        args,
+    )
+}
+
+/// `claim_overflow`: receive an overflow allocation and the unspent deposit
+pub fn claim_overflow(
+    buyer: &Pubkey,
+    token_sale: &Pubkey,
+    buyer_payment_account: &Pubkey,
+    buyer_token_account: &Pubkey,
+    seller_payment_account: &Pubkey,
+    platform_fee_account: &Pubkey,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::ClaimOverflow {
+            buyer: *buyer,
+            token_sale: *token_sale,
+            buyer_account: pda::buyer(buyer, token_sale).0,
+            deposit_vault: pda::deposit_vault(token_sale).0,
+            token_vault: pda::token_vault(token_sale).0,
+            buyer_payment_account: *buyer_payment_account,
+            buyer_token_account: *buyer_token_account,
+            seller_payment_account: *seller_payment_account,
+            platform_fee_account: *platform_fee_account,
+            token_program: TOKEN_PROGRAM_ID,
+        },
+        args::ClaimOverflow {},
+    )
+}
+
+/// `configure_token_lock`: hold purchased tokens until `unlock_time`, paying out receipts
+pub fn configure_token_lock(
+    seller: &Pubkey,
+    token_sale: &Pubkey,
+    token_mint: &Pubkey,
+    args: args::ConfigureTokenLock,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::ConfigureTokenLock {
+            seller: *seller,
+            token_sale: *token_sale,
+            token_mint: *token_mint,
+            receipt_mint: pda::receipt_mint(token_sale).0,
+            token_program: TOKEN_PROGRAM_ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        args,
+    )
+}
+
+/// `redeem_receipts`: burn the holder's receipts for the tokens they stand for
+pub fn redeem_receipts(
+    holder: &Pubkey,
+    token_sale: &Pubkey,
+    holder_receipt_account: &Pubkey,
+    holder_token_account: &Pubkey,
+    args: args::RedeemReceipts,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::RedeemReceipts {
+            holder: *holder,
+            token_sale: *token_sale,
+            receipt_mint: pda::receipt_mint(token_sale).0,
+            holder_receipt_account: *holder_receipt_account,
+            holder_token_account: *holder_token_account,
+            token_vault: pda::token_vault(token_sale).0,
+            token_program: TOKEN_PROGRAM_ID,
+        },
+        args,
+    )
+}
+
+/// `configure_lottery`: allocate the sale by lottery, with a committed seed or a slot hash draw
+pub fn configure_lottery(
+    seller: &Pubkey,
+    token_sale: &Pubkey,
+    payment_mint: &Pubkey,
+    args: args::ConfigureLottery,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::ConfigureLottery {
+            seller: *seller,
+            token_sale: *token_sale,
+            lottery: pda::lottery(token_sale).0,
+            payment_mint: *payment_mint,
+            lottery_vault: pda::lottery_vault(token_sale).0,
+            token_program: TOKEN_PROGRAM_ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        args,
+    )
+}
+
+/// `register_lottery_ticket`: deposit the price of one ticket
+pub fn register_lottery_ticket(
+    buyer: &Pubkey,
+    rent_payer: &Pubkey,
+    token_sale: &Pubkey,
+    buyer_payment_account: &Pubkey,
+) -> Instruction {
+    let deny_list = pda::deny_list().0;
+    build_instruction(
+        PROGRAM_ID,
+        accounts::RegisterLotteryTicket {
+            buyer: *buyer,
+            rent_payer: *rent_payer,
+            token_sale: *token_sale,
+            lottery: pda::lottery(token_sale).0,
+            ticket: pda::lottery_ticket(buyer, token_sale).0,
+            buyer_payment_account: *buyer_payment_account,
+            lottery_vault: pda::lottery_vault(token_sale).0,
+            deny_list,
+            denied_buyer: pda::denied_wallet(&deny_list, buyer).0,
+            token_program: TOKEN_PROGRAM_ID,
+            system_program: system_program::ID,
+        },
+        args::RegisterLotteryTicket {},
+    )
+}
+
+/// `reveal_lottery_seed`: reveal the committed seed and draw the winners
+pub fn reveal_lottery_seed(
+    revealer: &Pubkey,
+    token_sale: &Pubkey,
+    args: args::RevealLotterySeed,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::RevealLotterySeed {
+            revealer: *revealer,
+            token_sale: *token_sale,
+            lottery: pda::lottery(token_sale).0,
+        },
+        args,
+    )
+}
+
+/// `request_lottery_draw`: pick the future slot whose hash seeds the draw
+pub fn request_lottery_draw(token_sale: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        draw_accounts(token_sale),
+        args::RequestLotteryDraw {},
+    )
+}
+
+/// `draw_lottery`: draw the winners from the requested slot's hash
+pub fn draw_lottery(token_sale: &Pubkey) -> Instruction {
+    build_instruction(PROGRAM_ID, draw_accounts(token_sale), args::DrawLottery {})
+}
+
+fn draw_accounts(token_sale: &Pubkey) -> accounts::DrawLottery {
+    accounts::DrawLottery {
+        token_sale: *token_sale,
+        lottery: pda::lottery(token_sale).0,
+        slot_hashes: sysvar::slot_hashes::ID,
+    }
+}
+
+/// `claim_lottery_ticket`: receive a winning ticket's tokens or a losing ticket's refund
+pub fn claim_lottery_ticket(
+    buyer: &Pubkey,
+    token_sale: &Pubkey,
+    buyer_payment_account: &Pubkey,
+    buyer_token_account: &Pubkey,
+    seller_payment_account: &Pubkey,
+    platform_fee_account: &Pubkey,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::ClaimLotteryTicket {
+            buyer: *buyer,
+            token_sale: *token_sale,
+            lottery: pda::lottery(token_sale).0,
+            ticket: pda::lottery_ticket(buyer, token_sale).0,
+            lottery_vault: pda::lottery_vault(token_sale).0,
+            token_vault: pda::token_vault(token_sale).0,
+            buyer_payment_account: *buyer_payment_account,
+            buyer_token_account: *buyer_token_account,
+            seller_payment_account: *seller_payment_account,
+            platform_fee_account: *platform_fee_account,
+            token_program: TOKEN_PROGRAM_ID,
+        },
+        args::ClaimLotteryTicket {},
+    )
+}
+
+/// `close_lottery_ticket`: close the buyer's claimed ticket, refunding the rent to
+/// `rent_payer`, the account that paid it
+pub fn close_lottery_ticket(
+    buyer: &Pubkey,
+    rent_payer: &Pubkey,
+    token_sale: &Pubkey,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::CloseLotteryTicket {
+            buyer: *buyer,
+            rent_payer: *rent_payer,
+            token_sale: *token_sale,
+            ticket: pda::lottery_ticket(buyer, token_sale).0,
+        },
+        args::CloseLotteryTicket {},
+    )
+}
+
+/// `configure_auction`: sell the sale by uniform-price batch auction
+pub fn configure_auction(
+    seller: &Pubkey,
+    token_sale: &Pubkey,
+    payment_mint: &Pubkey,
+    args: args::ConfigureAuction,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::ConfigureAuction {
+            seller: *seller,
+            token_sale: *token_sale,
+            auction: pda::auction(token_sale).0,
+            payment_mint: *payment_mint,
+            bid_vault: pda::bid_vault(token_sale).0,
+            token_program: TOKEN_PROGRAM_ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        args,
+    )
+}
+
+/// `submit_bid`: bid in a sale's auction, depositing `quantity * max_price`
+pub fn submit_bid(
+    bidder: &Pubkey,
+    rent_payer: &Pubkey,
+    token_sale: &Pubkey,
+    bidder_payment_account: &Pubkey,
+    args: args::SubmitBid,
+) -> Instruction {
+    let deny_list = pda::deny_list().0;
+    build_instruction(
+        PROGRAM_ID,
+        accounts::SubmitBid {
+            bidder: *bidder,
+            rent_payer: *rent_payer,
+            token_sale: *token_sale,
+            auction: pda::auction(token_sale).0,
+            bid: pda::auction_bid(token_sale, bidder, args.bid_id).0,
+            bidder_payment_account: *bidder_payment_account,
+            bid_vault: pda::bid_vault(token_sale).0,
+            deny_list,
+            denied_bidder: pda::denied_wallet(&deny_list, bidder).0,
+            token_program: TOKEN_PROGRAM_ID,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// `configure_sealed_bids`: have bidders commit hashes and reveal after the sale
+pub fn configure_sealed_bids(
+    seller: &Pubkey,
+    token_sale: &Pubkey,
+    args: args::ConfigureSealedBids,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::ConfigureSealedBids {
+            seller: *seller,
+            token_sale: *token_sale,
+            auction: pda::auction(token_sale).0,
+        },
+        args,
+    )
+}
+
+/// `commit_sealed_bid`: commit a bid's hash, depositing the auction's collateral.
+/// [`AuctionBid::commitment_of`](super::AuctionBid::commitment_of) computes the hash.
+pub fn commit_sealed_bid(
+    bidder: &Pubkey,
+    rent_payer: &Pubkey,
+    token_sale: &Pubkey,
+    bidder_payment_account: &Pubkey,
+    args: args::CommitSealedBid,
+) -> Instruction {
+    let deny_list = pda::deny_list().0;
+    build_instruction(
+        PROGRAM_ID,
+        accounts::SubmitBid {
+            bidder: *bidder,
+            rent_payer: *rent_payer,
+            token_sale: *token_sale,
+            auction: pda::auction(token_sale).0,
+            bid: pda::auction_bid(token_sale, bidder, args.bid_id).0,
+            bidder_payment_account: *bidder_payment_account,
+            bid_vault: pda::bid_vault(token_sale).0,
+            deny_list,
+            denied_bidder: pda::denied_wallet(&deny_list, bidder).0,
+            token_program: TOKEN_PROGRAM_ID,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// `reveal_sealed_bid`: reveal a sealed bid, topping its deposit up to the full cost
+pub fn reveal_sealed_bid(
+    bidder: &Pubkey,
+    token_sale: &Pubkey,
+    bidder_payment_account: &Pubkey,
+    args: args::RevealSealedBid,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::RevealSealedBid {
+            bidder: *bidder,
+            token_sale: *token_sale,
+            auction: pda::auction(token_sale).0,
+            bid: pda::auction_bid(token_sale, bidder, args.bid_id).0,
+            bidder_payment_account: *bidder_payment_account,
+            bid_vault: pda::bid_vault(token_sale).0,
+            token_program: TOKEN_PROGRAM_ID,
+        },
+        args,
+    )
+}
+
+/// `clear_auction`: fix the clearing price once the sale has ended
+pub fn clear_auction(token_sale: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::ClearAuction {
+            token_sale: *token_sale,
+            auction: pda::auction(token_sale).0,
+        },
+        args::ClearAuction {},
+    )
+}
+
+/// `settle_auction_bids`: pay out a chunk of bids, each given as
+/// `(bid, bidder payment account, bidder token account)`
+pub fn settle_auction_bids(
+    token_sale: &Pubkey,
+    seller_payment_account: &Pubkey,
+    seller_token_account: &Pubkey,
+    platform_fee_account: &Pubkey,
+    bids: &[(Pubkey, Pubkey, Pubkey)],
+) -> Instruction {
+    let mut instruction = build_instruction(
+        PROGRAM_ID,
+        accounts::SettleAuctionBids {
+            token_sale: *token_sale,
+            auction: pda::auction(token_sale).0,
+            bid_vault: pda::bid_vault(token_sale).0,
+            token_vault: pda::token_vault(token_sale).0,
+            seller_payment_account: *seller_payment_account,
+            seller_token_account: *seller_token_account,
+            platform_fee_account: *platform_fee_account,
+            token_program: TOKEN_PROGRAM_ID,
+        },
+        args::SettleAuctionBids {},
+    );
+    instruction.accounts.extend(
+        bids.iter()
+            .flat_map(|(bid, payment, token)| [bid, payment, token])
+            .map(|account| AccountMeta::new(*account, false)),
+    );
+    instruction
+}
+
+/// `close_auction_bid`: close the bidder's settled bid, refunding the rent to
+/// `rent_payer`, the account that paid it
+pub fn close_auction_bid(
+    bidder: &Pubkey,
+    rent_payer: &Pubkey,
+    token_sale: &Pubkey,
+    bid_id: u64,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::CloseAuctionBid {
+            bidder: *bidder,
+            rent_payer: *rent_payer,
+            token_sale: *token_sale,
+            bid: pda::auction_bid(token_sale, bidder, bid_id).0,
+        },
+        args::CloseAuctionBid { bid_id },
+    )
+}
+
+/// `make_offer`: deposit token A into a new swap offer
+pub fn make_offer(
+    maker: &Pubkey,
+    token_mint_a: &Pubkey,
+    token_mint_b: &Pubkey,
+    maker_token_account_a: &Pubkey,
+    args: args::MakeOffer,
+) -> Instruction {
+    let (offer, _) = pda::offer(maker, args.offer_id);
+    build_instruction(
+        PROGRAM_ID,
+        accounts::MakeOffer {
+            maker: *maker,
+            offer,
+            token_mint_a: *token_mint_a,
+            token_mint_b: *token_mint_b,
+            maker_token_account_a: *maker_token_account_a,
+            offer_vault: pda::offer_vault(&offer).0,
+            token_program: TOKEN_PROGRAM_ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        args,
+    )
+}
+
+fn take_offer_accounts(
+    taker: &Pubkey,
+    maker: &Pubkey,
+    offer_id: u64,
+    taker_token_account_a: &Pubkey,
+    taker_token_account_b: &Pubkey,
+    maker_token_account_b: &Pubkey,
+) -> accounts::TakeOffer {
+    let (offer, _) = pda::offer(maker, offer_id);
+    accounts::TakeOffer {
+        taker: *taker,
+        maker: *maker,
+        offer,
+        taker_token_account_a: *taker_token_account_a,
+        taker_token_account_b: *taker_token_account_b,
+        maker_token_account_b: *maker_token_account_b,
+        offer_vault: pda::offer_vault(&offer).0,
+        token_program: TOKEN_PROGRAM_ID,
+    }
+}
+
+/// `take_offer`: swap for everything left in an offer
+pub fn take_offer(
+    taker: &Pubkey,
+    maker: &Pubkey,
+    offer_id: u64,
+    taker_token_account_a: &Pubkey,
+    taker_token_account_b: &Pubkey,
+    maker_token_account_b: &Pubkey,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        take_offer_accounts(
+            taker,
+            maker,
+            offer_id,
+            taker_token_account_a,
+            taker_token_account_b,
+            maker_token_account_b,
+        ),
+        args::TakeOffer {},
+    )
+}
+
+/// `fill_offer`: fill part of a partially fillable offer
+pub fn fill_offer(
+    taker: &Pubkey,
+    maker: &Pubkey,
+    offer_id: u64,
+    taker_token_account_a: &Pubkey,
+    taker_token_account_b: &Pubkey,
+    maker_token_account_b: &Pubkey,
+    args: args::FillOffer,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        take_offer_accounts(
+            taker,
+            maker,
+            offer_id,
+            taker_token_account_a,
+            taker_token_account_b,
+            maker_token_account_b,
+        ),
+        args,
+    )
+}
+
+/// `cancel_offer`: return an offer's remaining deposit to the maker
+pub fn cancel_offer(maker: &Pubkey, offer_id: u64, maker_token_account_a: &Pubkey) -> Instruction {
+    let (offer, _) = pda::offer(maker, offer_id);
+    build_instruction(
+        PROGRAM_ID,
+        accounts::CancelOffer {
+            maker: *maker,
+            offer,
+            maker_token_account_a: *maker_token_account_a,
+            offer_vault: pda::offer_vault(&offer).0,
+            token_program: TOKEN_PROGRAM_ID,
+        },
+        args::CancelOffer {},
+    )
+}
+
+/// `create_service_escrow`: lock a payment for a payee under an arbiter
+pub fn create_service_escrow(
+    payer: &Pubkey,
+    payee: &Pubkey,
+    arbiter: &Pubkey,
+    mint: &Pubkey,
+    payer_token_account: &Pubkey,
+    args: args::CreateServiceEscrow,
+) -> Instruction {
+    let (service_escrow, _) = pda::service_escrow(payer, args.escrow_id);
+    build_instruction(
+        PROGRAM_ID,
+        accounts::CreateServiceEscrow {
+            payer: *payer,
+            payee: *payee,
+            arbiter: *arbiter,
+            service_escrow,
+            mint: *mint,
+            payer_token_account: *payer_token_account,
+            escrow_vault: pda::service_vault(&service_escrow).0,
+            token_program: TOKEN_PROGRAM_ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        args,
+    )
+}
+
+fn settle_service_escrow_accounts(
+    authority: &Pubkey,
+    payer: &Pubkey,
+    escrow_id: u64,
+    payer_token_account: &Pubkey,
+    payee_token_account: &Pubkey,
+) -> accounts::SettleServiceEscrow {
+    let (service_escrow, _) = pda::service_escrow(payer, escrow_id);
+    accounts::SettleServiceEscrow {
+        authority: *authority,
+        payer: *payer,
+        service_escrow,
+        payer_token_account: *payer_token_account,
+        payee_token_account: *payee_token_account,
+        escrow_vault: pda::service_vault(&service_escrow).0,
+        token_program: TOKEN_PROGRAM_ID,
+    }
+}
+
+/// `release_service_escrow`: release funds to the payee (signed by the payer)
+pub fn release_service_escrow(
+    payer: &Pubkey,
+    escrow_id: u64,
+    payer_token_account: &Pubkey,
+    payee_token_account: &Pubkey,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        settle_service_escrow_accounts(
+            payer,
+            payer,
+            escrow_id,
+            payer_token_account,
+            payee_token_account,
+        ),
+        args::ReleaseServiceEscrow {},
+    )
+}
+
+/// `refund_service_escrow`: refund funds to the payer (signed by the payee)
+pub fn refund_service_escrow(
+    payee: &Pubkey,
+    payer: &Pubkey,
+    escrow_id: u64,
+    payer_token_account: &Pubkey,
+    payee_token_account: &Pubkey,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        settle_service_escrow_accounts(
+            payee,
+            payer,
+            escrow_id,
+            payer_token_account,
+            payee_token_account,
+        ),
+        args::RefundServiceEscrow {},
+    )
+}
+
+/// `raise_dispute`: freeze an escrow until the arbiter resolves it
+pub fn raise_dispute(authority: &Pubkey, payer: &Pubkey, escrow_id: u64) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::RaiseDispute {
+            authority: *authority,
+            service_escrow: pda::service_escrow(payer, escrow_id).0,
+        },
+        args::RaiseDispute {},
+    )
+}
+
+/// `resolve_dispute`: split a disputed escrow (signed by the arbiter)
+pub fn resolve_dispute(
+    arbiter: &Pubkey,
+    payer: &Pubkey,
+    escrow_id: u64,
+    payer_token_account: &Pubkey,
+    payee_token_account: &Pubkey,
+    args: args::ResolveDispute,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        settle_service_escrow_accounts(
+            arbiter,
+            payer,
+            escrow_id,
+            payer_token_account,
+            payee_token_account,
+        ),
+        args,
+    )
+}
+
+/// `claim_expired_service_escrow`: release an undisputed escrow after its deadline
+pub fn claim_expired_service_escrow(
+    caller: &Pubkey,
+    payer: &Pubkey,
+    escrow_id: u64,
+    payer_token_account: &Pubkey,
+    payee_token_account: &Pubkey,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        settle_service_escrow_accounts(
+            caller,
+            payer,
+            escrow_id,
+            payer_token_account,
+            payee_token_account,
+        ),
+        args::ClaimExpiredServiceEscrow {},
+    )
+}
+
+/// `create_deny_list`: create the program's deny list, kept by `args.authority`; `admin`
+/// pays for it and may update it
+pub fn create_deny_list(admin: &Pubkey, args: args::CreateDenyList) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::CreateDenyList {
+            admin: *admin,
+            deny_list: pda::deny_list().0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// `update_deny_list`: change the deny list's compliance authority and whether every
+/// sale must screen buyers
+pub fn update_deny_list(admin: &Pubkey, args: args::UpdateDenyList) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::UpdateDenyList {
+            admin: *admin,
+            deny_list: pda::deny_list().0,
+        },
+        args,
+    )
+}
+
+/// `deny_wallet`: add `args.wallet` to the deny list, `authority` paying the entry's rent
+pub fn deny_wallet(authority: &Pubkey, args: args::DenyWallet) -> Instruction {
+    let deny_list = pda::deny_list().0;
+    build_instruction(
+        PROGRAM_ID,
+        accounts::DenyWallet {
+            authority: *authority,
+            deny_list,
+            denied_wallet: pda::denied_wallet(&deny_list, &args.wallet).0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// `allow_wallet`: remove `wallet` from the deny list, refunding the entry's rent to
+/// `authority`
+pub fn allow_wallet(authority: &Pubkey, wallet: &Pubkey) -> Instruction {
+    let deny_list = pda::deny_list().0;
+    build_instruction(
+        PROGRAM_ID,
+        accounts::AllowWallet {
+            authority: *authority,
+            deny_list,
+            denied_wallet: pda::denied_wallet(&deny_list, wallet).0,
+        },
+        args::AllowWallet {},
+    )
+}
+
+fn migrate_accounts(payer: &Pubkey, account: &Pubkey) -> accounts::MigrateAccount {
+    accounts::MigrateAccount {
+        payer: *payer,
+        account: *account,
+        system_program: system_program::ID,
+    }
+}
+
+/// `migrate_token_sale`: upgrade a sale to the current layout, `payer` covering any extra rent
+pub fn migrate_token_sale(payer: &Pubkey, token_sale: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        migrate_accounts(payer, token_sale),
+        args::MigrateTokenSale {},
+    )
+}
+
+/// `migrate_buyer_account`: upgrade a buyer's tracker to the current layout, `payer` covering any extra rent
+pub fn migrate_buyer_account(payer: &Pubkey, buyer_account: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        migrate_accounts(payer, buyer_account),
+        args::MigrateBuyerAccount {},
+    )
+}
+
+/// `migrate_offer`: upgrade an offer to the current layout, `payer` covering any extra rent
+pub fn migrate_offer(payer: &Pubkey, offer: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        migrate_accounts(payer, offer),
+        args::MigrateOffer {},
+    )
+}
+
+/// `migrate_service_escrow`: upgrade a service escrow to the current layout, `payer` covering any extra rent
+pub fn migrate_service_escrow(payer: &Pubkey, service_escrow: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        migrate_accounts(payer, service_escrow),
+        args::MigrateServiceEscrow {},
+    )
+}
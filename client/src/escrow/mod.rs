@@ -0,0 +1,18 @@
+//! Client helpers for the `escrow` program.
+
+pub mod events;
+pub mod filters;
+pub mod instructions;
+pub mod pda;
+
+pub use escrow::{
+    legacy, Auction, AuctionBid, BuyerAccount, DeniedWallet, DenyList, Lottery, LotteryTicket,
+    Milestone, Offer, PriceTier, PurchaseHookArgs, PurchaseQuote, ServiceEscrow,
+    ServiceEscrowStatus, TokenSale, Versioned, ZeroCopyVersioned, ID as PROGRAM_ID,
+    LOTTERY_DRAW_DELAY_SLOTS, LOTTERY_REVEAL_PERIOD, MAX_AUCTION_PRICE_LEVELS,
+    MAX_PRICE_CHANGE_BPS, MAX_SALE_DURATION, MAX_SLOT_HASH_AGE, MIN_SALE_DURATION,
+    PRICE_CHANGE_DELAY, PURCHASE_HOOK_DISCRIMINATOR,
+};
+
+/// Instruction argument structs, as generated by Anchor
+pub use escrow::instruction as args;
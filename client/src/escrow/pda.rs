@@ -0,0 +1,123 @@
+//! Program address derivations for the `escrow` program.
+//!
+//! Every helper returns the address together with its bump seed.
+
+use anchor_lang::prelude::Pubkey;
+
+use super::PROGRAM_ID;
+
+/// Sale state: `["token_sale", seller, token_mint]`
+pub fn token_sale(seller: &Pubkey, token_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"token_sale", seller.as_ref(), token_mint.as_ref()],
+        &PROGRAM_ID,
+    )
+}
+
+/// Vault holding the tokens for sale: `["token_vault", token_sale]`
+pub fn token_vault(token_sale: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"token_vault", token_sale.as_ref()], &PROGRAM_ID)
+}
+
+/// Vault holding milestone-escrowed proceeds: `["proceeds_vault", token_sale]`
+pub fn proceeds_vault(token_sale: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"proceeds_vault", token_sale.as_ref()], &PROGRAM_ID)
+}
+
+/// Mint of the receipts a locked sale pays out: `["receipt_mint", token_sale]`
+pub fn receipt_mint(token_sale: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"receipt_mint", token_sale.as_ref()], &PROGRAM_ID)
+}
+
+/// The program's deny list singleton: `["deny_list"]`
+pub fn deny_list() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"deny_list"], &PROGRAM_ID)
+}
+
+/// Deny-list entry of a wallet, present while it is denied: `["denied", deny_list, wallet]`
+pub fn denied_wallet(deny_list: &Pubkey, wallet: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"denied", deny_list.as_ref(), wallet.as_ref()],
+        &PROGRAM_ID,
+    )
+}
+
+/// Per-buyer tracker: `["buyer", buyer, token_sale]`
+pub fn buyer(buyer: &Pubkey, token_sale: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"buyer", buyer.as_ref(), token_sale.as_ref()],
+        &PROGRAM_ID,
+    )
+}
+
+/// Vault holding overflow sale deposits: `["deposit_vault", token_sale]`
+pub fn deposit_vault(token_sale: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"deposit_vault", token_sale.as_ref()], &PROGRAM_ID)
+}
+
+/// Lottery state of a sale: `["lottery", token_sale]`
+pub fn lottery(token_sale: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"lottery", token_sale.as_ref()], &PROGRAM_ID)
+}
+
+/// Vault holding lottery ticket deposits: `["lottery_vault", token_sale]`
+pub fn lottery_vault(token_sale: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"lottery_vault", token_sale.as_ref()], &PROGRAM_ID)
+}
+
+/// Lottery ticket: `["lottery_ticket", buyer, token_sale]`
+pub fn lottery_ticket(buyer: &Pubkey, token_sale: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"lottery_ticket", buyer.as_ref(), token_sale.as_ref()],
+        &PROGRAM_ID,
+    )
+}
+
+/// Auction state of a sale: `["auction", token_sale]`
+pub fn auction(token_sale: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"auction", token_sale.as_ref()], &PROGRAM_ID)
+}
+
+/// Vault holding auction bid deposits: `["bid_vault", token_sale]`
+pub fn bid_vault(token_sale: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"bid_vault", token_sale.as_ref()], &PROGRAM_ID)
+}
+
+/// Auction bid: `["auction_bid", token_sale, bidder, bid_id (u64 LE)]`
+pub fn auction_bid(token_sale: &Pubkey, bidder: &Pubkey, bid_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            b"auction_bid",
+            token_sale.as_ref(),
+            bidder.as_ref(),
+            &bid_id.to_le_bytes(),
+        ],
+        &PROGRAM_ID,
+    )
+}
+
+/// Swap offer: `["offer", maker, offer_id (u64 LE)]`
+pub fn offer(maker: &Pubkey, offer_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"offer", maker.as_ref(), &offer_id.to_le_bytes()],
+        &PROGRAM_ID,
+    )
+}
+
+/// Vault holding an offer's token A: `["offer_vault", offer]`
+pub fn offer_vault(offer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"offer_vault", offer.as_ref()], &PROGRAM_ID)
+}
+
+/// Service escrow: `["service_escrow", payer, escrow_id (u64 LE)]`
+pub fn service_escrow(payer: &Pubkey, escrow_id: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"service_escrow", payer.as_ref(), &escrow_id.to_le_bytes()],
+        &PROGRAM_ID,
+    )
+}
+
+/// Vault holding a service escrow's funds: `["service_vault", service_escrow]`
+pub fn service_vault(service_escrow: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"service_vault", service_escrow.as_ref()], &PROGRAM_ID)
+}
@@ -0,0 +1,195 @@
+//! Event decoding from transaction logs.
+//!
+//! Both programs `emit!` their events as `Program data: <base64>` log lines. The logs are
+//! walked along the invoke stack, so each event is attributed to the program that emitted
+//! it, including events emitted from inside a CPI.
+
+use std::str::FromStr;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use base64::prelude::{Engine, BASE64_STANDARD};
+
+use crate::{escrow, multi_presale};
+
+/// A decoded event from either program
+pub enum ProgramEvent {
+    Escrow(escrow::events::Event),
+    MultiPresale(multi_presale::events::Event),
+}
+
+/// Raw event payload, as logged by `emit!`
+pub struct EventData {
+    /// Program that emitted the event
+    pub program_id: Pubkey,
+    /// Event discriminator followed by the Borsh-encoded fields
+    pub data: Vec<u8>,
+}
+
+/// Decode every event of either program found in `logs`, in emission order.
+///
+/// Events from other programs and payloads that fail to decode are skipped.
+pub fn parse_logs<S: AsRef<str>>(logs: &[S]) -> Vec<ProgramEvent> {
+    event_data(logs)
+        .iter()
+        .filter_map(|event| decode_event(&event.program_id, &event.data))
+        .collect()
+}
+
+/// Extract the raw payload of every event logged by any program, in emission order
+pub fn event_data<S: AsRef<str>>(logs: &[S]) -> Vec<EventData> {
+    let mut invoke_stack = Vec::new();
+    let mut events = Vec::new();
+
+    for line in logs {
+        let Some(rest) = line.as_ref().strip_prefix("Program ") else {
+            continue;
+        };
+        if let Some(encoded) = rest.strip_prefix("data: ") {
+            let (Some(program_id), Ok(data)) =
+                (invoke_stack.last(), BASE64_STANDARD.decode(encoded))
+            else {
+                continue;
+            };
+            events.push(EventData {
+                program_id: *program_id,
+                data,
+            });
+            continue;
+        }
+
+        let mut words = rest.split_whitespace();
+        let Some(Ok(program_id)) = words.next().map(Pubkey::from_str) else {
+            continue;
+        };
+        match words.next() {
+            Some("invoke") => invoke_stack.push(program_id),
+            Some("success") | Some("failed:") => {
+                invoke_stack.pop();
+            }
+            _ => {}
+        }
+    }
+
+    events
+}
+
+/// Decode one event payload emitted by `program_id`
+pub fn decode_event(program_id: &Pubkey, data: &[u8]) -> Option<ProgramEvent> {
+    if *program_id == escrow::PROGRAM_ID {
+        escrow::events::Event::decode(data).map(ProgramEvent::Escrow)
+    } else if *program_id == multi_presale::PROGRAM_ID {
+        multi_presale::events::Event::decode(data).map(ProgramEvent::MultiPresale)
+    } else {
+        None
+    }
+}
+
+/// Decode `data` as event `T` if it carries `T`'s discriminator
+pub(crate) fn decode_as<T: Discriminator + AnchorDeserialize>(data: &[u8]) -> Option<T> {
+    let fields = data.strip_prefix(T::DISCRIMINATOR)?;
+    T::try_from_slice(fields).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::Event;
+
+    fn data_line(event: &impl Event) -> String {
+        format!("Program data: {}", BASE64_STANDARD.encode(event.data()))
+    }
+
+    fn purchase(token_amount: u64) -> escrow::events::TokensPurchased {
+        escrow::events::TokensPurchased {
+            sale: Pubkey::new_unique(),
+            buyer: Pubkey::new_unique(),
+            token_amount,
+            seller_payment: 975,
+            platform_fee: 25,
+            platform_fee_recipient: Pubkey::new_unique(),
+            timestamp: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn events_are_attributed_along_the_invoke_stack() {
+        let other = Pubkey::new_unique();
+        let logs = vec![
+            format!("Program {} invoke [1]", escrow::PROGRAM_ID),
+            "Program log: Instruction: BuyTokens".to_string(),
+            format!("Program {} invoke [2]", other),
+            // Same layout, but emitted by an unrelated program
+            data_line(&purchase(1)),
+            format!("Program {} success", other),
+            data_line(&purchase(2)),
+            format!(
+                "Program {} consumed 5000 of 200000 compute units",
+                escrow::PROGRAM_ID
+            ),
+            format!("Program {} success", escrow::PROGRAM_ID),
+            format!("Program {} invoke [1]", multi_presale::PROGRAM_ID),
+            data_line(&multi_presale::events::ProjectApproved {
+                project_id: 7,
+                admin: Pubkey::new_unique(),
+                approved_at: 1_700_000_000,
+            }),
+            format!("Program {} success", multi_presale::PROGRAM_ID),
+        ];
+
+        assert_eq!(event_data(&logs).len(), 3);
+        let events = parse_logs(&logs);
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            &events[0],
+            ProgramEvent::Escrow(escrow::events::Event::TokensPurchased(event))
+                if event.token_amount == 2
+        ));
+        assert!(matches!(
+            &events[1],
+            ProgramEvent::MultiPresale(multi_presale::events::Event::ProjectApproved(event))
+                if event.project_id == 7
+        ));
+    }
+
+    #[test]
+    fn failed_invocations_pop_the_stack() {
+        let logs = vec![
+            format!("Program {} invoke [1]", multi_presale::PROGRAM_ID),
+            format!("Program {} invoke [2]", escrow::PROGRAM_ID),
+            format!(
+                "Program {} failed: custom program error: 0x1770",
+                escrow::PROGRAM_ID
+            ),
+            data_line(&multi_presale::events::SaleCancelled {
+                sale: Pubkey::new_unique(),
+                tokens_returned: 10,
+            }),
+        ];
+
+        let events = parse_logs(&logs);
+        assert!(matches!(
+            &events[..],
+            [ProgramEvent::MultiPresale(
+                multi_presale::events::Event::SaleCancelled(_)
+            )]
+        ));
+    }
+
+    #[test]
+    fn undecodable_payloads_are_skipped() {
+        let mut truncated = purchase(1).data();
+        truncated.pop();
+        let logs = vec![
+            format!("Program {} invoke [1]", escrow::PROGRAM_ID),
+            format!("Program data: {}", BASE64_STANDARD.encode(truncated)),
+            "Program data: not base64!".to_string(),
+            format!("Program data: {}", BASE64_STANDARD.encode([0u8; 8])),
+        ];
+
+        assert_eq!(event_data(&logs).len(), 2);
+        assert!(parse_logs(&logs).is_empty());
+        // Data outside any invocation has no program to belong to
+        assert!(event_data(&[data_line(&purchase(1))]).is_empty());
+    }
+}
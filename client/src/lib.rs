@@ -0,0 +1,74 @@
+//! Off-chain client for the `escrow` and `multi_presale` programs.
+//!
+//! Account and instruction types are re-exported from the program crates (built with
+//! their `cpi` feature), so everything here always matches the on-chain layout:
+//! - `pda`: typed program address derivations
+//! - `instructions`: builders for every program handler
+//! - `filters`: `getProgramAccounts` filters with memcmp offsets into each account
+//! - `events`: event types, with [`events::parse_logs`] decoding them from transaction logs
+//! - [`decode`]: account data decoding with discriminator checks
+//! - [`decode_return_data`]: values returned by the quote instructions, from a simulation
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{AccountDeserialize, AnchorDeserialize, InstructionData, ToAccountMetas};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use solana_transaction_status_client_types::UiTransactionReturnData;
+
+pub mod escrow;
+pub mod events;
+pub mod multi_presale;
+
+pub use anchor_lang;
+pub use anchor_spl;
+pub use solana_rpc_client_api::filter::{Memcmp, RpcFilterType};
+
+/// Size of the Anchor account discriminator that prefixes every account
+pub const DISCRIMINATOR_LEN: usize = 8;
+
+/// Decode a program account, verifying its discriminator.
+///
+/// Works for any account type from either program, e.g. `decode::<escrow::TokenSale>(&data)`.
+pub fn decode<T: AccountDeserialize>(data: &[u8]) -> anchor_lang::Result<T> {
+    let mut data = data;
+    T::try_deserialize(&mut data)
+}
+
+/// Decode the value a view instruction of `program_id` returned.
+///
+/// `return_data` is the `returnData` of a simulated transaction, e.g. an
+/// `escrow::PurchaseQuote` from [`escrow::instructions::quote_purchase`]. Returns `None`
+/// when another program set the return data last or it does not decode as `T`.
+pub fn decode_return_data<T: AnchorDeserialize>(
+    program_id: &Pubkey,
+    return_data: &UiTransactionReturnData,
+) -> Option<T> {
+    if return_data.program_id != program_id.to_string() {
+        return None;
+    }
+    let data = BASE64_STANDARD.decode(&return_data.data.0).ok()?;
+    T::try_from_slice(&data).ok()
+}
+
+/// Assemble an instruction from Anchor's generated account and argument structs
+pub(crate) fn build_instruction(
+    program_id: Pubkey,
+    accounts: impl ToAccountMetas,
+    args: impl InstructionData,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    }
+}
+
+/// Memcmp filter matching a pubkey stored at `offset`
+pub(crate) fn pubkey_filter(offset: usize, key: &Pubkey) -> RpcFilterType {
+    RpcFilterType::Memcmp(Memcmp::new_raw_bytes(offset, key.to_bytes().to_vec()))
+}
+
+/// Memcmp filter matching an account discriminator, i.e. selecting one account type
+pub(crate) fn discriminator_filter(discriminator: &[u8]) -> RpcFilterType {
+    RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, discriminator.to_vec()))
+}
@@ -0,0 +1,46 @@
+//! Events emitted by the `multi_presale` program.
+
+pub use multi_presale::{
+    DenyListConfigured, PlatformInitialized, ProjectApproved, ProjectCreated, ProjectStatusChanged,
+    SaleCancelled, SaleConfigured, SaleInitialized, SaleRoundCreated, TokensPurchased,
+    WalletAllowed, WalletDenied, WalletScreeningSet,
+};
+
+use crate::events::decode_as;
+
+/// A decoded `multi_presale` event
+pub enum Event {
+    PlatformInitialized(PlatformInitialized),
+    ProjectCreated(ProjectCreated),
+    ProjectApproved(ProjectApproved),
+    SaleConfigured(SaleConfigured),
+    SaleRoundCreated(SaleRoundCreated),
+    ProjectStatusChanged(ProjectStatusChanged),
+    SaleInitialized(SaleInitialized),
+    TokensPurchased(TokensPurchased),
+    SaleCancelled(SaleCancelled),
+    WalletScreeningSet(WalletScreeningSet),
+    DenyListConfigured(DenyListConfigured),
+    WalletDenied(WalletDenied),
+    WalletAllowed(WalletAllowed),
+}
+
+impl Event {
+    /// Decode an event payload (discriminator followed by the Borsh-encoded fields)
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        decode_as(data)
+            .map(Self::PlatformInitialized)
+            .or_else(|| decode_as(data).map(Self::ProjectCreated))
+            .or_else(|| decode_as(data).map(Self::ProjectApproved))
+            .or_else(|| decode_as(data).map(Self::SaleConfigured))
+            .or_else(|| decode_as(data).map(Self::SaleRoundCreated))
+            .or_else(|| decode_as(data).map(Self::ProjectStatusChanged))
+            .or_else(|| decode_as(data).map(Self::SaleInitialized))
+            .or_else(|| decode_as(data).map(Self::TokensPurchased))
+            .or_else(|| decode_as(data).map(Self::SaleCancelled))
+            .or_else(|| decode_as(data).map(Self::WalletScreeningSet))
+            .or_else(|| decode_as(data).map(Self::DenyListConfigured))
+            .or_else(|| decode_as(data).map(Self::WalletDenied))
+            .or_else(|| decode_as(data).map(Self::WalletAllowed))
+    }
+}
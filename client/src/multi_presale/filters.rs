@@ -0,0 +1,203 @@
+//! `getProgramAccounts` filters for `multi_presale` accounts.
+//!
+//! Only fixed-position fields can be filtered: `ProjectAccount` fields after `name`
+//! follow variable-length strings, so filter on the creator and decode the rest.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::Discriminator;
+
+use super::{
+    BuyerAccount, ProjectAccount, ProjectWhitelist, RoundBuyerAccount, SaleConfiguration,
+    SaleRound, TokenSale,
+};
+use crate::{discriminator_filter, pubkey_filter, Memcmp, RpcFilterType, DISCRIMINATOR_LEN};
+
+/// Byte offsets of the filterable fields, including the 8-byte discriminator
+pub mod offsets {
+    use super::DISCRIMINATOR_LEN;
+
+    pub const PROJECT_ID: usize = DISCRIMINATOR_LEN;
+    pub const PROJECT_CREATOR: usize = PROJECT_ID + 8;
+
+    pub const SALE_CONFIGURATION_PROJECT_ID: usize = DISCRIMINATOR_LEN;
+
+    pub const SALE_ROUND_PROJECT_ID: usize = DISCRIMINATOR_LEN;
+    // project_id and the other eight u64 fields, then the one-byte sale_type
+    pub const SALE_ROUND_ROUND_NUMBER: usize = SALE_ROUND_PROJECT_ID + 9 * 8 + 1;
+
+    pub const ROUND_BUYER_BUYER: usize = DISCRIMINATOR_LEN;
+    pub const ROUND_BUYER_PROJECT_ID: usize = ROUND_BUYER_BUYER + 32;
+
+    pub const WHITELIST_PROJECT_ID: usize = DISCRIMINATOR_LEN;
+
+    pub const TOKEN_SALE_SELLER: usize = DISCRIMINATOR_LEN;
+    pub const TOKEN_SALE_TOKEN_MINT: usize = TOKEN_SALE_SELLER + 32;
+
+    pub const BUYER_ACCOUNT_BUYER: usize = DISCRIMINATOR_LEN;
+    pub const BUYER_ACCOUNT_TOKEN_SALE: usize = BUYER_ACCOUNT_BUYER + 32;
+}
+
+fn u64_filter(offset: usize, value: u64) -> RpcFilterType {
+    RpcFilterType::Memcmp(Memcmp::new_raw_bytes(offset, value.to_le_bytes().to_vec()))
+}
+
+/// All projects
+pub fn projects() -> Vec<RpcFilterType> {
+    vec![discriminator_filter(ProjectAccount::DISCRIMINATOR)]
+}
+
+/// The project with numeric `project_id`
+pub fn project_by_id(project_id: u64) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(ProjectAccount::DISCRIMINATOR),
+        u64_filter(offsets::PROJECT_ID, project_id),
+    ]
+}
+
+/// Projects created by `creator`
+pub fn projects_by_creator(creator: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(ProjectAccount::DISCRIMINATOR),
+        pubkey_filter(offsets::PROJECT_CREATOR, creator),
+    ]
+}
+
+/// Sale tier configuration of a project
+pub fn sale_configurations_by_project(project_id: u64) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(SaleConfiguration::DISCRIMINATOR),
+        u64_filter(offsets::SALE_CONFIGURATION_PROJECT_ID, project_id),
+    ]
+}
+
+/// All sale rounds of a project
+pub fn sale_rounds_by_project(project_id: u64) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(SaleRound::DISCRIMINATOR),
+        u64_filter(offsets::SALE_ROUND_PROJECT_ID, project_id),
+    ]
+}
+
+/// Sale rounds with a given round number, across projects
+pub fn sale_rounds_by_number(round_number: u8) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(SaleRound::DISCRIMINATOR),
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+            offsets::SALE_ROUND_ROUND_NUMBER,
+            vec![round_number],
+        )),
+    ]
+}
+
+/// Round purchase trackers of `buyer`
+pub fn round_buyers_by_buyer(buyer: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(RoundBuyerAccount::DISCRIMINATOR),
+        pubkey_filter(offsets::ROUND_BUYER_BUYER, buyer),
+    ]
+}
+
+/// Round purchase trackers of a project
+pub fn round_buyers_by_project(project_id: u64) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(RoundBuyerAccount::DISCRIMINATOR),
+        u64_filter(offsets::ROUND_BUYER_PROJECT_ID, project_id),
+    ]
+}
+
+/// Whitelists of a project
+pub fn whitelists_by_project(project_id: u64) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(ProjectWhitelist::DISCRIMINATOR),
+        u64_filter(offsets::WHITELIST_PROJECT_ID, project_id),
+    ]
+}
+
+/// Legacy token sales created by `seller`
+pub fn token_sales_by_seller(seller: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(TokenSale::DISCRIMINATOR),
+        pubkey_filter(offsets::TOKEN_SALE_SELLER, seller),
+    ]
+}
+
+/// Legacy token sales selling `token_mint`
+pub fn token_sales_by_token_mint(token_mint: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(TokenSale::DISCRIMINATOR),
+        pubkey_filter(offsets::TOKEN_SALE_TOKEN_MINT, token_mint),
+    ]
+}
+
+/// Legacy buyer trackers of one sale
+pub fn buyer_accounts_by_sale(token_sale: &Pubkey) -> Vec<RpcFilterType> {
+    vec![
+        discriminator_filter(BuyerAccount::DISCRIMINATOR),
+        pubkey_filter(offsets::BUYER_ACCOUNT_TOKEN_SALE, token_sale),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::{AccountSerialize, ZeroCopy};
+
+    fn matches(filters: &[RpcFilterType], data: &[u8]) -> bool {
+        filters.iter().all(|filter| match filter {
+            RpcFilterType::Memcmp(memcmp) => memcmp.bytes_match(data),
+            _ => false,
+        })
+    }
+
+    fn serialize<T: AccountSerialize>(account: &T) -> Vec<u8> {
+        let mut data = Vec::new();
+        account.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    fn zero_copy_bytes<T: ZeroCopy>(account: &T) -> Vec<u8> {
+        [T::DISCRIMINATOR, bytemuck::bytes_of(account)].concat()
+    }
+
+    #[test]
+    fn project_offsets_match_layout() {
+        let project = ProjectAccount {
+            id: 42,
+            creator: Pubkey::new_unique(),
+            name: "Project".to_string(),
+            ..Default::default()
+        };
+        let data = serialize(&project);
+
+        assert!(matches(&project_by_id(42), &data));
+        assert!(!matches(&project_by_id(43), &data));
+        assert!(matches(&projects_by_creator(&project.creator), &data));
+    }
+
+    #[test]
+    fn sale_round_offsets_match_layout() {
+        let mut round: SaleRound = bytemuck::Zeroable::zeroed();
+        round.project_id = 7;
+        round.round_number = 3;
+        round.token_price = u64::MAX;
+        round.max_total_raise = u64::MAX;
+        round.sale_type = crate::multi_presale::SaleType::Private as u8;
+        let data = zero_copy_bytes(&round);
+
+        assert!(matches(&sale_rounds_by_project(7), &data));
+        assert!(matches(&sale_rounds_by_number(3), &data));
+        assert!(!matches(&sale_rounds_by_number(2), &data));
+    }
+
+    #[test]
+    fn round_buyer_offsets_match_layout() {
+        let mut round_buyer: RoundBuyerAccount = bytemuck::Zeroable::zeroed();
+        round_buyer.buyer = Pubkey::new_unique();
+        round_buyer.project_id = 9;
+        round_buyer.round_number = 1;
+        let data = zero_copy_bytes(&round_buyer);
+
+        assert!(matches(&round_buyers_by_buyer(&round_buyer.buyer), &data));
+        assert!(matches(&round_buyers_by_project(9), &data));
+    }
+}
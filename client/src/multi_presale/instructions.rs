@@ -0,0 +1,497 @@
+//! Instruction builders for every `multi_presale` handler.
+//!
+//! Builders take the accounts a caller has to choose (signers, mints, token accounts)
+//! and derive every program address themselves. Instruction arguments are passed as the
+//! Anchor generated structs from [`args`](super::args).
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::sysvar;
+use anchor_lang::system_program;
+use anchor_spl::token::ID as TOKEN_PROGRAM_ID;
+use multi_presale::accounts;
+
+use super::{args, pda, PROGRAM_ID};
+use crate::build_instruction;
+
+/// `initialize_platform`: create the platform configuration
+pub fn initialize_platform(
+    authority: &Pubkey,
+    treasury: &Pubkey,
+    args: args::InitializePlatform,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::InitializePlatform {
+            authority: *authority,
+            treasury: *treasury,
+            platform_account: pda::platform().0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// `create_project`: register a new project (PDA keyed by creator and name)
+pub fn create_project(
+    creator: &Pubkey,
+    token_mint: &Pubkey,
+    args: args::CreateProject,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::CreateProject {
+            creator: *creator,
+            platform_account: pda::platform().0,
+            project_account: pda::project(creator, &args.name).0,
+            token_mint: *token_mint,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// `configure_sale_tier`: configure the sale tier of an active project
+pub fn configure_sale_tier(
+    project_creator: &Pubkey,
+    project: &Pubkey,
+    payment_mint: &Pubkey,
+    args: args::ConfigureSaleTier,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::ConfigureSaleTier {
+            project_creator: *project_creator,
+            platform_account: pda::platform().0,
+            project_account: *project,
+            sale_configuration: pda::sale_config(project).0,
+            payment_mint: *payment_mint,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// `approve_project`: approve a draft project (platform admin)
+pub fn approve_project(admin: &Pubkey, project: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::ApproveProject {
+            admin: *admin,
+            platform_account: pda::platform().0,
+            project_account: *project,
+        },
+        args::ApproveProject {},
+    )
+}
+
+/// `initialize_project_vault`: create the project's token vault
+pub fn initialize_project_vault(
+    project_creator: &Pubkey,
+    project: &Pubkey,
+    token_mint: &Pubkey,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::InitializeProjectVault {
+            project_creator: *project_creator,
+            project_account: *project,
+            token_mint: *token_mint,
+            project_token_vault: pda::project_vault(project, token_mint).0,
+            token_program: TOKEN_PROGRAM_ID,
+            system_program: system_program::ID,
+        },
+        args::InitializeProjectVault {},
+    )
+}
+
+/// `create_sale_round`: create a numbered sale round for a project
+pub fn create_sale_round(
+    project_creator: &Pubkey,
+    project: &Pubkey,
+    args: args::CreateSaleRound,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::CreateSaleRound {
+            project_creator: *project_creator,
+            project_account: *project,
+            sale_round: pda::sale_round(project, args.round_number).0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// `initialize_platform_treasury`: create the treasury and its fee vault
+pub fn initialize_platform_treasury(
+    authority: &Pubkey,
+    payment_mint: &Pubkey,
+    args: args::InitializePlatformTreasury,
+) -> Instruction {
+    let (platform_treasury, _) = pda::platform_treasury();
+    build_instruction(
+        PROGRAM_ID,
+        accounts::InitializePlatformTreasury {
+            authority: *authority,
+            platform_treasury,
+            payment_mint: *payment_mint,
+            treasury_vault: pda::treasury_vault(&platform_treasury).0,
+            token_program: TOKEN_PROGRAM_ID,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// `advance_project_status`: run the automatic project lifecycle step
+pub fn advance_project_status(authority: &Pubkey, project: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::AdvanceProjectStatus {
+            authority: *authority,
+            project_account: *project,
+        },
+        args::AdvanceProjectStatus {},
+    )
+}
+
+/// `add_to_whitelist`: create a round whitelist and record a batch of addresses
+pub fn add_to_whitelist(
+    project_creator: &Pubkey,
+    project: &Pubkey,
+    args: args::AddToWhitelist,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::AddToWhitelist {
+            project_creator: *project_creator,
+            project_account: *project,
+            project_whitelist: pda::whitelist(project, args.sale_round).0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// `create_deny_list`: create the platform's deny list, kept by `args.authority`
+pub fn create_deny_list(admin: &Pubkey, args: args::CreateDenyList) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::CreateDenyList {
+            admin: *admin,
+            platform_account: pda::platform().0,
+            deny_list: pda::deny_list().0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// `update_deny_list`: change the deny list's compliance authority and whether every
+/// sale must screen buyers
+pub fn update_deny_list(admin: &Pubkey, args: args::UpdateDenyList) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::UpdateDenyList {
+            admin: *admin,
+            platform_account: pda::platform().0,
+            deny_list: pda::deny_list().0,
+        },
+        args,
+    )
+}
+
+/// `deny_wallet`: add `args.wallet` to the deny list, `authority` paying the entry's rent
+pub fn deny_wallet(authority: &Pubkey, args: args::DenyWallet) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::DenyWallet {
+            authority: *authority,
+            deny_list: pda::deny_list().0,
+            denied_wallet: pda::denied_wallet(&args.wallet).0,
+            system_program: system_program::ID,
+        },
+        args,
+    )
+}
+
+/// `allow_wallet`: remove `wallet` from the deny list, refunding the entry's rent to
+/// `authority`
+pub fn allow_wallet(authority: &Pubkey, wallet: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::AllowWallet {
+            authority: *authority,
+            deny_list: pda::deny_list().0,
+            denied_wallet: pda::denied_wallet(wallet).0,
+        },
+        args::AllowWallet {},
+    )
+}
+
+/// `initialize_sale`: create a legacy sale and move `total_tokens` into its vault
+pub fn initialize_sale(
+    seller: &Pubkey,
+    token_mint: &Pubkey,
+    payment_mint: &Pubkey,
+    seller_token_account: &Pubkey,
+    args: args::InitializeSale,
+) -> Instruction {
+    let (token_sale, _) = pda::token_sale(seller, token_mint);
+    build_instruction(
+        PROGRAM_ID,
+        accounts::InitializeSale {
+            seller: *seller,
+            token_sale,
+            token_mint: *token_mint,
+            payment_mint: *payment_mint,
+            seller_token_account: *seller_token_account,
+            token_vault: pda::token_vault(&token_sale).0,
+            token_program: TOKEN_PROGRAM_ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        args,
+    )
+}
+
+/// `create_buyer_account`: create the buyer's tracker for a legacy sale, `rent_payer`
+/// paying the rent
+pub fn create_buyer_account(
+    buyer: &Pubkey,
+    rent_payer: &Pubkey,
+    token_sale: &Pubkey,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::CreateBuyerAccount {
+            buyer: *buyer,
+            rent_payer: *rent_payer,
+            token_sale: *token_sale,
+            buyer_account: pda::buyer(buyer, token_sale).0,
+            system_program: system_program::ID,
+        },
+        args::CreateBuyerAccount {},
+    )
+}
+
+/// `close_buyer_account`: close the buyer's tracker for a legacy sale once it is over,
+/// refunding the rent to `rent_payer`, the account that paid it
+pub fn close_buyer_account(
+    buyer: &Pubkey,
+    rent_payer: &Pubkey,
+    token_sale: &Pubkey,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::CloseBuyerAccount {
+            buyer: *buyer,
+            rent_payer: *rent_payer,
+            token_sale: *token_sale,
+            buyer_account: pda::buyer(buyer, token_sale).0,
+        },
+        args::CloseBuyerAccount {},
+    )
+}
+
+/// `buy_tokens`: purchase from a legacy sale
+pub fn buy_tokens(
+    buyer: &Pubkey,
+    token_sale: &Pubkey,
+    buyer_payment_account: &Pubkey,
+    seller_payment_account: &Pubkey,
+    platform_fee_account: &Pubkey,
+    buyer_token_account: &Pubkey,
+    args: args::BuyTokens,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::BuyTokens {
+            buyer: *buyer,
+            token_sale: *token_sale,
+            buyer_account: pda::buyer(buyer, token_sale).0,
+            buyer_payment_account: *buyer_payment_account,
+            seller_payment_account: *seller_payment_account,
+            platform_fee_account: *platform_fee_account,
+            buyer_token_account: *buyer_token_account,
+            token_vault: pda::token_vault(token_sale).0,
+            deny_list: pda::deny_list().0,
+            denied_buyer: pda::denied_wallet(buyer).0,
+            token_program: TOKEN_PROGRAM_ID,
+        },
+        args,
+    )
+}
+
+/// `quote_purchase`: price a purchase as `buy_tokens` would, returned as return data.
+///
+/// Pass the buyer once they have a tracker account, so their per-buyer limit applies.
+pub fn quote_purchase(
+    token_sale: &Pubkey,
+    buyer: Option<&Pubkey>,
+    args: args::QuotePurchase,
+) -> Instruction {
+    build_instruction(PROGRAM_ID, quote_accounts(token_sale, buyer), args)
+}
+
+/// `quote_exact_in`: the largest purchase a payment covers, returned as return data
+pub fn quote_exact_in(
+    token_sale: &Pubkey,
+    buyer: Option<&Pubkey>,
+    args: args::QuoteExactIn,
+) -> Instruction {
+    build_instruction(PROGRAM_ID, quote_accounts(token_sale, buyer), args)
+}
+
+fn quote_accounts(token_sale: &Pubkey, buyer: Option<&Pubkey>) -> accounts::QuotePurchase {
+    accounts::QuotePurchase {
+        token_sale: *token_sale,
+        buyer_account: buyer.map(|buyer| pda::buyer(buyer, token_sale).0),
+    }
+}
+
+/// `cancel_sale`: return a legacy sale's unsold tokens to the seller
+pub fn cancel_sale(
+    seller: &Pubkey,
+    token_sale: &Pubkey,
+    seller_token_account: &Pubkey,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::CancelSale {
+            seller: *seller,
+            token_sale: *token_sale,
+            seller_token_account: *seller_token_account,
+            token_vault: pda::token_vault(token_sale).0,
+            token_program: TOKEN_PROGRAM_ID,
+        },
+        args::CancelSale {},
+    )
+}
+
+/// `toggle_pause`: pause or unpause a legacy sale
+pub fn toggle_pause(seller: &Pubkey, token_sale: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::TogglePause {
+            seller: *seller,
+            token_sale: *token_sale,
+        },
+        args::TogglePause {},
+    )
+}
+
+/// `set_wallet_screening`: screen a legacy sale's buyers against the deny list, or stop
+/// screening unless the platform requires it
+pub fn set_wallet_screening(
+    seller: &Pubkey,
+    token_sale: &Pubkey,
+    args: args::SetWalletScreening,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::SetWalletScreening {
+            seller: *seller,
+            token_sale: *token_sale,
+            deny_list: pda::deny_list().0,
+        },
+        args,
+    )
+}
+
+/// `update_sale_params`: change a legacy sale before it starts
+pub fn update_sale_params(
+    seller: &Pubkey,
+    token_sale: &Pubkey,
+    args: args::UpdateSaleParams,
+) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        accounts::UpdateSaleParams {
+            seller: *seller,
+            token_sale: *token_sale,
+        },
+        args,
+    )
+}
+
+fn migrate_accounts(payer: &Pubkey, account: &Pubkey) -> accounts::MigrateAccount {
+    accounts::MigrateAccount {
+        payer: *payer,
+        account: *account,
+        system_program: system_program::ID,
+    }
+}
+
+/// `migrate_platform`: upgrade the platform account to the current layout, `payer` covering any extra rent
+pub fn migrate_platform(payer: &Pubkey, platform: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        migrate_accounts(payer, platform),
+        args::MigratePlatform {},
+    )
+}
+
+/// `migrate_project`: upgrade a project to the current layout, `payer` covering any extra rent
+pub fn migrate_project(payer: &Pubkey, project: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        migrate_accounts(payer, project),
+        args::MigrateProject {},
+    )
+}
+
+/// `migrate_sale_configuration`: upgrade a sale tier to the current layout, `payer` covering any extra rent
+pub fn migrate_sale_configuration(payer: &Pubkey, sale_configuration: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        migrate_accounts(payer, sale_configuration),
+        args::MigrateSaleConfiguration {},
+    )
+}
+
+/// `migrate_sale_round`: upgrade a sale round to the current layout, `payer` covering any extra rent
+pub fn migrate_sale_round(payer: &Pubkey, sale_round: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        migrate_accounts(payer, sale_round),
+        args::MigrateSaleRound {},
+    )
+}
+
+/// `migrate_platform_treasury`: upgrade the platform treasury to the current layout, `payer` covering any extra rent
+pub fn migrate_platform_treasury(payer: &Pubkey, treasury: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        migrate_accounts(payer, treasury),
+        args::MigratePlatformTreasury {},
+    )
+}
+
+/// `migrate_whitelist`: upgrade a project whitelist to the current layout, `payer` covering any extra rent
+pub fn migrate_whitelist(payer: &Pubkey, whitelist: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        migrate_accounts(payer, whitelist),
+        args::MigrateWhitelist {},
+    )
+}
+
+/// `migrate_token_sale`: upgrade a legacy sale to the current layout, `payer` covering any extra rent
+pub fn migrate_token_sale(payer: &Pubkey, token_sale: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        migrate_accounts(payer, token_sale),
+        args::MigrateTokenSale {},
+    )
+}
+
+/// `migrate_buyer_account`: upgrade a legacy sale's buyer tracker to the current layout, `payer` covering any extra rent
+pub fn migrate_buyer_account(payer: &Pubkey, buyer_account: &Pubkey) -> Instruction {
+    build_instruction(
+        PROGRAM_ID,
+        migrate_accounts(payer, buyer_account),
+        args::MigrateBuyerAccount {},
+    )
+}
@@ -0,0 +1,65 @@
+//! Client helpers for the `multi_presale` program.
+
+pub mod events;
+pub mod filters;
+pub mod instructions;
+pub mod pda;
+
+pub use multi_presale::{
+    legacy, ApprovalStatus, BuyerAccount, DeniedWallet, DenyList, PlatformAccount,
+    PlatformTreasury, ProjectAccount, ProjectCategory, ProjectStatus, ProjectWhitelist,
+    PurchaseQuote, RoundBuyerAccount, SaleConfiguration, SaleRound, SaleType, TokenSale, Versioned,
+    ZeroCopyVersioned, ID as PROGRAM_ID,
+};
+
+/// Instruction argument structs, as generated by Anchor
+pub use multi_presale::instruction as args;
+
+/// Display name of a project category
+pub fn category_name(category: ProjectCategory) -> &'static str {
+    use ProjectCategory::*;
+    match category {
+        DeFi => "DeFi",
+        Gaming => "Gaming",
+        NFT => "NFT",
+        Infrastructure => "Infrastructure",
+        Social => "Social",
+        Metaverse => "Metaverse",
+        AI => "AI",
+        Other => "Other",
+    }
+}
+
+/// Display name of a project status
+pub fn status_name(status: ProjectStatus) -> &'static str {
+    use ProjectStatus::*;
+    match status {
+        Draft => "Draft",
+        Submitted => "Submitted",
+        Active => "Active",
+        Paused => "Paused",
+        Completed => "Completed",
+        Failed => "Failed",
+        Cancelled => "Cancelled",
+    }
+}
+
+/// Display name of a project approval status
+pub fn approval_name(status: ApprovalStatus) -> &'static str {
+    use ApprovalStatus::*;
+    match status {
+        Pending => "Pending",
+        Approved => "Approved",
+        Rejected => "Rejected",
+    }
+}
+
+/// Display name of a sale type
+pub fn sale_type_name(sale_type: SaleType) -> &'static str {
+    use SaleType::*;
+    match sale_type {
+        Seed => "Seed",
+        Private => "Private",
+        Public => "Public",
+    }
+}
@@ -0,0 +1,93 @@
+//! Program address derivations for the `multi_presale` program.
+//!
+//! Every helper returns the address together with its bump seed.
+
+use anchor_lang::prelude::Pubkey;
+
+use super::PROGRAM_ID;
+
+/// Platform configuration singleton: `["platform"]`
+pub fn platform() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"platform"], &PROGRAM_ID)
+}
+
+/// Project metadata: `["project", creator, name]`
+pub fn project(creator: &Pubkey, name: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"project", creator.as_ref(), name.as_bytes()],
+        &PROGRAM_ID,
+    )
+}
+
+/// Project token vault: `["project_vault", project, token_mint]`
+pub fn project_vault(project: &Pubkey, token_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"project_vault", project.as_ref(), token_mint.as_ref()],
+        &PROGRAM_ID,
+    )
+}
+
+/// Sale tier configuration: `["sale_config", project]`
+pub fn sale_config(project: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"sale_config", project.as_ref()], &PROGRAM_ID)
+}
+
+/// Sale round: `["sale_round", project, round_number]`
+pub fn sale_round(project: &Pubkey, round_number: u8) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"sale_round", project.as_ref(), &round_number.to_le_bytes()],
+        &PROGRAM_ID,
+    )
+}
+
+/// Round whitelist: `["whitelist", project, round_number]`
+pub fn whitelist(project: &Pubkey, round_number: u8) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"whitelist", project.as_ref(), &round_number.to_le_bytes()],
+        &PROGRAM_ID,
+    )
+}
+
+/// Platform treasury singleton: `["platform_treasury"]`
+pub fn platform_treasury() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"platform_treasury"], &PROGRAM_ID)
+}
+
+/// Treasury fee vault: `["treasury_vault", platform_treasury]`
+pub fn treasury_vault(platform_treasury: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"treasury_vault", platform_treasury.as_ref()],
+        &PROGRAM_ID,
+    )
+}
+
+/// Platform deny list singleton: `["deny_list"]`
+pub fn deny_list() -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"deny_list"], &PROGRAM_ID)
+}
+
+/// Deny-list entry of a wallet, present while it is denied: `["denied", wallet]`
+pub fn denied_wallet(wallet: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"denied", wallet.as_ref()], &PROGRAM_ID)
+}
+
+/// Legacy sale state: `["token_sale", seller, token_mint]`
+pub fn token_sale(seller: &Pubkey, token_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"token_sale", seller.as_ref(), token_mint.as_ref()],
+        &PROGRAM_ID,
+    )
+}
+
+/// Legacy sale vault: `["token_vault", token_sale]`
+pub fn token_vault(token_sale: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"token_vault", token_sale.as_ref()], &PROGRAM_ID)
+}
+
+/// Legacy buyer tracker: `["buyer", buyer, token_sale]`
+pub fn buyer(buyer: &Pubkey, token_sale: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"buyer", buyer.as_ref(), token_sale.as_ref()],
+        &PROGRAM_ID,
+    )
+}
@@ -0,0 +1,584 @@
+//! Escrow auction sales: bids at ladder prices, open or sealed until the sale ends, clear
+//! at one uniform price, and the bids are settled in chunks by anyone once it is fixed.
+
+use anchor_lang::error::ErrorCode as AnchorError;
+use escrow::ErrorCode;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use token_sale_client::escrow::{
+    args, instructions, pda, Auction, AuctionBid, PriceTier, TokenSale,
+};
+
+use crate::escrow_sale::{buy, create_buyer, create_sale, Buyer, Sale, SALE_END, SALE_START};
+use crate::harness::{assert_error, Harness, TxResult};
+
+const FUNDED: u64 = 10_000_000;
+
+async fn configure(h: &mut Harness, sale: &Sale, min_price: u64, price_levels: u8) -> TxResult {
+    h.process(
+        &[instructions::configure_auction(
+            &sale.seller.pubkey(),
+            &sale.address,
+            &sale.payment_mint,
+            args::ConfigureAuction {
+                min_price,
+                tick_size: 100,
+                price_levels,
+            },
+        )],
+        &[&sale.seller],
+    )
+    .await
+}
+
+async fn bid(
+    h: &mut Harness,
+    sale: &Sale,
+    bidder: &Buyer,
+    quantity: u64,
+    max_price: u64,
+) -> TxResult {
+    h.process(
+        &[instructions::submit_bid(
+            &bidder.wallet.pubkey(),
+            &bidder.wallet.pubkey(),
+            &sale.address,
+            &bidder.payment_account,
+            args::SubmitBid {
+                bid_id: 0,
+                quantity,
+                max_price,
+            },
+        )],
+        &[&bidder.wallet],
+    )
+    .await
+}
+
+/// Commit a sealed bid, salted with the quantity's low byte repeated
+async fn commit(
+    h: &mut Harness,
+    sale: &Sale,
+    bidder: &Buyer,
+    quantity: u64,
+    max_price: u64,
+) -> TxResult {
+    let salt = [quantity as u8; 32];
+    let commitment = AuctionBid::commitment_of(max_price, quantity, &salt, &bidder.wallet.pubkey());
+    h.process(
+        &[instructions::commit_sealed_bid(
+            &bidder.wallet.pubkey(),
+            &bidder.wallet.pubkey(),
+            &sale.address,
+            &bidder.payment_account,
+            args::CommitSealedBid {
+                bid_id: 0,
+                commitment,
+            },
+        )],
+        &[&bidder.wallet],
+    )
+    .await
+}
+
+/// Reveal a sealed bid, salted with `salt_byte` repeated
+async fn reveal(
+    h: &mut Harness,
+    sale: &Sale,
+    bidder: &Buyer,
+    quantity: u64,
+    max_price: u64,
+    salt_byte: u8,
+) -> TxResult {
+    h.process(
+        &[instructions::reveal_sealed_bid(
+            &bidder.wallet.pubkey(),
+            &sale.address,
+            &bidder.payment_account,
+            args::RevealSealedBid {
+                bid_id: 0,
+                quantity,
+                max_price,
+                salt: [salt_byte; 32],
+            },
+        )],
+        &[&bidder.wallet],
+    )
+    .await
+}
+
+async fn clear(h: &mut Harness, sale: &Sale) -> TxResult {
+    h.process(&[instructions::clear_auction(&sale.address)], &[])
+        .await
+}
+
+/// Settle the first bid of each of `bidders` in one transaction
+async fn settle(h: &mut Harness, sale: &Sale, bidders: &[&Buyer]) -> TxResult {
+    let bids: Vec<_> = bidders
+        .iter()
+        .map(|bidder| {
+            (
+                pda::auction_bid(&sale.address, &bidder.wallet.pubkey(), 0).0,
+                bidder.payment_account,
+                bidder.token_account,
+            )
+        })
+        .collect();
+    h.process(
+        &[instructions::settle_auction_bids(
+            &sale.address,
+            &sale.seller_payment_account,
+            &sale.seller_token_account,
+            &sale.fee_account,
+            &bids,
+        )],
+        &[],
+    )
+    .await
+}
+
+async fn cancel(h: &mut Harness, sale: &Sale) -> TxResult {
+    h.process(
+        &[instructions::cancel_sale(
+            &sale.seller.pubkey(),
+            &sale.address,
+            &sale.seller_token_account,
+            true,
+        )],
+        &[&sale.seller],
+    )
+    .await
+}
+
+/// A live auction with prices 1,000 to 1,400 and a bidder per `(quantity, max_price)`
+async fn auction_sale(h: &mut Harness, bids: &[(u64, u64)]) -> (Sale, Vec<Buyer>) {
+    let sale = create_sale(h).await;
+    configure(h, &sale, 1_000, 5).await.unwrap();
+    h.warp_to(SALE_START).await;
+    let mut bidders = Vec::new();
+    for (quantity, max_price) in bids {
+        let bidder = create_buyer(h, &sale).await;
+        bid(h, &sale, &bidder, *quantity, *max_price).await.unwrap();
+        bidders.push(bidder);
+    }
+    (sale, bidders)
+}
+
+#[tokio::test]
+async fn oversubscribed_auction_clears_at_the_marginal_bid() {
+    let mut h = Harness::new().await;
+    // 1,000 tokens: 700 go above 1,100 and the 760 bid at 1,100 share the last 300
+    let (sale, bidders) = auction_sale(
+        &mut h,
+        &[
+            (400, 1_400),
+            (300, 1_200),
+            (500, 1_100),
+            (260, 1_100),
+            (200, 1_000),
+        ],
+    )
+    .await;
+    assert_error(
+        buy(&mut h, &sale, &bidders[0], 10).await,
+        ErrorCode::AuctionSale,
+    );
+    let late = create_buyer(&mut h, &sale).await;
+    assert_error(
+        bid(&mut h, &sale, &late, 100, 1_050).await,
+        ErrorCode::InvalidBidPrice,
+    );
+    assert_error(
+        bid(&mut h, &sale, &late, 100, 1_500).await,
+        ErrorCode::InvalidBidPrice,
+    );
+    assert_error(clear(&mut h, &sale).await, ErrorCode::SaleNotEnded);
+    assert_error(
+        settle(&mut h, &sale, &[&bidders[0]]).await,
+        ErrorCode::AuctionNotCleared,
+    );
+
+    h.warp_to(SALE_END + 1).await;
+    assert_error(
+        bid(&mut h, &sale, &late, 100, 1_000).await,
+        ErrorCode::SaleEnded,
+    );
+    clear(&mut h, &sale).await.unwrap();
+    let auction: Auction = h.fetch(&pda::auction(&sale.address).0).await;
+    assert_eq!(auction.clearing_price, 1_100);
+    assert_eq!(
+        (auction.marginal_supply, auction.marginal_demand),
+        (300, 760)
+    );
+    assert_eq!(auction.tokens_reserved, 1_000);
+
+    // The seller may cancel once cleared; nothing is left to take back yet
+    cancel(&mut h, &sale).await.unwrap();
+    assert_eq!(h.token_balance(&sale.seller_token_account).await, 0);
+
+    // Two chunks, as a crank would
+    assert_error(
+        settle(&mut h, &sale, &[]).await,
+        ErrorCode::InvalidSettlementAccounts,
+    );
+    settle(&mut h, &sale, &[&bidders[0], &bidders[1]])
+        .await
+        .unwrap();
+    assert_error(
+        settle(&mut h, &sale, &[&bidders[2], &bidders[1]]).await,
+        ErrorCode::BidAlreadySettled,
+    );
+    settle(&mut h, &sale, &[&bidders[2], &bidders[3], &bidders[4]])
+        .await
+        .unwrap();
+
+    // Everyone pays 1,100; the marginal bids round down
+    for (bidder, filled) in bidders.iter().zip([400, 300, 197, 102, 0]) {
+        assert_eq!(h.token_balance(&bidder.token_account).await, filled);
+        assert_eq!(
+            h.token_balance(&bidder.payment_account).await,
+            FUNDED - filled * 1_100
+        );
+    }
+    assert_eq!(
+        h.token_balance(&sale.seller_payment_account).await,
+        1_071_428
+    );
+    assert_eq!(h.token_balance(&sale.fee_account).await, 27_472);
+    assert_eq!(h.token_balance(&pda::bid_vault(&sale.address).0).await, 0);
+
+    let auction: Auction = h.fetch(&pda::auction(&sale.address).0).await;
+    assert_eq!((auction.bids_settled, auction.tokens_filled), (5, 999));
+
+    // The last chunk pays the rounding dust to the seller, though the sale is cancelled
+    assert_eq!(h.token_balance(&sale.seller_token_account).await, 1);
+    assert_eq!(h.token_balance(&pda::token_vault(&sale.address).0).await, 0);
+}
+
+#[tokio::test]
+async fn undersubscribed_auction_clears_at_the_lowest_bid() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let tiers = |price_tiers| {
+        instructions::set_price_tiers(
+            &sale.seller.pubkey(),
+            &sale.address,
+            args::SetPriceTiers { price_tiers },
+        )
+    };
+
+    // Auctions set their own price
+    let tier = PriceTier {
+        tokens_sold_threshold: 100,
+        price_per_token: 2_000,
+    };
+    h.process(&[tiers(vec![tier])], &[&sale.seller])
+        .await
+        .unwrap();
+    assert_error(
+        configure(&mut h, &sale, 1_000, 5).await,
+        ErrorCode::AuctionIncompatible,
+    );
+    h.process(&[tiers(vec![])], &[&sale.seller]).await.unwrap();
+    // Bids are not totalled per bidder, so a per-buyer cap could not hold
+    let cap = |limit| {
+        instructions::update_sale_params(
+            &sale.seller.pubkey(),
+            &sale.address,
+            args::UpdateSaleParams {
+                new_price_per_token: None,
+                new_sale_start_time: None,
+                new_sale_end_time: None,
+                new_max_tokens_per_buyer: Some(limit),
+                new_min_sale_duration: None,
+                new_max_sale_duration: None,
+            },
+        )
+    };
+    h.process(&[cap(100)], &[&sale.seller]).await.unwrap();
+    assert_error(
+        configure(&mut h, &sale, 1_000, 5).await,
+        ErrorCode::AuctionIncompatible,
+    );
+    h.process(&[cap(0)], &[&sale.seller]).await.unwrap();
+    assert_error(
+        configure(&mut h, &sale, 1_000, 33).await,
+        ErrorCode::InvalidAuctionConfig,
+    );
+    configure(&mut h, &sale, 1_000, 5).await.unwrap();
+    h.next_slot().await;
+    assert_error(
+        h.process(&[tiers(vec![tier])], &[&sale.seller]).await,
+        ErrorCode::AuctionIncompatible,
+    );
+    assert_error(
+        h.process(&[cap(100)], &[&sale.seller]).await,
+        ErrorCode::AuctionIncompatible,
+    );
+    assert_error(
+        h.process(
+            &[instructions::configure_overflow(
+                &sale.seller.pubkey(),
+                &sale.address,
+                &sale.payment_mint,
+            )],
+            &[&sale.seller],
+        )
+        .await,
+        ErrorCode::OverflowIncompatible,
+    );
+
+    h.warp_to(SALE_START).await;
+    let high = create_buyer(&mut h, &sale).await;
+    let low = create_buyer(&mut h, &sale).await;
+    bid(&mut h, &sale, &high, 200, 1_200).await.unwrap();
+    bid(&mut h, &sale, &low, 100, 1_000).await.unwrap();
+
+    // The auction only exists once configured, so clear the flag to reach the handler's check
+    let mut state: TokenSale = h.fetch(&sale.address).await;
+    state.is_auction = 0;
+    h.store_zero_copy(&sale.address, &state).await;
+    let late = create_buyer(&mut h, &sale).await;
+    assert_error(
+        bid(&mut h, &sale, &late, 50, 1_000).await,
+        ErrorCode::NotAuctionSale,
+    );
+    state.is_auction = 1;
+    h.store_zero_copy(&sale.address, &state).await;
+
+    // Once it has ended, the seller cannot walk away from the bids
+    h.warp_to(SALE_END + 1).await;
+    assert_error(
+        h.process(
+            &[instructions::cancel_sale(
+                &sale.seller.pubkey(),
+                &sale.address,
+                &sale.seller_token_account,
+                false,
+            )],
+            &[&sale.seller],
+        )
+        .await,
+        ErrorCode::AuctionRequired,
+    );
+    assert_error(cancel(&mut h, &sale).await, ErrorCode::AuctionNotCleared);
+
+    clear(&mut h, &sale).await.unwrap();
+    h.next_slot().await;
+    assert_error(clear(&mut h, &sale).await, ErrorCode::AuctionAlreadyCleared);
+    let auction: Auction = h.fetch(&pda::auction(&sale.address).0).await;
+    assert_eq!(
+        (auction.clearing_price, auction.tokens_reserved),
+        (1_000, 300)
+    );
+
+    // Unsold tokens are the seller's to take back right away
+    cancel(&mut h, &sale).await.unwrap();
+    assert_eq!(h.token_balance(&sale.seller_token_account).await, 700);
+
+    settle(&mut h, &sale, &[&high, &low]).await.unwrap();
+    assert_eq!(h.token_balance(&high.token_account).await, 200);
+    assert_eq!(
+        h.token_balance(&high.payment_account).await,
+        FUNDED - 200_000
+    );
+    assert_eq!(h.token_balance(&low.token_account).await, 100);
+    assert_eq!(h.token_balance(&pda::token_vault(&sale.address).0).await, 0);
+}
+
+#[tokio::test]
+async fn cancelled_auction_refunds_every_bid() {
+    let mut h = Harness::new().await;
+    let (sale, bidders) = auction_sale(&mut h, &[(600, 1_300), (600, 1_000)]).await;
+
+    // Open auctions take no commitments
+    let late = create_buyer(&mut h, &sale).await;
+    assert_error(
+        commit(&mut h, &sale, &late, 100, 1_000).await,
+        ErrorCode::NotSealedAuction,
+    );
+
+    cancel(&mut h, &sale).await.unwrap();
+    assert_eq!(h.token_balance(&sale.seller_token_account).await, 1_000);
+
+    // A bid's rent goes back to whoever paid it, here the bidder, once it is settled
+    let bidder = bidders[0].wallet.pubkey();
+    let bid_address = pda::auction_bid(&sale.address, &bidder, 0).0;
+    let state: AuctionBid = h.fetch(&bid_address).await;
+    assert_eq!(state.rent_payer, bidder);
+    let close = |rent_payer: &Pubkey| {
+        instructions::close_auction_bid(&bidder, rent_payer, &sale.address, 0)
+    };
+    assert_error(
+        h.process(&[close(&bidder)], &[&bidders[0].wallet]).await,
+        ErrorCode::BidNotSettled,
+    );
+
+    h.warp_to(SALE_END + 1).await;
+    assert_error(clear(&mut h, &sale).await, ErrorCode::SaleNotActive);
+    settle(&mut h, &sale, &[&bidders[0], &bidders[1]])
+        .await
+        .unwrap();
+
+    let rent = h.account(&bid_address).await.unwrap().lamports;
+    let bidder_lamports = h.account(&bidder).await.unwrap().lamports;
+    assert_error(
+        h.process(&[close(&sale.seller.pubkey())], &[&bidders[0].wallet])
+            .await,
+        AnchorError::ConstraintRaw,
+    );
+    h.process(&[close(&bidder)], &[&bidders[0].wallet])
+        .await
+        .unwrap();
+    assert!(h.account(&bid_address).await.is_none());
+    assert_eq!(
+        h.account(&bidder).await.unwrap().lamports,
+        bidder_lamports + rent
+    );
+    for bidder in &bidders {
+        assert_eq!(h.token_balance(&bidder.payment_account).await, FUNDED);
+        assert_eq!(h.token_balance(&bidder.token_account).await, 0);
+    }
+    assert_eq!(h.token_balance(&sale.seller_payment_account).await, 0);
+}
+
+#[tokio::test]
+async fn sealed_bids_clear_once_revealed() {
+    const COLLATERAL: u64 = 100_000;
+    const REVEAL_PERIOD: i64 = 600;
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    configure(&mut h, &sale, 1_000, 5).await.unwrap();
+    let seal = |forfeit_bps| {
+        instructions::configure_sealed_bids(
+            &sale.seller.pubkey(),
+            &sale.address,
+            args::ConfigureSealedBids {
+                collateral: COLLATERAL,
+                reveal_period: REVEAL_PERIOD,
+                forfeit_bps,
+            },
+        )
+    };
+    for forfeit_bps in [0, 10_001] {
+        assert_error(
+            h.process(&[seal(forfeit_bps)], &[&sale.seller]).await,
+            ErrorCode::InvalidSealedBidConfig,
+        );
+    }
+    h.process(&[seal(5_000)], &[&sale.seller]).await.unwrap();
+
+    h.warp_to(SALE_START).await;
+    let mut bidders = Vec::new();
+    for _ in 0..4 {
+        bidders.push(create_buyer(&mut h, &sale).await);
+    }
+    assert_error(
+        bid(&mut h, &sale, &bidders[0], 700, 1_300).await,
+        ErrorCode::SealedAuction,
+    );
+    // Only the collateral shows while the sale runs; the last bid is never revealed
+    let bids = [(700, 1_300), (500, 1_000), (50, 1_000), (300, 1_400)];
+    for (bidder, (quantity, max_price)) in bidders.iter().zip(bids) {
+        commit(&mut h, &sale, bidder, quantity, max_price)
+            .await
+            .unwrap();
+        assert_eq!(
+            h.token_balance(&bidder.payment_account).await,
+            FUNDED - COLLATERAL
+        );
+    }
+    assert_error(
+        reveal(&mut h, &sale, &bidders[0], 700, 1_300, 188).await,
+        ErrorCode::SaleNotEnded,
+    );
+
+    h.warp_to(SALE_END + 1).await;
+    assert_error(
+        reveal(&mut h, &sale, &bidders[0], 700, 1_300, 0).await,
+        ErrorCode::SealedBidMismatch,
+    );
+    for (bidder, (quantity, max_price)) in bidders.iter().zip(bids).take(3) {
+        reveal(&mut h, &sale, bidder, quantity, max_price, quantity as u8)
+            .await
+            .unwrap();
+    }
+    h.next_slot().await;
+    assert_error(
+        reveal(&mut h, &sale, &bidders[0], 700, 1_300, 188).await,
+        ErrorCode::BidAlreadyRevealed,
+    );
+    assert_error(clear(&mut h, &sale).await, ErrorCode::RevealPeriodActive);
+    // The seller cannot back out once the bids are being revealed
+    assert_error(cancel(&mut h, &sale).await, ErrorCode::AuctionNotCleared);
+
+    h.warp_to(SALE_END + REVEAL_PERIOD + 1).await;
+    assert_error(
+        reveal(&mut h, &sale, &bidders[3], 300, 1_400, 44).await,
+        ErrorCode::RevealPeriodEnded,
+    );
+    clear(&mut h, &sale).await.unwrap();
+    let auction: Auction = h.fetch(&pda::auction(&sale.address).0).await;
+    assert_eq!((auction.bids, auction.bids_revealed), (4, 3));
+    assert_eq!(auction.clearing_price, 1_000);
+    assert_eq!(
+        (auction.marginal_supply, auction.marginal_demand),
+        (300, 550)
+    );
+
+    let refs: Vec<&Buyer> = bidders.iter().collect();
+    settle(&mut h, &sale, &refs).await.unwrap();
+    for (bidder, filled) in bidders.iter().zip([700, 272, 27]) {
+        assert_eq!(h.token_balance(&bidder.token_account).await, filled);
+        assert_eq!(
+            h.token_balance(&bidder.payment_account).await,
+            FUNDED - filled * 1_000
+        );
+    }
+    // Half the unrevealed bid's collateral goes to the seller
+    assert_eq!(
+        h.token_balance(&bidders[3].payment_account).await,
+        FUNDED - COLLATERAL / 2
+    );
+    assert_eq!(
+        h.token_balance(&sale.seller_payment_account).await,
+        974_025 + COLLATERAL / 2
+    );
+    assert_eq!(h.token_balance(&sale.fee_account).await, 24_975);
+    assert_eq!(h.token_balance(&pda::bid_vault(&sale.address).0).await, 0);
+}
+
+#[tokio::test]
+async fn auctions_do_not_soft_close() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let soft_close = |window, extension, ceiling| {
+        instructions::configure_soft_close(
+            &sale.seller.pubkey(),
+            &sale.address,
+            args::ConfigureSoftClose {
+                window,
+                extension,
+                ceiling,
+            },
+        )
+    };
+
+    // Bids never extend the sale, so the two exclude each other either way round
+    h.process(&[soft_close(100, 60, SALE_END + 100)], &[&sale.seller])
+        .await
+        .unwrap();
+    assert_error(
+        configure(&mut h, &sale, 1_000, 5).await,
+        ErrorCode::SoftCloseIncompatible,
+    );
+    h.process(&[soft_close(0, 0, 0)], &[&sale.seller])
+        .await
+        .unwrap();
+    configure(&mut h, &sale, 1_000, 5).await.unwrap();
+    assert_error(
+        h.process(&[soft_close(100, 60, SALE_END + 100)], &[&sale.seller])
+            .await,
+        ErrorCode::SoftCloseIncompatible,
+    );
+}
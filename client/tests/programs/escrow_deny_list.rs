@@ -0,0 +1,333 @@
+//! Escrow deny list: the compliance authority bars wallets from buying in the sales that
+//! screen against the program's one list, and a required list cannot be switched off.
+
+use anchor_lang::error::ErrorCode as AnchorError;
+use escrow::ErrorCode;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use token_sale_client::escrow::{args, instructions, pda, DenyList, TokenSale};
+
+use crate::escrow_sale::{buy, create_buyer, create_sale, Sale, SALE_START};
+use crate::harness::{assert_error, Harness, TxResult};
+
+async fn configure(
+    h: &mut Harness,
+    admin: &Keypair,
+    authority: Pubkey,
+    required: bool,
+) -> TxResult {
+    let ix = if h.account(&pda::deny_list().0).await.is_some() {
+        instructions::update_deny_list(
+            &admin.pubkey(),
+            args::UpdateDenyList {
+                authority,
+                required,
+            },
+        )
+    } else {
+        instructions::create_deny_list(
+            &admin.pubkey(),
+            args::CreateDenyList {
+                authority,
+                required,
+            },
+        )
+    };
+    h.process(&[ix], &[admin]).await
+}
+
+async fn screen(h: &mut Harness, sale: &Sale, enabled: bool) -> TxResult {
+    h.process(
+        &[instructions::set_wallet_screening(
+            &sale.seller.pubkey(),
+            &sale.address,
+            args::SetWalletScreening { enabled },
+        )],
+        &[&sale.seller],
+    )
+    .await
+}
+
+async fn deny(h: &mut Harness, authority: &Keypair, wallet: Pubkey) -> TxResult {
+    h.process(
+        &[instructions::deny_wallet(
+            &authority.pubkey(),
+            args::DenyWallet { wallet },
+        )],
+        &[authority],
+    )
+    .await
+}
+
+#[tokio::test]
+async fn denied_wallets_cannot_buy_in_screened_sales() {
+    let mut h = Harness::new().await;
+    let admin = h.wallet().await;
+    let compliance = h.wallet().await;
+    let sale = create_sale(&mut h).await;
+    let deny_list = pda::deny_list().0;
+
+    configure(&mut h, &admin, compliance.pubkey(), false)
+        .await
+        .unwrap();
+    h.warp_to(SALE_START).await;
+    let buyer = create_buyer(&mut h, &sale).await;
+    let other = create_buyer(&mut h, &sale).await;
+
+    // Only the compliance authority maintains the list
+    assert_error(
+        deny(&mut h, &admin, buyer.wallet.pubkey()).await,
+        AnchorError::ConstraintRaw,
+    );
+    deny(&mut h, &compliance, buyer.wallet.pubkey())
+        .await
+        .unwrap();
+    let list: DenyList = h.fetch(&deny_list).await;
+    assert_eq!(
+        (list.authority, list.denied_count),
+        (compliance.pubkey(), 1)
+    );
+
+    // Sales that don't screen are unaffected
+    buy(&mut h, &sale, &buyer, 10).await.unwrap();
+
+    screen(&mut h, &sale, true).await.unwrap();
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert!(state.screens_wallets());
+    h.next_slot().await;
+    assert_error(
+        buy(&mut h, &sale, &buyer, 10).await,
+        ErrorCode::WalletDenied,
+    );
+    buy(&mut h, &sale, &other, 10).await.unwrap();
+
+    // Buying on a denied wallet's behalf is screened too
+    let recipient_token_account = h
+        .create_token_account(&buyer.wallet.pubkey(), &sale.token_mint)
+        .await;
+    assert_error(
+        h.process(
+            &[instructions::buy_tokens_for(
+                &other.wallet.pubkey(),
+                &buyer.wallet.pubkey(),
+                &sale.address,
+                &other.payment_account,
+                &sale.seller_payment_account,
+                &sale.fee_account,
+                &recipient_token_account,
+                false,
+                false,
+                args::BuyTokensFor { token_amount: 10 },
+            )],
+            &[&other.wallet],
+        )
+        .await,
+        ErrorCode::WalletDenied,
+    );
+
+    // Allowing the wallet again closes its entry
+    h.process(
+        &[instructions::allow_wallet(
+            &compliance.pubkey(),
+            &buyer.wallet.pubkey(),
+        )],
+        &[&compliance],
+    )
+    .await
+    .unwrap();
+    assert!(h
+        .account(&pda::denied_wallet(&deny_list, &buyer.wallet.pubkey()).0)
+        .await
+        .is_none());
+    h.next_slot().await;
+    buy(&mut h, &sale, &buyer, 10).await.unwrap();
+    assert_eq!(h.token_balance(&buyer.token_account).await, 20);
+}
+
+#[tokio::test]
+async fn required_deny_list_screens_every_sale() {
+    let mut h = Harness::new().await;
+    let admin = h.wallet().await;
+    let compliance = h.wallet().await;
+    configure(&mut h, &admin, compliance.pubkey(), true)
+        .await
+        .unwrap();
+
+    // There is one list for the program: nobody creates another, and only its admin
+    // changes it
+    let intruder = h.wallet().await;
+    assert!(h
+        .process(
+            &[instructions::create_deny_list(
+                &intruder.pubkey(),
+                args::CreateDenyList {
+                    authority: intruder.pubkey(),
+                    required: false,
+                },
+            )],
+            &[&intruder],
+        )
+        .await
+        .is_err());
+    assert_error(
+        configure(&mut h, &intruder, intruder.pubkey(), false).await,
+        AnchorError::ConstraintRaw,
+    );
+    let list: DenyList = h.fetch(&pda::deny_list().0).await;
+    assert_eq!((list.admin, list.required), (admin.pubkey(), true));
+
+    // Whichever fee recipient the seller names, the sale is under the list
+    let sale = create_sale(&mut h).await;
+    h.warp_to(SALE_START).await;
+    let buyer = create_buyer(&mut h, &sale).await;
+    deny(&mut h, &compliance, buyer.wallet.pubkey())
+        .await
+        .unwrap();
+
+    // The seller never opted in, yet the requirement applies
+    assert_error(
+        buy(&mut h, &sale, &buyer, 10).await,
+        ErrorCode::WalletDenied,
+    );
+    assert_error(
+        screen(&mut h, &sale, false).await,
+        ErrorCode::DenyListRequired,
+    );
+
+    // Once the admin relaxes the requirement, unscreened sales are open again
+    configure(&mut h, &admin, compliance.pubkey(), false)
+        .await
+        .unwrap();
+    screen(&mut h, &sale, false).await.unwrap();
+    buy(&mut h, &sale, &buyer, 10).await.unwrap();
+}
+
+#[tokio::test]
+async fn required_deny_list_screens_tickets_deposits_and_bids() {
+    let mut h = Harness::new().await;
+    let admin = h.wallet().await;
+    let compliance = h.wallet().await;
+    configure(&mut h, &admin, compliance.pubkey(), true)
+        .await
+        .unwrap();
+
+    let lottery = create_sale(&mut h).await;
+    let overflow = create_sale(&mut h).await;
+    let auction = create_sale(&mut h).await;
+    h.process(
+        &[
+            instructions::configure_lottery(
+                &lottery.seller.pubkey(),
+                &lottery.address,
+                &lottery.payment_mint,
+                args::ConfigureLottery {
+                    ticket_tokens: 100,
+                    seed_commitment: None,
+                    revealer: None,
+                },
+            ),
+            instructions::configure_overflow(
+                &overflow.seller.pubkey(),
+                &overflow.address,
+                &overflow.payment_mint,
+            ),
+            instructions::configure_auction(
+                &auction.seller.pubkey(),
+                &auction.address,
+                &auction.payment_mint,
+                args::ConfigureAuction {
+                    min_price: 1_000,
+                    tick_size: 100,
+                    price_levels: 5,
+                },
+            ),
+        ],
+        &[&lottery.seller, &overflow.seller, &auction.seller],
+    )
+    .await
+    .unwrap();
+
+    h.warp_to(SALE_START).await;
+    let mut buyers = Vec::new();
+    for sale in [&lottery, &overflow, &auction] {
+        let buyer = create_buyer(&mut h, sale).await;
+        deny(&mut h, &compliance, buyer.wallet.pubkey())
+            .await
+            .unwrap();
+        buyers.push(buyer);
+    }
+
+    // None of the sellers opted in, yet no allocation method gets around the list
+    let wallet = buyers[0].wallet.pubkey();
+    let register = instructions::register_lottery_ticket(
+        &wallet,
+        &wallet,
+        &lottery.address,
+        &buyers[0].payment_account,
+    );
+    assert_error(
+        h.process(&[register], &[&buyers[0].wallet]).await,
+        ErrorCode::WalletDenied,
+    );
+    let deposit = instructions::deposit_overflow(
+        &buyers[1].wallet.pubkey(),
+        &overflow.address,
+        &buyers[1].payment_account,
+        args::DepositOverflow { amount: 1_000 },
+    );
+    assert_error(
+        h.process(&[deposit], &[&buyers[1].wallet]).await,
+        ErrorCode::WalletDenied,
+    );
+    let wallet = buyers[2].wallet.pubkey();
+    let bid = instructions::submit_bid(
+        &wallet,
+        &wallet,
+        &auction.address,
+        &buyers[2].payment_account,
+        args::SubmitBid {
+            bid_id: 0,
+            quantity: 100,
+            max_price: 1_000,
+        },
+    );
+    assert_error(
+        h.process(&[bid], &[&buyers[2].wallet]).await,
+        ErrorCode::WalletDenied,
+    );
+    // Sealed commitments are screened before the auction is even looked at
+    let commit = instructions::commit_sealed_bid(
+        &wallet,
+        &wallet,
+        &auction.address,
+        &buyers[2].payment_account,
+        args::CommitSealedBid {
+            bid_id: 0,
+            commitment: [0; 32],
+        },
+    );
+    assert_error(
+        h.process(&[commit], &[&buyers[2].wallet]).await,
+        ErrorCode::WalletDenied,
+    );
+
+    // A wallet not on the list still bids
+    let bidder = create_buyer(&mut h, &auction).await;
+    let wallet = bidder.wallet.pubkey();
+    h.process(
+        &[instructions::submit_bid(
+            &wallet,
+            &wallet,
+            &auction.address,
+            &bidder.payment_account,
+            args::SubmitBid {
+                bid_id: 0,
+                quantity: 100,
+                max_price: 1_000,
+            },
+        )],
+        &[&bidder.wallet],
+    )
+    .await
+    .unwrap();
+}
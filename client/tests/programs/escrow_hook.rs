@@ -0,0 +1,172 @@
+//! Escrow purchase hooks: a sale names an integrator program that every purchase calls
+//! through CPI, and a failing hook reverts the purchase.
+
+use anchor_lang::solana_program::account_info::AccountInfo;
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::solana_program::program_error::ProgramError;
+use anchor_lang::AnchorDeserialize;
+use escrow::ErrorCode;
+use solana_sdk::account::Account;
+use solana_sdk::instruction::AccountMeta;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use token_sale_client::escrow::{
+    instructions, PurchaseHookArgs, TokenSale, PROGRAM_ID, PURCHASE_HOOK_DISCRIMINATOR,
+};
+
+use crate::escrow_sale::{buy_ix, create_buyer, create_sale, Buyer, Sale, PRICE, SALE_START};
+use crate::harness::{assert_error, Harness, TxResult};
+
+/// Program ID of the stand-in hook loaded by the harness
+pub const HOOK_PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+
+/// Purchases of this many tokens are rejected by the stand-in hook
+const REJECTED_AMOUNT: u64 = 13;
+const HOOK_REJECTED: u32 = 0x4b1d;
+
+/// Stand-in hook: copies the purchase it is told about into its first account, followed
+/// by a byte saying whether any account arrived as a signer
+pub fn process_hook(_program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let args = data
+        .strip_prefix(&PURCHASE_HOOK_DISCRIMINATOR)
+        .and_then(|mut args| PurchaseHookArgs::deserialize(&mut args).ok())
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    if args.token_amount == REJECTED_AMOUNT {
+        return Err(ProgramError::Custom(HOOK_REJECTED));
+    }
+
+    let record = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let mut state = borsh_bytes(&args);
+    state.push(accounts.iter().any(|account| account.is_signer) as u8);
+    record.try_borrow_mut_data()?[..state.len()].copy_from_slice(&state);
+    Ok(())
+}
+
+fn borsh_bytes(args: &PurchaseHookArgs) -> Vec<u8> {
+    let mut data = Vec::new();
+    anchor_lang::AnchorSerialize::serialize(args, &mut data).unwrap();
+    data
+}
+
+async fn set_hook(h: &mut Harness, sale: &Sale, hook_program: &Pubkey) -> TxResult {
+    h.process(
+        &[instructions::set_purchase_hook(
+            &sale.seller.pubkey(),
+            &sale.address,
+            hook_program,
+        )],
+        &[&sale.seller],
+    )
+    .await
+}
+
+async fn buy_hooked(
+    h: &mut Harness,
+    sale: &Sale,
+    buyer: &Buyer,
+    hook_program: &Pubkey,
+    hook_accounts: &[AccountMeta],
+    token_amount: u64,
+) -> TxResult {
+    h.process(
+        &[instructions::with_purchase_hook(
+            buy_ix(sale, buyer, false, token_amount),
+            hook_program,
+            hook_accounts,
+        )],
+        &[&buyer.wallet],
+    )
+    .await
+}
+
+#[tokio::test]
+async fn purchase_hook_sees_every_purchase_and_can_revert_it() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let buyer = create_buyer(&mut h, &sale).await;
+
+    // Only programs other than this one can be hooks, and only once
+    assert_error(
+        set_hook(&mut h, &sale, &Pubkey::new_unique()).await,
+        ErrorCode::InvalidPurchaseHook,
+    );
+    assert_error(
+        set_hook(&mut h, &sale, &PROGRAM_ID).await,
+        ErrorCode::InvalidPurchaseHook,
+    );
+    set_hook(&mut h, &sale, &HOOK_PROGRAM_ID).await.unwrap();
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(state.purchase_hook(), Some(HOOK_PROGRAM_ID));
+    h.next_slot().await;
+    assert_error(
+        set_hook(&mut h, &sale, &HOOK_PROGRAM_ID).await,
+        ErrorCode::PurchaseHookAlreadySet,
+    );
+
+    let record = Pubkey::new_unique();
+    h.context.set_account(
+        &record,
+        &Account {
+            lamports: 1_000_000_000,
+            data: vec![0; 128],
+            owner: HOOK_PROGRAM_ID,
+            ..Account::default()
+        }
+        .into(),
+    );
+    // The buyer's signature is not handed on to the hook
+    let hook_accounts = [
+        AccountMeta::new(record, false),
+        AccountMeta::new_readonly(buyer.wallet.pubkey(), true),
+    ];
+
+    h.warp_to(SALE_START).await;
+    assert_error(
+        h.process(&[buy_ix(&sale, &buyer, false, 10)], &[&buyer.wallet])
+            .await,
+        ErrorCode::PurchaseHookAccountsMissing,
+    );
+    assert_error(
+        buy_hooked(
+            &mut h,
+            &sale,
+            &buyer,
+            &Pubkey::new_unique(),
+            &hook_accounts,
+            10,
+        )
+        .await,
+        ErrorCode::PurchaseHookAccountsMissing,
+    );
+    buy_hooked(&mut h, &sale, &buyer, &HOOK_PROGRAM_ID, &hook_accounts, 10)
+        .await
+        .unwrap();
+
+    let expected = PurchaseHookArgs {
+        sale: sale.address,
+        buyer: buyer.wallet.pubkey(),
+        token_amount: 10,
+        cost: 10 * PRICE,
+    };
+    let data = h.account(&record).await.unwrap().data;
+    let recorded = borsh_bytes(&expected);
+    assert_eq!(&data[..recorded.len()], recorded.as_slice());
+    assert_eq!(data[recorded.len()], 0);
+
+    // A failing hook takes the purchase down with it
+    assert_error(
+        buy_hooked(
+            &mut h,
+            &sale,
+            &buyer,
+            &HOOK_PROGRAM_ID,
+            &hook_accounts,
+            REJECTED_AMOUNT,
+        )
+        .await,
+        HOOK_REJECTED,
+    );
+    assert_eq!(h.token_balance(&buyer.token_account).await, 10);
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(state.tokens_available, 990);
+}
@@ -0,0 +1,218 @@
+//! Escrow token locks: purchases pay out transferable receipts, redeemed by whoever holds
+//! them for the locked tokens once the unlock time has passed.
+
+use anchor_lang::error::ErrorCode as AnchorError;
+use escrow::ErrorCode;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use token_sale_client::escrow::{args, instructions, pda, TokenSale};
+
+use crate::escrow_sale::{create_buyer, create_sale, Buyer, Sale, SALE_END, SALE_START};
+use crate::harness::{assert_error, Harness, TxResult};
+
+const UNLOCK_TIME: i64 = SALE_END + 500;
+
+async fn lock(h: &mut Harness, sale: &Sale, unlock_time: i64) -> TxResult {
+    h.process(
+        &[instructions::configure_token_lock(
+            &sale.seller.pubkey(),
+            &sale.address,
+            &sale.token_mint,
+            args::ConfigureTokenLock { unlock_time },
+        )],
+        &[&sale.seller],
+    )
+    .await
+}
+
+async fn buy_into(
+    h: &mut Harness,
+    sale: &Sale,
+    buyer: &Buyer,
+    token_account: &Pubkey,
+    locks_tokens: bool,
+    token_amount: u64,
+) -> TxResult {
+    h.process(
+        &[instructions::buy_tokens(
+            &buyer.wallet.pubkey(),
+            &sale.address,
+            &buyer.payment_account,
+            &sale.seller_payment_account,
+            &sale.fee_account,
+            token_account,
+            false,
+            locks_tokens,
+            args::BuyTokens { token_amount },
+        )],
+        &[&buyer.wallet],
+    )
+    .await
+}
+
+async fn redeem(
+    h: &mut Harness,
+    sale: &Sale,
+    holder: &Keypair,
+    receipt_account: &Pubkey,
+    token_account: &Pubkey,
+    amount: u64,
+) -> TxResult {
+    h.process(
+        &[instructions::redeem_receipts(
+            &holder.pubkey(),
+            &sale.address,
+            receipt_account,
+            token_account,
+            args::RedeemReceipts { amount },
+        )],
+        &[holder],
+    )
+    .await
+}
+
+#[tokio::test]
+async fn locked_purchases_pay_out_transferable_receipts() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let receipt_mint = pda::receipt_mint(&sale.address).0;
+
+    assert_error(
+        lock(&mut h, &sale, SALE_END - 1).await,
+        ErrorCode::InvalidUnlockTime,
+    );
+    lock(&mut h, &sale, UNLOCK_TIME).await.unwrap();
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(
+        (state.receipt_mint, state.unlock_time),
+        (receipt_mint, UNLOCK_TIME)
+    );
+    // Other allocation modes would pay out the tokens themselves
+    assert_error(
+        h.process(
+            &[instructions::configure_overflow(
+                &sale.seller.pubkey(),
+                &sale.address,
+                &sale.payment_mint,
+            )],
+            &[&sale.seller],
+        )
+        .await,
+        ErrorCode::OverflowIncompatible,
+    );
+    // Nor can a sale allocated another way lock its tokens
+    let lottery_sale = create_sale(&mut h).await;
+    h.process(
+        &[instructions::configure_lottery(
+            &lottery_sale.seller.pubkey(),
+            &lottery_sale.address,
+            &lottery_sale.payment_mint,
+            args::ConfigureLottery {
+                ticket_tokens: 500,
+                seed_commitment: None,
+                revealer: None,
+            },
+        )],
+        &[&lottery_sale.seller],
+    )
+    .await
+    .unwrap();
+    assert_error(
+        lock(&mut h, &lottery_sale, UNLOCK_TIME).await,
+        ErrorCode::TokenLockIncompatible,
+    );
+
+    h.warp_to(SALE_START).await;
+    let buyer = create_buyer(&mut h, &sale).await;
+    let receipts = h
+        .create_token_account(&buyer.wallet.pubkey(), &receipt_mint)
+        .await;
+    assert_error(
+        buy_into(&mut h, &sale, &buyer, &buyer.token_account, true, 100).await,
+        AnchorError::ConstraintRaw,
+    );
+    assert_error(
+        buy_into(&mut h, &sale, &buyer, &receipts, false, 100).await,
+        ErrorCode::ReceiptMintRequired,
+    );
+    buy_into(&mut h, &sale, &buyer, &receipts, true, 100)
+        .await
+        .unwrap();
+    assert_eq!(h.token_balance(&receipts).await, 100);
+    assert_eq!(h.token_balance(&buyer.token_account).await, 0);
+    assert_eq!(
+        h.token_balance(&pda::token_vault(&sale.address).0).await,
+        1_000
+    );
+
+    // Receipts change hands like any token; the holder redeems them
+    let trader = h.wallet().await;
+    let trader_receipts = h
+        .create_token_account(&trader.pubkey(), &receipt_mint)
+        .await;
+    let trader_tokens = h
+        .create_token_account(&trader.pubkey(), &sale.token_mint)
+        .await;
+    h.process(
+        &[spl_token::instruction::transfer(
+            &spl_token::ID,
+            &receipts,
+            &trader_receipts,
+            &buyer.wallet.pubkey(),
+            &[],
+            60,
+        )
+        .unwrap()],
+        &[&buyer.wallet],
+    )
+    .await
+    .unwrap();
+
+    h.warp_to(SALE_END + 1).await;
+    assert_error(
+        redeem(&mut h, &sale, &trader, &trader_receipts, &trader_tokens, 60).await,
+        ErrorCode::TokensLocked,
+    );
+    h.warp_to(UNLOCK_TIME).await;
+    redeem(&mut h, &sale, &trader, &trader_receipts, &trader_tokens, 60)
+        .await
+        .unwrap();
+    assert_eq!(h.token_balance(&trader_tokens).await, 60);
+    assert_eq!(h.token_balance(&trader_receipts).await, 0);
+    redeem(
+        &mut h,
+        &sale,
+        &buyer.wallet,
+        &receipts,
+        &buyer.token_account,
+        40,
+    )
+    .await
+    .unwrap();
+    assert_eq!(h.token_balance(&buyer.token_account).await, 40);
+
+    h.next_slot().await;
+    assert!(redeem(
+        &mut h,
+        &sale,
+        &buyer.wallet,
+        &receipts,
+        &buyer.token_account,
+        1
+    )
+    .await
+    .is_err());
+    assert_eq!(
+        h.token_balance(&pda::token_vault(&sale.address).0).await,
+        900
+    );
+
+    // The receipt mint always exists here, so clear the sale's record of it to reach the check
+    let mut state: TokenSale = h.fetch(&sale.address).await;
+    state.receipt_mint = Pubkey::default();
+    h.store_zero_copy(&sale.address, &state).await;
+    assert_error(
+        redeem(&mut h, &sale, &trader, &trader_receipts, &trader_tokens, 1).await,
+        ErrorCode::NotLockedSale,
+    );
+}
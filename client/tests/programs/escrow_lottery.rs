@@ -0,0 +1,421 @@
+//! Escrow lottery sales: buyers register tickets with a deposit, winners are drawn from a
+//! revealed seed or a future slot hash, and losers are refunded.
+
+use anchor_lang::error::ErrorCode as AnchorError;
+use escrow::ErrorCode;
+use solana_sdk::hash::{hash, hashv, Hash};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::sysvar::slot_hashes;
+use token_sale_client::escrow::{
+    args, instructions, pda, Lottery, LotteryTicket, PriceTier, TokenSale,
+    LOTTERY_DRAW_DELAY_SLOTS, LOTTERY_REVEAL_PERIOD, MAX_SLOT_HASH_AGE,
+};
+
+use crate::escrow_sale::{
+    buy, create_buyer, create_sale, Buyer, Sale, PRICE, SALE_END, SALE_START,
+};
+use crate::harness::{assert_error, Harness, TxResult};
+
+const SEED: [u8; 32] = [7; 32];
+
+async fn configure(
+    h: &mut Harness,
+    sale: &Sale,
+    ticket_tokens: u64,
+    seed_commitment: Option<[u8; 32]>,
+    revealer: Option<Pubkey>,
+) -> TxResult {
+    h.process(
+        &[instructions::configure_lottery(
+            &sale.seller.pubkey(),
+            &sale.address,
+            &sale.payment_mint,
+            args::ConfigureLottery {
+                ticket_tokens,
+                seed_commitment,
+                revealer,
+            },
+        )],
+        &[&sale.seller],
+    )
+    .await
+}
+
+/// Register a ticket, the transaction fee payer covering its rent
+async fn register(h: &mut Harness, sale: &Sale, buyer: &Buyer) -> TxResult {
+    let rent_payer = h.payer().pubkey();
+    h.process(
+        &[instructions::register_lottery_ticket(
+            &buyer.wallet.pubkey(),
+            &rent_payer,
+            &sale.address,
+            &buyer.payment_account,
+        )],
+        &[&buyer.wallet],
+    )
+    .await
+}
+
+async fn reveal(h: &mut Harness, sale: &Sale, revealer: &Keypair, seed: [u8; 32]) -> TxResult {
+    h.process(
+        &[instructions::reveal_lottery_seed(
+            &revealer.pubkey(),
+            &sale.address,
+            args::RevealLotterySeed { seed },
+        )],
+        &[revealer],
+    )
+    .await
+}
+
+async fn claim(h: &mut Harness, sale: &Sale, buyer: &Buyer) -> TxResult {
+    h.process(
+        &[instructions::claim_lottery_ticket(
+            &buyer.wallet.pubkey(),
+            &sale.address,
+            &buyer.payment_account,
+            &buyer.token_account,
+            &sale.seller_payment_account,
+            &sale.fee_account,
+        )],
+        &[&buyer.wallet],
+    )
+    .await
+}
+
+async fn cancel(h: &mut Harness, sale: &Sale) -> TxResult {
+    h.process(
+        &[instructions::cancel_sale(
+            &sale.seller.pubkey(),
+            &sale.address,
+            &sale.seller_token_account,
+            false,
+        )],
+        &[&sale.seller],
+    )
+    .await
+}
+
+/// `count` buyers, each holding a registered ticket
+async fn register_buyers(h: &mut Harness, sale: &Sale, count: usize) -> Vec<Buyer> {
+    let mut buyers = Vec::new();
+    for _ in 0..count {
+        let buyer = create_buyer(h, sale).await;
+        register(h, sale, &buyer).await.unwrap();
+        buyers.push(buyer);
+    }
+    buyers
+}
+
+#[tokio::test]
+async fn revealed_seed_picks_winners_and_refunds_the_rest() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let revealer = h.wallet().await;
+    let commitment = hash(&SEED).to_bytes();
+
+    assert_error(
+        configure(&mut h, &sale, 300, Some(commitment), None).await,
+        ErrorCode::InvalidLotteryConfig,
+    );
+    assert_error(
+        configure(
+            &mut h,
+            &sale,
+            2_000,
+            Some(commitment),
+            Some(revealer.pubkey()),
+        )
+        .await,
+        ErrorCode::InvalidTokenAmount,
+    );
+    // 1,000 tokens cover three tickets of 300
+    configure(
+        &mut h,
+        &sale,
+        300,
+        Some(commitment),
+        Some(revealer.pubkey()),
+    )
+    .await
+    .unwrap();
+
+    h.warp_to(SALE_START).await;
+    let buyers = register_buyers(&mut h, &sale, 5).await;
+    assert_error(
+        buy(&mut h, &sale, &buyers[0], 10).await,
+        ErrorCode::LotterySale,
+    );
+    h.next_slot().await;
+    assert!(register(&mut h, &sale, &buyers[0]).await.is_err());
+    let ticket: LotteryTicket = h
+        .fetch(&pda::lottery_ticket(&buyers[4].wallet.pubkey(), &sale.address).0)
+        .await;
+    assert_eq!((ticket.index, ticket.deposit), (4, 300 * PRICE));
+
+    assert_error(
+        reveal(&mut h, &sale, &revealer, SEED).await,
+        ErrorCode::SaleNotEnded,
+    );
+    h.warp_to(SALE_END + 1).await;
+    assert_error(
+        claim(&mut h, &sale, &buyers[0]).await,
+        ErrorCode::LotteryNotDrawn,
+    );
+    assert_error(
+        reveal(&mut h, &sale, &revealer, [8; 32]).await,
+        ErrorCode::LotterySeedMismatch,
+    );
+    reveal(&mut h, &sale, &revealer, SEED).await.unwrap();
+    assert_error(
+        reveal(&mut h, &sale, &revealer, SEED).await,
+        ErrorCode::LotteryAlreadyDrawn,
+    );
+
+    let lottery: Lottery = h.fetch(&pda::lottery(&sale.address).0).await;
+    assert_eq!((lottery.tickets, lottery.winners), (5, 3));
+    assert_eq!(lottery.seed, SEED);
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(state.tokens_available, 100);
+
+    let mut winners = 0;
+    for buyer in &buyers {
+        claim(&mut h, &sale, buyer).await.unwrap();
+        let tokens = h.token_balance(&buyer.token_account).await;
+        let payment = h.token_balance(&buyer.payment_account).await;
+        if tokens == 300 {
+            winners += 1;
+            assert_eq!(payment, 10_000_000 - 300 * PRICE);
+        } else {
+            assert_eq!((tokens, payment), (0, 10_000_000));
+        }
+    }
+    assert_eq!(winners, 3);
+    assert_eq!(h.token_balance(&sale.seller_payment_account).await, 877_500);
+    assert_eq!(h.token_balance(&sale.fee_account).await, 22_500);
+    assert_eq!(
+        h.token_balance(&pda::lottery_vault(&sale.address).0).await,
+        0
+    );
+
+    h.next_slot().await;
+    assert_error(
+        claim(&mut h, &sale, &buyers[0]).await,
+        ErrorCode::LotteryTicketClaimed,
+    );
+
+    // Only the tokens no ticket won go back to the seller
+    cancel(&mut h, &sale).await.unwrap();
+    assert_eq!(h.token_balance(&sale.seller_token_account).await, 100);
+}
+
+#[tokio::test]
+async fn slot_hash_draw_uses_a_future_slot() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    configure(&mut h, &sale, 500, None, None).await.unwrap();
+    h.warp_to(SALE_START).await;
+    let buyers = register_buyers(&mut h, &sale, 3).await;
+
+    let request = || instructions::request_lottery_draw(&sale.address);
+    let draw = || instructions::draw_lottery(&sale.address);
+    assert_error(h.process(&[request()], &[]).await, ErrorCode::SaleNotEnded);
+
+    h.warp_to(SALE_END + 1).await;
+    let slot = h.clock().await.slot;
+    h.process(&[request()], &[]).await.unwrap();
+    let lottery: Lottery = h.fetch(&pda::lottery(&sale.address).0).await;
+    assert_eq!(lottery.draw_slot, slot + LOTTERY_DRAW_DELAY_SLOTS);
+    assert_error(
+        h.process(&[request()], &[]).await,
+        ErrorCode::LotteryDrawRequested,
+    );
+    assert_error(
+        h.process(&[draw()], &[]).await,
+        ErrorCode::LotteryDrawSlotNotReached,
+    );
+
+    // A sysvar too short to hold its entry count is rejected
+    h.warp_to_slot(lottery.draw_slot + 1).await;
+    h.store_bytes(&slot_hashes::ID, vec![0; 4]).await;
+    assert_error(
+        h.process(&[draw()], &[]).await,
+        ErrorCode::InvalidSlotHashes,
+    );
+
+    // The requested slot's hash has left the sysvar: request a new slot
+    h.set_slot_hashes(&[(lottery.draw_slot - 1, Hash::new_unique())]);
+    assert_error(
+        h.process(&[draw()], &[]).await,
+        ErrorCode::LotteryDrawSlotExpired,
+    );
+    h.warp_to_slot(lottery.draw_slot + MAX_SLOT_HASH_AGE + 1)
+        .await;
+    h.process(&[request()], &[]).await.unwrap();
+    let lottery: Lottery = h.fetch(&pda::lottery(&sale.address).0).await;
+
+    h.warp_to_slot(lottery.draw_slot + 2).await;
+    let slot_hash = Hash::new_unique();
+    h.set_slot_hashes(&[
+        (lottery.draw_slot + 1, Hash::new_unique()),
+        (lottery.draw_slot, slot_hash),
+    ]);
+    h.process(&[draw()], &[]).await.unwrap();
+
+    let lottery: Lottery = h.fetch(&pda::lottery(&sale.address).0).await;
+    let seed = hashv(&[slot_hash.as_ref(), sale.address.as_ref()]).to_bytes();
+    assert_eq!(lottery.seed, seed);
+    assert_eq!((lottery.tickets, lottery.winners), (3, 2));
+
+    let mut sold = 0;
+    for buyer in &buyers {
+        claim(&mut h, &sale, buyer).await.unwrap();
+        sold += h.token_balance(&buyer.token_account).await;
+    }
+    assert_eq!(sold, 1_000);
+}
+
+#[tokio::test]
+async fn unrevealed_lottery_falls_back_or_refunds_on_cancel() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let revealer = Keypair::new();
+
+    // Lotteries replace tiered pricing and milestone escrow
+    h.process(
+        &[instructions::set_price_tiers(
+            &sale.seller.pubkey(),
+            &sale.address,
+            args::SetPriceTiers {
+                price_tiers: vec![PriceTier {
+                    tokens_sold_threshold: 100,
+                    price_per_token: 2 * PRICE,
+                }],
+            },
+        )],
+        &[&sale.seller],
+    )
+    .await
+    .unwrap();
+    assert_error(
+        configure(
+            &mut h,
+            &sale,
+            100,
+            Some(hash(&SEED).to_bytes()),
+            Some(revealer.pubkey()),
+        )
+        .await,
+        ErrorCode::LotteryIncompatible,
+    );
+    h.process(
+        &[instructions::set_price_tiers(
+            &sale.seller.pubkey(),
+            &sale.address,
+            args::SetPriceTiers {
+                price_tiers: vec![],
+            },
+        )],
+        &[&sale.seller],
+    )
+    .await
+    .unwrap();
+    configure(
+        &mut h,
+        &sale,
+        100,
+        Some(hash(&SEED).to_bytes()),
+        Some(revealer.pubkey()),
+    )
+    .await
+    .unwrap();
+
+    h.warp_to(SALE_START).await;
+    let buyers = register_buyers(&mut h, &sale, 2).await;
+
+    // The revealer gets the first chance to draw
+    let request = || instructions::request_lottery_draw(&sale.address);
+    h.warp_to(SALE_END + 1).await;
+    assert_error(
+        h.process(&[request()], &[]).await,
+        ErrorCode::LotteryRevealPending,
+    );
+    h.warp_to(SALE_END + LOTTERY_REVEAL_PERIOD + 1).await;
+    // Past its period the revealer cannot pick between its seed and the slot hash
+    assert_error(
+        reveal(&mut h, &sale, &revealer, SEED).await,
+        ErrorCode::LotteryRevealExpired,
+    );
+    h.process(&[request()], &[]).await.unwrap();
+
+    // Cancelled before the draw, every ticket is refunded
+    cancel(&mut h, &sale).await.unwrap();
+    assert_eq!(h.token_balance(&sale.seller_token_account).await, 1_000);
+    for buyer in &buyers {
+        claim(&mut h, &sale, buyer).await.unwrap();
+        assert_eq!(h.token_balance(&buyer.payment_account).await, 10_000_000);
+        assert_eq!(h.token_balance(&buyer.token_account).await, 0);
+    }
+    assert_eq!(h.token_balance(&sale.seller_payment_account).await, 0);
+}
+
+#[tokio::test]
+async fn closed_ticket_returns_its_rent_to_the_sponsor() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    configure(
+        &mut h,
+        &sale,
+        100,
+        Some(hash(&SEED).to_bytes()),
+        Some(Pubkey::new_unique()),
+    )
+    .await
+    .unwrap();
+
+    h.warp_to(SALE_START).await;
+    let buyer = create_buyer(&mut h, &sale).await;
+    let sponsor = h.wallet().await;
+    h.process(
+        &[instructions::register_lottery_ticket(
+            &buyer.wallet.pubkey(),
+            &sponsor.pubkey(),
+            &sale.address,
+            &buyer.payment_account,
+        )],
+        &[&buyer.wallet, &sponsor],
+    )
+    .await
+    .unwrap();
+    let ticket_address = pda::lottery_ticket(&buyer.wallet.pubkey(), &sale.address).0;
+    let ticket: LotteryTicket = h.fetch(&ticket_address).await;
+    assert_eq!(ticket.rent_payer, sponsor.pubkey());
+    let rent = h.account(&ticket_address).await.unwrap().lamports;
+    let sponsor_lamports = h.account(&sponsor.pubkey()).await.unwrap().lamports;
+
+    let close = |rent_payer: &Pubkey| {
+        instructions::close_lottery_ticket(&buyer.wallet.pubkey(), rent_payer, &sale.address)
+    };
+    assert_error(
+        h.process(&[close(&sponsor.pubkey())], &[&buyer.wallet])
+            .await,
+        ErrorCode::LotteryTicketNotClaimed,
+    );
+    cancel(&mut h, &sale).await.unwrap();
+    claim(&mut h, &sale, &buyer).await.unwrap();
+    assert_error(
+        h.process(&[close(&buyer.wallet.pubkey())], &[&buyer.wallet])
+            .await,
+        AnchorError::ConstraintRaw,
+    );
+    h.process(&[close(&sponsor.pubkey())], &[&buyer.wallet])
+        .await
+        .unwrap();
+
+    assert!(h.account(&ticket_address).await.is_none());
+    assert_eq!(
+        h.account(&sponsor.pubkey()).await.unwrap().lamports,
+        sponsor_lamports + rent
+    );
+}
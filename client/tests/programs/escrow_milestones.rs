@@ -0,0 +1,342 @@
+//! Escrow milestone sales: proceeds held in a vault, released by approval or buyer vote,
+//! refunded after a missed deadline.
+
+use escrow::ErrorCode;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use token_sale_client::escrow::{args, instructions, pda, Milestone, TokenSale};
+
+use crate::escrow_sale::{buy_ix, create_buyer, create_sale, Buyer, Sale, SALE_END, SALE_START};
+use crate::harness::{assert_error, Harness, TxResult, DAY};
+
+const FIRST_DEADLINE: i64 = SALE_END + DAY;
+const SECOND_DEADLINE: i64 = SALE_END + 2 * DAY;
+
+fn milestones() -> Vec<Milestone> {
+    vec![
+        Milestone {
+            release_bps: 4_000,
+            deadline: FIRST_DEADLINE,
+        },
+        Milestone {
+            release_bps: 6_000,
+            deadline: SECOND_DEADLINE,
+        },
+    ]
+}
+
+async fn configure(
+    h: &mut Harness,
+    sale: &Sale,
+    milestones: Vec<Milestone>,
+    approver: Option<Pubkey>,
+) -> TxResult {
+    h.process(
+        &[instructions::configure_milestones(
+            &sale.seller.pubkey(),
+            &sale.address,
+            &sale.payment_mint,
+            args::ConfigureMilestones {
+                milestones,
+                approver,
+            },
+        )],
+        &[&sale.seller],
+    )
+    .await
+}
+
+async fn buy(h: &mut Harness, sale: &Sale, buyer: &Buyer, token_amount: u64) -> TxResult {
+    h.process(&[buy_ix(sale, buyer, true, token_amount)], &[&buyer.wallet])
+        .await
+}
+
+async fn approve(h: &mut Harness, sale: &Sale, approver: &Keypair) -> TxResult {
+    h.process(
+        &[instructions::approve_milestone(
+            &approver.pubkey(),
+            &sale.address,
+        )],
+        &[approver],
+    )
+    .await
+}
+
+async fn vote(h: &mut Harness, sale: &Sale, buyer: &Buyer) -> TxResult {
+    h.process(
+        &[instructions::vote_milestone(
+            &buyer.wallet.pubkey(),
+            &sale.address,
+        )],
+        &[&buyer.wallet],
+    )
+    .await
+}
+
+async fn release(h: &mut Harness, sale: &Sale) -> TxResult {
+    h.process(
+        &[instructions::release_milestone(
+            &sale.address,
+            &sale.seller_payment_account,
+        )],
+        &[],
+    )
+    .await
+}
+
+async fn claim_refund(h: &mut Harness, sale: &Sale, buyer: &Buyer) -> TxResult {
+    h.process(
+        &[instructions::claim_milestone_refund(
+            &buyer.wallet.pubkey(),
+            &sale.address,
+            &buyer.payment_account,
+        )],
+        &[&buyer.wallet],
+    )
+    .await
+}
+
+/// A milestone sale with an approver, and two buyers holding 100 and 300 tokens
+async fn milestone_sale(h: &mut Harness) -> (Sale, Keypair, Buyer, Buyer) {
+    let sale = create_sale(h).await;
+    let approver = h.wallet().await;
+    configure(h, &sale, milestones(), Some(approver.pubkey()))
+        .await
+        .unwrap();
+
+    let small = create_buyer(h, &sale).await;
+    let large = create_buyer(h, &sale).await;
+    h.warp_to(SALE_START).await;
+    buy(h, &sale, &small, 100).await.unwrap();
+    buy(h, &sale, &large, 300).await.unwrap();
+    (sale, approver, small, large)
+}
+
+#[tokio::test]
+async fn configure_milestones_validates_schedule() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let milestone = |release_bps, deadline| Milestone {
+        release_bps,
+        deadline,
+    };
+
+    let cases = [
+        (vec![], ErrorCode::InvalidMilestones),
+        (
+            vec![milestone(2_000, FIRST_DEADLINE); 6],
+            ErrorCode::TooManyMilestones,
+        ),
+        (
+            vec![milestone(10_000, SALE_END)],
+            ErrorCode::InvalidMilestones,
+        ),
+        (
+            vec![milestone(0, FIRST_DEADLINE)],
+            ErrorCode::InvalidMilestones,
+        ),
+        (
+            vec![milestone(5_000, FIRST_DEADLINE)],
+            ErrorCode::InvalidMilestones,
+        ),
+        (
+            vec![
+                milestone(5_000, SECOND_DEADLINE),
+                milestone(5_000, FIRST_DEADLINE),
+            ],
+            ErrorCode::InvalidMilestones,
+        ),
+    ];
+    for (milestones, error) in cases {
+        assert_error(configure(&mut h, &sale, milestones, None).await, error);
+    }
+
+    configure(&mut h, &sale, milestones(), None).await.unwrap();
+    // Reconfiguring trips the proceeds vault `init` (system AccountAlreadyInUse) first
+    assert_error(configure(&mut h, &sale, milestones(), None).await, 0u32);
+
+    // The handler's own check only triggers for a schedule stored without a vault
+    let other = create_sale(&mut h).await;
+    let mut state: TokenSale = h.fetch(&other.address).await;
+    state.set_milestones(&milestones());
+    h.store_zero_copy(&other.address, &state).await;
+    assert_error(
+        configure(&mut h, &other, milestones(), None).await,
+        ErrorCode::MilestonesAlreadyConfigured,
+    );
+
+    // The sale end cannot move past the first deadline once milestones are set
+    assert_error(
+        h.process(
+            &[instructions::update_sale_params(
+                &sale.seller.pubkey(),
+                &sale.address,
+                args::UpdateSaleParams {
+                    new_price_per_token: None,
+                    new_sale_start_time: None,
+                    new_sale_end_time: Some(FIRST_DEADLINE),
+                    new_max_tokens_per_buyer: None,
+                    new_min_sale_duration: None,
+                    new_max_sale_duration: None,
+                },
+            )],
+            &[&sale.seller],
+        )
+        .await,
+        ErrorCode::InvalidMilestones,
+    );
+}
+
+#[tokio::test]
+async fn configure_milestones_only_before_start() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    h.warp_to(SALE_START).await;
+
+    assert_error(
+        configure(&mut h, &sale, milestones(), None).await,
+        ErrorCode::SaleAlreadyStarted,
+    );
+}
+
+#[tokio::test]
+async fn milestone_sales_escrow_proceeds() {
+    let mut h = Harness::new().await;
+    let (sale, _, small, _) = milestone_sale(&mut h).await;
+
+    let proceeds_vault = pda::proceeds_vault(&sale.address).0;
+    assert_eq!(h.token_balance(&proceeds_vault).await, 390_000);
+    assert_eq!(h.token_balance(&sale.seller_payment_account).await, 0);
+
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(state.proceeds_escrowed, 390_000);
+    assert_eq!(state.milestone_vote_supply, 400);
+
+    assert_error(
+        h.process(&[buy_ix(&sale, &small, false, 1)], &[&small.wallet])
+            .await,
+        ErrorCode::ProceedsVaultRequired,
+    );
+}
+
+#[tokio::test]
+async fn approver_releases_milestones_in_order() {
+    let mut h = Harness::new().await;
+    let (sale, approver, _, _) = milestone_sale(&mut h).await;
+    let stranger = h.wallet().await;
+
+    assert_error(
+        approve(&mut h, &sale, &approver).await,
+        ErrorCode::SaleNotEnded,
+    );
+    h.warp_to(SALE_END + 1).await;
+
+    assert_error(
+        approve(&mut h, &sale, &stranger).await,
+        ErrorCode::UnauthorizedMilestoneApprover,
+    );
+    assert_error(
+        release(&mut h, &sale).await,
+        ErrorCode::MilestoneNotApproved,
+    );
+
+    approve(&mut h, &sale, &approver).await.unwrap();
+    assert_error(
+        approve(&mut h, &sale, &approver).await,
+        ErrorCode::MilestoneAlreadyApproved,
+    );
+    release(&mut h, &sale).await.unwrap();
+    assert_eq!(h.token_balance(&sale.seller_payment_account).await, 156_000);
+
+    h.warp_to(SECOND_DEADLINE).await;
+    approve(&mut h, &sale, &approver).await.unwrap();
+    release(&mut h, &sale).await.unwrap();
+    assert_eq!(h.token_balance(&sale.seller_payment_account).await, 390_000);
+    assert_eq!(
+        h.token_balance(&pda::proceeds_vault(&sale.address).0).await,
+        0
+    );
+
+    assert_error(
+        approve(&mut h, &sale, &approver).await,
+        ErrorCode::NoPendingMilestone,
+    );
+}
+
+#[tokio::test]
+async fn buyer_majority_vote_unlocks_milestone() {
+    let mut h = Harness::new().await;
+    let (sale, _, small, large) = milestone_sale(&mut h).await;
+    h.warp_to(SALE_END + 1).await;
+
+    // 100 of 400 tokens is not a majority
+    vote(&mut h, &sale, &small).await.unwrap();
+    assert!(!h
+        .fetch::<TokenSale>(&sale.address)
+        .await
+        .milestone_approved());
+    assert_error(vote(&mut h, &sale, &small).await, ErrorCode::AlreadyVoted);
+
+    vote(&mut h, &sale, &large).await.unwrap();
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert!(state.milestone_approved());
+    assert_eq!(state.milestone_votes, 400);
+
+    release(&mut h, &sale).await.unwrap();
+    assert_eq!(
+        h.fetch::<TokenSale>(&sale.address).await.current_milestone,
+        1
+    );
+}
+
+#[tokio::test]
+async fn missed_deadline_opens_refunds() {
+    let mut h = Harness::new().await;
+    let (sale, approver, small, large) = milestone_sale(&mut h).await;
+    h.warp_to(SALE_END + 1).await;
+    approve(&mut h, &sale, &approver).await.unwrap();
+    release(&mut h, &sale).await.unwrap();
+
+    assert_error(
+        claim_refund(&mut h, &sale, &small).await,
+        ErrorCode::MilestoneDeadlineNotMissed,
+    );
+
+    h.warp_to(SECOND_DEADLINE + 1).await;
+    assert_error(
+        approve(&mut h, &sale, &approver).await,
+        ErrorCode::MilestoneDeadlinePassed,
+    );
+
+    // 60% of the proceeds were never released; each buyer gets their share of it
+    let before = h.token_balance(&small.payment_account).await;
+    claim_refund(&mut h, &sale, &small).await.unwrap();
+    assert_eq!(
+        h.token_balance(&small.payment_account).await - before,
+        58_500
+    );
+    assert_error(
+        claim_refund(&mut h, &sale, &small).await,
+        ErrorCode::RefundAlreadyClaimed,
+    );
+
+    let before = h.token_balance(&large.payment_account).await;
+    claim_refund(&mut h, &sale, &large).await.unwrap();
+    assert_eq!(
+        h.token_balance(&large.payment_account).await - before,
+        175_500
+    );
+
+    assert!(h
+        .fetch::<TokenSale>(&sale.address)
+        .await
+        .milestones_failed());
+    assert_error(
+        approve(&mut h, &sale, &approver).await,
+        ErrorCode::MilestonesFailed,
+    );
+    assert_error(
+        vote(&mut h, &sale, &large).await,
+        ErrorCode::MilestonesFailed,
+    );
+}
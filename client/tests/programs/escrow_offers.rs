@@ -0,0 +1,271 @@
+//! Escrow swap offers: make, take, partial fills, expiry and cancellation.
+
+use escrow::ErrorCode;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use token_sale_client::escrow::{args, instructions, pda, Offer};
+
+use crate::harness::{assert_error, Harness, TxResult, START_TIME};
+
+struct Swap {
+    maker: Keypair,
+    taker: Keypair,
+    maker_a: Pubkey,
+    maker_b: Pubkey,
+    taker_a: Pubkey,
+    taker_b: Pubkey,
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+}
+
+fn offer_args(offer_id: u64) -> args::MakeOffer {
+    args::MakeOffer {
+        offer_id,
+        token_a_amount: 300,
+        token_b_wanted: 1_000,
+        taker: None,
+        expires_at: 0,
+        partially_fillable: true,
+    }
+}
+
+async fn swap(h: &mut Harness) -> Swap {
+    let maker = h.wallet().await;
+    let taker = h.wallet().await;
+    let mint_a = h.create_mint(0).await;
+    let mint_b = h.create_mint(0).await;
+
+    Swap {
+        maker_a: h
+            .funded_token_account(&maker.pubkey(), &mint_a, 1_000)
+            .await,
+        maker_b: h.create_token_account(&maker.pubkey(), &mint_b).await,
+        taker_a: h.create_token_account(&taker.pubkey(), &mint_a).await,
+        taker_b: h
+            .funded_token_account(&taker.pubkey(), &mint_b, 10_000)
+            .await,
+        maker,
+        taker,
+        mint_a,
+        mint_b,
+    }
+}
+
+async fn make(h: &mut Harness, s: &Swap, args: args::MakeOffer) -> TxResult {
+    h.process(
+        &[instructions::make_offer(
+            &s.maker.pubkey(),
+            &s.mint_a,
+            &s.mint_b,
+            &s.maker_a,
+            args,
+        )],
+        &[&s.maker],
+    )
+    .await
+}
+
+async fn take(
+    h: &mut Harness,
+    s: &Swap,
+    taker: &Keypair,
+    taker_a: &Pubkey,
+    taker_b: &Pubkey,
+    offer_id: u64,
+) -> TxResult {
+    h.process(
+        &[instructions::take_offer(
+            &taker.pubkey(),
+            &s.maker.pubkey(),
+            offer_id,
+            taker_a,
+            taker_b,
+            &s.maker_b,
+        )],
+        &[taker],
+    )
+    .await
+}
+
+async fn fill(h: &mut Harness, s: &Swap, offer_id: u64, token_a_fill: u64) -> TxResult {
+    h.process(
+        &[instructions::fill_offer(
+            &s.taker.pubkey(),
+            &s.maker.pubkey(),
+            offer_id,
+            &s.taker_a,
+            &s.taker_b,
+            &s.maker_b,
+            args::FillOffer { token_a_fill },
+        )],
+        &[&s.taker],
+    )
+    .await
+}
+
+#[tokio::test]
+async fn take_offer_swaps_and_closes() {
+    let mut h = Harness::new().await;
+    let s = swap(&mut h).await;
+    make(&mut h, &s, offer_args(1)).await.unwrap();
+
+    let offer = pda::offer(&s.maker.pubkey(), 1).0;
+    assert_eq!(h.token_balance(&pda::offer_vault(&offer).0).await, 300);
+
+    take(&mut h, &s, &s.taker, &s.taker_a, &s.taker_b, 1)
+        .await
+        .unwrap();
+    assert_eq!(h.token_balance(&s.taker_a).await, 300);
+    assert_eq!(h.token_balance(&s.maker_b).await, 1_000);
+    assert!(h.account(&offer).await.is_none());
+    assert!(h.account(&pda::offer_vault(&offer).0).await.is_none());
+}
+
+#[tokio::test]
+async fn make_offer_validates_amounts_and_expiry() {
+    let mut h = Harness::new().await;
+    let s = swap(&mut h).await;
+
+    let cases = [
+        (
+            args::MakeOffer {
+                token_a_amount: 0,
+                ..offer_args(1)
+            },
+            ErrorCode::InvalidTokenAmount,
+        ),
+        (
+            args::MakeOffer {
+                token_b_wanted: 0,
+                ..offer_args(1)
+            },
+            ErrorCode::InvalidTokenAmount,
+        ),
+        (
+            args::MakeOffer {
+                expires_at: START_TIME,
+                ..offer_args(1)
+            },
+            ErrorCode::InvalidOfferExpiry,
+        ),
+    ];
+    for (args, error) in cases {
+        assert_error(make(&mut h, &s, args).await, error);
+    }
+}
+
+#[tokio::test]
+async fn partial_fills_round_in_makers_favour() {
+    let mut h = Harness::new().await;
+    let s = swap(&mut h).await;
+    make(&mut h, &s, offer_args(1)).await.unwrap();
+
+    assert_error(fill(&mut h, &s, 1, 0).await, ErrorCode::InvalidTokenAmount);
+    assert_error(
+        fill(&mut h, &s, 1, 301).await,
+        ErrorCode::InsufficientTokens,
+    );
+
+    // 100 of 300 token A owes ceil(1000 / 3) token B
+    fill(&mut h, &s, 1, 100).await.unwrap();
+    assert_eq!(h.token_balance(&s.maker_b).await, 334);
+    let offer: Offer = h.fetch(&pda::offer(&s.maker.pubkey(), 1).0).await;
+    assert_eq!(offer.token_a_remaining, 200);
+
+    fill(&mut h, &s, 1, 200).await.unwrap();
+    assert_eq!(h.token_balance(&s.maker_b).await, 1_000);
+    assert_eq!(h.token_balance(&s.taker_a).await, 300);
+}
+
+#[tokio::test]
+async fn fill_offer_requires_partial_fills() {
+    let mut h = Harness::new().await;
+    let s = swap(&mut h).await;
+    make(
+        &mut h,
+        &s,
+        args::MakeOffer {
+            partially_fillable: false,
+            ..offer_args(1)
+        },
+    )
+    .await
+    .unwrap();
+
+    assert_error(
+        fill(&mut h, &s, 1, 100).await,
+        ErrorCode::PartialFillsDisabled,
+    );
+    fill(&mut h, &s, 1, 300).await.unwrap();
+}
+
+#[tokio::test]
+async fn reserved_offer_rejects_other_takers() {
+    let mut h = Harness::new().await;
+    let s = swap(&mut h).await;
+    make(
+        &mut h,
+        &s,
+        args::MakeOffer {
+            taker: Some(s.taker.pubkey()),
+            ..offer_args(1)
+        },
+    )
+    .await
+    .unwrap();
+
+    let other = h.wallet().await;
+    let other_a = h.create_token_account(&other.pubkey(), &s.mint_a).await;
+    let other_b = h
+        .funded_token_account(&other.pubkey(), &s.mint_b, 10_000)
+        .await;
+    assert_error(
+        take(&mut h, &s, &other, &other_a, &other_b, 1).await,
+        ErrorCode::UnauthorizedTaker,
+    );
+    take(&mut h, &s, &s.taker, &s.taker_a, &s.taker_b, 1)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn expired_offer_cannot_be_filled() {
+    let mut h = Harness::new().await;
+    let s = swap(&mut h).await;
+    make(
+        &mut h,
+        &s,
+        args::MakeOffer {
+            expires_at: START_TIME + 60,
+            ..offer_args(1)
+        },
+    )
+    .await
+    .unwrap();
+
+    h.warp_to(START_TIME + 60).await;
+    fill(&mut h, &s, 1, 100).await.unwrap();
+
+    h.warp_to(START_TIME + 61).await;
+    assert_error(fill(&mut h, &s, 1, 100).await, ErrorCode::OfferExpired);
+}
+
+#[tokio::test]
+async fn cancel_offer_returns_remaining_deposit() {
+    let mut h = Harness::new().await;
+    let s = swap(&mut h).await;
+    make(&mut h, &s, offer_args(7)).await.unwrap();
+    fill(&mut h, &s, 7, 120).await.unwrap();
+
+    h.process(
+        &[instructions::cancel_offer(&s.maker.pubkey(), 7, &s.maker_a)],
+        &[&s.maker],
+    )
+    .await
+    .unwrap();
+    assert_eq!(h.token_balance(&s.maker_a).await, 1_000 - 120);
+    assert!(h
+        .account(&pda::offer(&s.maker.pubkey(), 7).0)
+        .await
+        .is_none());
+}
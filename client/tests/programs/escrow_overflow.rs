@@ -0,0 +1,249 @@
+//! Escrow overflow sales: buyers deposit any amount during the sale and claim a pro-rata
+//! allocation plus the unspent deposit once it ends.
+
+use escrow::ErrorCode;
+use solana_sdk::signature::Signer;
+use token_sale_client::escrow::{args, instructions, pda, BuyerAccount, PriceTier, TokenSale};
+
+use crate::escrow_sale::{
+    buy, create_buyer, create_sale, Buyer, Sale, PRICE, SALE_END, SALE_START,
+};
+use crate::harness::{assert_error, Harness, TxResult};
+
+const FUNDED: u64 = 10_000_000;
+
+async fn configure(h: &mut Harness, sale: &Sale) -> TxResult {
+    h.process(
+        &[instructions::configure_overflow(
+            &sale.seller.pubkey(),
+            &sale.address,
+            &sale.payment_mint,
+        )],
+        &[&sale.seller],
+    )
+    .await
+}
+
+async fn deposit(h: &mut Harness, sale: &Sale, buyer: &Buyer, amount: u64) -> TxResult {
+    h.process(
+        &[instructions::deposit_overflow(
+            &buyer.wallet.pubkey(),
+            &sale.address,
+            &buyer.payment_account,
+            args::DepositOverflow { amount },
+        )],
+        &[&buyer.wallet],
+    )
+    .await
+}
+
+async fn claim(h: &mut Harness, sale: &Sale, buyer: &Buyer) -> TxResult {
+    h.process(
+        &[instructions::claim_overflow(
+            &buyer.wallet.pubkey(),
+            &sale.address,
+            &buyer.payment_account,
+            &buyer.token_account,
+            &sale.seller_payment_account,
+            &sale.fee_account,
+        )],
+        &[&buyer.wallet],
+    )
+    .await
+}
+
+async fn cancel(h: &mut Harness, sale: &Sale) -> TxResult {
+    h.process(
+        &[instructions::cancel_sale(
+            &sale.seller.pubkey(),
+            &sale.address,
+            &sale.seller_token_account,
+            false,
+        )],
+        &[&sale.seller],
+    )
+    .await
+}
+
+/// An overflow sale, live, with a buyer per deposit
+async fn overflow_sale(h: &mut Harness, deposits: &[u64]) -> (Sale, Vec<Buyer>) {
+    let sale = create_sale(h).await;
+    configure(h, &sale).await.unwrap();
+    h.warp_to(SALE_START).await;
+    let mut buyers = Vec::new();
+    for amount in deposits {
+        let buyer = create_buyer(h, &sale).await;
+        deposit(h, &sale, &buyer, *amount).await.unwrap();
+        buyers.push(buyer);
+    }
+    (sale, buyers)
+}
+
+#[tokio::test]
+async fn oversubscribed_sale_allocates_pro_rata_and_refunds_the_rest() {
+    let mut h = Harness::new().await;
+    // Three times the 1,000 tokens on offer, split unevenly so shares round down
+    let (sale, buyers) = overflow_sale(&mut h, &[500_000, 1_000_000, 1_000_001]).await;
+    deposit(&mut h, &sale, &buyers[0], 500_000).await.unwrap();
+    assert_error(
+        buy(&mut h, &sale, &buyers[0], 10).await,
+        ErrorCode::OverflowSale,
+    );
+    assert_error(
+        claim(&mut h, &sale, &buyers[0]).await,
+        ErrorCode::SaleNotEnded,
+    );
+
+    h.warp_to(SALE_END + 1).await;
+    assert_error(
+        deposit(&mut h, &sale, &buyers[0], 1).await,
+        ErrorCode::SaleEnded,
+    );
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(state.total_deposits, 3_000_001);
+
+    // A tracker holding an unclaimed deposit stays open
+    let close = instructions::close_buyer_account(
+        &buyers[0].wallet.pubkey(),
+        &buyers[0].wallet.pubkey(),
+        &sale.address,
+    );
+    assert_error(
+        h.process(std::slice::from_ref(&close), &[&buyers[0].wallet])
+            .await,
+        ErrorCode::BuyerAccountInUse,
+    );
+
+    for buyer in &buyers {
+        claim(&mut h, &sale, buyer).await.unwrap();
+        assert_eq!(h.token_balance(&buyer.token_account).await, 333);
+        let tracker: BuyerAccount = h
+            .fetch(&pda::buyer(&buyer.wallet.pubkey(), &sale.address).0)
+            .await;
+        assert_eq!(tracker.tokens_purchased, 333);
+        let refund = tracker.overflow_deposit - 333 * PRICE;
+        assert_eq!(
+            h.token_balance(&buyer.payment_account).await,
+            FUNDED - tracker.overflow_deposit + refund
+        );
+    }
+    assert_eq!(h.token_balance(&sale.seller_payment_account).await, 974_025);
+    assert_eq!(h.token_balance(&sale.fee_account).await, 24_975);
+    assert_eq!(
+        h.token_balance(&pda::deposit_vault(&sale.address).0).await,
+        0
+    );
+
+    h.next_slot().await;
+    assert_error(
+        claim(&mut h, &sale, &buyers[0]).await,
+        ErrorCode::OverflowAlreadyClaimed,
+    );
+    h.process(&[close], &[&buyers[0].wallet]).await.unwrap();
+    let bystander = create_buyer(&mut h, &sale).await;
+    assert_error(
+        claim(&mut h, &sale, &bystander).await,
+        ErrorCode::NoOverflowDeposit,
+    );
+
+    // The rounding dust goes back to the seller
+    cancel(&mut h, &sale).await.unwrap();
+    assert_eq!(h.token_balance(&sale.seller_token_account).await, 1);
+}
+
+#[tokio::test]
+async fn undersubscribed_sale_fills_deposits_and_returns_the_rest() {
+    let mut h = Harness::new().await;
+    let (sale, buyers) = overflow_sale(&mut h, &[200_500, 100_000]).await;
+
+    // Deposits are made against a fixed price
+    assert_error(
+        h.process(
+            &[instructions::update_sale_params(
+                &sale.seller.pubkey(),
+                &sale.address,
+                args::UpdateSaleParams {
+                    new_price_per_token: Some(PRICE + 10),
+                    new_sale_start_time: None,
+                    new_sale_end_time: None,
+                    new_max_tokens_per_buyer: None,
+                    new_min_sale_duration: None,
+                    new_max_sale_duration: None,
+                },
+            )],
+            &[&sale.seller],
+        )
+        .await,
+        ErrorCode::OverflowIncompatible,
+    );
+
+    // The vault only exists once configured, so clear the flag to reach the handler's check
+    let mut state: TokenSale = h.fetch(&sale.address).await;
+    state.is_overflow = 0;
+    h.store_zero_copy(&sale.address, &state).await;
+    assert_error(
+        deposit(&mut h, &sale, &buyers[1], 1_000).await,
+        ErrorCode::NotOverflowSale,
+    );
+    state.is_overflow = 1;
+    h.store_zero_copy(&sale.address, &state).await;
+
+    // Cancelling after the end keeps back what the unclaimed deposits pay for
+    h.warp_to(SALE_END + 1).await;
+    cancel(&mut h, &sale).await.unwrap();
+    assert_eq!(h.token_balance(&sale.seller_token_account).await, 700);
+
+    claim(&mut h, &sale, &buyers[0]).await.unwrap();
+    claim(&mut h, &sale, &buyers[1]).await.unwrap();
+    assert_eq!(h.token_balance(&buyers[0].token_account).await, 200);
+    assert_eq!(
+        h.token_balance(&buyers[0].payment_account).await,
+        FUNDED - 200 * PRICE
+    );
+    assert_eq!(h.token_balance(&buyers[1].token_account).await, 100);
+    assert_eq!(h.token_balance(&pda::token_vault(&sale.address).0).await, 0);
+}
+
+#[tokio::test]
+async fn cancelled_overflow_sale_refunds_every_deposit() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let tiers = |price_tiers| {
+        instructions::set_price_tiers(
+            &sale.seller.pubkey(),
+            &sale.address,
+            args::SetPriceTiers { price_tiers },
+        )
+    };
+
+    // Allocation is at the flat price only
+    let tier = PriceTier {
+        tokens_sold_threshold: 100,
+        price_per_token: 2 * PRICE,
+    };
+    h.process(&[tiers(vec![tier])], &[&sale.seller])
+        .await
+        .unwrap();
+    assert_error(
+        configure(&mut h, &sale).await,
+        ErrorCode::OverflowIncompatible,
+    );
+    h.process(&[tiers(vec![])], &[&sale.seller]).await.unwrap();
+    configure(&mut h, &sale).await.unwrap();
+    h.next_slot().await;
+    assert_error(
+        h.process(&[tiers(vec![tier])], &[&sale.seller]).await,
+        ErrorCode::OverflowIncompatible,
+    );
+
+    h.warp_to(SALE_START).await;
+    let buyer = create_buyer(&mut h, &sale).await;
+    deposit(&mut h, &sale, &buyer, 5_000_000).await.unwrap();
+    cancel(&mut h, &sale).await.unwrap();
+    assert_eq!(h.token_balance(&sale.seller_token_account).await, 1_000);
+
+    claim(&mut h, &sale, &buyer).await.unwrap();
+    assert_eq!(h.token_balance(&buyer.payment_account).await, FUNDED);
+    assert_eq!(h.token_balance(&buyer.token_account).await, 0);
+    assert_eq!(h.token_balance(&sale.seller_payment_account).await, 0);
+}
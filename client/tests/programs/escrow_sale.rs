@@ -0,0 +1,1271 @@
+//! Escrow token sales: setup, purchases, throttles, price tiers and seller controls.
+
+use anchor_lang::error::ErrorCode as AnchorError;
+use escrow::ErrorCode;
+use solana_program_test::BanksClientError;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use token_sale_client::escrow::{
+    args, instructions, pda, PriceTier, PurchaseQuote, TokenSale, MAX_SALE_DURATION,
+    MIN_SALE_DURATION, PRICE_CHANGE_DELAY, PROGRAM_ID,
+};
+
+use crate::harness::{assert_error, Harness, TxResult, START_TIME};
+
+pub const PRICE: u64 = 1_000;
+pub const TOTAL_TOKENS: u64 = 1_000;
+pub const SALE_START: i64 = START_TIME + 100;
+pub const SALE_END: i64 = START_TIME + 1_000;
+
+pub struct Sale {
+    pub seller: Keypair,
+    pub token_mint: Pubkey,
+    pub payment_mint: Pubkey,
+    pub seller_token_account: Pubkey,
+    pub seller_payment_account: Pubkey,
+    pub fee_recipient: Pubkey,
+    pub fee_account: Pubkey,
+    pub address: Pubkey,
+}
+
+pub struct Buyer {
+    pub wallet: Keypair,
+    pub payment_account: Pubkey,
+    pub token_account: Pubkey,
+}
+
+pub fn init_args(fee_recipient: Pubkey) -> args::InitializeSale {
+    args::InitializeSale {
+        price_per_token: PRICE,
+        total_tokens: TOTAL_TOKENS,
+        sale_start_time: SALE_START,
+        sale_end_time: SALE_END,
+        max_tokens_per_buyer: 0,
+        platform_fee_bps: 250,
+        platform_fee_recipient: fee_recipient,
+    }
+}
+
+/// Mints and token accounts for a sale, without initializing it
+pub async fn prepare_sale(h: &mut Harness) -> Sale {
+    let seller = h.wallet().await;
+    let fee_recipient = Pubkey::new_unique();
+    let token_mint = h.create_mint(0).await;
+    let payment_mint = h.create_mint(6).await;
+    let seller_token_account = h
+        .funded_token_account(&seller.pubkey(), &token_mint, TOTAL_TOKENS)
+        .await;
+    let seller_payment_account = h
+        .create_token_account(&seller.pubkey(), &payment_mint)
+        .await;
+    let fee_account = h.create_token_account(&fee_recipient, &payment_mint).await;
+
+    Sale {
+        address: pda::token_sale(&seller.pubkey(), &token_mint).0,
+        seller,
+        token_mint,
+        payment_mint,
+        seller_token_account,
+        seller_payment_account,
+        fee_recipient,
+        fee_account,
+    }
+}
+
+pub async fn init_sale_with(h: &mut Harness, sale: &Sale, args: args::InitializeSale) -> TxResult {
+    h.process(
+        &[instructions::initialize_sale(
+            &sale.seller.pubkey(),
+            &sale.token_mint,
+            &sale.payment_mint,
+            &sale.seller_token_account,
+            args,
+        )],
+        &[&sale.seller],
+    )
+    .await
+}
+
+/// A sale running from `SALE_START` to `SALE_END` with a 2.5% fee
+pub async fn create_sale(h: &mut Harness) -> Sale {
+    let sale = prepare_sale(h).await;
+    init_sale_with(h, &sale, init_args(sale.fee_recipient))
+        .await
+        .unwrap();
+    sale
+}
+
+/// A buyer with a tracker account and enough payment tokens for the whole sale
+pub async fn create_buyer(h: &mut Harness, sale: &Sale) -> Buyer {
+    let wallet = h.wallet().await;
+    let payment_account = h
+        .funded_token_account(&wallet.pubkey(), &sale.payment_mint, 10_000_000)
+        .await;
+    let token_account = h
+        .create_token_account(&wallet.pubkey(), &sale.token_mint)
+        .await;
+    h.process(
+        &[instructions::create_buyer_account(
+            &wallet.pubkey(),
+            &wallet.pubkey(),
+            &sale.address,
+        )],
+        &[&wallet],
+    )
+    .await
+    .unwrap();
+
+    Buyer {
+        wallet,
+        payment_account,
+        token_account,
+    }
+}
+
+pub fn buy_ix(
+    sale: &Sale,
+    buyer: &Buyer,
+    escrows_proceeds: bool,
+    token_amount: u64,
+) -> solana_sdk::instruction::Instruction {
+    instructions::buy_tokens(
+        &buyer.wallet.pubkey(),
+        &sale.address,
+        &buyer.payment_account,
+        &sale.seller_payment_account,
+        &sale.fee_account,
+        &buyer.token_account,
+        escrows_proceeds,
+        false,
+        args::BuyTokens { token_amount },
+    )
+}
+
+pub async fn buy(h: &mut Harness, sale: &Sale, buyer: &Buyer, token_amount: u64) -> TxResult {
+    h.process(
+        &[buy_ix(sale, buyer, false, token_amount)],
+        &[&buyer.wallet],
+    )
+    .await
+}
+
+async fn quote(
+    h: &mut Harness,
+    sale: &Sale,
+    buyer: Option<&Buyer>,
+    token_amount: u64,
+) -> Result<PurchaseQuote, BanksClientError> {
+    let buyer = buyer.map(|buyer| buyer.wallet.pubkey());
+    let ix = instructions::quote_purchase(
+        &sale.address,
+        buyer.as_ref(),
+        args::QuotePurchase { token_amount },
+    );
+    h.simulate(&PROGRAM_ID, &[ix]).await
+}
+
+async fn quote_exact_in(
+    h: &mut Harness,
+    sale: &Sale,
+    buyer: Option<&Buyer>,
+    payment: u64,
+) -> Result<PurchaseQuote, BanksClientError> {
+    let buyer = buyer.map(|buyer| buyer.wallet.pubkey());
+    let ix = instructions::quote_exact_in(
+        &sale.address,
+        buyer.as_ref(),
+        args::QuoteExactIn { payment },
+    );
+    h.simulate(&PROGRAM_ID, &[ix]).await
+}
+
+async fn set_throttles(h: &mut Harness, sale: &Sale, args: args::SetPurchaseThrottles) -> TxResult {
+    h.process(
+        &[instructions::set_purchase_throttles(
+            &sale.seller.pubkey(),
+            &sale.address,
+            args,
+        )],
+        &[&sale.seller],
+    )
+    .await
+}
+
+async fn set_tiers(h: &mut Harness, sale: &Sale, price_tiers: Vec<PriceTier>) -> TxResult {
+    h.process(
+        &[instructions::set_price_tiers(
+            &sale.seller.pubkey(),
+            &sale.address,
+            args::SetPriceTiers { price_tiers },
+        )],
+        &[&sale.seller],
+    )
+    .await
+}
+
+async fn update(h: &mut Harness, sale: &Sale, args: args::UpdateSaleParams) -> TxResult {
+    h.process(
+        &[instructions::update_sale_params(
+            &sale.seller.pubkey(),
+            &sale.address,
+            args,
+        )],
+        &[&sale.seller],
+    )
+    .await
+}
+
+fn no_update() -> args::UpdateSaleParams {
+    args::UpdateSaleParams {
+        new_price_per_token: None,
+        new_sale_start_time: None,
+        new_sale_end_time: None,
+        new_max_tokens_per_buyer: None,
+        new_min_sale_duration: None,
+        new_max_sale_duration: None,
+    }
+}
+
+#[tokio::test]
+async fn initialize_sale_moves_tokens_into_vault() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(state.seller, sale.seller.pubkey());
+    assert_eq!(state.tokens_available, TOTAL_TOKENS);
+    assert!(state.is_active() && !state.is_paused());
+    assert_eq!(
+        h.token_balance(&pda::token_vault(&sale.address).0).await,
+        TOTAL_TOKENS
+    );
+    assert_eq!(h.token_balance(&sale.seller_token_account).await, 0);
+}
+
+#[tokio::test]
+async fn initialize_sale_validates_parameters() {
+    let mut h = Harness::new().await;
+    let sale = prepare_sale(&mut h).await;
+    let recipient = sale.fee_recipient;
+
+    let cases = [
+        (
+            args::InitializeSale {
+                price_per_token: 0,
+                ..init_args(recipient)
+            },
+            ErrorCode::InvalidPrice,
+        ),
+        (
+            args::InitializeSale {
+                total_tokens: 0,
+                ..init_args(recipient)
+            },
+            ErrorCode::InvalidTokenAmount,
+        ),
+        (
+            args::InitializeSale {
+                sale_start_time: 0,
+                ..init_args(recipient)
+            },
+            ErrorCode::InvalidStartTime,
+        ),
+        (
+            args::InitializeSale {
+                sale_end_time: SALE_START,
+                ..init_args(recipient)
+            },
+            ErrorCode::InvalidEndTime,
+        ),
+        (
+            args::InitializeSale {
+                platform_fee_bps: 10_001,
+                ..init_args(recipient)
+            },
+            ErrorCode::InvalidPlatformFee,
+        ),
+        (
+            args::InitializeSale {
+                sale_start_time: START_TIME - 200,
+                sale_end_time: START_TIME - 100,
+                ..init_args(recipient)
+            },
+            ErrorCode::SaleEndTimeInPast,
+        ),
+    ];
+    for (args, error) in cases {
+        assert_error(init_sale_with(&mut h, &sale, args).await, error);
+    }
+}
+
+#[tokio::test]
+async fn buy_tokens_pays_seller_and_fee_recipient() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let buyer = create_buyer(&mut h, &sale).await;
+    h.warp_to(SALE_START).await;
+
+    buy(&mut h, &sale, &buyer, 100).await.unwrap();
+
+    // 100 tokens at 1_000 each, 2.5% of which goes to the platform
+    assert_eq!(h.token_balance(&buyer.token_account).await, 100);
+    assert_eq!(h.token_balance(&sale.seller_payment_account).await, 97_500);
+    assert_eq!(h.token_balance(&sale.fee_account).await, 2_500);
+
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(state.tokens_available, TOTAL_TOKENS - 100);
+    let tracker: escrow::BuyerAccount = h
+        .fetch(&pda::buyer(&buyer.wallet.pubkey(), &sale.address).0)
+        .await;
+    assert_eq!(tracker.tokens_purchased, 100);
+    assert_eq!(tracker.last_purchase_time, SALE_START);
+}
+
+#[tokio::test]
+async fn buy_tokens_respects_sale_window() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let buyer = create_buyer(&mut h, &sale).await;
+
+    assert_error(
+        buy(&mut h, &sale, &buyer, 1).await,
+        ErrorCode::SaleNotStarted,
+    );
+
+    h.warp_to(SALE_END).await;
+    buy(&mut h, &sale, &buyer, 1).await.unwrap();
+
+    h.warp_to(SALE_END + 1).await;
+    assert_error(buy(&mut h, &sale, &buyer, 1).await, ErrorCode::SaleEnded);
+}
+
+#[tokio::test]
+async fn buy_tokens_validates_amounts_and_limits() {
+    let mut h = Harness::new().await;
+    let sale = prepare_sale(&mut h).await;
+    let recipient = sale.fee_recipient;
+    init_sale_with(
+        &mut h,
+        &sale,
+        args::InitializeSale {
+            max_tokens_per_buyer: 50,
+            ..init_args(recipient)
+        },
+    )
+    .await
+    .unwrap();
+    let buyer = create_buyer(&mut h, &sale).await;
+    h.warp_to(SALE_START).await;
+
+    assert_error(
+        buy(&mut h, &sale, &buyer, 0).await,
+        ErrorCode::InvalidTokenAmount,
+    );
+    assert_error(
+        buy(&mut h, &sale, &buyer, TOTAL_TOKENS + 1).await,
+        ErrorCode::InsufficientTokens,
+    );
+    buy(&mut h, &sale, &buyer, 40).await.unwrap();
+    assert_error(
+        buy(&mut h, &sale, &buyer, 11).await,
+        ErrorCode::ExceedsPurchaseLimit,
+    );
+}
+
+#[tokio::test]
+async fn buy_tokens_for_credits_the_recipient() {
+    let mut h = Harness::new().await;
+    let sale = prepare_sale(&mut h).await;
+    let fee_recipient = sale.fee_recipient;
+    init_sale_with(
+        &mut h,
+        &sale,
+        args::InitializeSale {
+            max_tokens_per_buyer: 50,
+            ..init_args(fee_recipient)
+        },
+    )
+    .await
+    .unwrap();
+    let custodian = create_buyer(&mut h, &sale).await;
+    let recipient = Keypair::new().pubkey();
+    let recipient_token_account = h.create_token_account(&recipient, &sale.token_mint).await;
+    h.warp_to(SALE_START).await;
+
+    let buy_for = |token_account: &Pubkey, token_amount| {
+        instructions::buy_tokens_for(
+            &custodian.wallet.pubkey(),
+            &recipient,
+            &sale.address,
+            &custodian.payment_account,
+            &sale.seller_payment_account,
+            &sale.fee_account,
+            token_account,
+            false,
+            false,
+            args::BuyTokensFor { token_amount },
+        )
+    };
+    let signers = [&custodian.wallet];
+
+    // The recipient's tracker has to exist; the payer can create it without them
+    assert!(h
+        .process(&[buy_for(&recipient_token_account, 40)], &signers)
+        .await
+        .is_err());
+    h.process(
+        &[instructions::create_buyer_account_for(
+            &custodian.wallet.pubkey(),
+            &recipient,
+            &sale.address,
+        )],
+        &signers,
+    )
+    .await
+    .unwrap();
+    assert!(h
+        .process(&[buy_for(&custodian.token_account, 40)], &signers)
+        .await
+        .is_err());
+    h.process(&[buy_for(&recipient_token_account, 40)], &signers)
+        .await
+        .unwrap();
+
+    assert_eq!(h.token_balance(&recipient_token_account).await, 40);
+    assert_eq!(
+        h.token_balance(&custodian.payment_account).await,
+        10_000_000 - 40_000
+    );
+    let tracker: escrow::BuyerAccount = h.fetch(&pda::buyer(&recipient, &sale.address).0).await;
+    assert_eq!((tracker.buyer, tracker.tokens_purchased), (recipient, 40));
+
+    // The cap is the recipient's, not the payer's
+    assert_error(
+        h.process(&[buy_for(&recipient_token_account, 11)], &signers)
+            .await,
+        ErrorCode::ExceedsPurchaseLimit,
+    );
+    buy(&mut h, &sale, &custodian, 50).await.unwrap();
+}
+
+#[tokio::test]
+async fn buying_for_others_restarts_the_payers_cooldown() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    set_throttles(
+        &mut h,
+        &sale,
+        args::SetPurchaseThrottles {
+            max_tokens_per_tx: None,
+            min_purchase_interval: Some(60),
+            max_tokens_per_slot: None,
+        },
+    )
+    .await
+    .unwrap();
+    let custodian = create_buyer(&mut h, &sale).await;
+    let mut recipients = Vec::new();
+    for _ in 0..2 {
+        let recipient = Keypair::new().pubkey();
+        let token_account = h.create_token_account(&recipient, &sale.token_mint).await;
+        h.process(
+            &[instructions::create_buyer_account_for(
+                &custodian.wallet.pubkey(),
+                &recipient,
+                &sale.address,
+            )],
+            &[&custodian.wallet],
+        )
+        .await
+        .unwrap();
+        recipients.push((recipient, token_account));
+    }
+    let buy_for = |(recipient, token_account): &(Pubkey, Pubkey)| {
+        instructions::buy_tokens_for(
+            &custodian.wallet.pubkey(),
+            recipient,
+            &sale.address,
+            &custodian.payment_account,
+            &sale.seller_payment_account,
+            &sale.fee_account,
+            token_account,
+            false,
+            false,
+            args::BuyTokensFor { token_amount: 10 },
+        )
+    };
+    h.warp_to(SALE_START).await;
+
+    // One payer can't keep restarting recipients' cooldowns faster than its own
+    h.process(&[buy_for(&recipients[0])], &[&custodian.wallet])
+        .await
+        .unwrap();
+    assert_error(
+        h.process(&[buy_for(&recipients[1])], &[&custodian.wallet])
+            .await,
+        ErrorCode::PurchaseCooldownActive,
+    );
+    assert_error(
+        buy(&mut h, &sale, &custodian, 10).await,
+        ErrorCode::PurchaseCooldownActive,
+    );
+
+    h.warp_to(SALE_START + 60).await;
+    h.process(&[buy_for(&recipients[1])], &[&custodian.wallet])
+        .await
+        .unwrap();
+    let tracker: escrow::BuyerAccount = h
+        .fetch(&pda::buyer(&custodian.wallet.pubkey(), &sale.address).0)
+        .await;
+    assert_eq!(
+        (tracker.tokens_purchased, tracker.last_purchase_time),
+        (0, SALE_START + 60)
+    );
+}
+
+#[tokio::test]
+async fn sponsor_pays_tracker_rent_and_gets_it_back_on_close() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let sponsor = h.wallet().await;
+    // A wallet holding only payment tokens, no SOL
+    let wallet = Keypair::new();
+    let buyer = Buyer {
+        payment_account: h
+            .funded_token_account(&wallet.pubkey(), &sale.payment_mint, 10_000_000)
+            .await,
+        token_account: h
+            .create_token_account(&wallet.pubkey(), &sale.token_mint)
+            .await,
+        wallet,
+    };
+    let tracker = pda::buyer(&buyer.wallet.pubkey(), &sale.address).0;
+
+    h.process(
+        &[instructions::create_buyer_account(
+            &buyer.wallet.pubkey(),
+            &sponsor.pubkey(),
+            &sale.address,
+        )],
+        &[&buyer.wallet, &sponsor],
+    )
+    .await
+    .unwrap();
+    let state: escrow::BuyerAccount = h.fetch(&tracker).await;
+    assert_eq!(state.rent_recipient(), sponsor.pubkey());
+    let rent = h.account(&tracker).await.unwrap().lamports;
+    let sponsor_lamports = h.account(&sponsor.pubkey()).await.unwrap().lamports;
+
+    h.warp_to(SALE_START).await;
+    buy(&mut h, &sale, &buyer, 10).await.unwrap();
+    assert!(h.account(&buyer.wallet.pubkey()).await.is_none());
+
+    let close = |rent_payer: &Pubkey| {
+        instructions::close_buyer_account(&buyer.wallet.pubkey(), rent_payer, &sale.address)
+    };
+    assert_error(
+        h.process(&[close(&sponsor.pubkey())], &[&buyer.wallet])
+            .await,
+        ErrorCode::SaleNotEnded,
+    );
+    h.warp_to(SALE_END + 1).await;
+    assert_error(
+        h.process(&[close(&buyer.wallet.pubkey())], &[&buyer.wallet])
+            .await,
+        AnchorError::ConstraintRaw,
+    );
+    h.process(&[close(&sponsor.pubkey())], &[&buyer.wallet])
+        .await
+        .unwrap();
+
+    assert!(h.account(&tracker).await.is_none());
+    assert_eq!(
+        h.account(&sponsor.pubkey()).await.unwrap().lamports,
+        sponsor_lamports + rent
+    );
+    assert_eq!(h.token_balance(&buyer.token_account).await, 10);
+}
+
+#[tokio::test]
+async fn buy_tokens_reports_cost_overflow() {
+    let mut h = Harness::new().await;
+    let sale = prepare_sale(&mut h).await;
+    let recipient = sale.fee_recipient;
+    init_sale_with(
+        &mut h,
+        &sale,
+        args::InitializeSale {
+            price_per_token: u64::MAX,
+            ..init_args(recipient)
+        },
+    )
+    .await
+    .unwrap();
+    let buyer = create_buyer(&mut h, &sale).await;
+    h.warp_to(SALE_START).await;
+
+    assert_error(buy(&mut h, &sale, &buyer, 2).await, ErrorCode::MathOverflow);
+}
+
+#[tokio::test]
+async fn toggle_pause_blocks_purchases() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let buyer = create_buyer(&mut h, &sale).await;
+    h.warp_to(SALE_START).await;
+    let pause = instructions::toggle_pause(&sale.seller.pubkey(), &sale.address);
+
+    h.process(std::slice::from_ref(&pause), &[&sale.seller])
+        .await
+        .unwrap();
+    assert!(h.fetch::<TokenSale>(&sale.address).await.is_paused());
+    assert_error(buy(&mut h, &sale, &buyer, 1).await, ErrorCode::SalePaused);
+
+    h.process(&[pause], &[&sale.seller]).await.unwrap();
+    buy(&mut h, &sale, &buyer, 1).await.unwrap();
+}
+
+#[tokio::test]
+async fn cancel_sale_returns_unsold_tokens() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let buyer = create_buyer(&mut h, &sale).await;
+    h.warp_to(SALE_START).await;
+    buy(&mut h, &sale, &buyer, 300).await.unwrap();
+
+    let cancel = instructions::cancel_sale(
+        &sale.seller.pubkey(),
+        &sale.address,
+        &sale.seller_token_account,
+        false,
+    );
+    h.process(std::slice::from_ref(&cancel), &[&sale.seller])
+        .await
+        .unwrap();
+    assert_eq!(h.token_balance(&sale.seller_token_account).await, 700);
+
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert!(!state.is_active());
+    assert_eq!(state.tokens_available, 0);
+
+    assert_error(
+        h.process(&[cancel], &[&sale.seller]).await,
+        ErrorCode::SaleNotActive,
+    );
+    assert_error(
+        buy(&mut h, &sale, &buyer, 1).await,
+        ErrorCode::SaleNotActive,
+    );
+    assert_error(
+        h.process(
+            &[instructions::toggle_pause(
+                &sale.seller.pubkey(),
+                &sale.address,
+            )],
+            &[&sale.seller],
+        )
+        .await,
+        ErrorCode::SaleNotActive,
+    );
+}
+
+#[tokio::test]
+async fn update_sale_params_only_before_start() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+
+    update(
+        &mut h,
+        &sale,
+        args::UpdateSaleParams {
+            new_price_per_token: Some(2 * PRICE),
+            new_sale_start_time: Some(SALE_START + 10),
+            new_sale_end_time: Some(SALE_END + 10),
+            new_max_tokens_per_buyer: Some(25),
+            ..no_update()
+        },
+    )
+    .await
+    .unwrap();
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(state.price_per_token, 2 * PRICE);
+    assert_eq!(state.sale_start_time, SALE_START + 10);
+    assert_eq!(state.sale_end_time, SALE_END + 10);
+    assert_eq!(state.max_tokens_per_buyer, 25);
+
+    let cases = [
+        (
+            args::UpdateSaleParams {
+                new_price_per_token: Some(0),
+                ..no_update()
+            },
+            ErrorCode::InvalidPrice,
+        ),
+        (
+            args::UpdateSaleParams {
+                new_sale_start_time: Some(START_TIME),
+                ..no_update()
+            },
+            ErrorCode::InvalidStartTime,
+        ),
+        (
+            args::UpdateSaleParams {
+                new_sale_end_time: Some(SALE_START),
+                ..no_update()
+            },
+            ErrorCode::InvalidEndTime,
+        ),
+    ];
+    for (args, error) in cases {
+        assert_error(update(&mut h, &sale, args).await, error);
+    }
+
+    h.warp_to(SALE_START + 10).await;
+    assert_error(
+        update(&mut h, &sale, no_update()).await,
+        ErrorCode::SaleAlreadyStarted,
+    );
+}
+
+async fn extend(h: &mut Harness, sale: &Sale, new_sale_end_time: i64) -> TxResult {
+    h.process(
+        &[instructions::extend_sale(
+            &sale.seller.pubkey(),
+            &sale.address,
+            args::ExtendSale { new_sale_end_time },
+        )],
+        &[&sale.seller],
+    )
+    .await
+}
+
+async fn close_early(h: &mut Harness, sale: &Sale) -> TxResult {
+    h.process(
+        &[instructions::close_sale_early(
+            &sale.seller.pubkey(),
+            &sale.address,
+        )],
+        &[&sale.seller],
+    )
+    .await
+}
+
+#[tokio::test]
+async fn extend_sale_only_moves_the_end_later() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let buyer = create_buyer(&mut h, &sale).await;
+    h.warp_to(SALE_START).await;
+
+    assert_error(
+        extend(&mut h, &sale, SALE_END).await,
+        ErrorCode::InvalidEndTime,
+    );
+    assert_error(
+        extend(&mut h, &sale, SALE_START + MAX_SALE_DURATION + 1).await,
+        ErrorCode::SaleDurationTooLong,
+    );
+    let stranger = h.wallet().await;
+    assert!(h
+        .process(
+            &[instructions::extend_sale(
+                &stranger.pubkey(),
+                &sale.address,
+                args::ExtendSale {
+                    new_sale_end_time: SALE_END + 100,
+                },
+            )],
+            &[&stranger],
+        )
+        .await
+        .is_err());
+
+    extend(&mut h, &sale, SALE_START + MAX_SALE_DURATION)
+        .await
+        .unwrap();
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(state.sale_end_time, SALE_START + MAX_SALE_DURATION);
+
+    // Buyers can keep buying past the original end
+    h.warp_to(SALE_END + 1).await;
+    buy(&mut h, &sale, &buyer, 10).await.unwrap();
+
+    h.warp_to(SALE_START + MAX_SALE_DURATION + 1).await;
+    assert_error(
+        extend(&mut h, &sale, SALE_START + MAX_SALE_DURATION + 2).await,
+        ErrorCode::SaleEnded,
+    );
+}
+
+#[tokio::test]
+async fn close_sale_early_after_minimum_duration_or_sell_out() {
+    let mut h = Harness::new().await;
+    let sale = prepare_sale(&mut h).await;
+    init_sale_with(
+        &mut h,
+        &sale,
+        args::InitializeSale {
+            sale_end_time: SALE_START + 2 * MIN_SALE_DURATION,
+            ..init_args(sale.fee_recipient)
+        },
+    )
+    .await
+    .unwrap();
+    let buyer = create_buyer(&mut h, &sale).await;
+
+    assert_error(close_early(&mut h, &sale).await, ErrorCode::SaleNotStarted);
+    h.warp_to(SALE_START + MIN_SALE_DURATION - 1).await;
+    assert_error(
+        close_early(&mut h, &sale).await,
+        ErrorCode::EarlyCloseNotAllowed,
+    );
+
+    h.warp_to(SALE_START + MIN_SALE_DURATION).await;
+    close_early(&mut h, &sale).await.unwrap();
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(state.sale_end_time, SALE_START + MIN_SALE_DURATION - 1);
+    assert_error(buy(&mut h, &sale, &buyer, 10).await, ErrorCode::SaleEnded);
+
+    // Unsold tokens come back through cancel_sale as before
+    h.process(
+        &[instructions::cancel_sale(
+            &sale.seller.pubkey(),
+            &sale.address,
+            &sale.seller_token_account,
+            false,
+        )],
+        &[&sale.seller],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        h.token_balance(&sale.seller_token_account).await,
+        TOTAL_TOKENS
+    );
+}
+
+#[tokio::test]
+async fn sold_out_sale_can_close_at_once() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let buyer = create_buyer(&mut h, &sale).await;
+    h.warp_to(SALE_START).await;
+
+    buy(&mut h, &sale, &buyer, TOTAL_TOKENS).await.unwrap();
+    close_early(&mut h, &sale).await.unwrap();
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert!(state.sale_end_time < h.clock().await.unix_timestamp);
+}
+
+#[tokio::test]
+async fn seller_sets_its_own_duration_bounds() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let bounds = |min, max| args::UpdateSaleParams {
+        new_min_sale_duration: min,
+        new_max_sale_duration: max,
+        ..no_update()
+    };
+
+    let cases = [
+        (bounds(Some(-1), None), ErrorCode::InvalidSaleDuration),
+        (
+            bounds(Some(2_000), Some(1_000)),
+            ErrorCode::InvalidSaleDuration,
+        ),
+        // The window already set must fit the new maximum
+        (
+            bounds(Some(300), Some(SALE_END - SALE_START - 1)),
+            ErrorCode::SaleDurationTooLong,
+        ),
+    ];
+    for (args, error) in cases {
+        assert_error(update(&mut h, &sale, args).await, error);
+    }
+    update(&mut h, &sale, bounds(Some(300), Some(2_000)))
+        .await
+        .unwrap();
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(
+        (state.min_sale_duration(), state.max_sale_duration()),
+        (300, 2_000)
+    );
+
+    // The soft close ceiling and extensions stay within the seller's maximum
+    assert_error(
+        h.process(
+            &[instructions::configure_soft_close(
+                &sale.seller.pubkey(),
+                &sale.address,
+                args::ConfigureSoftClose {
+                    window: 60,
+                    extension: 60,
+                    ceiling: SALE_START + 2_001,
+                },
+            )],
+            &[&sale.seller],
+        )
+        .await,
+        ErrorCode::SaleDurationTooLong,
+    );
+    h.warp_to(SALE_START).await;
+    assert_error(
+        update(&mut h, &sale, bounds(None, Some(3_000))).await,
+        ErrorCode::SaleAlreadyStarted,
+    );
+    assert_error(
+        extend(&mut h, &sale, SALE_START + 2_001).await,
+        ErrorCode::SaleDurationTooLong,
+    );
+    extend(&mut h, &sale, SALE_START + 2_000).await.unwrap();
+
+    // and the sale may close early once it has run the seller's minimum
+    h.warp_to(SALE_START + 299).await;
+    assert_error(
+        close_early(&mut h, &sale).await,
+        ErrorCode::EarlyCloseNotAllowed,
+    );
+    h.warp_to(SALE_START + 300).await;
+    close_early(&mut h, &sale).await.unwrap();
+}
+
+#[tokio::test]
+async fn soft_close_extends_late_purchases_up_to_the_ceiling() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let buyer = create_buyer(&mut h, &sale).await;
+    let soft_close = |window, extension, ceiling| {
+        instructions::configure_soft_close(
+            &sale.seller.pubkey(),
+            &sale.address,
+            args::ConfigureSoftClose {
+                window,
+                extension,
+                ceiling,
+            },
+        )
+    };
+
+    let cases = [
+        (
+            soft_close(0, 60, SALE_END + 100),
+            ErrorCode::InvalidSoftClose,
+        ),
+        (soft_close(60, 60, SALE_END), ErrorCode::InvalidSoftClose),
+        (
+            soft_close(60, 60, SALE_START + MAX_SALE_DURATION + 1),
+            ErrorCode::SaleDurationTooLong,
+        ),
+    ];
+    for (ix, error) in cases {
+        assert_error(h.process(&[ix], &[&sale.seller]).await, error);
+    }
+    h.process(&[soft_close(100, 60, SALE_END + 100)], &[&sale.seller])
+        .await
+        .unwrap();
+
+    // Purchases before the final window leave the end alone
+    h.warp_to(SALE_END - 100).await;
+    buy(&mut h, &sale, &buyer, 10).await.unwrap();
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(state.sale_end_time, SALE_END);
+
+    h.warp_to(SALE_END - 99).await;
+    buy(&mut h, &sale, &buyer, 10).await.unwrap();
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(state.sale_end_time, SALE_END + 60);
+
+    // Capped at the ceiling, after which the sale ends for good
+    h.warp_to(SALE_END + 30).await;
+    buy(&mut h, &sale, &buyer, 10).await.unwrap();
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(state.sale_end_time, SALE_END + 100);
+
+    h.warp_to(SALE_END + 101).await;
+    assert_error(buy(&mut h, &sale, &buyer, 10).await, ErrorCode::SaleEnded);
+    assert_error(
+        h.process(&[soft_close(0, 0, 0)], &[&sale.seller]).await,
+        ErrorCode::SaleAlreadyStarted,
+    );
+}
+
+#[tokio::test]
+async fn live_price_changes_are_bounded_and_announced() {
+    let mut h = Harness::new().await;
+    let sale = prepare_sale(&mut h).await;
+    init_sale_with(
+        &mut h,
+        &sale,
+        args::InitializeSale {
+            sale_end_time: SALE_START + 3 * PRICE_CHANGE_DELAY,
+            ..init_args(sale.fee_recipient)
+        },
+    )
+    .await
+    .unwrap();
+    let buyer = create_buyer(&mut h, &sale).await;
+    h.warp_to(SALE_START).await;
+    let price = |new_price| args::UpdateSaleParams {
+        new_price_per_token: Some(new_price),
+        ..no_update()
+    };
+
+    // Once live only the price may change, by at most 10% of the current price
+    let cases = [
+        (
+            args::UpdateSaleParams {
+                new_max_tokens_per_buyer: Some(25),
+                ..price(PRICE)
+            },
+            ErrorCode::SaleAlreadyStarted,
+        ),
+        (price(PRICE * 11 / 10 + 1), ErrorCode::PriceChangeTooLarge),
+        (price(PRICE * 9 / 10 - 1), ErrorCode::PriceChangeTooLarge),
+    ];
+    for (args, error) in cases {
+        assert_error(update(&mut h, &sale, args).await, error);
+    }
+
+    update(&mut h, &sale, price(PRICE * 11 / 10)).await.unwrap();
+    let effective_time = h.clock().await.unix_timestamp + PRICE_CHANGE_DELAY;
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(state.price_per_token, PRICE);
+    assert_eq!(state.pending_price_per_token, PRICE * 11 / 10);
+    assert_eq!(state.price_change_time, effective_time);
+
+    // Buyers pay the old price until the announced time
+    h.warp_to(effective_time - 1).await;
+    let quoted = quote(&mut h, &sale, None, 10).await.unwrap();
+    assert_eq!(quoted.gross_cost, 10 * PRICE);
+    buy(&mut h, &sale, &buyer, 10).await.unwrap();
+
+    h.warp_to(effective_time).await;
+    let quoted = quote(&mut h, &sale, None, 10).await.unwrap();
+    assert_eq!(quoted.gross_cost, 10 * PRICE * 11 / 10);
+    let paid_before = h.token_balance(&sale.seller_payment_account).await;
+    buy(&mut h, &sale, &buyer, 10).await.unwrap();
+    assert_eq!(
+        h.token_balance(&sale.seller_payment_account).await - paid_before,
+        quoted.seller_net
+    );
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(state.price_per_token, PRICE * 11 / 10);
+    assert_eq!(state.pending_price_per_token, 0);
+
+    // The next change is bounded by the new price
+    update(&mut h, &sale, price(PRICE * 121 / 100))
+        .await
+        .unwrap();
+
+    h.warp_to(SALE_START + 3 * PRICE_CHANGE_DELAY + 1).await;
+    assert_error(
+        update(&mut h, &sale, price(PRICE)).await,
+        ErrorCode::SaleEnded,
+    );
+}
+
+#[tokio::test]
+async fn purchase_throttles_limit_transactions_buyers_and_slots() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let buyer = create_buyer(&mut h, &sale).await;
+    let other = create_buyer(&mut h, &sale).await;
+
+    assert_error(
+        set_throttles(
+            &mut h,
+            &sale,
+            args::SetPurchaseThrottles {
+                max_tokens_per_tx: None,
+                min_purchase_interval: Some(-1),
+                max_tokens_per_slot: None,
+            },
+        )
+        .await,
+        ErrorCode::InvalidThrottleConfig,
+    );
+    set_throttles(
+        &mut h,
+        &sale,
+        args::SetPurchaseThrottles {
+            max_tokens_per_tx: Some(50),
+            min_purchase_interval: Some(60),
+            max_tokens_per_slot: Some(80),
+        },
+    )
+    .await
+    .unwrap();
+    h.warp_to(SALE_START).await;
+
+    assert_error(
+        buy(&mut h, &sale, &buyer, 51).await,
+        ErrorCode::ExceedsTransactionLimit,
+    );
+    buy(&mut h, &sale, &buyer, 50).await.unwrap();
+    assert_error(
+        buy(&mut h, &sale, &buyer, 1).await,
+        ErrorCode::PurchaseCooldownActive,
+    );
+
+    // The slot limit is shared by every buyer until the slot changes
+    assert_error(
+        buy(&mut h, &sale, &other, 31).await,
+        ErrorCode::ExceedsSlotLimit,
+    );
+    buy(&mut h, &sale, &other, 30).await.unwrap();
+    h.next_slot().await;
+    h.warp_to(SALE_START + 60).await;
+    buy(&mut h, &sale, &buyer, 50).await.unwrap();
+
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(state.tokens_available, TOTAL_TOKENS - 130);
+    assert_eq!(state.tokens_sold_in_slot, 50);
+}
+
+#[tokio::test]
+async fn price_tiers_blend_across_boundaries() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let buyer = create_buyer(&mut h, &sale).await;
+
+    let tier = |tokens_sold_threshold, price_per_token| PriceTier {
+        tokens_sold_threshold,
+        price_per_token,
+    };
+    assert_error(
+        set_tiers(&mut h, &sale, vec![tier(100, 1); 6]).await,
+        ErrorCode::TooManyPriceTiers,
+    );
+    assert_error(
+        set_tiers(&mut h, &sale, vec![tier(100, 0)]).await,
+        ErrorCode::InvalidPrice,
+    );
+    assert_error(
+        set_tiers(&mut h, &sale, vec![tier(200, 1), tier(100, 2)]).await,
+        ErrorCode::InvalidPriceTiers,
+    );
+    assert_error(
+        set_tiers(&mut h, &sale, vec![tier(TOTAL_TOKENS + 1, 1)]).await,
+        ErrorCode::InvalidPriceTiers,
+    );
+    set_tiers(&mut h, &sale, vec![tier(100, 500), tier(200, 750)])
+        .await
+        .unwrap();
+
+    h.warp_to(SALE_START).await;
+    buy(&mut h, &sale, &buyer, 250).await.unwrap();
+
+    // 100 at 500, 100 at 750, 50 at the base price; the seller receives 97.5%
+    let gross = 100 * 500 + 100 * 750 + 50 * PRICE;
+    assert_eq!(
+        h.token_balance(&sale.seller_payment_account).await,
+        gross - gross * 250 / 10_000
+    );
+    assert_error(
+        set_tiers(&mut h, &sale, vec![]).await,
+        ErrorCode::SaleAlreadyStarted,
+    );
+}
+
+#[tokio::test]
+async fn quote_purchase_matches_what_buy_tokens_charges() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let buyer = create_buyer(&mut h, &sale).await;
+    set_tiers(
+        &mut h,
+        &sale,
+        vec![PriceTier {
+            tokens_sold_threshold: 100,
+            price_per_token: 500,
+        }],
+    )
+    .await
+    .unwrap();
+
+    // Quotes are available before the sale opens and blend across tiers like purchases
+    let quoted = quote(&mut h, &sale, Some(&buyer), 150).await.unwrap();
+    let gross = 100 * 500 + 50 * PRICE;
+    assert_eq!(
+        quoted,
+        PurchaseQuote {
+            token_amount: 150,
+            gross_cost: gross,
+            platform_fee: gross * 250 / 10_000,
+            seller_net: gross - gross * 250 / 10_000,
+            remaining_allowance: TOTAL_TOKENS - 150,
+        }
+    );
+
+    h.warp_to(SALE_START).await;
+    buy(&mut h, &sale, &buyer, 150).await.unwrap();
+    assert_eq!(
+        h.token_balance(&sale.seller_payment_account).await,
+        quoted.seller_net
+    );
+    assert_eq!(
+        h.token_balance(&sale.fee_account).await,
+        quoted.platform_fee
+    );
+
+    assert_error(
+        quote(&mut h, &sale, None, 0).await,
+        ErrorCode::InvalidTokenAmount,
+    );
+    assert_error(
+        quote(&mut h, &sale, None, TOTAL_TOKENS).await,
+        ErrorCode::InsufficientTokens,
+    );
+}
+
+#[tokio::test]
+async fn quote_exact_in_stays_within_payment_and_allowance() {
+    let mut h = Harness::new().await;
+    let sale = prepare_sale(&mut h).await;
+    let recipient = sale.fee_recipient;
+    init_sale_with(
+        &mut h,
+        &sale,
+        args::InitializeSale {
+            max_tokens_per_buyer: 50,
+            ..init_args(recipient)
+        },
+    )
+    .await
+    .unwrap();
+    let buyer = create_buyer(&mut h, &sale).await;
+    set_tiers(
+        &mut h,
+        &sale,
+        vec![PriceTier {
+            tokens_sold_threshold: 5,
+            price_per_token: 500,
+        }],
+    )
+    .await
+    .unwrap();
+
+    // 5 at 500 and 3 at the base price; the remainder cannot buy a 9th token
+    let quoted = quote_exact_in(&mut h, &sale, None, 5 * 500 + 3 * PRICE + 999)
+        .await
+        .unwrap();
+    assert_eq!(quoted.token_amount, 8);
+    assert_eq!(quoted.gross_cost, 5 * 500 + 3 * PRICE);
+    assert_eq!(quoted.remaining_allowance, 42);
+
+    h.warp_to(SALE_START).await;
+    buy(&mut h, &sale, &buyer, 40).await.unwrap();
+    let quoted = quote_exact_in(&mut h, &sale, Some(&buyer), u64::MAX / 2)
+        .await
+        .unwrap();
+    assert_eq!(quoted.token_amount, 10);
+    assert_eq!(quoted.remaining_allowance, 0);
+    assert_error(
+        quote(&mut h, &sale, Some(&buyer), 11).await,
+        ErrorCode::ExceedsPurchaseLimit,
+    );
+    assert_error(
+        quote_exact_in(&mut h, &sale, Some(&buyer), PRICE - 1).await,
+        ErrorCode::InvalidTokenAmount,
+    );
+}
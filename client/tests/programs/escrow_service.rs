@@ -0,0 +1,247 @@
+//! Escrow service payments: release, refund, disputes and deadline expiry.
+
+use escrow::ErrorCode;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use token_sale_client::escrow::{args, instructions, pda, ServiceEscrow, ServiceEscrowStatus};
+
+use crate::harness::{assert_error, Harness, TxResult, DAY, START_TIME};
+
+const AMOUNT: u64 = 10_000;
+const DEADLINE: i64 = START_TIME + 7 * DAY;
+
+struct Service {
+    payer: Keypair,
+    payee: Keypair,
+    arbiter: Keypair,
+    mint: Pubkey,
+    payer_token: Pubkey,
+    payee_token: Pubkey,
+}
+
+async fn service(h: &mut Harness) -> Service {
+    let payer = h.wallet().await;
+    let payee = h.wallet().await;
+    let arbiter = h.wallet().await;
+    let mint = h.create_mint(6).await;
+
+    Service {
+        payer_token: h.funded_token_account(&payer.pubkey(), &mint, AMOUNT).await,
+        payee_token: h.create_token_account(&payee.pubkey(), &mint).await,
+        payer,
+        payee,
+        arbiter,
+        mint,
+    }
+}
+
+async fn create(h: &mut Harness, s: &Service, escrow_id: u64, release_deadline: i64) -> TxResult {
+    h.process(
+        &[instructions::create_service_escrow(
+            &s.payer.pubkey(),
+            &s.payee.pubkey(),
+            &s.arbiter.pubkey(),
+            &s.mint,
+            &s.payer_token,
+            args::CreateServiceEscrow {
+                escrow_id,
+                amount: AMOUNT,
+                release_deadline,
+            },
+        )],
+        &[&s.payer],
+    )
+    .await
+}
+
+async fn dispute(h: &mut Harness, s: &Service, authority: &Keypair) -> TxResult {
+    h.process(
+        &[instructions::raise_dispute(
+            &authority.pubkey(),
+            &s.payer.pubkey(),
+            1,
+        )],
+        &[authority],
+    )
+    .await
+}
+
+async fn release(h: &mut Harness, s: &Service) -> TxResult {
+    h.process(
+        &[instructions::release_service_escrow(
+            &s.payer.pubkey(),
+            1,
+            &s.payer_token,
+            &s.payee_token,
+        )],
+        &[&s.payer],
+    )
+    .await
+}
+
+async fn refund(h: &mut Harness, s: &Service, authority: &Keypair) -> TxResult {
+    h.process(
+        &[instructions::refund_service_escrow(
+            &authority.pubkey(),
+            &s.payer.pubkey(),
+            1,
+            &s.payer_token,
+            &s.payee_token,
+        )],
+        &[authority],
+    )
+    .await
+}
+
+async fn resolve(
+    h: &mut Harness,
+    s: &Service,
+    arbiter: &Keypair,
+    payee_share_bps: u16,
+) -> TxResult {
+    h.process(
+        &[instructions::resolve_dispute(
+            &arbiter.pubkey(),
+            &s.payer.pubkey(),
+            1,
+            &s.payer_token,
+            &s.payee_token,
+            args::ResolveDispute { payee_share_bps },
+        )],
+        &[arbiter],
+    )
+    .await
+}
+
+async fn claim_expired(h: &mut Harness, s: &Service) -> TxResult {
+    let caller = h.payer();
+    h.process(
+        &[instructions::claim_expired_service_escrow(
+            &caller.pubkey(),
+            &s.payer.pubkey(),
+            1,
+            &s.payer_token,
+            &s.payee_token,
+        )],
+        &[],
+    )
+    .await
+}
+
+fn escrow_address(s: &Service) -> Pubkey {
+    pda::service_escrow(&s.payer.pubkey(), 1).0
+}
+
+#[tokio::test]
+async fn create_service_escrow_validates_inputs() {
+    let mut h = Harness::new().await;
+    let s = service(&mut h).await;
+
+    assert_error(
+        create(&mut h, &s, 1, START_TIME).await,
+        ErrorCode::InvalidReleaseDeadline,
+    );
+    // Neither party may settle its own disputes
+    for arbiter in [&s.payer, &s.payee] {
+        let judged_by_a_party = Service {
+            arbiter: arbiter.insecure_clone(),
+            payer: s.payer.insecure_clone(),
+            payee: s.payee.insecure_clone(),
+            ..s
+        };
+        assert_error(
+            create(&mut h, &judged_by_a_party, 1, DEADLINE).await,
+            ErrorCode::InvalidArbiter,
+        );
+    }
+    create(&mut h, &s, 1, DEADLINE).await.unwrap();
+
+    let escrow: ServiceEscrow = h.fetch(&escrow_address(&s)).await;
+    assert_eq!(escrow.amount, AMOUNT);
+    assert!(escrow.status == ServiceEscrowStatus::Funded);
+    let vault = pda::service_vault(&escrow_address(&s)).0;
+    assert_eq!(h.token_balance(&vault).await, AMOUNT);
+}
+
+#[tokio::test]
+async fn payer_releases_to_payee() {
+    let mut h = Harness::new().await;
+    let s = service(&mut h).await;
+    create(&mut h, &s, 1, DEADLINE).await.unwrap();
+
+    release(&mut h, &s).await.unwrap();
+    assert_eq!(h.token_balance(&s.payee_token).await, AMOUNT);
+    assert!(h.account(&escrow_address(&s)).await.is_none());
+}
+
+#[tokio::test]
+async fn payee_refunds_to_payer() {
+    let mut h = Harness::new().await;
+    let s = service(&mut h).await;
+    create(&mut h, &s, 1, DEADLINE).await.unwrap();
+
+    assert_error(
+        refund(&mut h, &s, &s.payer).await,
+        ErrorCode::UnauthorizedEscrowParty,
+    );
+    refund(&mut h, &s, &s.payee).await.unwrap();
+    assert_eq!(h.token_balance(&s.payer_token).await, AMOUNT);
+}
+
+#[tokio::test]
+async fn arbiter_splits_disputed_funds() {
+    let mut h = Harness::new().await;
+    let s = service(&mut h).await;
+    create(&mut h, &s, 1, DEADLINE).await.unwrap();
+
+    assert_error(
+        resolve(&mut h, &s, &s.arbiter, 5_000).await,
+        ErrorCode::InvalidEscrowStatus,
+    );
+    assert_error(
+        dispute(&mut h, &s, &s.arbiter).await,
+        ErrorCode::UnauthorizedEscrowParty,
+    );
+    dispute(&mut h, &s, &s.payee).await.unwrap();
+    assert_error(
+        dispute(&mut h, &s, &s.payer).await,
+        ErrorCode::InvalidEscrowStatus,
+    );
+
+    // A disputed escrow can only be settled by the arbiter
+    assert_error(release(&mut h, &s).await, ErrorCode::InvalidEscrowStatus);
+    assert_error(
+        resolve(&mut h, &s, &s.payer, 5_000).await,
+        ErrorCode::UnauthorizedEscrowParty,
+    );
+    assert_error(
+        resolve(&mut h, &s, &s.arbiter, 10_001).await,
+        ErrorCode::InvalidSplitBps,
+    );
+
+    resolve(&mut h, &s, &s.arbiter, 7_500).await.unwrap();
+    assert_eq!(h.token_balance(&s.payee_token).await, 7_500);
+    assert_eq!(h.token_balance(&s.payer_token).await, 2_500);
+}
+
+#[tokio::test]
+async fn deadline_gates_disputes_and_auto_release() {
+    let mut h = Harness::new().await;
+    let s = service(&mut h).await;
+    create(&mut h, &s, 1, DEADLINE).await.unwrap();
+
+    h.warp_to(DEADLINE).await;
+    assert_error(
+        claim_expired(&mut h, &s).await,
+        ErrorCode::ReleaseDeadlineNotReached,
+    );
+
+    h.warp_to(DEADLINE + 1).await;
+    assert_error(
+        dispute(&mut h, &s, &s.payer).await,
+        ErrorCode::ReleaseDeadlinePassed,
+    );
+    claim_expired(&mut h, &s).await.unwrap();
+    assert_eq!(h.token_balance(&s.payee_token).await, AMOUNT);
+    assert!(h.account(&escrow_address(&s)).await.is_none());
+}
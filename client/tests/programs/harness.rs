@@ -0,0 +1,351 @@
+//! In-process test environment for both programs.
+//!
+//! Programs run natively inside `solana-program-test`, alongside the bundled SPL Token
+//! program and a stand-in purchase hook, so the suite needs no validator or network. The `Clock` sysvar is under test
+//! control: [`Harness::warp_to`] moves `unix_timestamp` and [`Harness::next_slot`] moves
+//! `slot`, and nothing else advances them.
+
+use anchor_lang::solana_program::account_info::AccountInfo;
+use anchor_lang::solana_program::entrypoint::ProgramResult;
+use anchor_lang::{
+    AccountDeserialize, AccountSerialize, AnchorDeserialize, Discriminator, ZeroCopy,
+};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::clock::{Clock, Slot};
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::slot_hashes::SlotHashes;
+use solana_sdk::transaction::{Transaction, TransactionError};
+use solana_system_interface::instruction as system_instruction;
+
+/// Unix timestamp every test starts at
+pub const START_TIME: i64 = 1_700_000_000;
+pub const DAY: i64 = 86_400;
+
+pub type TxResult = Result<(), BanksClientError>;
+
+// Anchor's generated entrypoints tie the account slice to the `'info` lifetime, which
+// the native processor signature cannot express; leaking the slice satisfies it.
+fn escrow_entry(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    escrow::entry(program_id, accounts, data)
+}
+
+fn multi_presale_entry(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    multi_presale::entry(program_id, accounts, data)
+}
+
+pub struct Harness {
+    pub context: ProgramTestContext,
+}
+
+impl Harness {
+    pub async fn new() -> Self {
+        let mut program_test = ProgramTest::default();
+        program_test.prefer_bpf(false);
+        program_test.add_program("escrow", escrow::ID, processor!(escrow_entry));
+        program_test.add_program(
+            "multi_presale",
+            multi_presale::ID,
+            processor!(multi_presale_entry),
+        );
+        program_test.add_program(
+            "purchase_hook",
+            crate::escrow_hook::HOOK_PROGRAM_ID,
+            processor!(crate::escrow_hook::process_hook),
+        );
+
+        let harness = Self {
+            context: program_test.start_with_context().await,
+        };
+        harness
+            .set_clock(|clock| clock.unix_timestamp = START_TIME)
+            .await;
+        harness
+    }
+
+    pub fn payer(&self) -> Keypair {
+        self.context.payer.insecure_clone()
+    }
+
+    /// Sign with the payer plus `signers` and process, failing on any error
+    pub async fn process(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> TxResult {
+        // Identical transactions must not collide with ones already processed
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let payer = self.payer();
+        let mut all_signers = vec![&payer];
+        all_signers.extend_from_slice(signers);
+
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+    }
+
+    /// Simulate `instructions` and decode the value `program_id` returned
+    pub async fn simulate<T: AnchorDeserialize>(
+        &mut self,
+        program_id: &Pubkey,
+        instructions: &[Instruction],
+    ) -> Result<T, BanksClientError> {
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let payer = self.payer();
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&payer.pubkey()),
+            &[&payer],
+            blockhash,
+        );
+        let simulation = self
+            .context
+            .banks_client
+            .simulate_transaction(transaction)
+            .await?;
+        simulation.result.expect("transaction was not simulated")?;
+
+        let return_data = simulation
+            .simulation_details
+            .and_then(|details| details.return_data)
+            .expect("no return data");
+        assert_eq!(return_data.program_id, *program_id);
+        Ok(T::try_from_slice(&return_data.data).unwrap())
+    }
+
+    pub async fn clock(&self) -> Clock {
+        self.context
+            .banks_client
+            .get_sysvar::<Clock>()
+            .await
+            .unwrap()
+    }
+
+    async fn set_clock(&self, update: impl FnOnce(&mut Clock)) {
+        let mut clock = self.clock().await;
+        update(&mut clock);
+        self.context.set_sysvar(&clock);
+    }
+
+    /// Move the clock to `unix_timestamp`
+    pub async fn warp_to(&self, unix_timestamp: i64) {
+        self.set_clock(|clock| clock.unix_timestamp = unix_timestamp)
+            .await;
+    }
+
+    /// Advance the clock's slot, leaving the timestamp unchanged
+    pub async fn next_slot(&self) {
+        self.set_clock(|clock| clock.slot += 1).await;
+    }
+
+    /// Move the clock's slot to `slot`, leaving the timestamp unchanged
+    pub async fn warp_to_slot(&self, slot: Slot) {
+        self.set_clock(|clock| clock.slot = slot).await;
+    }
+
+    /// Replace the SlotHashes sysvar, the mock randomness source of slot hash draws
+    pub fn set_slot_hashes(&self, slot_hashes: &[(Slot, Hash)]) {
+        self.context.set_sysvar(&SlotHashes::new(slot_hashes));
+    }
+
+    /// A new keypair funded with 10 SOL
+    pub async fn wallet(&mut self) -> Keypair {
+        let wallet = Keypair::new();
+        let payer = self.payer();
+        self.process(
+            &[system_instruction::transfer(
+                &payer.pubkey(),
+                &wallet.pubkey(),
+                10_000_000_000,
+            )],
+            &[],
+        )
+        .await
+        .unwrap();
+        wallet
+    }
+
+    pub async fn create_mint(&mut self, decimals: u8) -> Pubkey {
+        let mint = Keypair::new();
+        let payer = self.payer();
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        self.process(
+            &[
+                system_instruction::create_account(
+                    &payer.pubkey(),
+                    &mint.pubkey(),
+                    rent.minimum_balance(spl_token::state::Mint::LEN),
+                    spl_token::state::Mint::LEN as u64,
+                    &spl_token::ID,
+                ),
+                spl_token::instruction::initialize_mint2(
+                    &spl_token::ID,
+                    &mint.pubkey(),
+                    &payer.pubkey(),
+                    None,
+                    decimals,
+                )
+                .unwrap(),
+            ],
+            &[&mint],
+        )
+        .await
+        .unwrap();
+        mint.pubkey()
+    }
+
+    pub async fn create_token_account(&mut self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        let account = Keypair::new();
+        let payer = self.payer();
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        self.process(
+            &[
+                system_instruction::create_account(
+                    &payer.pubkey(),
+                    &account.pubkey(),
+                    rent.minimum_balance(spl_token::state::Account::LEN),
+                    spl_token::state::Account::LEN as u64,
+                    &spl_token::ID,
+                ),
+                spl_token::instruction::initialize_account3(
+                    &spl_token::ID,
+                    &account.pubkey(),
+                    mint,
+                    owner,
+                )
+                .unwrap(),
+            ],
+            &[&account],
+        )
+        .await
+        .unwrap();
+        account.pubkey()
+    }
+
+    pub async fn mint_to(&mut self, mint: &Pubkey, account: &Pubkey, amount: u64) {
+        let payer = self.payer();
+        self.process(
+            &[spl_token::instruction::mint_to(
+                &spl_token::ID,
+                mint,
+                account,
+                &payer.pubkey(),
+                &[],
+                amount,
+            )
+            .unwrap()],
+            &[],
+        )
+        .await
+        .unwrap();
+    }
+
+    /// A token account for `owner` holding `amount` of `mint`
+    pub async fn funded_token_account(
+        &mut self,
+        owner: &Pubkey,
+        mint: &Pubkey,
+        amount: u64,
+    ) -> Pubkey {
+        let account = self.create_token_account(owner, mint).await;
+        if amount > 0 {
+            self.mint_to(mint, &account, amount).await;
+        }
+        account
+    }
+
+    pub async fn token_balance(&mut self, account: &Pubkey) -> u64 {
+        let account = self.account(account).await.expect("token account missing");
+        spl_token::state::Account::unpack(&account.data)
+            .unwrap()
+            .amount
+    }
+
+    pub async fn account(&mut self, address: &Pubkey) -> Option<Account> {
+        self.context
+            .banks_client
+            .get_account(*address)
+            .await
+            .unwrap()
+    }
+
+    pub async fn fetch<T: AccountDeserialize>(&mut self, address: &Pubkey) -> T {
+        let account = self.account(address).await.expect("account missing");
+        token_sale_client::decode(&account.data).unwrap()
+    }
+
+    /// Overwrite a program account's state, for states no instruction can reach
+    pub async fn store<T: AccountSerialize + Discriminator>(
+        &mut self,
+        address: &Pubkey,
+        state: &T,
+    ) {
+        let mut account = self.account(address).await.expect("account missing");
+        let mut data = Vec::new();
+        state.try_serialize(&mut data).unwrap();
+        account.data[..data.len()].copy_from_slice(&data);
+        self.context.set_account(address, &account.into());
+    }
+
+    /// Replace a program account with `state` in an older Borsh layout, `len` bytes long
+    /// (zero-padded or truncated) and holding just the rent that size needs
+    pub async fn store_legacy<T: AccountSerialize>(
+        &mut self,
+        address: &Pubkey,
+        state: &T,
+        len: usize,
+    ) {
+        let mut data = Vec::new();
+        state.try_serialize(&mut data).unwrap();
+        data.resize(len, 0);
+        self.store_bytes(address, data).await;
+    }
+
+    /// Replace a program account's data outright, holding just the rent its size needs
+    pub async fn store_bytes(&mut self, address: &Pubkey, data: Vec<u8>) {
+        let mut account = self.account(address).await.expect("account missing");
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        account.lamports = rent.minimum_balance(data.len());
+        account.data = data;
+        self.context.set_account(address, &account.into());
+    }
+
+    /// Overwrite a zero-copy program account's state, like [`Harness::store`]
+    pub async fn store_zero_copy<T: ZeroCopy>(&mut self, address: &Pubkey, state: &T) {
+        let mut account = self.account(address).await.expect("account missing");
+        let data = [T::DISCRIMINATOR, bytemuck::bytes_of(state)].concat();
+        account.data[..data.len()].copy_from_slice(&data);
+        self.context.set_account(address, &account.into());
+    }
+}
+
+/// Assert that a transaction failed with the given program error code
+pub fn assert_error<T>(result: Result<T, BanksClientError>, expected: impl Into<u32>) {
+    let expected = expected.into();
+    match result {
+        Err(err) => match err.unwrap() {
+            TransactionError::InstructionError(_, InstructionError::Custom(code)) => {
+                assert_eq!(code, expected, "expected error {expected}, got {code}")
+            }
+            other => panic!("expected error {expected}, got {other:?}"),
+        },
+        Ok(_) => panic!("expected error {expected}, but the transaction succeeded"),
+    }
+}
@@ -0,0 +1,15 @@
+mod harness;
+
+mod escrow_auction;
+mod escrow_deny_list;
+mod escrow_hook;
+mod escrow_lock;
+mod escrow_lottery;
+mod escrow_milestones;
+mod escrow_offers;
+mod escrow_overflow;
+mod escrow_sale;
+mod escrow_service;
+mod migrations;
+mod presale_platform;
+mod presale_sale;
@@ -0,0 +1,345 @@
+//! Account migrations: legacy accounts without a version byte are grown in place and
+//! stamped with the current version, and the Borsh sale and buyer accounts are rewritten
+//! in their zero-copy layouts, by anyone willing to pay the extra rent.
+
+use ::escrow::ErrorCode;
+use anchor_lang::error::ErrorCode as AnchorError;
+use anchor_lang::{AccountSerialize, Discriminator};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use token_sale_client::escrow::{
+    self, legacy, BuyerAccount, Milestone, PriceTier, TokenSale, ZeroCopyVersioned,
+};
+use token_sale_client::multi_presale::{self, PlatformAccount};
+
+use crate::escrow_sale::{buy, create_buyer, create_sale, SALE_END, SALE_START};
+use crate::harness::{assert_error, Harness, DAY};
+
+/// Rewrite `address` as it was laid out before versioning: without the version byte that
+/// follows the serialized fields, and holding only the rent the smaller size needed
+async fn make_legacy<T: AccountSerialize + anchor_lang::AccountDeserialize>(
+    h: &mut Harness,
+    address: &Pubkey,
+) -> usize {
+    let state: T = h.fetch(address).await;
+    let legacy_len = h.account(address).await.unwrap().data.len() - 1;
+    h.store_legacy(address, &state, legacy_len).await;
+    legacy_len
+}
+
+/// The Borsh tracker `state` was converted from, at version 0 (no version byte)
+fn legacy_buyer_account(state: &BuyerAccount) -> legacy::BuyerAccount {
+    legacy::BuyerAccount {
+        buyer: state.buyer,
+        token_sale: state.token_sale,
+        tokens_purchased: state.tokens_purchased,
+        bump: state.bump,
+        last_purchase_time: state.last_purchase_time,
+        payment_escrowed: state.payment_escrowed,
+        last_voted_milestone: state.last_voted_milestone,
+        refund_claimed: state.refund_claimed(),
+        version: 0,
+    }
+}
+
+#[tokio::test]
+async fn legacy_buyer_account_is_converted_to_zero_copy() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let buyer = create_buyer(&mut h, &sale).await;
+    let tracker = escrow::pda::buyer(&buyer.wallet.pubkey(), &sale.address).0;
+    h.warp_to(SALE_START).await;
+    buy(&mut h, &sale, &buyer, 10).await.unwrap();
+
+    let state: BuyerAccount = h.fetch(&tracker).await;
+    let legacy_len = BuyerAccount::LEGACY_SPACE - 1;
+    h.store_legacy(&tracker, &legacy_buyer_account(&state), legacy_len)
+        .await;
+    // The zero-copy loader refuses the Borsh layout rather than misreading it
+    assert_error(
+        buy(&mut h, &sale, &buyer, 10).await,
+        AnchorError::AccountDiscriminatorMismatch,
+    );
+
+    // Anyone may migrate, paying the extra rent
+    let sponsor = h.wallet().await;
+    let migrate = || escrow::instructions::migrate_buyer_account(&sponsor.pubkey(), &tracker);
+    h.process(&[migrate()], &[&sponsor]).await.unwrap();
+
+    let account = h.account(&tracker).await.unwrap();
+    let rent = h.context.banks_client.get_rent().await.unwrap();
+    assert_eq!(account.data.len(), BuyerAccount::SPACE);
+    assert!(rent.is_exempt(account.lamports, account.data.len()));
+    let migrated: BuyerAccount = h.fetch(&tracker).await;
+    assert_eq!(migrated.version, BuyerAccount::VERSION);
+    assert_eq!(migrated.buyer, buyer.wallet.pubkey());
+    assert_eq!(migrated.token_sale, sale.address);
+    assert_eq!(migrated.tokens_purchased, 10);
+    assert_eq!(migrated.bump, state.bump);
+
+    buy(&mut h, &sale, &buyer, 10).await.unwrap();
+    let state: BuyerAccount = h.fetch(&tracker).await;
+    assert_eq!(state.tokens_purchased, 20);
+
+    h.next_slot().await;
+    assert_error(
+        h.process(&[migrate()], &[&sponsor]).await,
+        ErrorCode::AccountAlreadyMigrated,
+    );
+}
+
+#[tokio::test]
+async fn legacy_sale_keeps_tiers_and_milestones() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let payer = h.payer().pubkey();
+    let approver = Pubkey::new_unique();
+
+    let state: TokenSale = h.fetch(&sale.address).await;
+    let legacy_sale = legacy::TokenSale {
+        seller: state.seller,
+        token_mint: state.token_mint,
+        payment_mint: state.payment_mint,
+        price_per_token: state.price_per_token,
+        total_tokens: state.total_tokens,
+        tokens_available: state.tokens_available - 100,
+        sale_start_time: state.sale_start_time,
+        sale_end_time: state.sale_end_time,
+        platform_fee_bps: state.platform_fee_bps,
+        platform_fee_recipient: state.platform_fee_recipient,
+        is_active: true,
+        bump: state.bump,
+        max_tokens_per_tx: 50,
+        price_tiers: vec![
+            PriceTier {
+                tokens_sold_threshold: 200,
+                price_per_token: 500,
+            },
+            PriceTier {
+                tokens_sold_threshold: 400,
+                price_per_token: 750,
+            },
+        ],
+        milestones: vec![
+            Milestone {
+                release_bps: 4_000,
+                deadline: SALE_END + DAY,
+            },
+            Milestone {
+                release_bps: 6_000,
+                deadline: SALE_END + 2 * DAY,
+            },
+        ],
+        milestone_approver: Some(approver),
+        milestone_vote_supply: 100,
+        proceeds_escrowed: 50_000,
+        version: 1,
+        ..Default::default()
+    };
+    h.store_legacy(&sale.address, &legacy_sale, TokenSale::LEGACY_SPACE)
+        .await;
+
+    assert_error(
+        h.process(
+            &[escrow::instructions::migrate_offer(&payer, &sale.address)],
+            &[],
+        )
+        .await,
+        AnchorError::AccountDiscriminatorMismatch,
+    );
+    assert_error(
+        h.process(
+            &[escrow::instructions::migrate_token_sale(
+                &payer,
+                &sale.seller_token_account,
+            )],
+            &[],
+        )
+        .await,
+        AnchorError::AccountOwnedByWrongProgram,
+    );
+
+    h.process(
+        &[escrow::instructions::migrate_token_sale(
+            &payer,
+            &sale.address,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    // The zero-copy layout is smaller; the surplus rent stays with the sale
+    assert_eq!(
+        h.account(&sale.address).await.unwrap().data.len(),
+        TokenSale::SPACE
+    );
+    let migrated: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(migrated.version, TokenSale::VERSION);
+    assert_eq!(migrated.seller, sale.seller.pubkey());
+    assert_eq!(migrated.tokens_available, state.tokens_available - 100);
+    assert_eq!(migrated.max_tokens_per_tx, 50);
+    assert!(migrated.is_active() && !migrated.is_paused());
+    let tiers: Vec<_> = migrated
+        .price_tiers()
+        .iter()
+        .map(|tier| (tier.tokens_sold_threshold, tier.price_per_token))
+        .collect();
+    assert_eq!(tiers, [(200, 500), (400, 750)]);
+    let milestones: Vec<_> = migrated
+        .milestones()
+        .map(|milestone| (milestone.release_bps, milestone.deadline))
+        .collect();
+    assert_eq!(
+        milestones,
+        [(4_000, SALE_END + DAY), (6_000, SALE_END + 2 * DAY)]
+    );
+    assert_eq!(migrated.milestone_approver(), Some(approver));
+    assert_eq!(migrated.milestone_vote_supply, 100);
+    assert_eq!(migrated.proceeds_escrowed, 50_000);
+}
+
+#[tokio::test]
+async fn zero_copy_sale_grows_to_the_current_version() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let buyer = create_buyer(&mut h, &sale).await;
+
+    // Version 2 ended before the announced price change fields
+    let mut state: TokenSale = h.fetch(&sale.address).await;
+    state.version = 2;
+    let mut data = [TokenSale::DISCRIMINATOR, bytemuck::bytes_of(&state)].concat();
+    data.truncate(8 + std::mem::offset_of!(TokenSale, pending_price_per_token));
+    h.store_bytes(&sale.address, data).await;
+    h.warp_to(SALE_START).await;
+    assert!(buy(&mut h, &sale, &buyer, 10).await.is_err());
+
+    let payer = h.payer().pubkey();
+    let migrate = || escrow::instructions::migrate_token_sale(&payer, &sale.address);
+    h.process(&[migrate()], &[]).await.unwrap();
+
+    let account = h.account(&sale.address).await.unwrap();
+    let rent = h.context.banks_client.get_rent().await.unwrap();
+    assert_eq!(account.data.len(), TokenSale::SPACE);
+    assert!(rent.is_exempt(account.lamports, account.data.len()));
+    let migrated: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(migrated.version, TokenSale::VERSION);
+    assert_eq!(migrated.seller, state.seller);
+    assert_eq!(migrated.tokens_available, state.tokens_available);
+    assert_eq!(migrated.pending_price_per_token, 0);
+    buy(&mut h, &sale, &buyer, 10).await.unwrap();
+
+    h.next_slot().await;
+    assert_error(
+        h.process(&[migrate()], &[]).await,
+        ErrorCode::AccountAlreadyMigrated,
+    );
+}
+
+#[tokio::test]
+async fn zero_copy_buyer_account_grows_to_the_current_version() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+    let buyer = create_buyer(&mut h, &sale).await;
+    let tracker = escrow::pda::buyer(&buyer.wallet.pubkey(), &sale.address).0;
+    h.warp_to(SALE_START).await;
+    buy(&mut h, &sale, &buyer, 10).await.unwrap();
+
+    // Version 2 ended before the overflow deposit
+    let mut state: BuyerAccount = h.fetch(&tracker).await;
+    state.version = 2;
+    let mut data = [BuyerAccount::DISCRIMINATOR, bytemuck::bytes_of(&state)].concat();
+    data.truncate(8 + std::mem::offset_of!(BuyerAccount, overflow_deposit));
+    h.store_bytes(&tracker, data).await;
+    assert!(buy(&mut h, &sale, &buyer, 10).await.is_err());
+
+    let payer = h.payer().pubkey();
+    h.process(
+        &[escrow::instructions::migrate_buyer_account(
+            &payer, &tracker,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        h.account(&tracker).await.unwrap().data.len(),
+        BuyerAccount::SPACE
+    );
+    let migrated: BuyerAccount = h.fetch(&tracker).await;
+    assert_eq!(migrated.version, BuyerAccount::VERSION);
+    assert_eq!(migrated.tokens_purchased, 10);
+    assert_eq!(migrated.overflow_deposit, 0);
+    // Older trackers were paid for by the buyer, who gets the rent back on close
+    assert_eq!(migrated.rent_payer, Pubkey::default());
+    assert_eq!(migrated.rent_recipient(), buyer.wallet.pubkey());
+    buy(&mut h, &sale, &buyer, 10).await.unwrap();
+}
+
+#[tokio::test]
+async fn new_accounts_start_at_the_current_version() {
+    let mut h = Harness::new().await;
+    let sale = create_sale(&mut h).await;
+
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(state.version, TokenSale::VERSION);
+    assert_eq!(
+        h.account(&sale.address).await.unwrap().data.len(),
+        TokenSale::SPACE
+    );
+    assert_error(
+        h.process(
+            &[escrow::instructions::migrate_token_sale(
+                &h.payer().pubkey(),
+                &sale.address,
+            )],
+            &[],
+        )
+        .await,
+        ErrorCode::AccountAlreadyMigrated,
+    );
+}
+
+#[tokio::test]
+async fn legacy_platform_account_is_migrated() {
+    use multi_presale::Versioned;
+
+    let mut h = Harness::new().await;
+    let admin = h.wallet().await;
+    h.process(
+        &[multi_presale::instructions::initialize_platform(
+            &admin.pubkey(),
+            &Pubkey::new_unique(),
+            multi_presale::args::InitializePlatform {
+                platform_fee: 250,
+                min_project_duration: DAY,
+                max_project_duration: 30 * DAY,
+            },
+        )],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+    let platform = multi_presale::pda::platform().0;
+    make_legacy::<PlatformAccount>(&mut h, &platform).await;
+
+    h.process(
+        &[multi_presale::instructions::migrate_platform(
+            &h.payer().pubkey(),
+            &platform,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let state: PlatformAccount = h.fetch(&platform).await;
+    assert_eq!(
+        h.account(&platform).await.unwrap().data.len(),
+        PlatformAccount::SPACE
+    );
+    assert_eq!(state.version, PlatformAccount::VERSION);
+    assert_eq!(state.authority, admin.pubkey());
+    assert_eq!(state.platform_fee, 250);
+}
@@ -0,0 +1,611 @@
+//! Multi-presale platform: configuration, project lifecycle, rounds, treasury and whitelists.
+//!
+//! Error variants with no test here cannot be triggered by any transaction:
+//! `ProjectNotEditable`, `IncompleteProject`, `NotWhitelisted`, `InvalidRoundNumber` and
+//! `SaleRoundNotActive` are never raised, and `NameTooLong` sits behind the 32-byte PDA
+//! seed limit on the project name.
+
+use multi_presale::ErrorCode;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use token_sale_client::multi_presale::{
+    args, instructions, pda, ApprovalStatus, PlatformAccount, PlatformTreasury, ProjectAccount,
+    ProjectCategory, ProjectStatus, ProjectWhitelist, SaleConfiguration, SaleRound, SaleType,
+};
+
+use crate::harness::{assert_error, Harness, TxResult, DAY, START_TIME};
+
+const NAME: &str = "Solar";
+
+struct Platform {
+    admin: Keypair,
+    creator: Keypair,
+    token_mint: Pubkey,
+    payment_mint: Pubkey,
+    project: Pubkey,
+}
+
+fn platform_args() -> args::InitializePlatform {
+    args::InitializePlatform {
+        platform_fee: 250,
+        min_project_duration: DAY,
+        max_project_duration: 30 * DAY,
+    }
+}
+
+fn project_args(name: &str) -> args::CreateProject {
+    args::CreateProject {
+        name: name.to_string(),
+        description: "Community solar financing".to_string(),
+        logo_url: "ipfs://logo".to_string(),
+        website: "https://solar.example".to_string(),
+        category: ProjectCategory::Infrastructure,
+        tags: vec!["energy".to_string()],
+        token_name: "Solar Token".to_string(),
+        token_symbol: "SOL4R".to_string(),
+        token_decimals: 6,
+    }
+}
+
+fn tier_args() -> args::ConfigureSaleTier {
+    args::ConfigureSaleTier {
+        sale_type: SaleType::Public,
+        token_price: 1_000,
+        total_tokens: 1_000_000,
+        min_purchase: 10,
+        max_purchase: 10_000,
+        start_time: START_TIME + DAY,
+        end_time: START_TIME + 8 * DAY,
+        is_whitelist_only: false,
+        requires_kyc: false,
+        referral_enabled: true,
+        referral_rate: 500,
+    }
+}
+
+fn round_args(round_number: u8) -> args::CreateSaleRound {
+    args::CreateSaleRound {
+        round_number,
+        sale_type: SaleType::Seed,
+        token_price: 500,
+        total_tokens: 100_000,
+        max_tokens_per_buyer: 5_000,
+        start_time: START_TIME + DAY,
+        end_time: START_TIME + 2 * DAY,
+        whitelist_required: true,
+        min_purchase: 100,
+        max_total_raise: 50_000_000,
+    }
+}
+
+/// An initialized platform with one draft project
+async fn platform(h: &mut Harness) -> Platform {
+    let admin = h.wallet().await;
+    h.process(
+        &[instructions::initialize_platform(
+            &admin.pubkey(),
+            &Pubkey::new_unique(),
+            platform_args(),
+        )],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+
+    let creator = h.wallet().await;
+    let token_mint = h.create_mint(6).await;
+    let payment_mint = h.create_mint(6).await;
+    h.process(
+        &[instructions::create_project(
+            &creator.pubkey(),
+            &token_mint,
+            project_args(NAME),
+        )],
+        &[&creator],
+    )
+    .await
+    .unwrap();
+
+    Platform {
+        project: pda::project(&creator.pubkey(), NAME).0,
+        admin,
+        creator,
+        token_mint,
+        payment_mint,
+    }
+}
+
+async fn approve(h: &mut Harness, p: &Platform, admin: &Keypair) -> TxResult {
+    h.process(
+        &[instructions::approve_project(&admin.pubkey(), &p.project)],
+        &[admin],
+    )
+    .await
+}
+
+async fn init_vault(h: &mut Harness, p: &Platform) -> TxResult {
+    h.process(
+        &[instructions::initialize_project_vault(
+            &p.creator.pubkey(),
+            &p.project,
+            &p.token_mint,
+        )],
+        &[&p.creator],
+    )
+    .await
+}
+
+async fn configure_tier(h: &mut Harness, p: &Platform, args: args::ConfigureSaleTier) -> TxResult {
+    h.process(
+        &[instructions::configure_sale_tier(
+            &p.creator.pubkey(),
+            &p.project,
+            &p.payment_mint,
+            args,
+        )],
+        &[&p.creator],
+    )
+    .await
+}
+
+async fn create_round(h: &mut Harness, p: &Platform, args: args::CreateSaleRound) -> TxResult {
+    h.process(
+        &[instructions::create_sale_round(
+            &p.creator.pubkey(),
+            &p.project,
+            args,
+        )],
+        &[&p.creator],
+    )
+    .await
+}
+
+async fn advance(h: &mut Harness, p: &Platform) -> TxResult {
+    h.process(
+        &[instructions::advance_project_status(
+            &p.creator.pubkey(),
+            &p.project,
+        )],
+        &[&p.creator],
+    )
+    .await
+}
+
+async fn update_project(h: &mut Harness, p: &Platform, update: impl FnOnce(&mut ProjectAccount)) {
+    let mut project: ProjectAccount = h.fetch(&p.project).await;
+    update(&mut project);
+    h.store(&p.project, &project).await;
+}
+
+#[tokio::test]
+async fn initialize_platform_validates_fee_and_durations() {
+    let mut h = Harness::new().await;
+    let admin = h.wallet().await;
+    let treasury = Pubkey::new_unique();
+    let init = |args| instructions::initialize_platform(&admin.pubkey(), &treasury, args);
+
+    let cases = [
+        (
+            args::InitializePlatform {
+                platform_fee: 10_001,
+                ..platform_args()
+            },
+            ErrorCode::InvalidPlatformFee,
+        ),
+        (
+            args::InitializePlatform {
+                min_project_duration: 0,
+                ..platform_args()
+            },
+            ErrorCode::InvalidDuration,
+        ),
+        (
+            args::InitializePlatform {
+                max_project_duration: DAY,
+                ..platform_args()
+            },
+            ErrorCode::InvalidDuration,
+        ),
+    ];
+    for (args, error) in cases {
+        assert_error(h.process(&[init(args)], &[&admin]).await, error);
+    }
+
+    h.process(&[init(platform_args())], &[&admin])
+        .await
+        .unwrap();
+    let platform: PlatformAccount = h.fetch(&pda::platform().0).await;
+    assert_eq!(platform.authority, admin.pubkey());
+    assert_eq!(platform.treasury, treasury);
+    assert_eq!(platform.platform_fee, 250);
+    assert!(!platform.is_paused);
+}
+
+#[tokio::test]
+async fn create_project_records_metadata() {
+    let mut h = Harness::new().await;
+    let p = platform(&mut h).await;
+
+    let project: ProjectAccount = h.fetch(&p.project).await;
+    assert_eq!(project.id, 1);
+    assert_eq!(project.name, NAME);
+    assert_eq!(project.creator, p.creator.pubkey());
+    assert!(project.status == ProjectStatus::Draft);
+    assert!(project.approval_status == ApprovalStatus::Pending);
+    assert_eq!(project.created_at, START_TIME);
+    assert_eq!(
+        h.fetch::<PlatformAccount>(&pda::platform().0)
+            .await
+            .total_projects,
+        1
+    );
+}
+
+#[tokio::test]
+async fn create_project_validates_metadata() {
+    let mut h = Harness::new().await;
+    let p = platform(&mut h).await;
+    let create = |args| instructions::create_project(&p.creator.pubkey(), &p.token_mint, args);
+
+    let cases = [
+        (
+            args::CreateProject {
+                description: "x".repeat(501),
+                ..project_args("Long")
+            },
+            ErrorCode::DescriptionTooLong,
+        ),
+        (
+            args::CreateProject {
+                tags: vec!["t".to_string(); 11],
+                ..project_args("Tags")
+            },
+            ErrorCode::TooManyTags,
+        ),
+    ];
+    for (args, error) in cases {
+        assert_error(h.process(&[create(args)], &[&p.creator]).await, error);
+    }
+}
+
+#[tokio::test]
+async fn paused_platform_rejects_projects_and_tiers() {
+    let mut h = Harness::new().await;
+    let p = platform(&mut h).await;
+    approve(&mut h, &p, &p.admin).await.unwrap();
+
+    // No instruction pauses the platform yet
+    let address = pda::platform().0;
+    let mut platform: PlatformAccount = h.fetch(&address).await;
+    platform.is_paused = true;
+    h.store(&address, &platform).await;
+
+    assert_error(
+        h.process(
+            &[instructions::create_project(
+                &p.creator.pubkey(),
+                &p.token_mint,
+                project_args("Paused"),
+            )],
+            &[&p.creator],
+        )
+        .await,
+        ErrorCode::PlatformPaused,
+    );
+    assert_error(
+        configure_tier(&mut h, &p, tier_args()).await,
+        ErrorCode::PlatformPaused,
+    );
+}
+
+#[tokio::test]
+async fn approve_project_activates_draft() {
+    let mut h = Harness::new().await;
+    let p = platform(&mut h).await;
+
+    let stranger = h.wallet().await;
+    assert_error(
+        approve(&mut h, &p, &stranger).await,
+        ErrorCode::UnauthorizedAccess,
+    );
+
+    h.warp_to(START_TIME + 60).await;
+    approve(&mut h, &p, &p.admin).await.unwrap();
+    let project: ProjectAccount = h.fetch(&p.project).await;
+    assert!(project.status == ProjectStatus::Active);
+    assert!(project.approval_status == ApprovalStatus::Approved);
+    assert_eq!(project.approved_by, Some(p.admin.pubkey()));
+    assert_eq!(project.approved_at, Some(START_TIME + 60));
+
+    assert_error(
+        approve(&mut h, &p, &p.admin).await,
+        ErrorCode::InvalidProjectStatus,
+    );
+
+    update_project(&mut h, &p, |project| project.status = ProjectStatus::Draft).await;
+    assert_error(
+        approve(&mut h, &p, &p.admin).await,
+        ErrorCode::InvalidApprovalStatus,
+    );
+}
+
+#[tokio::test]
+async fn project_vault_requires_approved_project() {
+    let mut h = Harness::new().await;
+    let p = platform(&mut h).await;
+
+    assert_error(
+        init_vault(&mut h, &p).await,
+        ErrorCode::InvalidProjectStatus,
+    );
+
+    update_project(&mut h, &p, |project| project.status = ProjectStatus::Active).await;
+    assert_error(
+        init_vault(&mut h, &p).await,
+        ErrorCode::InvalidApprovalStatus,
+    );
+
+    update_project(&mut h, &p, |project| project.status = ProjectStatus::Draft).await;
+    approve(&mut h, &p, &p.admin).await.unwrap();
+    init_vault(&mut h, &p).await.unwrap();
+
+    let vault = pda::project_vault(&p.project, &p.token_mint).0;
+    let project: ProjectAccount = h.fetch(&p.project).await;
+    assert_eq!(project.token_vault, Some(vault));
+    assert_eq!(h.token_balance(&vault).await, 0);
+}
+
+#[tokio::test]
+async fn configure_sale_tier_validates_terms() {
+    let mut h = Harness::new().await;
+    let p = platform(&mut h).await;
+
+    assert_error(
+        configure_tier(&mut h, &p, tier_args()).await,
+        ErrorCode::ProjectNotActive,
+    );
+    approve(&mut h, &p, &p.admin).await.unwrap();
+
+    let other = h.wallet().await;
+    assert_error(
+        h.process(
+            &[instructions::configure_sale_tier(
+                &other.pubkey(),
+                &p.project,
+                &p.payment_mint,
+                tier_args(),
+            )],
+            &[&other],
+        )
+        .await,
+        ErrorCode::UnauthorizedAccess,
+    );
+
+    let cases = [
+        (
+            args::ConfigureSaleTier {
+                token_price: 0,
+                ..tier_args()
+            },
+            ErrorCode::InvalidPrice,
+        ),
+        (
+            args::ConfigureSaleTier {
+                total_tokens: 0,
+                ..tier_args()
+            },
+            ErrorCode::InvalidTokenAmount,
+        ),
+        (
+            args::ConfigureSaleTier {
+                min_purchase: 0,
+                ..tier_args()
+            },
+            ErrorCode::InvalidPrice,
+        ),
+        (
+            args::ConfigureSaleTier {
+                max_purchase: 9,
+                ..tier_args()
+            },
+            ErrorCode::InvalidPrice,
+        ),
+        (
+            args::ConfigureSaleTier {
+                end_time: START_TIME + DAY,
+                ..tier_args()
+            },
+            ErrorCode::SaleEndTimeInPast,
+        ),
+        (
+            args::ConfigureSaleTier {
+                referral_rate: 10_001,
+                ..tier_args()
+            },
+            ErrorCode::InvalidPlatformFee,
+        ),
+        (
+            args::ConfigureSaleTier {
+                start_time: START_TIME,
+                ..tier_args()
+            },
+            ErrorCode::SaleEndTimeInPast,
+        ),
+        (
+            args::ConfigureSaleTier {
+                end_time: START_TIME + DAY + 60,
+                ..tier_args()
+            },
+            ErrorCode::InvalidDuration,
+        ),
+        (
+            args::ConfigureSaleTier {
+                end_time: START_TIME + 32 * DAY,
+                ..tier_args()
+            },
+            ErrorCode::InvalidDuration,
+        ),
+    ];
+    for (args, error) in cases {
+        assert_error(configure_tier(&mut h, &p, args).await, error);
+    }
+
+    configure_tier(&mut h, &p, tier_args()).await.unwrap();
+    let config: SaleConfiguration = h.fetch(&pda::sale_config(&p.project).0).await;
+    assert_eq!(config.project_id, 1);
+    assert_eq!(config.payment_mint, p.payment_mint);
+    assert!(config.is_active);
+}
+
+#[tokio::test]
+async fn create_sale_round_requires_vault() {
+    let mut h = Harness::new().await;
+    let p = platform(&mut h).await;
+    approve(&mut h, &p, &p.admin).await.unwrap();
+
+    let cases = [
+        (
+            args::CreateSaleRound {
+                token_price: 0,
+                ..round_args(1)
+            },
+            ErrorCode::InvalidPrice,
+        ),
+        (
+            args::CreateSaleRound {
+                total_tokens: 0,
+                ..round_args(1)
+            },
+            ErrorCode::InvalidTokenAmount,
+        ),
+        (
+            args::CreateSaleRound {
+                end_time: START_TIME + DAY,
+                ..round_args(1)
+            },
+            ErrorCode::InvalidEndTime,
+        ),
+        (
+            args::CreateSaleRound {
+                start_time: START_TIME,
+                ..round_args(1)
+            },
+            ErrorCode::InvalidStartTime,
+        ),
+        (round_args(1), ErrorCode::ProjectVaultNotInitialized),
+    ];
+    for (args, error) in cases {
+        assert_error(create_round(&mut h, &p, args).await, error);
+    }
+
+    init_vault(&mut h, &p).await.unwrap();
+    create_round(&mut h, &p, round_args(1)).await.unwrap();
+    create_round(&mut h, &p, round_args(2)).await.unwrap();
+
+    let round: SaleRound = h.fetch(&pda::sale_round(&p.project, 2).0).await;
+    assert_eq!(round.round_number, 2);
+    assert_eq!(round.project_id, 1);
+    assert!(round.is_active() && round.whitelist_required());
+}
+
+#[tokio::test]
+async fn platform_treasury_caps_fee() {
+    let mut h = Harness::new().await;
+    let authority = h.wallet().await;
+    let payment_mint = h.create_mint(6).await;
+    let init = |fee_percentage| {
+        instructions::initialize_platform_treasury(
+            &authority.pubkey(),
+            &payment_mint,
+            args::InitializePlatformTreasury { fee_percentage },
+        )
+    };
+
+    assert_error(
+        h.process(&[init(1_001)], &[&authority]).await,
+        ErrorCode::InvalidPlatformFee,
+    );
+    h.process(&[init(300)], &[&authority]).await.unwrap();
+
+    let treasury_address = pda::platform_treasury().0;
+    let treasury: PlatformTreasury = h.fetch(&treasury_address).await;
+    assert_eq!(treasury.fee_percentage, 300);
+    assert_eq!(
+        treasury.treasury_vault,
+        pda::treasury_vault(&treasury_address).0
+    );
+    assert_eq!(h.token_balance(&treasury.treasury_vault).await, 0);
+}
+
+#[tokio::test]
+async fn active_project_completes_after_a_day() {
+    let mut h = Harness::new().await;
+    let p = platform(&mut h).await;
+
+    let stranger = h.wallet().await;
+    assert_error(
+        h.process(
+            &[instructions::advance_project_status(
+                &stranger.pubkey(),
+                &p.project,
+            )],
+            &[&stranger],
+        )
+        .await,
+        ErrorCode::UnauthorizedAccess,
+    );
+
+    // Draft and submitted projects never advance on their own
+    advance(&mut h, &p).await.unwrap();
+    update_project(&mut h, &p, |project| {
+        project.status = ProjectStatus::Submitted
+    })
+    .await;
+    advance(&mut h, &p).await.unwrap();
+    assert!(h.fetch::<ProjectAccount>(&p.project).await.status == ProjectStatus::Submitted);
+
+    update_project(&mut h, &p, |project| project.status = ProjectStatus::Draft).await;
+    approve(&mut h, &p, &p.admin).await.unwrap();
+    h.warp_to(START_TIME + DAY).await;
+    advance(&mut h, &p).await.unwrap();
+    assert!(h.fetch::<ProjectAccount>(&p.project).await.status == ProjectStatus::Active);
+
+    h.warp_to(START_TIME + DAY + 1).await;
+    advance(&mut h, &p).await.unwrap();
+    let project: ProjectAccount = h.fetch(&p.project).await;
+    assert!(project.status == ProjectStatus::Completed);
+    assert_eq!(project.updated_at, START_TIME + DAY + 1);
+}
+
+#[tokio::test]
+async fn whitelist_is_created_without_capacity() {
+    let mut h = Harness::new().await;
+    let p = platform(&mut h).await;
+    let add = |sale_round, addresses| {
+        instructions::add_to_whitelist(
+            &p.creator.pubkey(),
+            &p.project,
+            args::AddToWhitelist {
+                sale_round,
+                addresses,
+            },
+        )
+    };
+
+    // The whitelist is created by its first batch with `max_entries` left at zero
+    assert_error(
+        h.process(&[add(1, vec![Pubkey::new_unique()])], &[&p.creator])
+            .await,
+        ErrorCode::WhitelistFull,
+    );
+    assert_error(
+        h.process(&[add(1, vec![Pubkey::new_unique(); 101])], &[&p.creator])
+            .await,
+        ErrorCode::TooManyAddresses,
+    );
+
+    h.process(&[add(2, vec![])], &[&p.creator]).await.unwrap();
+    let whitelist: ProjectWhitelist = h.fetch(&pda::whitelist(&p.project, 2).0).await;
+    assert_eq!(whitelist.max_entries, 0);
+    assert_eq!(whitelist.current_entries, 0);
+}
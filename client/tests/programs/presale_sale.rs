@@ -0,0 +1,717 @@
+//! Multi-presale standalone token sales.
+
+use anchor_lang::error::ErrorCode as AnchorError;
+use anchor_lang::Discriminator;
+use multi_presale::ErrorCode;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use token_sale_client::multi_presale::{
+    args, instructions, legacy, pda, BuyerAccount, DenyList, PurchaseQuote, TokenSale,
+    ZeroCopyVersioned, PROGRAM_ID,
+};
+
+use crate::harness::{assert_error, Harness, TxResult, START_TIME};
+
+const PRICE: u64 = 1_000;
+const TOTAL_TOKENS: u64 = 1_000;
+const SALE_START: i64 = START_TIME + 100;
+const SALE_END: i64 = START_TIME + 1_000;
+
+struct Sale {
+    seller: Keypair,
+    token_mint: Pubkey,
+    payment_mint: Pubkey,
+    seller_token_account: Pubkey,
+    seller_payment_account: Pubkey,
+    fee_recipient: Pubkey,
+    fee_account: Pubkey,
+    address: Pubkey,
+}
+
+struct Buyer {
+    wallet: Keypair,
+    payment_account: Pubkey,
+    token_account: Pubkey,
+}
+
+fn init_args(fee_recipient: Pubkey) -> args::InitializeSale {
+    args::InitializeSale {
+        price_per_token: PRICE,
+        total_tokens: TOTAL_TOKENS,
+        sale_start_time: SALE_START,
+        sale_end_time: SALE_END,
+        max_tokens_per_buyer: 0,
+        platform_fee_bps: 100,
+        platform_fee_recipient: fee_recipient,
+    }
+}
+
+async fn prepare_sale(h: &mut Harness) -> Sale {
+    let seller = h.wallet().await;
+    let fee_recipient = Pubkey::new_unique();
+    let token_mint = h.create_mint(0).await;
+    let payment_mint = h.create_mint(6).await;
+
+    Sale {
+        seller_token_account: h
+            .funded_token_account(&seller.pubkey(), &token_mint, TOTAL_TOKENS)
+            .await,
+        seller_payment_account: h
+            .create_token_account(&seller.pubkey(), &payment_mint)
+            .await,
+        fee_account: h.create_token_account(&fee_recipient, &payment_mint).await,
+        address: pda::token_sale(&seller.pubkey(), &token_mint).0,
+        seller,
+        token_mint,
+        payment_mint,
+        fee_recipient,
+    }
+}
+
+async fn init_sale_with(h: &mut Harness, sale: &Sale, args: args::InitializeSale) -> TxResult {
+    h.process(
+        &[instructions::initialize_sale(
+            &sale.seller.pubkey(),
+            &sale.token_mint,
+            &sale.payment_mint,
+            &sale.seller_token_account,
+            args,
+        )],
+        &[&sale.seller],
+    )
+    .await
+}
+
+async fn create_buyer(h: &mut Harness, sale: &Sale) -> Buyer {
+    let wallet = h.wallet().await;
+    let payment_account = h
+        .funded_token_account(&wallet.pubkey(), &sale.payment_mint, 10_000_000)
+        .await;
+    let token_account = h
+        .create_token_account(&wallet.pubkey(), &sale.token_mint)
+        .await;
+    h.process(
+        &[instructions::create_buyer_account(
+            &wallet.pubkey(),
+            &wallet.pubkey(),
+            &sale.address,
+        )],
+        &[&wallet],
+    )
+    .await
+    .unwrap();
+
+    Buyer {
+        wallet,
+        payment_account,
+        token_account,
+    }
+}
+
+async fn buy(h: &mut Harness, sale: &Sale, buyer: &Buyer, token_amount: u64) -> TxResult {
+    h.process(
+        &[instructions::buy_tokens(
+            &buyer.wallet.pubkey(),
+            &sale.address,
+            &buyer.payment_account,
+            &sale.seller_payment_account,
+            &sale.fee_account,
+            &buyer.token_account,
+            args::BuyTokens { token_amount },
+        )],
+        &[&buyer.wallet],
+    )
+    .await
+}
+
+async fn update(h: &mut Harness, sale: &Sale, args: args::UpdateSaleParams) -> TxResult {
+    h.process(
+        &[instructions::update_sale_params(
+            &sale.seller.pubkey(),
+            &sale.address,
+            args,
+        )],
+        &[&sale.seller],
+    )
+    .await
+}
+
+fn no_update() -> args::UpdateSaleParams {
+    args::UpdateSaleParams {
+        new_price_per_token: None,
+        new_sale_start_time: None,
+        new_sale_end_time: None,
+        new_max_tokens_per_buyer: None,
+    }
+}
+
+#[tokio::test]
+async fn initialize_sale_validates_parameters() {
+    let mut h = Harness::new().await;
+    let sale = prepare_sale(&mut h).await;
+    let recipient = sale.fee_recipient;
+
+    let cases = [
+        (
+            args::InitializeSale {
+                price_per_token: 0,
+                ..init_args(recipient)
+            },
+            ErrorCode::InvalidPrice,
+        ),
+        (
+            args::InitializeSale {
+                total_tokens: 0,
+                ..init_args(recipient)
+            },
+            ErrorCode::InvalidTokenAmount,
+        ),
+        (
+            args::InitializeSale {
+                sale_start_time: 0,
+                ..init_args(recipient)
+            },
+            ErrorCode::InvalidStartTime,
+        ),
+        (
+            args::InitializeSale {
+                sale_end_time: SALE_START,
+                ..init_args(recipient)
+            },
+            ErrorCode::InvalidEndTime,
+        ),
+        (
+            args::InitializeSale {
+                platform_fee_bps: 10_001,
+                ..init_args(recipient)
+            },
+            ErrorCode::InvalidPlatformFee,
+        ),
+        (
+            args::InitializeSale {
+                sale_start_time: START_TIME - 200,
+                sale_end_time: START_TIME - 100,
+                ..init_args(recipient)
+            },
+            ErrorCode::SaleEndTimeInPast,
+        ),
+    ];
+    for (args, error) in cases {
+        assert_error(init_sale_with(&mut h, &sale, args).await, error);
+    }
+
+    init_sale_with(&mut h, &sale, init_args(recipient))
+        .await
+        .unwrap();
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(state.tokens_available, TOTAL_TOKENS);
+    assert_eq!(
+        h.token_balance(&pda::token_vault(&sale.address).0).await,
+        TOTAL_TOKENS
+    );
+}
+
+#[tokio::test]
+async fn buy_tokens_enforces_window_and_limits() {
+    let mut h = Harness::new().await;
+    let sale = prepare_sale(&mut h).await;
+    init_sale_with(
+        &mut h,
+        &sale,
+        args::InitializeSale {
+            max_tokens_per_buyer: 200,
+            ..init_args(sale.fee_recipient)
+        },
+    )
+    .await
+    .unwrap();
+    let buyer = create_buyer(&mut h, &sale).await;
+
+    assert_error(
+        buy(&mut h, &sale, &buyer, 1).await,
+        ErrorCode::SaleNotStarted,
+    );
+    h.warp_to(SALE_START).await;
+
+    assert_error(
+        buy(&mut h, &sale, &buyer, 0).await,
+        ErrorCode::InvalidTokenAmount,
+    );
+    assert_error(
+        buy(&mut h, &sale, &buyer, TOTAL_TOKENS + 1).await,
+        ErrorCode::InsufficientTokens,
+    );
+    buy(&mut h, &sale, &buyer, 150).await.unwrap();
+    assert_error(
+        buy(&mut h, &sale, &buyer, 51).await,
+        ErrorCode::ExceedsPurchaseLimit,
+    );
+
+    // 150 tokens at 1_000 each, 1% of which goes to the platform
+    assert_eq!(h.token_balance(&buyer.token_account).await, 150);
+    assert_eq!(h.token_balance(&sale.seller_payment_account).await, 148_500);
+    assert_eq!(h.token_balance(&sale.fee_account).await, 1_500);
+    let tracker: BuyerAccount = h
+        .fetch(&pda::buyer(&buyer.wallet.pubkey(), &sale.address).0)
+        .await;
+    assert_eq!(tracker.tokens_purchased, 150);
+
+    h.warp_to(SALE_END + 1).await;
+    assert_error(buy(&mut h, &sale, &buyer, 1).await, ErrorCode::SaleEnded);
+}
+
+#[tokio::test]
+async fn buy_tokens_reports_cost_overflow() {
+    let mut h = Harness::new().await;
+    let sale = prepare_sale(&mut h).await;
+    init_sale_with(
+        &mut h,
+        &sale,
+        args::InitializeSale {
+            price_per_token: u64::MAX,
+            ..init_args(sale.fee_recipient)
+        },
+    )
+    .await
+    .unwrap();
+    let buyer = create_buyer(&mut h, &sale).await;
+    h.warp_to(SALE_START).await;
+
+    assert_error(buy(&mut h, &sale, &buyer, 2).await, ErrorCode::MathOverflow);
+}
+
+#[tokio::test]
+async fn pause_and_cancel_stop_purchases() {
+    let mut h = Harness::new().await;
+    let sale = prepare_sale(&mut h).await;
+    init_sale_with(&mut h, &sale, init_args(sale.fee_recipient))
+        .await
+        .unwrap();
+    let buyer = create_buyer(&mut h, &sale).await;
+    h.warp_to(SALE_START).await;
+    let pause = instructions::toggle_pause(&sale.seller.pubkey(), &sale.address);
+
+    h.process(std::slice::from_ref(&pause), &[&sale.seller])
+        .await
+        .unwrap();
+    assert_error(buy(&mut h, &sale, &buyer, 1).await, ErrorCode::SalePaused);
+    h.process(std::slice::from_ref(&pause), &[&sale.seller])
+        .await
+        .unwrap();
+    buy(&mut h, &sale, &buyer, 400).await.unwrap();
+
+    h.process(
+        &[instructions::cancel_sale(
+            &sale.seller.pubkey(),
+            &sale.address,
+            &sale.seller_token_account,
+        )],
+        &[&sale.seller],
+    )
+    .await
+    .unwrap();
+    assert_eq!(h.token_balance(&sale.seller_token_account).await, 600);
+    assert!(!h.fetch::<TokenSale>(&sale.address).await.is_active());
+
+    assert_error(
+        buy(&mut h, &sale, &buyer, 1).await,
+        ErrorCode::SaleNotActive,
+    );
+    assert_error(
+        h.process(&[pause], &[&sale.seller]).await,
+        ErrorCode::SaleNotActive,
+    );
+}
+
+#[tokio::test]
+async fn update_sale_params_only_before_start() {
+    let mut h = Harness::new().await;
+    let sale = prepare_sale(&mut h).await;
+    init_sale_with(&mut h, &sale, init_args(sale.fee_recipient))
+        .await
+        .unwrap();
+
+    update(
+        &mut h,
+        &sale,
+        args::UpdateSaleParams {
+            new_price_per_token: Some(3 * PRICE),
+            new_sale_start_time: Some(SALE_START + 50),
+            new_sale_end_time: Some(SALE_END + 50),
+            new_max_tokens_per_buyer: Some(10),
+        },
+    )
+    .await
+    .unwrap();
+    let state: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(state.price_per_token, 3 * PRICE);
+    assert_eq!(state.sale_start_time, SALE_START + 50);
+    assert_eq!(state.sale_end_time, SALE_END + 50);
+    assert_eq!(state.max_tokens_per_buyer, 10);
+
+    let cases = [
+        (
+            args::UpdateSaleParams {
+                new_price_per_token: Some(0),
+                ..no_update()
+            },
+            ErrorCode::InvalidPrice,
+        ),
+        (
+            args::UpdateSaleParams {
+                new_sale_start_time: Some(START_TIME),
+                ..no_update()
+            },
+            ErrorCode::InvalidStartTime,
+        ),
+        (
+            args::UpdateSaleParams {
+                new_sale_end_time: Some(SALE_START),
+                ..no_update()
+            },
+            ErrorCode::InvalidEndTime,
+        ),
+    ];
+    for (args, error) in cases {
+        assert_error(update(&mut h, &sale, args).await, error);
+    }
+
+    h.warp_to(SALE_START + 50).await;
+    assert_error(
+        update(&mut h, &sale, no_update()).await,
+        ErrorCode::SaleAlreadyStarted,
+    );
+}
+
+#[tokio::test]
+async fn quotes_match_purchases_and_limits() {
+    let mut h = Harness::new().await;
+    let sale = prepare_sale(&mut h).await;
+    init_sale_with(
+        &mut h,
+        &sale,
+        args::InitializeSale {
+            max_tokens_per_buyer: 200,
+            ..init_args(sale.fee_recipient)
+        },
+    )
+    .await
+    .unwrap();
+    let buyer = create_buyer(&mut h, &sale).await;
+    let wallet = buyer.wallet.pubkey();
+
+    let quoted: PurchaseQuote = h
+        .simulate(
+            &PROGRAM_ID,
+            &[instructions::quote_purchase(
+                &sale.address,
+                Some(&wallet),
+                args::QuotePurchase { token_amount: 150 },
+            )],
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        quoted,
+        PurchaseQuote {
+            token_amount: 150,
+            gross_cost: 150_000,
+            platform_fee: 1_500,
+            seller_net: 148_500,
+            remaining_allowance: 50,
+        }
+    );
+
+    h.warp_to(SALE_START).await;
+    buy(&mut h, &sale, &buyer, 150).await.unwrap();
+    assert_eq!(
+        h.token_balance(&sale.seller_payment_account).await,
+        quoted.seller_net
+    );
+
+    // The rest of the buyer's allowance, however large the payment
+    let quoted: PurchaseQuote = h
+        .simulate(
+            &PROGRAM_ID,
+            &[instructions::quote_exact_in(
+                &sale.address,
+                Some(&wallet),
+                args::QuoteExactIn { payment: 1_000_000 },
+            )],
+        )
+        .await
+        .unwrap();
+    assert_eq!(quoted.token_amount, 50);
+    assert_eq!(quoted.remaining_allowance, 0);
+}
+
+#[tokio::test]
+async fn legacy_sale_and_tracker_are_converted_to_zero_copy() {
+    let mut h = Harness::new().await;
+    let sale = prepare_sale(&mut h).await;
+    init_sale_with(&mut h, &sale, init_args(sale.fee_recipient))
+        .await
+        .unwrap();
+    let buyer = create_buyer(&mut h, &sale).await;
+    let tracker = pda::buyer(&buyer.wallet.pubkey(), &sale.address).0;
+    h.warp_to(SALE_START).await;
+    buy(&mut h, &sale, &buyer, 10).await.unwrap();
+
+    let state: TokenSale = h.fetch(&sale.address).await;
+    let legacy_sale = legacy::TokenSale {
+        seller: state.seller,
+        token_mint: state.token_mint,
+        payment_mint: state.payment_mint,
+        price_per_token: state.price_per_token,
+        total_tokens: state.total_tokens,
+        tokens_available: state.tokens_available,
+        sale_start_time: state.sale_start_time,
+        sale_end_time: state.sale_end_time,
+        max_tokens_per_buyer: 20,
+        platform_fee_bps: state.platform_fee_bps,
+        platform_fee_recipient: state.platform_fee_recipient,
+        is_active: true,
+        is_paused: false,
+        bump: state.bump,
+        version: 1,
+    };
+    h.store_legacy(&sale.address, &legacy_sale, TokenSale::LEGACY_SPACE)
+        .await;
+    let tracker_state: BuyerAccount = h.fetch(&tracker).await;
+    let legacy_tracker = legacy::BuyerAccount {
+        buyer: tracker_state.buyer,
+        token_sale: tracker_state.token_sale,
+        tokens_purchased: tracker_state.tokens_purchased,
+        bump: tracker_state.bump,
+        version: 0,
+    };
+    h.store_legacy(&tracker, &legacy_tracker, BuyerAccount::LEGACY_SPACE - 1)
+        .await;
+
+    let payer = h.payer().pubkey();
+    h.process(
+        &[
+            instructions::migrate_token_sale(&payer, &sale.address),
+            instructions::migrate_buyer_account(&payer, &tracker),
+        ],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        h.account(&sale.address).await.unwrap().data.len(),
+        TokenSale::SPACE
+    );
+    let migrated: TokenSale = h.fetch(&sale.address).await;
+    assert_eq!(migrated.version, TokenSale::VERSION);
+    assert_eq!(migrated.max_tokens_per_buyer, 20);
+    assert_eq!(migrated.tokens_available, TOTAL_TOKENS - 10);
+    assert!(migrated.is_active() && !migrated.is_paused());
+    let migrated: BuyerAccount = h.fetch(&tracker).await;
+    assert_eq!(migrated.version, BuyerAccount::VERSION);
+    assert_eq!(migrated.tokens_purchased, 10);
+    assert_eq!(migrated.rent_recipient(), buyer.wallet.pubkey());
+
+    // The carried-over per-buyer cap still applies to the converted tracker
+    assert_error(
+        buy(&mut h, &sale, &buyer, 11).await,
+        ErrorCode::ExceedsPurchaseLimit,
+    );
+    buy(&mut h, &sale, &buyer, 10).await.unwrap();
+}
+
+#[tokio::test]
+async fn zero_copy_tracker_grows_to_the_current_version() {
+    let mut h = Harness::new().await;
+    let sale = prepare_sale(&mut h).await;
+    init_sale_with(&mut h, &sale, init_args(sale.fee_recipient))
+        .await
+        .unwrap();
+    let buyer = create_buyer(&mut h, &sale).await;
+    let tracker = pda::buyer(&buyer.wallet.pubkey(), &sale.address).0;
+    h.warp_to(SALE_START).await;
+    buy(&mut h, &sale, &buyer, 10).await.unwrap();
+
+    // Version 2 ended before the rent payer
+    let mut state: BuyerAccount = h.fetch(&tracker).await;
+    state.version = 2;
+    let mut data = [BuyerAccount::DISCRIMINATOR, bytemuck::bytes_of(&state)].concat();
+    data.truncate(8 + std::mem::offset_of!(BuyerAccount, rent_payer));
+    h.store_bytes(&tracker, data).await;
+    assert!(buy(&mut h, &sale, &buyer, 10).await.is_err());
+
+    let payer = h.payer().pubkey();
+    let migrate = || instructions::migrate_buyer_account(&payer, &tracker);
+    h.process(&[migrate()], &[]).await.unwrap();
+    assert_eq!(
+        h.account(&tracker).await.unwrap().data.len(),
+        BuyerAccount::SPACE
+    );
+    let migrated: BuyerAccount = h.fetch(&tracker).await;
+    assert_eq!(migrated.version, BuyerAccount::VERSION);
+    assert_eq!(migrated.tokens_purchased, 10);
+    // Older trackers were paid for by the buyer, who gets the rent back on close
+    assert_eq!(migrated.rent_payer, Pubkey::default());
+    assert_eq!(migrated.rent_recipient(), buyer.wallet.pubkey());
+    buy(&mut h, &sale, &buyer, 10).await.unwrap();
+
+    h.next_slot().await;
+    assert_error(
+        h.process(&[migrate()], &[]).await,
+        ErrorCode::AccountAlreadyMigrated,
+    );
+}
+
+#[tokio::test]
+async fn closed_tracker_returns_its_rent_to_the_sponsor() {
+    let mut h = Harness::new().await;
+    let sale = prepare_sale(&mut h).await;
+    init_sale_with(&mut h, &sale, init_args(sale.fee_recipient))
+        .await
+        .unwrap();
+    let sponsor = h.wallet().await;
+    // The buyer holds no SOL of its own
+    let buyer = Keypair::new();
+    let tracker = pda::buyer(&buyer.pubkey(), &sale.address).0;
+    h.process(
+        &[instructions::create_buyer_account(
+            &buyer.pubkey(),
+            &sponsor.pubkey(),
+            &sale.address,
+        )],
+        &[&buyer, &sponsor],
+    )
+    .await
+    .unwrap();
+    let state: BuyerAccount = h.fetch(&tracker).await;
+    assert_eq!(state.rent_recipient(), sponsor.pubkey());
+    let rent = h.account(&tracker).await.unwrap().lamports;
+    let sponsor_lamports = h.account(&sponsor.pubkey()).await.unwrap().lamports;
+
+    let close = |rent_payer: &Pubkey| {
+        instructions::close_buyer_account(&buyer.pubkey(), rent_payer, &sale.address)
+    };
+    assert_error(
+        h.process(&[close(&sponsor.pubkey())], &[&buyer]).await,
+        ErrorCode::SaleNotEnded,
+    );
+    h.warp_to(SALE_END + 1).await;
+    assert_error(
+        h.process(&[close(&buyer.pubkey())], &[&buyer]).await,
+        AnchorError::ConstraintRaw,
+    );
+    h.process(&[close(&sponsor.pubkey())], &[&buyer])
+        .await
+        .unwrap();
+
+    assert!(h.account(&tracker).await.is_none());
+    assert_eq!(
+        h.account(&sponsor.pubkey()).await.unwrap().lamports,
+        sponsor_lamports + rent
+    );
+}
+
+#[tokio::test]
+async fn deny_list_screens_purchases() {
+    let mut h = Harness::new().await;
+    let admin = h.wallet().await;
+    let compliance = h.wallet().await;
+    h.process(
+        &[instructions::initialize_platform(
+            &admin.pubkey(),
+            &Pubkey::new_unique(),
+            args::InitializePlatform {
+                platform_fee: 250,
+                min_project_duration: 86_400,
+                max_project_duration: 30 * 86_400,
+            },
+        )],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+
+    // Only the platform admin sets up the list
+    let create = |admin: &Keypair, required| {
+        instructions::create_deny_list(
+            &admin.pubkey(),
+            args::CreateDenyList {
+                authority: compliance.pubkey(),
+                required,
+            },
+        )
+    };
+    assert_error(
+        h.process(&[create(&compliance, false)], &[&compliance])
+            .await,
+        ErrorCode::UnauthorizedAccess,
+    );
+    h.process(&[create(&admin, false)], &[&admin])
+        .await
+        .unwrap();
+
+    let sale = prepare_sale(&mut h).await;
+    init_sale_with(&mut h, &sale, init_args(sale.fee_recipient))
+        .await
+        .unwrap();
+    h.warp_to(SALE_START).await;
+    let buyer = create_buyer(&mut h, &sale).await;
+    h.process(
+        &[instructions::deny_wallet(
+            &compliance.pubkey(),
+            args::DenyWallet {
+                wallet: buyer.wallet.pubkey(),
+            },
+        )],
+        &[&compliance],
+    )
+    .await
+    .unwrap();
+    let deny_list: DenyList = h.fetch(&pda::deny_list().0).await;
+    assert_eq!(deny_list.denied_count, 1);
+
+    let screen = |enabled| {
+        instructions::set_wallet_screening(
+            &sale.seller.pubkey(),
+            &sale.address,
+            args::SetWalletScreening { enabled },
+        )
+    };
+    buy(&mut h, &sale, &buyer, 10).await.unwrap();
+    h.process(&[screen(true)], &[&sale.seller]).await.unwrap();
+    h.next_slot().await;
+    assert_error(
+        buy(&mut h, &sale, &buyer, 10).await,
+        ErrorCode::WalletDenied,
+    );
+
+    // A required list can't be switched off by the seller
+    h.process(
+        &[instructions::update_deny_list(
+            &admin.pubkey(),
+            args::UpdateDenyList {
+                authority: compliance.pubkey(),
+                required: true,
+            },
+        )],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+    assert_error(
+        h.process(&[screen(false)], &[&sale.seller]).await,
+        ErrorCode::DenyListRequired,
+    );
+
+    h.process(
+        &[instructions::allow_wallet(
+            &compliance.pubkey(),
+            &buyer.wallet.pubkey(),
+        )],
+        &[&compliance],
+    )
+    .await
+    .unwrap();
+    buy(&mut h, &sale, &buyer, 10).await.unwrap();
+}
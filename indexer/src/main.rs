@@ -0,0 +1,107 @@
+//! `token-sale-indexer`: materialise escrow and multi-presale state into SQLite.
+//!
+//! Events from both programs are decoded from transaction logs, read either from a JSON
+//! lines file or replayed from a validator, and folded into `sales`, `purchases`,
+//! `buyers`, `fees`, `projects`, `sale_rounds` and `platform` tables. Transactions are
+//! keyed by signature, so re-running over the same input is safe, and dropped forks are
+//! undone with `rollback` (file input) or automatically on `sync`.
+
+mod source;
+mod store;
+
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+
+use source::Report;
+use store::Store;
+
+#[derive(Parser)]
+#[command(name = "token-sale-indexer", version, about)]
+struct Cli {
+    /// SQLite database, created if missing
+    #[arg(
+        short,
+        long,
+        global = true,
+        env = "TOKEN_SALE_INDEX_DB",
+        default_value = "token-sale.db"
+    )]
+    db: PathBuf,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Index transactions from a JSON lines file (`-` for standard input)
+    Ingest { file: PathBuf },
+    /// Replay both programs' new transactions from a validator
+    Sync {
+        /// RPC endpoint, or one of `localhost`, `devnet`, `mainnet-beta`
+        #[arg(
+            short = 'u',
+            long,
+            env = "TOKEN_SALE_RPC_URL",
+            default_value = "localhost"
+        )]
+        url: String,
+        /// Commitment of the transactions to index
+        #[arg(long, default_value = "confirmed")]
+        commitment: CommitmentLevel,
+    },
+    /// Forget every transaction at or after a slot, e.g. after a fork was abandoned
+    Rollback { slot: u64 },
+    /// Recompute the derived tables from the stored events
+    Rebuild,
+}
+
+fn resolve_url(url: &str) -> String {
+    match url {
+        "localhost" | "l" => "http://127.0.0.1:8899",
+        "devnet" | "d" => "https://api.devnet.solana.com",
+        "mainnet-beta" | "m" => "https://api.mainnet-beta.solana.com",
+        url => url,
+    }
+    .to_string()
+}
+
+fn print_report(report: &Report) {
+    println!(
+        "Indexed {} transactions ({} events); skipped {} already indexed and {} failed",
+        report.indexed, report.events, report.duplicates, report.failed
+    );
+    if report.dropped > 0 {
+        println!(
+            "Dropped {} transactions from abandoned forks",
+            report.dropped
+        );
+    }
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let mut store = Store::open(&cli.db)?;
+
+    match cli.command {
+        Command::Ingest { file } => print_report(&source::ingest_file(&mut store, &file)?),
+        Command::Sync { url, commitment } => {
+            let rpc = RpcClient::new(resolve_url(&url));
+            let commitment = CommitmentConfig { commitment };
+            print_report(&source::sync(&mut store, &rpc, commitment)?);
+        }
+        Command::Rollback { slot } => {
+            let dropped = store.rollback(slot)?;
+            println!("Dropped {dropped} transactions at or after slot {slot}");
+        }
+        Command::Rebuild => {
+            store.rebuild()?;
+            println!("Rebuilt the derived tables");
+        }
+    }
+    Ok(())
+}
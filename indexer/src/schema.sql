@@ -0,0 +1,126 @@
+-- Ingestion log: every indexed transaction and the raw events it emitted.
+-- The tables below it are derived from these two and can be rebuilt at any time.
+
+CREATE TABLE IF NOT EXISTS transactions (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    signature TEXT NOT NULL UNIQUE,
+    slot INTEGER NOT NULL,
+    block_time INTEGER
+);
+CREATE INDEX IF NOT EXISTS transactions_slot ON transactions (slot);
+
+CREATE TABLE IF NOT EXISTS events (
+    signature TEXT NOT NULL REFERENCES transactions (signature) ON DELETE CASCADE,
+    idx INTEGER NOT NULL,
+    program TEXT NOT NULL,
+    data BLOB NOT NULL,
+    PRIMARY KEY (signature, idx)
+);
+
+-- Newest signature fetched per program during validator replay
+CREATE TABLE IF NOT EXISTS cursors (
+    program TEXT PRIMARY KEY,
+    signature TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS platform (
+    authority TEXT PRIMARY KEY,
+    treasury TEXT NOT NULL,
+    platform_fee_bps INTEGER NOT NULL,
+    signature TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS projects (
+    project_id INTEGER PRIMARY KEY,
+    creator TEXT NOT NULL,
+    name TEXT NOT NULL,
+    token_mint TEXT NOT NULL,
+    category TEXT NOT NULL,
+    status TEXT NOT NULL,
+    approved_by TEXT,
+    approved_at INTEGER,
+    status_changed_at INTEGER,
+    sale_type TEXT,
+    token_price INTEGER,
+    total_tokens INTEGER,
+    sale_start_time INTEGER,
+    sale_end_time INTEGER,
+    signature TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS sale_rounds (
+    project_id INTEGER NOT NULL,
+    round_number INTEGER NOT NULL,
+    sale_type TEXT NOT NULL,
+    token_price INTEGER NOT NULL,
+    total_tokens INTEGER NOT NULL,
+    signature TEXT NOT NULL,
+    PRIMARY KEY (project_id, round_number)
+);
+
+CREATE TABLE IF NOT EXISTS sales (
+    address TEXT PRIMARY KEY,
+    program TEXT NOT NULL,
+    seller TEXT NOT NULL,
+    token_mint TEXT NOT NULL,
+    payment_mint TEXT NOT NULL,
+    price_per_token INTEGER NOT NULL,
+    total_tokens INTEGER NOT NULL,
+    tokens_sold INTEGER NOT NULL DEFAULT 0,
+    proceeds INTEGER NOT NULL DEFAULT 0,
+    sale_start_time INTEGER NOT NULL,
+    sale_end_time INTEGER NOT NULL,
+    platform_fee_bps INTEGER NOT NULL,
+    platform_fee_recipient TEXT NOT NULL,
+    cancelled INTEGER NOT NULL DEFAULT 0,
+    tokens_returned INTEGER,
+    signature TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS sales_seller ON sales (seller);
+CREATE INDEX IF NOT EXISTS sales_token_mint ON sales (token_mint);
+
+-- Price changes announced by live escrow sales, effective from effective_time
+CREATE TABLE IF NOT EXISTS price_changes (
+    signature TEXT NOT NULL,
+    idx INTEGER NOT NULL,
+    sale TEXT NOT NULL,
+    current_price INTEGER NOT NULL,
+    new_price INTEGER NOT NULL,
+    effective_time INTEGER NOT NULL,
+    PRIMARY KEY (signature, idx)
+);
+CREATE INDEX IF NOT EXISTS price_changes_sale ON price_changes (sale);
+
+CREATE TABLE IF NOT EXISTS purchases (
+    signature TEXT NOT NULL,
+    idx INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    sale TEXT NOT NULL,
+    buyer TEXT NOT NULL,
+    token_amount INTEGER NOT NULL,
+    seller_payment INTEGER NOT NULL,
+    platform_fee INTEGER NOT NULL,
+    timestamp INTEGER NOT NULL,
+    PRIMARY KEY (signature, idx)
+);
+CREATE INDEX IF NOT EXISTS purchases_sale ON purchases (sale);
+CREATE INDEX IF NOT EXISTS purchases_buyer ON purchases (buyer);
+
+CREATE TABLE IF NOT EXISTS buyers (
+    sale TEXT NOT NULL,
+    buyer TEXT NOT NULL,
+    tokens_purchased INTEGER NOT NULL,
+    amount_paid INTEGER NOT NULL,
+    purchases INTEGER NOT NULL,
+    first_purchase_at INTEGER NOT NULL,
+    last_purchase_at INTEGER NOT NULL,
+    PRIMARY KEY (sale, buyer)
+);
+
+CREATE TABLE IF NOT EXISTS fees (
+    recipient TEXT NOT NULL,
+    sale TEXT NOT NULL,
+    amount INTEGER NOT NULL,
+    purchases INTEGER NOT NULL,
+    PRIMARY KEY (recipient, sale)
+);
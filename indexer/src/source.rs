@@ -0,0 +1,241 @@
+//! Transaction sources: a JSON lines file, or a replay of both programs' history over RPC.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status_client_types::{
+    option_serializer::OptionSerializer, UiTransactionEncoding,
+};
+use token_sale_client::{escrow, multi_presale};
+
+use crate::store::{Ingested, Store};
+
+/// Most signatures `getSignaturesForAddress` returns per page
+const SIGNATURE_PAGE: usize = 1_000;
+/// Most signatures `getSignatureStatuses` accepts per call
+const STATUS_BATCH: usize = 256;
+
+/// One transaction's logs, as read from a file or fetched over RPC.
+///
+/// File input is one JSON object per line, e.g.
+/// `{"signature": "5h6x…", "slot": 1234, "blockTime": 1700000000, "logs": ["Program …"], "err": null}`.
+/// `blockTime` and `err` are optional; `logMessages` is accepted for `logs`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionLogs {
+    pub signature: String,
+    pub slot: u64,
+    #[serde(default)]
+    pub block_time: Option<i64>,
+    #[serde(alias = "logMessages")]
+    pub logs: Vec<String>,
+    /// Set when the transaction failed on-chain
+    #[serde(default)]
+    pub err: Option<serde_json::Value>,
+}
+
+#[derive(Default)]
+pub struct Report {
+    pub indexed: usize,
+    pub events: usize,
+    pub duplicates: usize,
+    pub failed: usize,
+    pub dropped: usize,
+}
+
+impl Report {
+    fn record(&mut self, outcome: Ingested) {
+        match outcome {
+            Ingested::Indexed(events) => {
+                self.indexed += 1;
+                self.events += events;
+            }
+            Ingested::Duplicate => self.duplicates += 1,
+            Ingested::Failed => self.failed += 1,
+        }
+    }
+}
+
+/// Ingest a JSON lines file, or standard input for `-`
+pub fn ingest_file(store: &mut Store, path: &Path) -> Result<Report> {
+    let reader: Box<dyn BufRead> = if path == Path::new("-") {
+        Box::new(io::stdin().lock())
+    } else {
+        let file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        Box::new(BufReader::new(file))
+    };
+
+    let mut report = Report::default();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let transaction: TransactionLogs = serde_json::from_str(&line)
+            .with_context(|| format!("{}:{}: invalid transaction", path.display(), number + 1))?;
+        report.record(store.ingest(&transaction)?);
+    }
+    Ok(report)
+}
+
+/// Replay both programs' transactions from a validator.
+///
+/// Transactions indexed above the finalized slot are re-checked first, and any that the
+/// cluster no longer knows about (an abandoned fork) are dropped. New signatures are then
+/// fetched for each program back to the last replay's cursor and ingested oldest first.
+pub fn sync(store: &mut Store, rpc: &RpcClient, commitment: CommitmentConfig) -> Result<Report> {
+    let mut report = Report {
+        dropped: drop_abandoned(store, rpc)?,
+        ..Report::default()
+    };
+
+    let mut cursors = Vec::new();
+    let mut signatures = Vec::new();
+    for program_id in [escrow::PROGRAM_ID, multi_presale::PROGRAM_ID] {
+        let mut program_signatures = new_signatures(store, rpc, &program_id, commitment)?;
+        if let Some((newest, _)) = program_signatures.first() {
+            cursors.push((program_id, newest.clone()));
+        }
+        program_signatures.reverse();
+        signatures.extend(program_signatures);
+    }
+    // Stable, so each program's own ordering within a slot is kept
+    signatures.sort_by_key(|(_, slot)| *slot);
+
+    // A transaction invoking both programs is listed for each of them
+    let mut seen = HashSet::new();
+    for (signature, _) in signatures {
+        if !seen.insert(signature.clone()) {
+            continue;
+        }
+        if store.contains(&signature)? {
+            report.duplicates += 1;
+            continue;
+        }
+        let transaction = fetch_transaction(rpc, &signature, commitment)?;
+        report.record(store.ingest(&transaction)?);
+    }
+
+    for (program_id, signature) in cursors {
+        store.set_cursor(&program_id, &signature)?;
+    }
+    Ok(report)
+}
+
+/// Drop indexed transactions above the finalized slot that the cluster has forgotten
+fn drop_abandoned(store: &mut Store, rpc: &RpcClient) -> Result<usize> {
+    let finalized_slot = rpc
+        .get_slot_with_commitment(CommitmentConfig::finalized())
+        .context("failed to fetch the finalized slot")?;
+    let unfinalized = store.signatures_after(finalized_slot)?;
+
+    let mut abandoned = Vec::new();
+    for batch in unfinalized.chunks(STATUS_BATCH) {
+        let signatures = batch
+            .iter()
+            .map(|signature| Signature::from_str(signature))
+            .collect::<Result<Vec<_>, _>>()
+            .context("invalid signature in the index")?;
+        let statuses = rpc
+            .get_signature_statuses_with_history(&signatures)
+            .context("failed to fetch signature statuses")?
+            .value;
+        for (signature, status) in batch.iter().zip(statuses) {
+            if status.is_none() {
+                abandoned.push(signature.clone());
+            }
+        }
+    }
+    store.drop_signatures(&abandoned)
+}
+
+/// Successful signatures for `program_id` newer than its cursor, newest first
+fn new_signatures(
+    store: &Store,
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    commitment: CommitmentConfig,
+) -> Result<Vec<(String, u64)>> {
+    let until = store
+        .cursor(program_id)?
+        .map(|signature| Signature::from_str(&signature))
+        .transpose()
+        .context("invalid cursor signature")?;
+
+    let mut signatures = Vec::new();
+    let mut before = None;
+    loop {
+        let page = rpc
+            .get_signatures_for_address_with_config(
+                program_id,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until,
+                    limit: Some(SIGNATURE_PAGE),
+                    commitment: Some(commitment),
+                },
+            )
+            .with_context(|| format!("failed to list signatures for {program_id}"))?;
+        let full_page = page.len() == SIGNATURE_PAGE;
+
+        for status in page {
+            before = Some(
+                Signature::from_str(&status.signature)
+                    .map_err(|err| anyhow!("invalid signature {}: {err}", status.signature))?,
+            );
+            if status.err.is_none() {
+                signatures.push((status.signature, status.slot));
+            }
+        }
+        if !full_page {
+            return Ok(signatures);
+        }
+    }
+}
+
+fn fetch_transaction(
+    rpc: &RpcClient,
+    signature: &str,
+    commitment: CommitmentConfig,
+) -> Result<TransactionLogs> {
+    let parsed = Signature::from_str(signature)
+        .map_err(|err| anyhow!("invalid signature {signature}: {err}"))?;
+    let transaction = rpc
+        .get_transaction_with_config(
+            &parsed,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Json),
+                commitment: Some(commitment),
+                max_supported_transaction_version: Some(0),
+            },
+        )
+        .with_context(|| format!("failed to fetch transaction {signature}"))?;
+    let meta = transaction
+        .transaction
+        .meta
+        .ok_or_else(|| anyhow!("transaction {signature} has no status metadata"))?;
+
+    let logs = match meta.log_messages {
+        OptionSerializer::Some(logs) => logs,
+        _ => Vec::new(),
+    };
+    let err = meta.err.map(serde_json::to_value).transpose()?;
+    Ok(TransactionLogs {
+        signature: signature.to_string(),
+        slot: transaction.slot,
+        block_time: transaction.block_time,
+        logs,
+        err,
+    })
+}
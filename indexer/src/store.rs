@@ -0,0 +1,821 @@
+//! SQLite store: the ingestion log plus the tables materialised from it.
+//!
+//! Every indexed transaction is recorded with its raw event payloads, keyed by signature,
+//! so replaying the same transaction twice is a no-op. The `sales`, `purchases`, `buyers`,
+//! `fees`, `projects`, `sale_rounds` and `platform` tables are derived from the stored
+//! events: they are updated incrementally as transactions arrive in slot order, and
+//! rebuilt from scratch whenever transactions are dropped (a reorg) or arrive late.
+
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use token_sale_client::anchor_lang::prelude::Pubkey;
+use token_sale_client::escrow::events as escrow_events;
+use token_sale_client::events::{self, ProgramEvent};
+use token_sale_client::multi_presale::{self, events as presale_events};
+
+use crate::source::TransactionLogs;
+
+const SCHEMA: &str = include_str!("schema.sql");
+
+/// Tables derived from the `events` table, cleared on rebuild
+const DERIVED_TABLES: [&str; 8] = [
+    "platform",
+    "projects",
+    "sale_rounds",
+    "sales",
+    "price_changes",
+    "purchases",
+    "buyers",
+    "fees",
+];
+
+/// Outcome of ingesting one transaction
+#[derive(Debug, PartialEq, Eq)]
+pub enum Ingested {
+    /// Recorded, with this many events from either program
+    Indexed(usize),
+    /// Already indexed under the same signature
+    Duplicate,
+    /// Failed on-chain, so its events never took effect
+    Failed,
+}
+
+pub struct Store {
+    conn: Connection,
+}
+
+impl Store {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("failed to open database {}", path.display()))?;
+        Self::init(conn)
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        conn.execute_batch(SCHEMA)
+            .context("failed to create the schema")?;
+        Ok(Self { conn })
+    }
+
+    #[cfg(test)]
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    pub fn contains(&self, signature: &str) -> Result<bool> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT 1 FROM transactions WHERE signature = ?1",
+                [signature],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some())
+    }
+
+    /// Record a transaction and apply its events.
+    ///
+    /// A transaction older than the newest one indexed triggers a rebuild, so the derived
+    /// tables always reflect slot order.
+    pub fn ingest(&mut self, transaction: &TransactionLogs) -> Result<Ingested> {
+        if transaction.err.is_some() {
+            return Ok(Ingested::Failed);
+        }
+        if self.contains(&transaction.signature)? {
+            return Ok(Ingested::Duplicate);
+        }
+
+        let latest_slot: Option<u64> =
+            self.conn
+                .query_row("SELECT MAX(slot) FROM transactions", [], |row| row.get(0))?;
+        let in_order = latest_slot.is_none_or(|slot| transaction.slot >= slot);
+
+        let db = self.conn.transaction()?;
+        db.execute(
+            "INSERT INTO transactions (signature, slot, block_time) VALUES (?1, ?2, ?3)",
+            params![
+                transaction.signature,
+                transaction.slot,
+                transaction.block_time
+            ],
+        )?;
+
+        let mut indexed = 0;
+        for event in events::event_data(&transaction.logs) {
+            let Some(decoded) = events::decode_event(&event.program_id, &event.data) else {
+                continue;
+            };
+            db.execute(
+                "INSERT INTO events (signature, idx, program, data) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    transaction.signature,
+                    indexed,
+                    event.program_id.to_string(),
+                    event.data
+                ],
+            )?;
+            if in_order {
+                apply(
+                    &db,
+                    &transaction.signature,
+                    transaction.slot,
+                    indexed,
+                    decoded,
+                )?;
+            }
+            indexed += 1;
+        }
+        db.commit()?;
+
+        if !in_order {
+            self.rebuild()?;
+        }
+        Ok(Ingested::Indexed(indexed))
+    }
+
+    /// Forget every transaction at or after `slot`, e.g. after a fork was abandoned
+    pub fn rollback(&mut self, slot: u64) -> Result<usize> {
+        let dropped = self
+            .conn
+            .execute("DELETE FROM transactions WHERE slot >= ?1", [slot])?;
+        if dropped > 0 {
+            self.conn.execute("DELETE FROM cursors", [])?;
+            self.rebuild()?;
+        }
+        Ok(dropped)
+    }
+
+    /// Forget the given transactions, e.g. ones that never reached finality
+    pub fn drop_signatures(&mut self, signatures: &[String]) -> Result<usize> {
+        let db = self.conn.transaction()?;
+        let mut dropped = 0;
+        for signature in signatures {
+            dropped += db.execute("DELETE FROM transactions WHERE signature = ?1", [signature])?;
+        }
+        if dropped > 0 {
+            db.execute("DELETE FROM cursors", [])?;
+        }
+        db.commit()?;
+
+        if dropped > 0 {
+            self.rebuild()?;
+        }
+        Ok(dropped)
+    }
+
+    /// Recompute every derived table from the stored events
+    pub fn rebuild(&mut self) -> Result<()> {
+        let db = self.conn.transaction()?;
+        for table in DERIVED_TABLES {
+            db.execute(&format!("DELETE FROM {table}"), [])?;
+        }
+
+        {
+            let mut statement = db.prepare(
+                "SELECT t.signature, t.slot, e.idx, e.program, e.data
+                 FROM events e JOIN transactions t ON t.signature = e.signature
+                 ORDER BY t.slot, t.seq, e.idx",
+            )?;
+            let mut rows = statement.query([])?;
+            while let Some(row) = rows.next()? {
+                let signature: String = row.get(0)?;
+                let program: String = row.get(3)?;
+                let data: Vec<u8> = row.get(4)?;
+                let program_id = Pubkey::from_str(&program)
+                    .with_context(|| format!("invalid program id {program} in the event log"))?;
+                if let Some(event) = events::decode_event(&program_id, &data) {
+                    apply(&db, &signature, row.get(1)?, row.get(2)?, event)?;
+                }
+            }
+        }
+
+        db.commit()?;
+        Ok(())
+    }
+
+    /// Signatures indexed after `slot`, i.e. not yet known to be finalized
+    pub fn signatures_after(&self, slot: u64) -> Result<Vec<String>> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT signature FROM transactions WHERE slot > ?1 ORDER BY seq")?;
+        let signatures = statement
+            .query_map([slot], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(signatures)
+    }
+
+    /// Newest signature fetched for `program_id` by the last replay
+    pub fn cursor(&self, program_id: &Pubkey) -> Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT signature FROM cursors WHERE program = ?1",
+                [program_id.to_string()],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    pub fn set_cursor(&self, program_id: &Pubkey, signature: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO cursors (program, signature) VALUES (?1, ?2)
+             ON CONFLICT (program) DO UPDATE SET signature = excluded.signature",
+            params![program_id.to_string(), signature],
+        )?;
+        Ok(())
+    }
+}
+
+/// Fields of the `SaleInitialized` event both programs emit
+struct NewSale {
+    sale: Pubkey,
+    seller: Pubkey,
+    token_mint: Pubkey,
+    payment_mint: Pubkey,
+    price_per_token: u64,
+    total_tokens: u64,
+    sale_start_time: i64,
+    sale_end_time: i64,
+    platform_fee_bps: u16,
+    platform_fee_recipient: Pubkey,
+}
+
+/// Fields of the `TokensPurchased` event both programs emit
+struct Purchase {
+    sale: Pubkey,
+    buyer: Pubkey,
+    token_amount: u64,
+    seller_payment: u64,
+    platform_fee: u64,
+    platform_fee_recipient: Pubkey,
+    timestamp: i64,
+}
+
+macro_rules! impl_sale_events {
+    ($events:ident) => {
+        impl From<$events::SaleInitialized> for NewSale {
+            fn from(event: $events::SaleInitialized) -> Self {
+                Self {
+                    sale: event.sale,
+                    seller: event.seller,
+                    token_mint: event.token_mint,
+                    payment_mint: event.payment_mint,
+                    price_per_token: event.price_per_token,
+                    total_tokens: event.total_tokens,
+                    sale_start_time: event.sale_start_time,
+                    sale_end_time: event.sale_end_time,
+                    platform_fee_bps: event.platform_fee_bps,
+                    platform_fee_recipient: event.platform_fee_recipient,
+                }
+            }
+        }
+
+        impl From<$events::TokensPurchased> for Purchase {
+            fn from(event: $events::TokensPurchased) -> Self {
+                Self {
+                    sale: event.sale,
+                    buyer: event.buyer,
+                    token_amount: event.token_amount,
+                    seller_payment: event.seller_payment,
+                    platform_fee: event.platform_fee,
+                    platform_fee_recipient: event.platform_fee_recipient,
+                    timestamp: event.timestamp,
+                }
+            }
+        }
+    };
+}
+
+impl_sale_events!(escrow_events);
+impl_sale_events!(presale_events);
+
+fn apply(
+    db: &Connection,
+    signature: &str,
+    slot: u64,
+    idx: usize,
+    event: ProgramEvent,
+) -> Result<()> {
+    use presale_events::Event as Presale;
+
+    match event {
+        ProgramEvent::Escrow(event) => match event {
+            escrow_events::Event::SaleInitialized(event) => {
+                insert_sale(db, signature, "escrow", event.into())
+            }
+            escrow_events::Event::TokensPurchased(event) => {
+                insert_purchase(db, signature, slot, idx, event.into())
+            }
+            escrow_events::Event::SaleCancelled(event) => {
+                cancel_sale(db, &event.sale, event.tokens_returned)
+            }
+            escrow_events::Event::PriceChangeScheduled(event) => {
+                db.execute(
+                    "INSERT OR REPLACE INTO price_changes
+                     (signature, idx, sale, current_price, new_price, effective_time)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        signature,
+                        idx,
+                        event.sale.to_string(),
+                        event.current_price,
+                        event.new_price,
+                        event.effective_time
+                    ],
+                )?;
+                Ok(())
+            }
+            escrow_events::Event::SaleExtended(event) => {
+                set_sale_end(db, &event.sale, event.new_end_time)
+            }
+            escrow_events::Event::SaleSoftCloseExtended(event) => {
+                set_sale_end(db, &event.sale, event.new_end_time)
+            }
+            escrow_events::Event::SaleClosedEarly(event) => {
+                set_sale_end(db, &event.sale, event.new_end_time)
+            }
+            // Winning claims also emit `TokensPurchased`; the rest lives in the raw events
+            escrow_events::Event::LotteryTicketRegistered(_)
+            | escrow_events::Event::LotteryDrawn(_)
+            | escrow_events::Event::LotteryTicketClaimed(_) => Ok(()),
+            // Likewise for overflow claims that allocated tokens
+            escrow_events::Event::OverflowDeposited(_)
+            | escrow_events::Event::OverflowClaimed(_) => Ok(()),
+            // And for settled auction bids that won tokens
+            escrow_events::Event::AuctionBidSubmitted(_)
+            | escrow_events::Event::AuctionCleared(_)
+            | escrow_events::Event::AuctionBidSettled(_)
+            | escrow_events::Event::SealedBidCommitted(_)
+            | escrow_events::Event::SealedBidRevealed(_) => Ok(()),
+            // The purchase itself is recorded from `TokensPurchased`, under the recipient
+            escrow_events::Event::PurchasedOnBehalf(_) => Ok(()),
+            // Closing a tracker leaves the purchase history untouched
+            escrow_events::Event::BuyerAccountClosed(_) => Ok(()),
+            escrow_events::Event::PurchaseHookSet(_) => Ok(()),
+            // Receipts are minted by purchases already recorded from `TokensPurchased`
+            escrow_events::Event::ReceiptsRedeemed(_) => Ok(()),
+            // Compliance changes are an audit trail, kept as raw events
+            escrow_events::Event::WalletScreeningSet(_)
+            | escrow_events::Event::DenyListConfigured(_)
+            | escrow_events::Event::WalletDenied(_)
+            | escrow_events::Event::WalletAllowed(_) => Ok(()),
+        },
+        ProgramEvent::MultiPresale(event) => match event {
+            Presale::SaleInitialized(event) => {
+                insert_sale(db, signature, "multi_presale", event.into())
+            }
+            Presale::TokensPurchased(event) => {
+                insert_purchase(db, signature, slot, idx, event.into())
+            }
+            Presale::SaleCancelled(event) => cancel_sale(db, &event.sale, event.tokens_returned),
+            Presale::PlatformInitialized(event) => {
+                db.execute(
+                    "INSERT OR REPLACE INTO platform (authority, treasury, platform_fee_bps, signature)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        event.authority.to_string(),
+                        event.treasury.to_string(),
+                        event.platform_fee,
+                        signature
+                    ],
+                )?;
+                Ok(())
+            }
+            Presale::ProjectCreated(event) => {
+                db.execute(
+                    "INSERT OR REPLACE INTO projects
+                     (project_id, creator, name, token_mint, category, status, signature)
+                     VALUES (?1, ?2, ?3, ?4, ?5, 'Draft', ?6)",
+                    params![
+                        event.project_id,
+                        event.creator.to_string(),
+                        event.name,
+                        event.token_mint.to_string(),
+                        multi_presale::category_name(event.category),
+                        signature
+                    ],
+                )?;
+                Ok(())
+            }
+            Presale::ProjectApproved(event) => {
+                db.execute(
+                    "UPDATE projects SET status = 'Active', approved_by = ?2, approved_at = ?3
+                     WHERE project_id = ?1",
+                    params![event.project_id, event.admin.to_string(), event.approved_at],
+                )?;
+                Ok(())
+            }
+            Presale::SaleConfigured(event) => {
+                db.execute(
+                    "UPDATE projects SET sale_type = ?2, token_price = ?3, total_tokens = ?4,
+                     sale_start_time = ?5, sale_end_time = ?6
+                     WHERE project_id = ?1",
+                    params![
+                        event.project_id,
+                        multi_presale::sale_type_name(event.sale_type),
+                        event.token_price,
+                        event.total_tokens,
+                        event.start_time,
+                        event.end_time
+                    ],
+                )?;
+                Ok(())
+            }
+            Presale::SaleRoundCreated(event) => {
+                db.execute(
+                    "INSERT OR REPLACE INTO sale_rounds
+                     (project_id, round_number, sale_type, token_price, total_tokens, signature)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        event.project_id,
+                        event.round_number,
+                        multi_presale::sale_type_name(event.sale_type),
+                        event.token_price,
+                        event.total_tokens,
+                        signature
+                    ],
+                )?;
+                Ok(())
+            }
+            Presale::ProjectStatusChanged(event) => {
+                db.execute(
+                    "UPDATE projects SET status = ?2, status_changed_at = ?3 WHERE project_id = ?1",
+                    params![
+                        event.project_id,
+                        multi_presale::status_name(event.new_status),
+                        event.timestamp
+                    ],
+                )?;
+                Ok(())
+            }
+            // Compliance changes are an audit trail, kept as raw events
+            Presale::WalletScreeningSet(_)
+            | Presale::DenyListConfigured(_)
+            | Presale::WalletDenied(_)
+            | Presale::WalletAllowed(_) => Ok(()),
+        },
+    }
+}
+
+fn insert_sale(db: &Connection, signature: &str, program: &str, sale: NewSale) -> Result<()> {
+    db.execute(
+        "INSERT OR REPLACE INTO sales
+         (address, program, seller, token_mint, payment_mint, price_per_token, total_tokens,
+          sale_start_time, sale_end_time, platform_fee_bps, platform_fee_recipient, signature)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            sale.sale.to_string(),
+            program,
+            sale.seller.to_string(),
+            sale.token_mint.to_string(),
+            sale.payment_mint.to_string(),
+            sale.price_per_token,
+            sale.total_tokens,
+            sale.sale_start_time,
+            sale.sale_end_time,
+            sale.platform_fee_bps,
+            sale.platform_fee_recipient.to_string(),
+            signature
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_purchase(
+    db: &Connection,
+    signature: &str,
+    slot: u64,
+    idx: usize,
+    purchase: Purchase,
+) -> Result<()> {
+    let sale = purchase.sale.to_string();
+    let buyer = purchase.buyer.to_string();
+    let amount_paid = purchase
+        .seller_payment
+        .checked_add(purchase.platform_fee)
+        .context("purchase payment overflows")?;
+
+    db.execute(
+        "INSERT INTO purchases
+         (signature, idx, slot, sale, buyer, token_amount, seller_payment, platform_fee, timestamp)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            signature,
+            idx,
+            slot,
+            sale,
+            buyer,
+            purchase.token_amount,
+            purchase.seller_payment,
+            purchase.platform_fee,
+            purchase.timestamp
+        ],
+    )?;
+    db.execute(
+        "UPDATE sales SET tokens_sold = tokens_sold + ?2, proceeds = proceeds + ?3
+         WHERE address = ?1",
+        params![sale, purchase.token_amount, purchase.seller_payment],
+    )?;
+    db.execute(
+        "INSERT INTO buyers
+         (sale, buyer, tokens_purchased, amount_paid, purchases, first_purchase_at, last_purchase_at)
+         VALUES (?1, ?2, ?3, ?4, 1, ?5, ?5)
+         ON CONFLICT (sale, buyer) DO UPDATE SET
+             tokens_purchased = tokens_purchased + excluded.tokens_purchased,
+             amount_paid = amount_paid + excluded.amount_paid,
+             purchases = purchases + 1,
+             last_purchase_at = excluded.last_purchase_at",
+        params![sale, buyer, purchase.token_amount, amount_paid, purchase.timestamp],
+    )?;
+    if purchase.platform_fee > 0 {
+        db.execute(
+            "INSERT INTO fees (recipient, sale, amount, purchases) VALUES (?1, ?2, ?3, 1)
+             ON CONFLICT (recipient, sale) DO UPDATE SET
+                 amount = amount + excluded.amount,
+                 purchases = purchases + 1",
+            params![
+                purchase.platform_fee_recipient.to_string(),
+                sale,
+                purchase.platform_fee
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+fn set_sale_end(db: &Connection, sale: &Pubkey, sale_end_time: i64) -> Result<()> {
+    db.execute(
+        "UPDATE sales SET sale_end_time = ?2 WHERE address = ?1",
+        params![sale.to_string(), sale_end_time],
+    )?;
+    Ok(())
+}
+
+fn cancel_sale(db: &Connection, sale: &Pubkey, tokens_returned: u64) -> Result<()> {
+    db.execute(
+        "UPDATE sales SET cancelled = 1, tokens_returned = ?2 WHERE address = ?1",
+        params![sale.to_string(), tokens_returned],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::prelude::{Engine, BASE64_STANDARD};
+    use token_sale_client::anchor_lang::Event;
+    use token_sale_client::{escrow, multi_presale::ProjectStatus};
+
+    struct Fixture {
+        sale: Pubkey,
+        buyer: Pubkey,
+        recipient: Pubkey,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            Self {
+                sale: Pubkey::new_unique(),
+                buyer: Pubkey::new_unique(),
+                recipient: Pubkey::new_unique(),
+            }
+        }
+
+        fn sale_initialized(&self) -> escrow_events::SaleInitialized {
+            escrow_events::SaleInitialized {
+                sale: self.sale,
+                seller: Pubkey::new_unique(),
+                token_mint: Pubkey::new_unique(),
+                payment_mint: Pubkey::new_unique(),
+                price_per_token: 1_000,
+                total_tokens: 1_000,
+                sale_start_time: 100,
+                sale_end_time: 200,
+                platform_fee_bps: 250,
+                platform_fee_recipient: self.recipient,
+            }
+        }
+
+        fn purchase(&self, token_amount: u64) -> escrow_events::TokensPurchased {
+            let gross = token_amount * 1_000;
+            escrow_events::TokensPurchased {
+                sale: self.sale,
+                buyer: self.buyer,
+                token_amount,
+                seller_payment: gross - gross / 40,
+                platform_fee: gross / 40,
+                platform_fee_recipient: self.recipient,
+                timestamp: 150,
+            }
+        }
+    }
+
+    fn transaction(
+        signature: &str,
+        slot: u64,
+        program_id: Pubkey,
+        events: &[Vec<u8>],
+    ) -> TransactionLogs {
+        let mut logs = vec![format!("Program {program_id} invoke [1]")];
+        for data in events {
+            logs.push(format!("Program data: {}", BASE64_STANDARD.encode(data)));
+        }
+        logs.push(format!("Program {program_id} success"));
+        TransactionLogs {
+            signature: signature.to_string(),
+            slot,
+            block_time: None,
+            logs,
+            err: None,
+        }
+    }
+
+    fn escrow_tx(signature: &str, slot: u64, events: &[Vec<u8>]) -> TransactionLogs {
+        transaction(signature, slot, escrow::PROGRAM_ID, events)
+    }
+
+    fn count(store: &Store, table: &str) -> u64 {
+        store
+            .connection()
+            .query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+    }
+
+    fn sale_totals(store: &Store, sale: &Pubkey) -> (u64, u64) {
+        store
+            .connection()
+            .query_row(
+                "SELECT tokens_sold, proceeds FROM sales WHERE address = ?1",
+                [sale.to_string()],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+    }
+
+    fn buyer_totals(store: &Store, fixture: &Fixture) -> (u64, u64, u64) {
+        store
+            .connection()
+            .query_row(
+                "SELECT tokens_purchased, amount_paid, purchases FROM buyers
+                 WHERE sale = ?1 AND buyer = ?2",
+                [fixture.sale.to_string(), fixture.buyer.to_string()],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn purchases_materialise_sales_buyers_and_fees() {
+        let mut store = Store::open_in_memory().unwrap();
+        let f = Fixture::new();
+
+        let init = escrow_tx("init", 1, &[f.sale_initialized().data()]);
+        assert_eq!(store.ingest(&init).unwrap(), Ingested::Indexed(1));
+        let buys = escrow_tx("buys", 2, &[f.purchase(10).data(), f.purchase(30).data()]);
+        assert_eq!(store.ingest(&buys).unwrap(), Ingested::Indexed(2));
+
+        assert_eq!(sale_totals(&store, &f.sale), (40, 39_000));
+        assert_eq!(buyer_totals(&store, &f), (40, 40_000, 2));
+        assert_eq!(count(&store, "purchases"), 2);
+        let fees: u64 = store
+            .connection()
+            .query_row(
+                "SELECT amount FROM fees WHERE recipient = ?1",
+                [f.recipient.to_string()],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(fees, 1_000);
+
+        let cancel = escrow_tx(
+            "cancel",
+            3,
+            &[escrow_events::SaleCancelled {
+                sale: f.sale,
+                tokens_returned: 960,
+            }
+            .data()],
+        );
+        store.ingest(&cancel).unwrap();
+        let cancelled: bool = store
+            .connection()
+            .query_row("SELECT cancelled FROM sales", [], |row| row.get(0))
+            .unwrap();
+        assert!(cancelled);
+    }
+
+    #[test]
+    fn replays_and_failed_transactions_are_ignored() {
+        let mut store = Store::open_in_memory().unwrap();
+        let f = Fixture::new();
+        store
+            .ingest(&escrow_tx("init", 1, &[f.sale_initialized().data()]))
+            .unwrap();
+
+        let buy = escrow_tx("buy", 2, &[f.purchase(10).data()]);
+        store.ingest(&buy).unwrap();
+        assert_eq!(store.ingest(&buy).unwrap(), Ingested::Duplicate);
+
+        let mut failed = escrow_tx("failed", 3, &[f.purchase(10).data()]);
+        failed.err = Some(serde_json::json!({ "InstructionError": [0, { "Custom": 6000 }] }));
+        assert_eq!(store.ingest(&failed).unwrap(), Ingested::Failed);
+        assert!(!store.contains("failed").unwrap());
+
+        assert_eq!(sale_totals(&store, &f.sale), (10, 9_750));
+    }
+
+    #[test]
+    fn rollback_drops_forked_transactions() {
+        let mut store = Store::open_in_memory().unwrap();
+        let f = Fixture::new();
+        store
+            .ingest(&escrow_tx("init", 1, &[f.sale_initialized().data()]))
+            .unwrap();
+        store
+            .ingest(&escrow_tx("buy-1", 2, &[f.purchase(10).data()]))
+            .unwrap();
+        store
+            .ingest(&escrow_tx("buy-2", 5, &[f.purchase(20).data()]))
+            .unwrap();
+        store.set_cursor(&escrow::PROGRAM_ID, "buy-2").unwrap();
+
+        assert_eq!(store.rollback(5).unwrap(), 1);
+        assert_eq!(sale_totals(&store, &f.sale), (10, 9_750));
+        assert_eq!(buyer_totals(&store, &f), (10, 10_000, 1));
+        assert_eq!(count(&store, "events"), 2);
+        assert_eq!(store.cursor(&escrow::PROGRAM_ID).unwrap(), None);
+
+        // The dropped transaction can land again on the new fork
+        assert_eq!(
+            store
+                .ingest(&escrow_tx("buy-2", 6, &[f.purchase(20).data()]))
+                .unwrap(),
+            Ingested::Indexed(1)
+        );
+        assert_eq!(sale_totals(&store, &f.sale), (30, 29_250));
+
+        store.drop_signatures(&["buy-1".to_string()]).unwrap();
+        assert_eq!(buyer_totals(&store, &f), (20, 20_000, 1));
+    }
+
+    #[test]
+    fn late_transactions_are_applied_in_slot_order() {
+        let mut store = Store::open_in_memory().unwrap();
+        let project_id = 1;
+        let created = multi_presale::events::ProjectCreated {
+            project_id,
+            creator: Pubkey::new_unique(),
+            name: "Project".to_string(),
+            token_mint: Pubkey::new_unique(),
+            category: multi_presale::ProjectCategory::DeFi,
+        };
+        let approved = multi_presale::events::ProjectApproved {
+            project_id,
+            admin: Pubkey::new_unique(),
+            approved_at: 10,
+        };
+        let completed = multi_presale::events::ProjectStatusChanged {
+            project_id,
+            old_status: ProjectStatus::Active,
+            new_status: ProjectStatus::Completed,
+            timestamp: 20,
+        };
+        let presale_tx = |signature, slot, data: Vec<u8>| {
+            transaction(signature, slot, multi_presale::PROGRAM_ID, &[data])
+        };
+
+        store
+            .ingest(&presale_tx("create", 1, created.data()))
+            .unwrap();
+        store
+            .ingest(&presale_tx("complete", 3, completed.data()))
+            .unwrap();
+        // Approval arrives after the completion that followed it on-chain
+        store
+            .ingest(&presale_tx("approve", 2, approved.data()))
+            .unwrap();
+
+        let (status, approved_at): (String, i64) = store
+            .connection()
+            .query_row(
+                "SELECT status, approved_at FROM projects WHERE project_id = ?1",
+                [project_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(status, "Completed");
+        assert_eq!(approved_at, 10);
+    }
+}
@@ -0,0 +1,220 @@
+//! Account layout versioning and migration, shared by the `escrow` and `multi_presale`
+//! programs.
+//!
+//! Each program implements [`MigrationAccounts`] for its `MigrateAccount` context and
+//! calls [`migrate_account`] or [`migrate_to_zero_copy`] from its `migrate_*` handlers:
+//! - [`Versioned`]: Borsh accounts, grown in place, declared with [`versioned!`]
+//! - [`ZeroCopyVersioned`]: zero-copy accounts that replaced a Borsh layout
+
+use anchor_lang::prelude::*;
+use anchor_lang::{Discriminator, ZeroCopy};
+
+/// Program account with a layout version.
+///
+/// Accounts created before versioning carry no version byte and read as version 0.
+/// Each layout change bumps `VERSION`, appends its new fields at the end of the struct
+/// and fills them in `upgrade`.
+pub trait Versioned: AccountSerialize + AccountDeserialize + Discriminator + Owner {
+    /// Current layout version
+    const VERSION: u8;
+    /// Account size at the current version, discriminator included
+    const SPACE: usize;
+
+    fn version(&self) -> u8;
+    fn set_version(&mut self, version: u8);
+
+    /// Fill the fields added since `from_version` (zeroed by the realloc)
+    fn upgrade(&mut self, _from_version: u8) {}
+}
+
+/// Implement [`Versioned`] for an `#[account]` with `INIT_SPACE` and a `version` field
+#[macro_export]
+macro_rules! versioned {
+    ($account:ty, $version:expr) => {
+        impl $crate::Versioned for $account {
+            const VERSION: u8 = $version;
+            const SPACE: usize = 8 + <$account>::INIT_SPACE;
+
+            fn version(&self) -> u8 {
+                self.version
+            }
+
+            fn set_version(&mut self, version: u8) {
+                self.version = version;
+            }
+        }
+    };
+}
+
+/// Zero-copy account that replaced a Borsh layout.
+///
+/// The zero-copy layout has its own discriminator, so `AccountLoader` rejects accounts
+/// still in the Borsh layout instead of misreading them until they are migrated.
+pub trait ZeroCopyVersioned: ZeroCopy + Owner {
+    /// Current layout version
+    const VERSION: u8;
+    /// Account size at the current version, discriminator included
+    const SPACE: usize = 8 + std::mem::size_of::<Self>();
+
+    /// Borsh layout the account replaced
+    type Legacy: AccountDeserialize + Discriminator;
+    /// Size of the legacy layout at its last version, discriminator included
+    const LEGACY_SPACE: usize;
+
+    /// Carry a legacy account's state over to the zero-copy layout
+    fn from_legacy(legacy: Self::Legacy) -> Self;
+
+    fn version(&self) -> u8;
+    fn set_version(&mut self, version: u8);
+}
+
+/// The accounts of a program's `migrate_*` instructions
+pub trait MigrationAccounts<'info> {
+    /// Account being migrated
+    fn account(&self) -> &AccountInfo<'info>;
+    /// Signer topping up the account's rent when it grows
+    fn payer(&self) -> AccountInfo<'info>;
+    fn system_program(&self) -> AccountInfo<'info>;
+
+    /// The program's error for an account already at the current version
+    fn already_migrated(&self) -> Error;
+}
+
+/// Resize the migrated account to `space` bytes, topping up rent from the payer
+fn resize_account<'info>(accounts: &impl MigrationAccounts<'info>, space: usize) -> Result<()> {
+    let account = accounts.account();
+    let required_lamports = Rent::get()?.minimum_balance(space);
+    let shortfall = required_lamports.saturating_sub(account.lamports());
+    if shortfall > 0 {
+        let transfer_ctx = CpiContext::new(
+            accounts.system_program(),
+            anchor_lang::system_program::Transfer {
+                from: accounts.payer(),
+                to: account.clone(),
+            },
+        );
+        anchor_lang::system_program::transfer(transfer_ctx, shortfall)?;
+    }
+    account.resize(space)?;
+    Ok(())
+}
+
+/// Grow the migrated account to `T`'s current size, topping up rent from the payer,
+/// then upgrade its fields and stamp the current version.
+pub fn migrate_account<'info, T: Versioned>(
+    accounts: &impl MigrationAccounts<'info>,
+) -> Result<()> {
+    let account = accounts.account();
+    require_keys_eq!(
+        *account.owner,
+        T::owner(),
+        ErrorCode::AccountOwnedByWrongProgram
+    );
+    require!(
+        account.try_borrow_data()?.starts_with(T::DISCRIMINATOR),
+        ErrorCode::AccountDiscriminatorMismatch
+    );
+
+    let old_len = account.data_len();
+    if old_len < T::SPACE {
+        resize_account(accounts, T::SPACE)?;
+    }
+
+    // Fields appended since the old layout read as zero, so a legacy account is version 0
+    let mut state = T::try_deserialize(&mut &account.try_borrow_data()?[..])?;
+    let from_version = state.version();
+    if from_version >= T::VERSION {
+        return Err(accounts.already_migrated());
+    }
+
+    state.upgrade(from_version);
+    state.set_version(T::VERSION);
+    state.try_serialize(&mut &mut account.try_borrow_mut_data()?[..])?;
+
+    msg!(
+        "Account {} migrated from version {} to {} ({} -> {} bytes)",
+        account.key,
+        from_version,
+        T::VERSION,
+        old_len,
+        account.data_len()
+    );
+    Ok(())
+}
+
+/// Rewrite a Borsh account in `T`'s zero-copy layout: carry its state over, resize it
+/// to `T::SPACE` (topping up rent from the payer) and stamp `T`'s discriminator.
+/// Accounts already zero-copy but at an older, shorter version are grown instead.
+pub fn migrate_to_zero_copy<'info, T: ZeroCopyVersioned>(
+    accounts: &impl MigrationAccounts<'info>,
+) -> Result<()> {
+    let account = accounts.account();
+    require_keys_eq!(
+        *account.owner,
+        T::owner(),
+        ErrorCode::AccountOwnedByWrongProgram
+    );
+
+    if account.try_borrow_data()?.starts_with(T::DISCRIMINATOR) {
+        return grow_zero_copy::<T>(accounts);
+    }
+
+    let legacy = {
+        let data = account.try_borrow_data()?;
+        require!(
+            data.starts_with(T::Legacy::DISCRIMINATOR),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+
+        // Accounts from before versioning end without the version byte
+        let mut legacy_data = data.to_vec();
+        legacy_data.resize(legacy_data.len().max(T::LEGACY_SPACE), 0);
+        T::Legacy::try_deserialize(&mut &legacy_data[..])?
+    };
+    let state = T::from_legacy(legacy);
+
+    let old_len = account.data_len();
+    resize_account(accounts, T::SPACE)?;
+
+    let mut data = account.try_borrow_mut_data()?;
+    let (discriminator, fields) = data.split_at_mut(T::DISCRIMINATOR.len());
+    discriminator.copy_from_slice(T::DISCRIMINATOR);
+    fields.copy_from_slice(bytemuck::bytes_of(&state));
+
+    msg!(
+        "Account {} migrated to zero-copy version {} ({} -> {} bytes)",
+        account.key,
+        T::VERSION,
+        old_len,
+        T::SPACE
+    );
+    Ok(())
+}
+
+/// Grow a zero-copy account written at an older version. Later versions only append
+/// fields, so the existing bytes keep their offsets and the new fields read as zero.
+fn grow_zero_copy<'info, T: ZeroCopyVersioned>(
+    accounts: &impl MigrationAccounts<'info>,
+) -> Result<()> {
+    let account = accounts.account();
+    let old_len = account.data_len();
+    if old_len >= T::SPACE {
+        return Err(accounts.already_migrated());
+    }
+    resize_account(accounts, T::SPACE)?;
+
+    let mut data = account.try_borrow_mut_data()?;
+    let state: &mut T = bytemuck::from_bytes_mut(&mut data[T::DISCRIMINATOR.len()..]);
+    let from_version = state.version();
+    state.set_version(T::VERSION);
+
+    msg!(
+        "Account {} migrated from version {} to {} ({} -> {} bytes)",
+        account.key,
+        from_version,
+        T::VERSION,
+        old_len,
+        T::SPACE
+    );
+    Ok(())
+}
@@ -1,5 +1,7 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+use anchor_spl::token::{self, Burn, CloseAccount, Mint, MintTo, Token, TokenAccount, Transfer};
+pub use token_sale_migration::{Versioned, ZeroCopyVersioned};
+use token_sale_migration::{migrate_account, migrate_to_zero_copy, versioned, MigrationAccounts};
 
 // This is your program's on-chain address
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
@@ -29,23 +31,28 @@ pub mod escrow {
         let current_time = Clock::get()?.unix_timestamp;
         require!(sale_end_time > current_time, ErrorCode::SaleEndTimeInPast);
 
-        let sale = &mut ctx.accounts.token_sale;
-        
-        // Initialize sale account with security features
-        sale.seller = ctx.accounts.seller.key();
-        sale.token_mint = ctx.accounts.token_mint.key();
-        sale.payment_mint = ctx.accounts.payment_mint.key();
-        sale.price_per_token = price_per_token;
-        sale.total_tokens = total_tokens;
-        sale.tokens_available = total_tokens;
-        sale.sale_start_time = sale_start_time;
-        sale.sale_end_time = sale_end_time;
-        sale.max_tokens_per_buyer = max_tokens_per_buyer;
-        sale.platform_fee_bps = platform_fee_bps;
-        sale.platform_fee_recipient = platform_fee_recipient;
-        sale.is_active = true;
-        sale.is_paused = false;
-        sale.bump = ctx.bumps.token_sale;
+        let sale_key = ctx.accounts.token_sale.key();
+        {
+            let mut sale = ctx.accounts.token_sale.load_init()?;
+
+            // Initialize sale account with security features; throttles, price tiers and
+            // milestones start zeroed (disabled)
+            sale.seller = ctx.accounts.seller.key();
+            sale.token_mint = ctx.accounts.token_mint.key();
+            sale.payment_mint = ctx.accounts.payment_mint.key();
+            sale.price_per_token = price_per_token;
+            sale.total_tokens = total_tokens;
+            sale.tokens_available = total_tokens;
+            sale.sale_start_time = sale_start_time;
+            sale.sale_end_time = sale_end_time;
+            sale.max_tokens_per_buyer = max_tokens_per_buyer;
+            sale.platform_fee_bps = platform_fee_bps;
+            sale.platform_fee_recipient = platform_fee_recipient;
+            sale.is_active = 1;
+            sale.is_paused = 0;
+            sale.bump = ctx.bumps.token_sale;
+            sale.version = TokenSale::VERSION;
+        }
 
         // Transfer seller's tokens to the program's vault
         let transfer_ctx = CpiContext::new(
@@ -58,6 +65,19 @@ pub mod escrow {
         );
         token::transfer(transfer_ctx, total_tokens)?;
 
+        emit!(SaleInitialized {
+            sale: sale_key,
+            seller: ctx.accounts.seller.key(),
+            token_mint: ctx.accounts.token_mint.key(),
+            payment_mint: ctx.accounts.payment_mint.key(),
+            price_per_token,
+            total_tokens,
+            sale_start_time,
+            sale_end_time,
+            platform_fee_bps,
+            platform_fee_recipient,
+        });
+
         msg!("Token sale initialized: {} tokens at {} lamports each, from {} to {}", 
             total_tokens, price_per_token, sale_start_time, sale_end_time);
         Ok(())
@@ -65,140 +85,148 @@ pub mod escrow {
 
     /// Create a buyer tracking account (first-time buyers only)
     pub fn create_buyer_account(ctx: Context<CreateBuyerAccount>) -> Result<()> {
-        let buyer_account = &mut ctx.accounts.buyer_account;
+        let mut buyer_account = ctx.accounts.buyer_account.load_init()?;
         buyer_account.buyer = ctx.accounts.buyer.key();
         buyer_account.token_sale = ctx.accounts.token_sale.key();
         buyer_account.tokens_purchased = 0;
         buyer_account.bump = ctx.bumps.buyer_account;
+        buyer_account.last_purchase_time = 0;
+        buyer_account.payment_escrowed = 0;
+        buyer_account.last_voted_milestone = 0;
+        buyer_account.refund_claimed = 0;
+        buyer_account.overflow_deposit = 0;
+        buyer_account.overflow_claimed = 0;
+        buyer_account.rent_payer = ctx.accounts.rent_payer.key();
+        buyer_account.version = BuyerAccount::VERSION;
         
-        msg!("Buyer account created for {}", ctx.accounts.buyer.key());
+        msg!("Buyer account created for {}, rent paid by {}",
+            ctx.accounts.buyer.key(), ctx.accounts.rent_payer.key());
         Ok(())
     }
 
-    /// Buy tokens from the sale with comprehensive security checks
-    pub fn buy_tokens(
-        ctx: Context<BuyTokens>,
-        token_amount: u64,           // How many tokens to buy
-    ) -> Result<()> {
-        // Get sale data and perform security checks
-        let sale = &ctx.accounts.token_sale;
-        
-        // Basic sale status checks
-        require!(sale.is_active, ErrorCode::SaleNotActive);
-        require!(!sale.is_paused, ErrorCode::SalePaused);
-        require!(token_amount > 0, ErrorCode::InvalidTokenAmount);
-        require!(token_amount <= sale.tokens_available, ErrorCode::InsufficientTokens);
-        
-        // Time-based validation
-        let current_time = Clock::get()?.unix_timestamp;
-        require!(current_time >= sale.sale_start_time, ErrorCode::SaleNotStarted);
-        require!(current_time <= sale.sale_end_time, ErrorCode::SaleEnded);
-        
-        // Check per-buyer purchase limit if set
-        if sale.max_tokens_per_buyer > 0 {
-            let current_purchased = ctx.accounts.buyer_account.tokens_purchased;
-            let total_after_purchase = current_purchased
-                .checked_add(token_amount)
-                .ok_or(ErrorCode::MathOverflow)?;
-            require!(
-                total_after_purchase <= sale.max_tokens_per_buyer,
-                ErrorCode::ExceedsPurchaseLimit
-            );
-        }
-
-        // Calculate payment amount and platform fee
-        let gross_payment = token_amount
-            .checked_mul(sale.price_per_token)
-            .ok_or(ErrorCode::MathOverflow)?;
-            
-        let platform_fee = if sale.platform_fee_bps > 0 {
-            gross_payment
-                .checked_mul(sale.platform_fee_bps as u64)
-                .ok_or(ErrorCode::MathOverflow)?
-                .checked_div(10000)
-                .ok_or(ErrorCode::MathOverflow)?
-        } else {
-            0
-        };
-        
-        let seller_payment = gross_payment
-            .checked_sub(platform_fee)
-            .ok_or(ErrorCode::MathOverflow)?;
+    /// Create the purchase tracker of `recipient`, paid for by someone buying on their behalf
+    pub fn create_buyer_account_for(ctx: Context<CreateBuyerAccountFor>) -> Result<()> {
+        let mut buyer_account = ctx.accounts.buyer_account.load_init()?;
+        buyer_account.buyer = ctx.accounts.recipient.key();
+        buyer_account.token_sale = ctx.accounts.token_sale.key();
+        buyer_account.bump = ctx.bumps.buyer_account;
+        buyer_account.rent_payer = ctx.accounts.payer.key();
+        buyer_account.version = BuyerAccount::VERSION;
 
-        // Transfer payment from buyer to seller
-        if seller_payment > 0 {
-            let payment_transfer_ctx = CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.buyer_payment_account.to_account_info(),
-                    to: ctx.accounts.seller_payment_account.to_account_info(),
-                    authority: ctx.accounts.buyer.to_account_info(),
-                },
-            );
-            token::transfer(payment_transfer_ctx, seller_payment)?;
-        }
+        msg!("Buyer account created for {} by {}",
+            ctx.accounts.recipient.key(), ctx.accounts.payer.key());
+        Ok(())
+    }
 
-        // Transfer platform fee if applicable
-        if platform_fee > 0 {
-            let fee_transfer_ctx = CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.buyer_payment_account.to_account_info(),
-                    to: ctx.accounts.platform_fee_account.to_account_info(),
-                    authority: ctx.accounts.buyer.to_account_info(),
-                },
-            );
-            token::transfer(fee_transfer_ctx, platform_fee)?;
-        }
+    /// Close a purchase tracker once the sale is over and nothing is left to claim
+    /// through it, returning the rent to whoever paid it
+    pub fn close_buyer_account(ctx: Context<CloseBuyerAccount>) -> Result<()> {
+        let sale = ctx.accounts.token_sale.load()?;
+        let buyer_account = ctx.accounts.buyer_account.load()?;
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(current_time > sale.sale_end_time || !sale.is_active(), ErrorCode::SaleNotEnded);
+        require!(!buyer_account.has_pending_claim(&sale), ErrorCode::BuyerAccountInUse);
 
-        // Transfer tokens from vault to buyer
-        let seeds = &[
-            b"token_sale",
-            sale.seller.as_ref(),
-            sale.token_mint.as_ref(),
-            &[sale.bump],
-        ];
-        let signer = &[&seeds[..]];
+        emit!(BuyerAccountClosed {
+            sale: ctx.accounts.token_sale.key(),
+            buyer: buyer_account.buyer,
+            rent_payer: ctx.accounts.rent_payer.key(),
+        });
+        msg!("Buyer account of {} closed, rent returned to {}",
+            buyer_account.buyer, ctx.accounts.rent_payer.key());
+        Ok(())
+    }
 
-        let token_transfer_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.token_vault.to_account_info(),
-                to: ctx.accounts.buyer_token_account.to_account_info(),
-                authority: ctx.accounts.token_sale.to_account_info(),
+    /// Buy tokens from the sale with comprehensive security checks
+    pub fn buy_tokens<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BuyTokens<'info>>,
+        token_amount: u64,           // How many tokens to buy
+    ) -> Result<()> {
+        let accounts = &ctx.accounts;
+        process_purchase(
+            Purchase {
+                payer: &accounts.buyer,
+                recipient: accounts.buyer.key(),
+                token_sale: &accounts.token_sale,
+                buyer_account: &accounts.buyer_account,
+                payer_account: None,
+                payer_payment_account: &accounts.buyer_payment_account,
+                seller_payment_account: &accounts.seller_payment_account,
+                platform_fee_account: &accounts.platform_fee_account,
+                recipient_token_account: &accounts.buyer_token_account,
+                token_vault: &accounts.token_vault,
+                proceeds_vault: accounts.proceeds_vault.as_ref(),
+                receipt_mint: accounts.receipt_mint.as_ref(),
+                deny_list: &accounts.deny_list,
+                deny_entries: &[&accounts.denied_buyer],
+                token_program: &accounts.token_program,
+                hook_accounts: ctx.remaining_accounts,
             },
-            signer,
-        );
-        token::transfer(token_transfer_ctx, token_amount)?;
-
-        // Update sale state
-        let sale = &mut ctx.accounts.token_sale;
-        sale.tokens_available = sale.tokens_available
-            .checked_sub(token_amount)
-            .ok_or(ErrorCode::MathOverflow)?;
-
-        // Update buyer tracking
-        let buyer_account = &mut ctx.accounts.buyer_account;
-        buyer_account.tokens_purchased = buyer_account.tokens_purchased
-            .checked_add(token_amount)
-            .ok_or(ErrorCode::MathOverflow)?;
+            token_amount,
+        )
+    }
 
-        msg!("Sold {} tokens for {} payment (fee: {})", token_amount, seller_payment, platform_fee);
-        Ok(())
+    /// Buy tokens for someone else: the payer pays, the recipient's tracker counts the
+    /// purchase against its caps and the recipient's token account receives the tokens.
+    /// The payer's own tracker holds a cooldown too, so it can't restart the recipient's
+    /// any faster than it could buy for itself.
+    pub fn buy_tokens_for<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BuyTokensFor<'info>>,
+        token_amount: u64,           // How many tokens to buy
+    ) -> Result<()> {
+        let accounts = &ctx.accounts;
+        process_purchase(
+            Purchase {
+                payer: &accounts.payer,
+                recipient: accounts.recipient.key(),
+                token_sale: &accounts.token_sale,
+                buyer_account: &accounts.buyer_account,
+                payer_account: Some(&accounts.payer_account),
+                payer_payment_account: &accounts.payer_payment_account,
+                seller_payment_account: &accounts.seller_payment_account,
+                platform_fee_account: &accounts.platform_fee_account,
+                recipient_token_account: &accounts.recipient_token_account,
+                token_vault: &accounts.token_vault,
+                proceeds_vault: accounts.proceeds_vault.as_ref(),
+                receipt_mint: accounts.receipt_mint.as_ref(),
+                deny_list: &accounts.deny_list,
+                deny_entries: &[&accounts.denied_payer, &accounts.denied_recipient],
+                token_program: &accounts.token_program,
+                hook_accounts: ctx.remaining_accounts,
+            },
+            token_amount,
+        )
     }
 
-    /// Cancel sale and return unsold tokens to seller
+    /// Cancel sale and return unsold tokens to seller. Once an overflow sale has ended,
+    /// the tokens its unclaimed deposits may still be allocated stay in the vault, and an
+    /// ended auction has to be cleared first.
     pub fn cancel_sale(ctx: Context<CancelSale>) -> Result<()> {
-        let sale = &ctx.accounts.token_sale;
-        require!(sale.is_active, ErrorCode::SaleNotActive);
+        let current_time = Clock::get()?.unix_timestamp;
+        let sale = ctx.accounts.token_sale.load()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
+
+        // An ended auction binds the seller: the winning bids are reserved by clearing it
+        // before the unsold rest can come back
+        if sale.is_auction() && current_time > sale.sale_end_time {
+            let auction = ctx.accounts.auction
+                .as_ref()
+                .ok_or(ErrorCode::AuctionRequired)?;
+            require!(auction.load()?.is_cleared(), ErrorCode::AuctionNotCleared);
+        }
+
+        let tokens_reserved = sale.overflow_reserved(current_time)?;
+        let tokens_returned = sale.tokens_available - tokens_reserved;
+        let (seller, token_mint, bump) = (sale.seller, sale.token_mint, sale.bump);
+        drop(sale);
 
         // Return remaining tokens to seller
-        if sale.tokens_available > 0 {
+        if tokens_returned > 0 {
             let seeds = &[
                 b"token_sale",
-                sale.seller.as_ref(),
-                sale.token_mint.as_ref(),
-                &[sale.bump],
+                seller.as_ref(),
+                token_mint.as_ref(),
+                &[bump],
             ];
             let signer = &[&seeds[..]];
 
@@ -211,13 +239,18 @@ pub mod escrow {
                 },
                 signer,
             );
-            token::transfer(return_ctx, sale.tokens_available)?;
+            token::transfer(return_ctx, tokens_returned)?;
         }
 
         // Update sale state
-        let sale = &mut ctx.accounts.token_sale;
-        sale.is_active = false;
-        sale.tokens_available = 0;
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        sale.is_active = 0;
+        sale.tokens_available = tokens_reserved;
+
+        emit!(SaleCancelled {
+            sale: ctx.accounts.token_sale.key(),
+            tokens_returned,
+        });
 
         msg!("Sale cancelled, tokens returned to seller");
         Ok(())
@@ -225,12 +258,12 @@ pub mod escrow {
 
     /// Emergency pause/unpause functionality (seller only)
     pub fn toggle_pause(ctx: Context<TogglePause>) -> Result<()> {
-        let sale = &mut ctx.accounts.token_sale;
-        require!(sale.is_active, ErrorCode::SaleNotActive);
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
         
-        sale.is_paused = !sale.is_paused;
+        sale.is_paused = !sale.is_paused() as u8;
         
-        msg!("Sale pause status changed to: {}", sale.is_paused);
+        msg!("Sale pause status changed to: {}", sale.is_paused());
         Ok(())
     }
 
@@ -241,12 +274,51 @@ pub mod escrow {
         new_sale_start_time: Option<i64>,
         new_sale_end_time: Option<i64>,
         new_max_tokens_per_buyer: Option<u64>,
+        new_min_sale_duration: Option<i64>,
+        new_max_sale_duration: Option<i64>,
     ) -> Result<()> {
-        let sale = &mut ctx.accounts.token_sale;
-        require!(sale.is_active, ErrorCode::SaleNotActive);
+        let sale_key = ctx.accounts.token_sale.key();
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
         
         let current_time = Clock::get()?.unix_timestamp;
-        require!(current_time < sale.sale_start_time, ErrorCode::SaleAlreadyStarted);
+        if current_time >= sale.sale_start_time {
+            // Once the sale is live only the price may change: by at most
+            // MAX_PRICE_CHANGE_BPS, announced PRICE_CHANGE_DELAY ahead
+            require!(
+                new_sale_start_time.is_none() && new_sale_end_time.is_none() && new_max_tokens_per_buyer.is_none()
+                    && new_min_sale_duration.is_none() && new_max_sale_duration.is_none(),
+                ErrorCode::SaleAlreadyStarted
+            );
+            let price = new_price_per_token.ok_or(ErrorCode::SaleAlreadyStarted)?;
+            require!(current_time <= sale.sale_end_time, ErrorCode::SaleEnded);
+            require!(price > 0, ErrorCode::InvalidPrice);
+            // Overflow deposits are sized and settled at the price they were made at
+            require!(!sale.is_overflow(), ErrorCode::OverflowIncompatible);
+
+            // A newer announcement replaces a pending one, measured from the price in effect
+            sale.apply_due_price_change(current_time);
+            let change = price.abs_diff(sale.price_per_token) as u128;
+            require!(
+                change * 10000 <= sale.price_per_token as u128 * MAX_PRICE_CHANGE_BPS as u128,
+                ErrorCode::PriceChangeTooLarge
+            );
+            let effective_time = current_time
+                .checked_add(PRICE_CHANGE_DELAY)
+                .ok_or(ErrorCode::MathOverflow)?;
+            sale.pending_price_per_token = price;
+            sale.price_change_time = effective_time;
+
+            emit!(PriceChangeScheduled {
+                sale: sale_key,
+                current_price: sale.price_per_token,
+                new_price: price,
+                effective_time,
+            });
+            msg!("Price change from {} to {} takes effect at {}",
+                sale.price_per_token, price, effective_time);
+            return Ok(());
+        }
 
         // Update price if provided
         if let Some(price) = new_price_per_token {
@@ -263,240 +335,5228 @@ pub mod escrow {
         // Update end time if provided
         if let Some(end_time) = new_sale_end_time {
             require!(end_time > sale.sale_start_time, ErrorCode::InvalidEndTime);
+            if let Some(first_milestone) = sale.milestone(0) {
+                require!(end_time < first_milestone.deadline, ErrorCode::InvalidMilestones);
+            }
             sale.sale_end_time = end_time;
         }
 
-        // Update purchase limit if provided
+        // Update purchase limit if provided; auction bids are not totalled per bidder
         if let Some(limit) = new_max_tokens_per_buyer {
+            require!(limit == 0 || !sale.is_auction(), ErrorCode::AuctionIncompatible);
             sale.max_tokens_per_buyer = limit;
         }
 
+        // Update the duration bounds if provided (0 = program default); the window
+        // already set, soft close included, must fit the new maximum
+        if new_min_sale_duration.is_some() || new_max_sale_duration.is_some() {
+            if let Some(duration) = new_min_sale_duration {
+                sale.min_sale_duration = duration;
+            }
+            if let Some(duration) = new_max_sale_duration {
+                sale.max_sale_duration = duration;
+            }
+            require!(
+                sale.min_sale_duration >= 0 && sale.max_sale_duration >= 0
+                    && sale.min_sale_duration() <= sale.max_sale_duration(),
+                ErrorCode::InvalidSaleDuration
+            );
+            let duration = sale.latest_end_time()
+                .checked_sub(sale.sale_start_time)
+                .ok_or(ErrorCode::MathOverflow)?;
+            require!(duration <= sale.max_sale_duration(), ErrorCode::SaleDurationTooLong);
+        }
+
         msg!("Sale parameters updated");
         Ok(())
     }
-}
 
-/// Account structure for token sale state
-#[account]
-#[derive(Default)]
-pub struct TokenSale {
-    pub seller: Pubkey,              // Who created the sale (32 bytes)
-    pub token_mint: Pubkey,          // Token being sold (32 bytes)
-    pub payment_mint: Pubkey,        // Payment token (32 bytes)
-    pub price_per_token: u64,        // Price in payment token lamports (8 bytes)
-    pub total_tokens: u64,           // Original token amount (8 bytes)
-    pub tokens_available: u64,       // Tokens left to sell (8 bytes)
-    pub sale_start_time: i64,        // Unix timestamp when sale starts (8 bytes)
-    pub sale_end_time: i64,          // Unix timestamp when sale ends (8 bytes)
-    pub max_tokens_per_buyer: u64,   // Maximum tokens one buyer can purchase (8 bytes)
-    pub platform_fee_bps: u16,       // Platform fee in basis points (2 bytes)
-    pub platform_fee_recipient: Pubkey, // Where platform fees go (32 bytes)
-    pub is_active: bool,             // Sale status (1 byte)
-    pub is_paused: bool,             // Emergency pause status (1 byte)
-    pub bump: u8,                    // PDA bump seed (1 byte)
-}
+    /// Move a sale's end time later, keeping the whole sale within its maximum duration (seller only)
+    pub fn extend_sale(ctx: Context<ChangeSaleWindow>, new_sale_end_time: i64) -> Result<()> {
+        let sale_key = ctx.accounts.token_sale.key();
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
 
-impl TokenSale {
-    pub const INIT_SPACE: usize = 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 2 + 32 + 1 + 1 + 1; // 181 bytes
-}
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(current_time <= sale.sale_end_time, ErrorCode::SaleEnded);
+        require!(new_sale_end_time > sale.sale_end_time, ErrorCode::InvalidEndTime);
+        let duration = new_sale_end_time
+            .checked_sub(sale.sale_start_time)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(duration <= sale.max_sale_duration(), ErrorCode::SaleDurationTooLong);
+        if let Some(first_milestone) = sale.milestone(0) {
+            require!(new_sale_end_time < first_milestone.deadline, ErrorCode::InvalidMilestones);
+        }
 
-/// Account to track individual buyer purchases
-#[account]
-#[derive(Default)]
-pub struct BuyerAccount {
-    pub buyer: Pubkey,              // Buyer's public key (32 bytes)
-    pub token_sale: Pubkey,         // Associated token sale (32 bytes)
-    pub tokens_purchased: u64,      // Total tokens purchased (8 bytes)
-    pub bump: u8,                   // PDA bump seed (1 byte)
-}
+        let previous_end_time = sale.sale_end_time;
+        sale.sale_end_time = new_sale_end_time;
 
-impl BuyerAccount {
-    pub const INIT_SPACE: usize = 32 + 32 + 8 + 1; // 73 bytes
-}
+        emit!(SaleExtended {
+            sale: sale_key,
+            previous_end_time,
+            new_end_time: new_sale_end_time,
+        });
+        msg!("Sale extended from {} to {}", previous_end_time, new_sale_end_time);
+        Ok(())
+    }
 
-/// Account validation for initializing a sale
-#[derive(Accounts)]
-pub struct InitializeSale<'info> {
-    #[account(mut)]
-    pub seller: Signer<'info>,
+    /// End a live sale now (seller only): allowed once it has run its minimum duration or
+    /// sold out. Unsold tokens stay in the vault until the seller cancels the sale.
+    pub fn close_sale_early(ctx: Context<ChangeSaleWindow>) -> Result<()> {
+        let sale_key = ctx.accounts.token_sale.key();
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
 
-    #[account(
-        init,
-        payer = seller,
-        space = 8 + TokenSale::INIT_SPACE,
-        seeds = [b"token_sale", seller.key().as_ref(), token_mint.key().as_ref()],
-        bump
-    )]
-    pub token_sale: Account<'info, TokenSale>,
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(current_time >= sale.sale_start_time, ErrorCode::SaleNotStarted);
+        require!(current_time <= sale.sale_end_time, ErrorCode::SaleEnded);
+        let elapsed = current_time
+            .checked_sub(sale.sale_start_time)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require!(
+            sale.tokens_available == 0 || elapsed >= sale.min_sale_duration(),
+            ErrorCode::EarlyCloseNotAllowed
+        );
 
-    pub token_mint: Account<'info, Mint>,
-    pub payment_mint: Account<'info, Mint>,
+        // Purchases accept the end second itself, so end the sale the second before
+        let previous_end_time = sale.sale_end_time;
+        sale.sale_end_time = current_time.checked_sub(1).ok_or(ErrorCode::MathOverflow)?;
 
-    #[account(
-        mut,
-        constraint = seller_token_account.mint == token_mint.key(),
-        constraint = seller_token_account.owner == seller.key()
-    )]
-    pub seller_token_account: Account<'info, TokenAccount>,
+        emit!(SaleClosedEarly {
+            sale: sale_key,
+            previous_end_time,
+            new_end_time: sale.sale_end_time,
+            tokens_available: sale.tokens_available,
+        });
+        msg!("Sale closed early at {}, {} tokens unsold", current_time, sale.tokens_available);
+        Ok(())
+    }
 
-    #[account(
-        init,
-        payer = seller,
-        token::mint = token_mint,
-        token::authority = token_sale,
-        seeds = [b"token_vault", token_sale.key().as_ref()],
-        bump
-    )]
-    pub token_vault: Account<'info, TokenAccount>,
+    /// Configure the anti-sniping soft close (seller only, before sale starts, all 0 = off).
+    /// Only fixed-price purchases extend a sale, so lotteries, overflow sales and auctions can't use it.
+    pub fn configure_soft_close(
+        ctx: Context<ChangeSaleWindow>,
+        window: i64,                    // Final seconds in which a purchase extends the sale
+        extension: i64,                 // Seconds each such purchase adds to the end time
+        ceiling: i64,                   // Latest end time the extensions may reach
+    ) -> Result<()> {
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
-}
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(current_time < sale.sale_start_time, ErrorCode::SaleAlreadyStarted);
 
-/// Account validation for creating buyer tracking account
-#[derive(Accounts)]
-pub struct CreateBuyerAccount<'info> {
-    #[account(mut)]
-    pub buyer: Signer<'info>,
+        if window != 0 || extension != 0 || ceiling != 0 {
+            // Tickets, deposits and bids never extend the sale
+            require!(
+                !sale.is_lottery() && !sale.is_overflow() && !sale.is_auction(),
+                ErrorCode::SoftCloseIncompatible
+            );
+            require!(window > 0 && extension > 0, ErrorCode::InvalidSoftClose);
+            require!(ceiling > sale.sale_end_time, ErrorCode::InvalidSoftClose);
+            let duration = ceiling
+                .checked_sub(sale.sale_start_time)
+                .ok_or(ErrorCode::MathOverflow)?;
+            require!(duration <= sale.max_sale_duration(), ErrorCode::SaleDurationTooLong);
+            if let Some(first_milestone) = sale.milestone(0) {
+                require!(ceiling < first_milestone.deadline, ErrorCode::InvalidMilestones);
+            }
+        }
 
-    pub token_sale: Account<'info, TokenSale>,
+        sale.soft_close_window = window;
+        sale.soft_close_extension = extension;
+        sale.soft_close_ceiling = ceiling;
 
-    #[account(
-        init,
-        payer = buyer,
-        space = 8 + BuyerAccount::INIT_SPACE,
-        seeds = [b"buyer", buyer.key().as_ref(), token_sale.key().as_ref()],
-        bump
-    )]
-    pub buyer_account: Account<'info, BuyerAccount>,
+        msg!("Soft close: purchases in the last {}s add {}s, up to {}", window, extension, ceiling);
+        Ok(())
+    }
 
-    pub system_program: Program<'info, System>,
-}
+    /// Configure anti-bot purchase throttles (seller only, 0 disables a throttle)
+    pub fn set_purchase_throttles(
+        ctx: Context<SetPurchaseThrottles>,
+        max_tokens_per_tx: Option<u64>,      // Maximum tokens in a single purchase
+        min_purchase_interval: Option<i64>,  // Seconds a buyer must wait between purchases
+        max_tokens_per_slot: Option<u64>,    // Maximum tokens sold across all buyers in one slot
+    ) -> Result<()> {
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
 
-/// Account validation for buying tokens
-#[derive(Accounts)]
-pub struct BuyTokens<'info> {
-    #[account(mut)]
-    pub buyer: Signer<'info>,
+        if let Some(limit) = max_tokens_per_tx {
+            sale.max_tokens_per_tx = limit;
+        }
 
-    #[account(
-        mut,
-        seeds = [b"token_sale", token_sale.seller.as_ref(), token_sale.token_mint.as_ref()],
-        bump = token_sale.bump
-    )]
-    pub token_sale: Account<'info, TokenSale>,
+        if let Some(interval) = min_purchase_interval {
+            require!(interval >= 0, ErrorCode::InvalidThrottleConfig);
+            sale.min_purchase_interval = interval;
+        }
 
-    #[account(
-        mut,
-        seeds = [b"buyer", buyer.key().as_ref(), token_sale.key().as_ref()],
-        bump = buyer_account.bump
-    )]
-    pub buyer_account: Account<'info, BuyerAccount>,
+        if let Some(limit) = max_tokens_per_slot {
+            sale.max_tokens_per_slot = limit;
+        }
 
-    #[account(
-        mut,
-        constraint = buyer_payment_account.mint == token_sale.payment_mint,
-        constraint = buyer_payment_account.owner == buyer.key()
-    )]
-    pub buyer_payment_account: Account<'info, TokenAccount>,
+        msg!("Purchase throttles updated: {} per tx, {}s between purchases, {} per slot",
+            sale.max_tokens_per_tx, sale.min_purchase_interval, sale.max_tokens_per_slot);
+        Ok(())
+    }
 
-    #[account(
-        mut,
-        constraint = seller_payment_account.mint == token_sale.payment_mint,
-        constraint = seller_payment_account.owner == token_sale.seller
-    )]
-    pub seller_payment_account: Account<'info, TokenAccount>,
+    /// Name the program `buy_tokens` calls after every purchase (seller only, once).
+    /// Purchases then pass the hook program first in the remaining accounts, followed by
+    /// the accounts the hook needs; a failing hook reverts the purchase.
+    pub fn set_purchase_hook(ctx: Context<SetPurchaseHook>) -> Result<()> {
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        let hook_program = ctx.accounts.hook_program.key();
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
+        require!(sale.purchase_hook().is_none(), ErrorCode::PurchaseHookAlreadySet);
+        require!(
+            ctx.accounts.hook_program.executable && hook_program != crate::ID,
+            ErrorCode::InvalidPurchaseHook
+        );
+        sale.purchase_hook = hook_program;
 
-    #[account(
-        mut,
-        constraint = platform_fee_account.mint == token_sale.payment_mint,
-        constraint = platform_fee_account.owner == token_sale.platform_fee_recipient
-    )]
-    pub platform_fee_account: Account<'info, TokenAccount>,
+        emit!(PurchaseHookSet {
+            sale: ctx.accounts.token_sale.key(),
+            hook_program,
+        });
+        msg!("Purchase hook set to {}", hook_program);
+        Ok(())
+    }
 
-    #[account(
-        mut,
-        constraint = buyer_token_account.mint == token_sale.token_mint,
-        constraint = buyer_token_account.owner == buyer.key()
-    )]
-    pub buyer_token_account: Account<'info, TokenAccount>,
+    /// Screen the sale's buyers against the deny list, or stop screening (seller only).
+    /// Sellers cannot stop while the deny list is required.
+    pub fn set_wallet_screening(ctx: Context<SetWalletScreening>, enabled: bool) -> Result<()> {
+        if !enabled {
+            let required = load_deny_list(&ctx.accounts.deny_list)?
+                .is_some_and(|deny_list| deny_list.required);
+            require!(!required, ErrorCode::DenyListRequired);
+        }
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
+        sale.screens_wallets = enabled as u8;
 
-    #[account(
-        mut,
-        seeds = [b"token_vault", token_sale.key().as_ref()],
-        bump
-    )]
-    pub token_vault: Account<'info, TokenAccount>,
+        emit!(WalletScreeningSet {
+            sale: ctx.accounts.token_sale.key(),
+            enabled,
+        });
+        msg!("Wallet screening {}", if enabled { "enabled" } else { "disabled" });
+        Ok(())
+    }
 
-    pub token_program: Program<'info, Token>,
-}
+    /// Set volume-tiered price breakpoints (seller only, before sale starts)
+    pub fn set_price_tiers(
+        ctx: Context<SetPriceTiers>,
+        price_tiers: Vec<PriceTier>,    // Ordered by tokens_sold_threshold, empty = flat price
+    ) -> Result<()> {
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
 
-/// Account validation for cancelling a sale
-#[derive(Accounts)]
-pub struct CancelSale<'info> {
-    #[account(
-        mut,
-        constraint = seller.key() == token_sale.seller
-    )]
-    pub seller: Signer<'info>,
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(current_time < sale.sale_start_time, ErrorCode::SaleAlreadyStarted);
+        require!(!sale.is_lottery(), ErrorCode::LotteryIncompatible);
+        require!(!sale.is_overflow(), ErrorCode::OverflowIncompatible);
+        require!(!sale.is_auction(), ErrorCode::AuctionIncompatible);
+        require!(price_tiers.len() <= MAX_PRICE_TIERS, ErrorCode::TooManyPriceTiers);
+
+        // Thresholds must be strictly increasing and fit within the sale
+        let mut previous_threshold = 0;
+        for tier in price_tiers.iter() {
+            require!(tier.price_per_token > 0, ErrorCode::InvalidPrice);
+            require!(
+                tier.tokens_sold_threshold > previous_threshold,
+                ErrorCode::InvalidPriceTiers
+            );
+            require!(
+                tier.tokens_sold_threshold <= sale.total_tokens,
+                ErrorCode::InvalidPriceTiers
+            );
+            previous_threshold = tier.tokens_sold_threshold;
+        }
+
+        msg!("Price tiers updated: {} tiers", price_tiers.len());
+        sale.set_price_tiers(&price_tiers);
+        Ok(())
+    }
+
+    /// Hold proceeds in a program vault and release them by milestone (seller only, before sale starts)
+    pub fn configure_milestones(
+        ctx: Context<ConfigureMilestones>,
+        milestones: Vec<Milestone>,     // Ordered by deadline, release_bps must sum to 10000
+        approver: Option<Pubkey>,       // Who can unlock milestones (None = buyer vote only)
+    ) -> Result<()> {
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
+        require!(sale.milestone_count == 0, ErrorCode::MilestonesAlreadyConfigured);
+
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(current_time < sale.sale_start_time, ErrorCode::SaleAlreadyStarted);
+        require!(!sale.is_lottery(), ErrorCode::LotteryIncompatible);
+        require!(!sale.is_overflow(), ErrorCode::OverflowIncompatible);
+        require!(!sale.is_auction(), ErrorCode::AuctionIncompatible);
+        require!(!milestones.is_empty(), ErrorCode::InvalidMilestones);
+        require!(milestones.len() <= MAX_MILESTONES, ErrorCode::TooManyMilestones);
+
+        // Deadlines must follow the sale end (soft close included) and each other; shares
+        // must cover all proceeds
+        let mut previous_deadline = sale.latest_end_time();
+        let mut total_bps: u32 = 0;
+        for milestone in milestones.iter() {
+            require!(milestone.release_bps > 0, ErrorCode::InvalidMilestones);
+            require!(milestone.deadline > previous_deadline, ErrorCode::InvalidMilestones);
+            previous_deadline = milestone.deadline;
+            total_bps += milestone.release_bps as u32;
+        }
+        require!(total_bps == 10000, ErrorCode::InvalidMilestones);
+
+        msg!("Milestones configured: {} tranches, approver {:?}", milestones.len(), approver);
+        sale.set_milestones(&milestones);
+        sale.milestone_approver = approver.unwrap_or_default();
+        Ok(())
+    }
+
+    /// Unlock the current milestone (designated approver only)
+    pub fn approve_milestone(ctx: Context<ApproveMilestone>) -> Result<()> {
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        require!(
+            sale.milestone_approver() == Some(ctx.accounts.approver.key()),
+            ErrorCode::UnauthorizedMilestoneApprover
+        );
+
+        let current_time = Clock::get()?.unix_timestamp;
+        sale.require_pending_milestone(current_time)?;
+
+        sale.milestone_approved = 1;
+
+        msg!("Milestone {} approved by {}", sale.current_milestone, ctx.accounts.approver.key());
+        Ok(())
+    }
+
+    /// Vote to unlock the current milestone, weighted by tokens purchased (buyers only)
+    pub fn vote_milestone(ctx: Context<VoteMilestone>) -> Result<()> {
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        let current_time = Clock::get()?.unix_timestamp;
+        sale.require_pending_milestone(current_time)?;
+
+        // Buyers record the milestone they voted on as index + 1, so 0 means never voted
+        let mut buyer_account = ctx.accounts.buyer_account.load_mut()?;
+        require!(
+            buyer_account.last_voted_milestone <= sale.current_milestone,
+            ErrorCode::AlreadyVoted
+        );
+        require!(buyer_account.tokens_purchased > 0, ErrorCode::InvalidTokenAmount);
+        buyer_account.last_voted_milestone = sale.current_milestone + 1;
+
+        sale.milestone_votes = sale.milestone_votes
+            .checked_add(buyer_account.tokens_purchased)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        // A simple majority of the tokens sold unlocks the milestone
+        if sale.milestone_votes > sale.milestone_vote_supply / 2 {
+            sale.milestone_approved = 1;
+        }
+
+        msg!("Milestone {} vote: {} of {} tokens", sale.current_milestone,
+            sale.milestone_votes, sale.milestone_vote_supply);
+        Ok(())
+    }
+
+    /// Pay the seller the tranche of an approved milestone (anyone can call)
+    pub fn release_milestone(ctx: Context<ReleaseMilestone>) -> Result<()> {
+        let sale = ctx.accounts.token_sale.load()?;
+        require!(sale.milestone_approved(), ErrorCode::MilestoneNotApproved);
+
+        // Tranches are computed cumulatively so the last one releases any rounding dust
+        let released_bps: u64 = sale.milestone_release_bps[..=sale.current_milestone as usize]
+            .iter()
+            .map(|release_bps| *release_bps as u64)
+            .sum();
+        let releasable_total = (sale.proceeds_escrowed as u128)
+            .checked_mul(released_bps as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(10000)
+            .ok_or(ErrorCode::MathOverflow)? as u64;
+        let tranche = releasable_total
+            .checked_sub(sale.proceeds_released)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let (seller, token_mint, bump) = (sale.seller, sale.token_mint, sale.bump);
+        drop(sale);
+
+        if tranche > 0 {
+            let seeds = &[
+                b"token_sale",
+                seller.as_ref(),
+                token_mint.as_ref(),
+                &[bump],
+            ];
+            let signer = &[&seeds[..]];
+
+            let release_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.proceeds_vault.to_account_info(),
+                    to: ctx.accounts.seller_payment_account.to_account_info(),
+                    authority: ctx.accounts.token_sale.to_account_info(),
+                },
+                signer,
+            );
+            token::transfer(release_ctx, tranche)?;
+        }
+
+        // Advance to the next milestone
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        sale.proceeds_released = releasable_total;
+        sale.current_milestone += 1;
+        sale.milestone_approved = 0;
+        sale.milestone_votes = 0;
+
+        msg!("Milestone {} released: {} to seller", sale.current_milestone - 1, tranche);
+        Ok(())
+    }
+
+    /// Claim a pro-rata refund of unreleased proceeds after a missed milestone deadline (buyers only)
+    pub fn claim_milestone_refund(ctx: Context<ClaimMilestoneRefund>) -> Result<()> {
+        let sale = ctx.accounts.token_sale.load()?;
+        let current_time = Clock::get()?.unix_timestamp;
+
+        // The first claim after a missed deadline marks the sale as failed
+        if !sale.milestones_failed() {
+            let milestone = sale.milestone(sale.current_milestone)
+                .ok_or(ErrorCode::NoPendingMilestone)?;
+            require!(
+                !sale.milestone_approved() && current_time > milestone.deadline,
+                ErrorCode::MilestoneDeadlineNotMissed
+            );
+        }
+
+        let buyer_account = ctx.accounts.buyer_account.load()?;
+        require!(!buyer_account.refund_claimed(), ErrorCode::RefundAlreadyClaimed);
+
+        // Refund share is the buyer's contribution over all escrowed proceeds
+        let unreleased = sale.proceeds_escrowed
+            .checked_sub(sale.proceeds_released)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let refund = (unreleased as u128)
+            .checked_mul(buyer_account.payment_escrowed as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(sale.proceeds_escrowed as u128)
+            .ok_or(ErrorCode::MathOverflow)? as u64;
+        let (seller, token_mint, bump) = (sale.seller, sale.token_mint, sale.bump);
+        drop(sale);
+        drop(buyer_account);
+
+        if refund > 0 {
+            let seeds = &[
+                b"token_sale",
+                seller.as_ref(),
+                token_mint.as_ref(),
+                &[bump],
+            ];
+            let signer = &[&seeds[..]];
+
+            let refund_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.proceeds_vault.to_account_info(),
+                    to: ctx.accounts.buyer_payment_account.to_account_info(),
+                    authority: ctx.accounts.token_sale.to_account_info(),
+                },
+                signer,
+            );
+            token::transfer(refund_ctx, refund)?;
+        }
+
+        ctx.accounts.token_sale.load_mut()?.milestones_failed = 1;
+        ctx.accounts.buyer_account.load_mut()?.refund_claimed = 1;
+
+        msg!("Milestone refund of {} paid to {}", refund, ctx.accounts.buyer.key());
+        Ok(())
+    }
+
+    /// Lock purchased tokens in the vault until `unlock_time` (seller only, before sale
+    /// starts). Purchases then pay out transferable receipts, minted 1:1 by the sale, and
+    /// whoever holds them redeems the tokens once the lock and the sale are over.
+    pub fn configure_token_lock(
+        ctx: Context<ConfigureTokenLock>,
+        unlock_time: i64,               // Unix timestamp from which receipts redeem
+    ) -> Result<()> {
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
+
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(current_time < sale.sale_start_time, ErrorCode::SaleAlreadyStarted);
+        require!(
+            !sale.is_lottery() && !sale.is_overflow() && !sale.is_auction(),
+            ErrorCode::TokenLockIncompatible
+        );
+        require!(unlock_time >= sale.latest_end_time(), ErrorCode::InvalidUnlockTime);
+        sale.unlock_time = unlock_time;
+        sale.receipt_mint = ctx.accounts.receipt_mint.key();
+
+        msg!("Tokens locked until {}, receipts minted as {}", unlock_time, sale.receipt_mint);
+        Ok(())
+    }
+
+    /// Burn allocation receipts for the tokens they stand for (any holder, after the
+    /// unlock time and the end of the sale)
+    pub fn redeem_receipts(ctx: Context<RedeemReceipts>, amount: u64) -> Result<()> {
+        let sale = ctx.accounts.token_sale.load()?;
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(sale.is_locked(), ErrorCode::NotLockedSale);
+        require!(
+            current_time >= sale.unlock_time
+                && (current_time > sale.sale_end_time || !sale.is_active()),
+            ErrorCode::TokensLocked
+        );
+        require!(amount > 0, ErrorCode::InvalidTokenAmount);
+        let (seller, token_mint, bump) = (sale.seller, sale.token_mint, sale.bump);
+        drop(sale);
+
+        let burn_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Burn {
+                mint: ctx.accounts.receipt_mint.to_account_info(),
+                from: ctx.accounts.holder_receipt_account.to_account_info(),
+                authority: ctx.accounts.holder.to_account_info(),
+            },
+        );
+        token::burn(burn_ctx, amount)?;
+
+        let seeds = &[
+            b"token_sale",
+            seller.as_ref(),
+            token_mint.as_ref(),
+            &[bump],
+        ];
+        let signer = &[&seeds[..]];
+        let transfer_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.token_vault.to_account_info(),
+                to: ctx.accounts.holder_token_account.to_account_info(),
+                authority: ctx.accounts.token_sale.to_account_info(),
+            },
+            signer,
+        );
+        token::transfer(transfer_ctx, amount)?;
+
+        emit!(ReceiptsRedeemed {
+            sale: ctx.accounts.token_sale.key(),
+            holder: ctx.accounts.holder.key(),
+            amount,
+        });
+        msg!("Redeemed {} receipts for tokens", amount);
+        Ok(())
+    }
+
+    /// Allocate the sale by lottery instead of first come, first served (seller only,
+    /// before sale starts). During the sale buyers register for one ticket each, paying
+    /// for `ticket_tokens` up front; once it ends the draw picks as many winners as the
+    /// supply covers. The randomness is the preimage of `seed_commitment`, revealed by
+    /// `revealer`, or a slot hash fixed after the sale ends when there is no revealer or
+    /// they miss LOTTERY_REVEAL_PERIOD.
+    pub fn configure_lottery(
+        ctx: Context<ConfigureLottery>,
+        ticket_tokens: u64,                   // Tokens allocated to each winning ticket
+        seed_commitment: Option<[u8; 32]>,    // sha256 of the revealer's seed
+        revealer: Option<Pubkey>,             // Who reveals the seed (None = slot hash draw)
+    ) -> Result<()> {
+        let sale_key = ctx.accounts.token_sale.key();
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
+
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(current_time < sale.sale_start_time, ErrorCode::SaleAlreadyStarted);
+        require!(
+            sale.price_tier_count == 0 && sale.milestone_count == 0 && !sale.is_locked(),
+            ErrorCode::LotteryIncompatible
+        );
+        require!(!sale.is_overflow(), ErrorCode::OverflowIncompatible);
+        require!(!sale.is_auction(), ErrorCode::AuctionIncompatible);
+        require!(sale.soft_close_window == 0, ErrorCode::SoftCloseIncompatible);
+        require!(
+            ticket_tokens > 0 && ticket_tokens <= sale.tokens_available,
+            ErrorCode::InvalidTokenAmount
+        );
+        require!(seed_commitment.is_some() == revealer.is_some(), ErrorCode::InvalidLotteryConfig);
+        sale.is_lottery = 1;
+
+        let mut lottery = ctx.accounts.lottery.load_init()?;
+        lottery.token_sale = sale_key;
+        lottery.revealer = revealer.unwrap_or_default();
+        lottery.seed_commitment = seed_commitment.unwrap_or_default();
+        lottery.ticket_tokens = ticket_tokens;
+        lottery.bump = ctx.bumps.lottery;
+        lottery.version = Lottery::VERSION;
+
+        msg!("Lottery configured: {} tokens per ticket, revealer {:?}", ticket_tokens, revealer);
+        Ok(())
+    }
+
+    /// Register for one lottery ticket, depositing its price at the current sale price
+    pub fn register_lottery_ticket(ctx: Context<RegisterLotteryTicket>) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        ctx.accounts.token_sale.load_mut()?.apply_due_price_change(current_time);
+
+        let sale = ctx.accounts.token_sale.load()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
+        require!(!sale.is_paused(), ErrorCode::SalePaused);
+        require!(current_time >= sale.sale_start_time, ErrorCode::SaleNotStarted);
+        require!(current_time <= sale.sale_end_time, ErrorCode::SaleEnded);
+        screen_wallets(&sale, &ctx.accounts.deny_list, &[&ctx.accounts.denied_buyer])?;
+
+        let mut lottery = ctx.accounts.lottery.load_mut()?;
+        let deposit = lottery.ticket_tokens
+            .checked_mul(sale.price_per_token)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let index = lottery.tickets;
+        lottery.tickets = index.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        drop(sale);
+        drop(lottery);
+
+        let deposit_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.buyer_payment_account.to_account_info(),
+                to: ctx.accounts.lottery_vault.to_account_info(),
+                authority: ctx.accounts.buyer.to_account_info(),
+            },
+        );
+        token::transfer(deposit_ctx, deposit)?;
+
+        let mut ticket = ctx.accounts.ticket.load_init()?;
+        ticket.buyer = ctx.accounts.buyer.key();
+        ticket.token_sale = ctx.accounts.token_sale.key();
+        ticket.index = index;
+        ticket.deposit = deposit;
+        ticket.bump = ctx.bumps.ticket;
+        ticket.rent_payer = ctx.accounts.rent_payer.key();
+        ticket.version = LotteryTicket::VERSION;
+
+        emit!(LotteryTicketRegistered {
+            sale: ctx.accounts.token_sale.key(),
+            buyer: ctx.accounts.buyer.key(),
+            index,
+            deposit,
+        });
+        msg!("Lottery ticket {} registered, {} deposited", index, deposit);
+        Ok(())
+    }
+
+    /// Reveal the committed seed and draw the winners (revealer only), within
+    /// LOTTERY_REVEAL_PERIOD of the sale's end. A late revealer could otherwise wait for the
+    /// slot hash fallback and reveal only if its seed draws better.
+    pub fn reveal_lottery_seed(ctx: Context<RevealLotterySeed>, seed: [u8; 32]) -> Result<()> {
+        {
+            let sale = ctx.accounts.token_sale.load()?;
+            let lottery = ctx.accounts.lottery.load()?;
+            let current_time = Clock::get()?.unix_timestamp;
+            require!(sale.is_active(), ErrorCode::SaleNotActive);
+            require!(current_time > sale.sale_end_time, ErrorCode::SaleNotEnded);
+            require!(!lottery.is_drawn(), ErrorCode::LotteryAlreadyDrawn);
+            let reveal_deadline = sale.sale_end_time
+                .checked_add(LOTTERY_REVEAL_PERIOD)
+                .ok_or(ErrorCode::MathOverflow)?;
+            require!(
+                current_time <= reveal_deadline && lottery.draw_slot == 0,
+                ErrorCode::LotteryRevealExpired
+            );
+            require!(
+                anchor_lang::solana_program::hash::hash(&seed).to_bytes() == lottery.seed_commitment,
+                ErrorCode::LotterySeedMismatch
+            );
+        }
+
+        finish_lottery_draw(&ctx.accounts.token_sale, &ctx.accounts.lottery, seed)
+    }
+
+    /// Pick the future slot whose hash will seed the draw (anyone, after the sale ends;
+    /// with a revealer only once LOTTERY_REVEAL_PERIOD has passed without a reveal)
+    pub fn request_lottery_draw(ctx: Context<DrawLottery>) -> Result<()> {
+        let clock = Clock::get()?;
+        let sale = ctx.accounts.token_sale.load()?;
+        let mut lottery = ctx.accounts.lottery.load_mut()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
+        require!(clock.unix_timestamp > sale.sale_end_time, ErrorCode::SaleNotEnded);
+        require!(!lottery.is_drawn(), ErrorCode::LotteryAlreadyDrawn);
+        if lottery.has_revealer() {
+            let reveal_deadline = sale.sale_end_time
+                .checked_add(LOTTERY_REVEAL_PERIOD)
+                .ok_or(ErrorCode::MathOverflow)?;
+            require!(clock.unix_timestamp > reveal_deadline, ErrorCode::LotteryRevealPending);
+        }
+
+        // A requested slot can be replaced only once its hash has left the sysvar
+        require!(
+            lottery.draw_slot == 0
+                || clock.slot > lottery.draw_slot.saturating_add(MAX_SLOT_HASH_AGE),
+            ErrorCode::LotteryDrawRequested
+        );
+        lottery.draw_slot = clock.slot
+            .checked_add(LOTTERY_DRAW_DELAY_SLOTS)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        msg!("Lottery will be drawn from the hash of slot {}", lottery.draw_slot);
+        Ok(())
+    }
+
+    /// Draw the winners from the requested slot's hash (anyone, once that slot has passed)
+    pub fn draw_lottery(ctx: Context<DrawLottery>) -> Result<()> {
+        let seed = {
+            let lottery = ctx.accounts.lottery.load()?;
+            require!(!lottery.is_drawn(), ErrorCode::LotteryAlreadyDrawn);
+            require!(
+                lottery.draw_slot > 0 && Clock::get()?.slot > lottery.draw_slot,
+                ErrorCode::LotteryDrawSlotNotReached
+            );
+            let slot_hash = recent_slot_hash(&ctx.accounts.slot_hashes, lottery.draw_slot)?
+                .ok_or(ErrorCode::LotteryDrawSlotExpired)?;
+
+            // Mixing in the sale keeps lotteries drawn from the same slot independent
+            let sale_key = ctx.accounts.token_sale.key();
+            anchor_lang::solana_program::hash::hashv(&[&slot_hash, sale_key.as_ref()]).to_bytes()
+        };
+
+        finish_lottery_draw(&ctx.accounts.token_sale, &ctx.accounts.lottery, seed)
+    }
+
+    /// Settle a lottery ticket: a winner receives `ticket_tokens` and their deposit pays
+    /// the seller and platform fee, anyone else gets the deposit back. Every ticket is
+    /// refunded if the sale is cancelled before the draw.
+    pub fn claim_lottery_ticket(ctx: Context<ClaimLotteryTicket>) -> Result<()> {
+        let sale = ctx.accounts.token_sale.load()?;
+        let lottery = ctx.accounts.lottery.load()?;
+        let ticket = ctx.accounts.ticket.load()?;
+        require!(!ticket.claimed(), ErrorCode::LotteryTicketClaimed);
+        require!(lottery.is_drawn() || !sale.is_active(), ErrorCode::LotteryNotDrawn);
+
+        let won = lottery.is_winner(ticket.index);
+        let deposit = ticket.deposit;
+        let token_amount = if won { lottery.ticket_tokens } else { 0 };
+        let platform_fee = if won {
+            (deposit as u128 * sale.platform_fee_bps as u128 / 10000) as u64
+        } else {
+            0
+        };
+        let seller_payment = if won { deposit - platform_fee } else { 0 };
+        let index = ticket.index;
+        let (seller, token_mint, bump) = (sale.seller, sale.token_mint, sale.bump);
+        let platform_fee_recipient = sale.platform_fee_recipient;
+        drop(sale);
+        drop(lottery);
+        drop(ticket);
+
+        let seeds = &[
+            b"token_sale",
+            seller.as_ref(),
+            token_mint.as_ref(),
+            &[bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        // Winners' deposits go to the seller and fee recipient, the rest back to the buyer
+        let payouts = if won {
+            [
+                (ctx.accounts.seller_payment_account.to_account_info(), seller_payment),
+                (ctx.accounts.platform_fee_account.to_account_info(), platform_fee),
+            ]
+        } else {
+            [
+                (ctx.accounts.buyer_payment_account.to_account_info(), deposit),
+                (ctx.accounts.platform_fee_account.to_account_info(), 0),
+            ]
+        };
+        for (destination, amount) in payouts {
+            if amount > 0 {
+                let payout_ctx = CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.lottery_vault.to_account_info(),
+                        to: destination,
+                        authority: ctx.accounts.token_sale.to_account_info(),
+                    },
+                    signer,
+                );
+                token::transfer(payout_ctx, amount)?;
+            }
+        }
+
+        if token_amount > 0 {
+            let token_transfer_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.token_vault.to_account_info(),
+                    to: ctx.accounts.buyer_token_account.to_account_info(),
+                    authority: ctx.accounts.token_sale.to_account_info(),
+                },
+                signer,
+            );
+            token::transfer(token_transfer_ctx, token_amount)?;
+        }
+        ctx.accounts.ticket.load_mut()?.claimed = 1;
+
+        let current_time = Clock::get()?.unix_timestamp;
+        if won {
+            emit!(TokensPurchased {
+                sale: ctx.accounts.token_sale.key(),
+                buyer: ctx.accounts.buyer.key(),
+                token_amount,
+                seller_payment,
+                platform_fee,
+                platform_fee_recipient,
+                timestamp: current_time,
+            });
+        }
+        emit!(LotteryTicketClaimed {
+            sale: ctx.accounts.token_sale.key(),
+            buyer: ctx.accounts.buyer.key(),
+            index,
+            won,
+            token_amount,
+            refund: if won { 0 } else { deposit },
+        });
+        msg!("Lottery ticket {} claimed: {}", index, if won { "won" } else { "refunded" });
+        Ok(())
+    }
+
+    /// Close a claimed lottery ticket, returning the rent to whoever paid it
+    pub fn close_lottery_ticket(ctx: Context<CloseLotteryTicket>) -> Result<()> {
+        let ticket = ctx.accounts.ticket.load()?;
+        require!(ticket.claimed(), ErrorCode::LotteryTicketNotClaimed);
+
+        msg!("Lottery ticket {} closed, rent returned to {}",
+            ticket.index, ctx.accounts.rent_payer.key());
+        Ok(())
+    }
+
+    /// Allocate the sale pro rata instead of first come, first served (seller only, before
+    /// sale starts). Buyers deposit any amount of payment during the sale; once it ends each
+    /// deposit is allocated `deposit * total_tokens / total_deposits` tokens, capped at what
+    /// it pays for, and `claim_overflow` refunds the rest.
+    pub fn configure_overflow(ctx: Context<ConfigureOverflow>) -> Result<()> {
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
+
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(current_time < sale.sale_start_time, ErrorCode::SaleAlreadyStarted);
+        require!(
+            sale.price_tier_count == 0
+                && sale.milestone_count == 0
+                && !sale.is_lottery()
+                && !sale.is_auction()
+                && !sale.is_locked(),
+            ErrorCode::OverflowIncompatible
+        );
+        require!(sale.soft_close_window == 0, ErrorCode::SoftCloseIncompatible);
+        sale.is_overflow = 1;
+
+        msg!("Overflow allocation configured for {} tokens", sale.total_tokens);
+        Ok(())
+    }
+
+    /// Deposit payment into an overflow sale, adding to any earlier deposit
+    pub fn deposit_overflow(
+        ctx: Context<DepositOverflow>,
+        amount: u64,                    // Payment to deposit, in payment token lamports
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        {
+            let sale = ctx.accounts.token_sale.load()?;
+            require!(sale.is_overflow(), ErrorCode::NotOverflowSale);
+            require!(sale.is_active(), ErrorCode::SaleNotActive);
+            require!(!sale.is_paused(), ErrorCode::SalePaused);
+            require!(current_time >= sale.sale_start_time, ErrorCode::SaleNotStarted);
+            require!(current_time <= sale.sale_end_time, ErrorCode::SaleEnded);
+            require!(amount > 0, ErrorCode::InvalidTokenAmount);
+            screen_wallets(&sale, &ctx.accounts.deny_list, &[&ctx.accounts.denied_buyer])?;
+        }
+
+        let deposit_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.buyer_payment_account.to_account_info(),
+                to: ctx.accounts.deposit_vault.to_account_info(),
+                authority: ctx.accounts.buyer.to_account_info(),
+            },
+        );
+        token::transfer(deposit_ctx, amount)?;
+
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        let mut buyer_account = ctx.accounts.buyer_account.load_mut()?;
+        sale.total_deposits = sale.total_deposits
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        buyer_account.overflow_deposit = buyer_account.overflow_deposit
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(OverflowDeposited {
+            sale: ctx.accounts.token_sale.key(),
+            buyer: ctx.accounts.buyer.key(),
+            amount,
+            total_deposits: sale.total_deposits,
+        });
+        msg!("Deposited {}, {} in total", amount, buyer_account.overflow_deposit);
+        Ok(())
+    }
+
+    /// Settle an overflow deposit after the sale ends: the buyer receives their allocation
+    /// and the unspent deposit in one go, and the allocation's cost pays the seller and
+    /// platform fee. A sale cancelled before it ended refunds every deposit in full.
+    pub fn claim_overflow(ctx: Context<ClaimOverflow>) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        let mut buyer_account = ctx.accounts.buyer_account.load_mut()?;
+        require!(sale.is_overflow(), ErrorCode::NotOverflowSale);
+        require!(current_time > sale.sale_end_time || !sale.is_active(), ErrorCode::SaleNotEnded);
+        require!(buyer_account.overflow_deposit > 0, ErrorCode::NoOverflowDeposit);
+        require!(!buyer_account.overflow_claimed(), ErrorCode::OverflowAlreadyClaimed);
+
+        // A cancelled sale keeps only what the unclaimed deposits are owed, none at all
+        // when it was cancelled before the end
+        let deposit = buyer_account.overflow_deposit;
+        let token_amount = sale.overflow_allocation(deposit)?.min(sale.tokens_available);
+        let cost = token_amount
+            .checked_mul(sale.price_per_token)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let platform_fee = (cost as u128 * sale.platform_fee_bps as u128 / 10000) as u64;
+        let seller_payment = cost - platform_fee;
+        let refund = deposit - cost;
+
+        sale.tokens_available -= token_amount;
+        sale.deposits_claimed = sale.deposits_claimed
+            .checked_add(deposit)
+            .ok_or(ErrorCode::MathOverflow)?;
+        buyer_account.tokens_purchased = buyer_account.tokens_purchased
+            .checked_add(token_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        buyer_account.last_purchase_time = current_time;
+        buyer_account.overflow_claimed = 1;
+        let (seller, token_mint, bump) = (sale.seller, sale.token_mint, sale.bump);
+        let platform_fee_recipient = sale.platform_fee_recipient;
+        drop(sale);
+        drop(buyer_account);
+
+        let seeds = &[
+            b"token_sale",
+            seller.as_ref(),
+            token_mint.as_ref(),
+            &[bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        let payouts = [
+            (ctx.accounts.seller_payment_account.to_account_info(), seller_payment),
+            (ctx.accounts.platform_fee_account.to_account_info(), platform_fee),
+            (ctx.accounts.buyer_payment_account.to_account_info(), refund),
+        ];
+        for (destination, amount) in payouts {
+            if amount > 0 {
+                let payout_ctx = CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.deposit_vault.to_account_info(),
+                        to: destination,
+                        authority: ctx.accounts.token_sale.to_account_info(),
+                    },
+                    signer,
+                );
+                token::transfer(payout_ctx, amount)?;
+            }
+        }
+
+        if token_amount > 0 {
+            let token_transfer_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.token_vault.to_account_info(),
+                    to: ctx.accounts.buyer_token_account.to_account_info(),
+                    authority: ctx.accounts.token_sale.to_account_info(),
+                },
+                signer,
+            );
+            token::transfer(token_transfer_ctx, token_amount)?;
+
+            emit!(TokensPurchased {
+                sale: ctx.accounts.token_sale.key(),
+                buyer: ctx.accounts.buyer.key(),
+                token_amount,
+                seller_payment,
+                platform_fee,
+                platform_fee_recipient,
+                timestamp: current_time,
+            });
+        }
+        emit!(OverflowClaimed {
+            sale: ctx.accounts.token_sale.key(),
+            buyer: ctx.accounts.buyer.key(),
+            deposit,
+            token_amount,
+            refund,
+        });
+        msg!("Overflow claim: {} tokens for {}, {} refunded", token_amount, cost, refund);
+        Ok(())
+    }
+
+    /// Sell the tokens by uniform-price batch auction (seller only, before sale starts).
+    /// Bids name a quantity and a maximum price on the ladder `min_price + level *
+    /// tick_size`; after the sale ends everyone who won pays the single clearing price.
+    pub fn configure_auction(
+        ctx: Context<ConfigureAuction>,
+        min_price: u64,                 // Lowest bid price, the seller's reserve
+        tick_size: u64,                 // Step between bid prices
+        price_levels: u8,               // Bid prices on the ladder, at most MAX_AUCTION_PRICE_LEVELS
+    ) -> Result<()> {
+        let sale_key = ctx.accounts.token_sale.key();
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
+
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(current_time < sale.sale_start_time, ErrorCode::SaleAlreadyStarted);
+        require!(
+            sale.price_tier_count == 0
+                && sale.milestone_count == 0
+                && sale.max_tokens_per_buyer == 0
+                && !sale.is_lottery()
+                && !sale.is_overflow()
+                && !sale.is_locked(),
+            ErrorCode::AuctionIncompatible
+        );
+        require!(sale.soft_close_window == 0, ErrorCode::SoftCloseIncompatible);
+        require!(min_price > 0, ErrorCode::InvalidPrice);
+        require!(
+            price_levels > 0 && price_levels as usize <= MAX_AUCTION_PRICE_LEVELS,
+            ErrorCode::InvalidAuctionConfig
+        );
+        require!(price_levels == 1 || tick_size > 0, ErrorCode::InvalidAuctionConfig);
+        // The top of the ladder has to be payable for the whole supply
+        let max_price = tick_size
+            .checked_mul(price_levels as u64 - 1)
+            .and_then(|step| step.checked_add(min_price))
+            .ok_or(ErrorCode::InvalidAuctionConfig)?;
+        max_price
+            .checked_mul(sale.total_tokens)
+            .ok_or(ErrorCode::InvalidAuctionConfig)?;
+        sale.is_auction = 1;
+
+        let mut auction = ctx.accounts.auction.load_init()?;
+        auction.token_sale = sale_key;
+        auction.min_price = min_price;
+        auction.tick_size = tick_size;
+        auction.price_levels = price_levels;
+        auction.bump = ctx.bumps.auction;
+        auction.version = Auction::VERSION;
+
+        msg!("Auction configured: {} price levels from {} in steps of {}",
+            price_levels, min_price, tick_size);
+        Ok(())
+    }
+
+    /// Seal an auction's bids (seller, before the sale starts): bidders commit a hash and a
+    /// fixed collateral during the sale, and reveal the bid within `reveal_period` after it
+    /// ends. Bids never revealed forfeit `forfeit_bps` of their collateral to the seller.
+    pub fn configure_sealed_bids(
+        ctx: Context<ConfigureSealedBids>,
+        collateral: u64,                // Deposit committed with each sealed bid
+        reveal_period: i64,             // Seconds after the sale ends for revealing
+        forfeit_bps: u16,               // Share of the collateral an unrevealed bid loses
+    ) -> Result<()> {
+        let sale = ctx.accounts.token_sale.load()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
+        require!(
+            Clock::get()?.unix_timestamp < sale.sale_start_time,
+            ErrorCode::SaleAlreadyStarted
+        );
+        require!(collateral > 0, ErrorCode::InvalidSealedBidConfig);
+        require!(
+            reveal_period > 0 && reveal_period <= sale.max_sale_duration(),
+            ErrorCode::InvalidSealedBidConfig
+        );
+        // Without a forfeit, committing and never revealing would cost nothing
+        require!(forfeit_bps > 0 && forfeit_bps <= 10000, ErrorCode::InvalidSealedBidConfig);
+
+        let mut auction = ctx.accounts.auction.load_mut()?;
+        auction.is_sealed = 1;
+        auction.collateral = collateral;
+        auction.reveal_period = reveal_period;
+        auction.forfeit_bps = forfeit_bps;
+
+        msg!("Sealed bids: {} collateral, {}s to reveal, {} bps forfeited",
+            collateral, reveal_period, forfeit_bps);
+        Ok(())
+    }
+
+    /// Bid for `quantity` tokens at up to `max_price` each, depositing the full amount
+    pub fn submit_bid(
+        ctx: Context<SubmitBid>,
+        bid_id: u64,                    // Bidder-chosen identifier, allows several bids
+        quantity: u64,                  // Tokens wanted
+        max_price: u64,                 // Highest price per token, a price on the auction ladder
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let sale = ctx.accounts.token_sale.load()?;
+        require!(sale.is_auction(), ErrorCode::NotAuctionSale);
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
+        require!(!sale.is_paused(), ErrorCode::SalePaused);
+        require!(current_time >= sale.sale_start_time, ErrorCode::SaleNotStarted);
+        require!(current_time <= sale.sale_end_time, ErrorCode::SaleEnded);
+        require!(quantity > 0 && quantity <= sale.total_tokens, ErrorCode::InvalidTokenAmount);
+        screen_wallets(&sale, &ctx.accounts.deny_list, &[&ctx.accounts.denied_bidder])?;
+        drop(sale);
+
+        let mut auction = ctx.accounts.auction.load_mut()?;
+        require!(!auction.is_sealed(), ErrorCode::SealedAuction);
+        let price_level = auction.price_level(max_price).ok_or(ErrorCode::InvalidBidPrice)?;
+        let deposit = quantity.checked_mul(max_price).ok_or(ErrorCode::MathOverflow)?;
+        let demand = &mut auction.demand[price_level as usize];
+        *demand = demand.checked_add(quantity).ok_or(ErrorCode::MathOverflow)?;
+        auction.bids = auction.bids.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        drop(auction);
+
+        let deposit_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.bidder_payment_account.to_account_info(),
+                to: ctx.accounts.bid_vault.to_account_info(),
+                authority: ctx.accounts.bidder.to_account_info(),
+            },
+        );
+        token::transfer(deposit_ctx, deposit)?;
+
+        let mut bid = ctx.accounts.bid.load_init()?;
+        bid.bidder = ctx.accounts.bidder.key();
+        bid.token_sale = ctx.accounts.token_sale.key();
+        bid.bid_id = bid_id;
+        bid.quantity = quantity;
+        bid.max_price = max_price;
+        bid.deposit = deposit;
+        bid.price_level = price_level;
+        bid.bump = ctx.bumps.bid;
+        bid.rent_payer = ctx.accounts.rent_payer.key();
+        bid.version = AuctionBid::VERSION;
+
+        emit!(AuctionBidSubmitted {
+            sale: ctx.accounts.token_sale.key(),
+            bidder: ctx.accounts.bidder.key(),
+            bid_id,
+            quantity,
+            max_price,
+            deposit,
+        });
+        msg!("Bid {} for {} tokens at up to {}", bid_id, quantity, max_price);
+        Ok(())
+    }
+
+    /// Commit a sealed bid: `sha256(max_price LE, quantity LE, salt, bidder)`, with the
+    /// auction's collateral. Only the bidder can reveal it, so copying a commitment gains nothing.
+    pub fn commit_sealed_bid(
+        ctx: Context<SubmitBid>,
+        bid_id: u64,                    // Bidder-chosen identifier, allows several bids
+        commitment: [u8; 32],           // Hash of the hidden bid
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let sale = ctx.accounts.token_sale.load()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
+        require!(!sale.is_paused(), ErrorCode::SalePaused);
+        require!(current_time >= sale.sale_start_time, ErrorCode::SaleNotStarted);
+        require!(current_time <= sale.sale_end_time, ErrorCode::SaleEnded);
+        screen_wallets(&sale, &ctx.accounts.deny_list, &[&ctx.accounts.denied_bidder])?;
+        drop(sale);
+
+        let mut auction = ctx.accounts.auction.load_mut()?;
+        require!(auction.is_sealed(), ErrorCode::NotSealedAuction);
+        auction.bids = auction.bids.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        let collateral = auction.collateral;
+        drop(auction);
+
+        let deposit_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.bidder_payment_account.to_account_info(),
+                to: ctx.accounts.bid_vault.to_account_info(),
+                authority: ctx.accounts.bidder.to_account_info(),
+            },
+        );
+        token::transfer(deposit_ctx, collateral)?;
+
+        let mut bid = ctx.accounts.bid.load_init()?;
+        bid.bidder = ctx.accounts.bidder.key();
+        bid.token_sale = ctx.accounts.token_sale.key();
+        bid.bid_id = bid_id;
+        bid.deposit = collateral;
+        bid.sealed = 1;
+        bid.commitment = commitment;
+        bid.bump = ctx.bumps.bid;
+        bid.rent_payer = ctx.accounts.rent_payer.key();
+        bid.version = AuctionBid::VERSION;
+
+        emit!(SealedBidCommitted {
+            sale: ctx.accounts.token_sale.key(),
+            bidder: ctx.accounts.bidder.key(),
+            bid_id,
+            collateral,
+        });
+        msg!("Sealed bid {} committed", bid_id);
+        Ok(())
+    }
+
+    /// Reveal a sealed bid once the sale has ended, topping the collateral up to the
+    /// bid's full cost. Revealed bids clear exactly like open ones.
+    pub fn reveal_sealed_bid(
+        ctx: Context<RevealSealedBid>,
+        bid_id: u64,                    // Identifier the bid was committed under
+        quantity: u64,                  // Tokens wanted
+        max_price: u64,                 // Highest price per token, a price on the auction ladder
+        salt: [u8; 32],                 // Salt the commitment was made with
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let sale = ctx.accounts.token_sale.load()?;
+        let mut auction = ctx.accounts.auction.load_mut()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
+        require!(current_time > sale.sale_end_time, ErrorCode::SaleNotEnded);
+        require!(
+            current_time <= sale.sale_end_time + auction.reveal_period,
+            ErrorCode::RevealPeriodEnded
+        );
+        require!(quantity > 0 && quantity <= sale.total_tokens, ErrorCode::InvalidTokenAmount);
+
+        let mut bid = ctx.accounts.bid.load_mut()?;
+        require!(bid.unrevealed(), ErrorCode::BidAlreadyRevealed);
+        require!(
+            AuctionBid::commitment_of(max_price, quantity, &salt, &bid.bidder) == bid.commitment,
+            ErrorCode::SealedBidMismatch
+        );
+        let price_level = auction.price_level(max_price).ok_or(ErrorCode::InvalidBidPrice)?;
+        let cost = quantity.checked_mul(max_price).ok_or(ErrorCode::MathOverflow)?;
+        let top_up = cost.saturating_sub(bid.deposit);
+        let demand = &mut auction.demand[price_level as usize];
+        *demand = demand.checked_add(quantity).ok_or(ErrorCode::MathOverflow)?;
+        auction.bids_revealed += 1;
+
+        bid.quantity = quantity;
+        bid.max_price = max_price;
+        bid.price_level = price_level;
+        bid.deposit += top_up;
+        bid.revealed = 1;
+        let deposit = bid.deposit;
+        drop(bid);
+        drop(auction);
+        drop(sale);
+
+        if top_up > 0 {
+            let deposit_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.bidder_payment_account.to_account_info(),
+                    to: ctx.accounts.bid_vault.to_account_info(),
+                    authority: ctx.accounts.bidder.to_account_info(),
+                },
+            );
+            token::transfer(deposit_ctx, top_up)?;
+        }
+
+        emit!(SealedBidRevealed {
+            sale: ctx.accounts.token_sale.key(),
+            bidder: ctx.accounts.bidder.key(),
+            bid_id,
+            quantity,
+            max_price,
+            deposit,
+        });
+        msg!("Sealed bid {} revealed: {} tokens at up to {}", bid_id, quantity, max_price);
+        Ok(())
+    }
+
+    /// Compute the clearing price once the sale ends (anyone): the highest ladder price at
+    /// which the bids at or above it cover the supply, or the lowest bid price if they never
+    /// do. Bids at the clearing price share what the higher bids leave, pro rata.
+    pub fn clear_auction(ctx: Context<ClearAuction>) -> Result<()> {
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        let mut auction = ctx.accounts.auction.load_mut()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(current_time > sale.sale_end_time, ErrorCode::SaleNotEnded);
+        require!(
+            current_time > sale.sale_end_time + auction.reveal_period,
+            ErrorCode::RevealPeriodActive
+        );
+        require!(!auction.is_cleared(), ErrorCode::AuctionAlreadyCleared);
+
+        auction.clear(sale.tokens_available);
+        sale.tokens_available = sale
+            .tokens_available
+            .checked_sub(auction.tokens_reserved)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(AuctionCleared {
+            sale: ctx.accounts.token_sale.key(),
+            clearing_price: auction.clearing_price,
+            tokens_sold: auction.tokens_reserved,
+            bids: auction.bids,
+        });
+        msg!("Auction cleared at {}: {} tokens sold", auction.clearing_price, auction.tokens_reserved);
+        Ok(())
+    }
+
+    /// Settle a chunk of bids (anyone, once cleared or the sale is cancelled). Remaining
+    /// accounts are `(bid, bidder payment account, bidder token account)` triples; each bid
+    /// receives its fill and the unspent deposit, the fills' cost pays the seller and fee.
+    /// The last chunk sends the reserved tokens left unfilled by rounding to the seller.
+    pub fn settle_auction_bids<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SettleAuctionBids<'info>>,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let sale = *ctx.accounts.token_sale.load()?;
+        let auction = *ctx.accounts.auction.load()?;
+        require!(auction.is_cleared() || !sale.is_active(), ErrorCode::AuctionNotCleared);
+        require!(
+            !ctx.remaining_accounts.is_empty() && ctx.remaining_accounts.len().is_multiple_of(3),
+            ErrorCode::InvalidSettlementAccounts
+        );
+
+        let seeds = &[
+            b"token_sale",
+            sale.seller.as_ref(),
+            sale.token_mint.as_ref(),
+            &[sale.bump],
+        ];
+        let signer = &[&seeds[..]];
+        let pay_out = |from: &AccountInfo<'info>, to: &AccountInfo<'info>, amount: u64| {
+            if amount == 0 {
+                return Ok(());
+            }
+            let transfer_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: from.clone(),
+                    to: to.clone(),
+                    authority: ctx.accounts.token_sale.to_account_info(),
+                },
+                signer,
+            );
+            token::transfer(transfer_ctx, amount)
+        };
+
+        let bid_vault = ctx.accounts.bid_vault.to_account_info();
+        let token_vault = ctx.accounts.token_vault.to_account_info();
+        let (mut tokens_filled, mut seller_payment, mut platform_fee) = (0u64, 0u64, 0u64);
+        for accounts in ctx.remaining_accounts.chunks(3) {
+            let bid_loader = AccountLoader::<AuctionBid>::try_from(&accounts[0])?;
+            let mut bid = bid_loader.load_mut()?;
+            require_keys_eq!(bid.token_sale, ctx.accounts.token_sale.key(), ErrorCode::InvalidSettlementAccounts);
+            require!(!bid.settled(), ErrorCode::BidAlreadySettled);
+
+            let payment_account = Account::<TokenAccount>::try_from(&accounts[1])?;
+            let token_account = Account::<TokenAccount>::try_from(&accounts[2])?;
+            require!(
+                payment_account.owner == bid.bidder && payment_account.mint == sale.payment_mint,
+                ErrorCode::InvalidSettlementAccounts
+            );
+            require!(
+                token_account.owner == bid.bidder && token_account.mint == sale.token_mint,
+                ErrorCode::InvalidSettlementAccounts
+            );
+
+            let filled = auction.fill(bid.price_level, bid.quantity);
+            let cost = filled
+                .checked_mul(auction.clearing_price)
+                .ok_or(ErrorCode::MathOverflow)?;
+            let fee = (cost as u128 * sale.platform_fee_bps as u128 / 10000) as u64;
+            // Unrevealed sealed bids pay for hiding from the clearing, unless the sale was
+            // cancelled before it
+            let forfeited = if bid.unrevealed() && auction.is_cleared() {
+                (bid.deposit as u128 * auction.forfeit_bps as u128 / 10000) as u64
+            } else {
+                0
+            };
+            let refund = bid
+                .deposit
+                .checked_sub(cost)
+                .and_then(|rest| rest.checked_sub(forfeited))
+                .ok_or(ErrorCode::MathOverflow)?;
+            bid.settled = 1;
+            let (bidder, bid_id) = (bid.bidder, bid.bid_id);
+            drop(bid);
+
+            pay_out(&token_vault, &accounts[2], filled)?;
+            pay_out(&bid_vault, &accounts[1], refund)?;
+            tokens_filled = tokens_filled.checked_add(filled).ok_or(ErrorCode::MathOverflow)?;
+            seller_payment = (cost - fee)
+                .checked_add(forfeited)
+                .and_then(|payment| seller_payment.checked_add(payment))
+                .ok_or(ErrorCode::MathOverflow)?;
+            platform_fee = platform_fee.checked_add(fee).ok_or(ErrorCode::MathOverflow)?;
+
+            if filled > 0 {
+                emit!(TokensPurchased {
+                    sale: ctx.accounts.token_sale.key(),
+                    buyer: bidder,
+                    token_amount: filled,
+                    seller_payment: cost - fee,
+                    platform_fee: fee,
+                    platform_fee_recipient: sale.platform_fee_recipient,
+                    timestamp: current_time,
+                });
+            }
+            emit!(AuctionBidSettled {
+                sale: ctx.accounts.token_sale.key(),
+                bidder,
+                bid_id,
+                filled,
+                cost,
+                forfeited,
+                refund,
+            });
+        }
+
+        pay_out(&bid_vault, &ctx.accounts.seller_payment_account.to_account_info(), seller_payment)?;
+        pay_out(&bid_vault, &ctx.accounts.platform_fee_account.to_account_info(), platform_fee)?;
+
+        let mut auction = ctx.accounts.auction.load_mut()?;
+        auction.bids_settled = auction
+            .bids_settled
+            .checked_add((ctx.remaining_accounts.len() / 3) as u64)
+            .ok_or(ErrorCode::MathOverflow)?;
+        auction.tokens_filled = auction
+            .tokens_filled
+            .checked_add(tokens_filled)
+            .ok_or(ErrorCode::MathOverflow)?;
+        // Rounding at the clearing price leaves a few reserved tokens unsold; they go
+        // straight to the seller, who may have cancelled the sale since the clearing
+        let unsold = if auction.is_cleared() && auction.bids_settled == auction.bids {
+            auction
+                .tokens_reserved
+                .checked_sub(auction.tokens_filled)
+                .ok_or(ErrorCode::MathOverflow)?
+        } else {
+            0
+        };
+        drop(auction);
+        pay_out(&token_vault, &ctx.accounts.seller_token_account.to_account_info(), unsold)?;
+
+        msg!("Settled {} bids, {} tokens filled", ctx.remaining_accounts.len() / 3, tokens_filled);
+        Ok(())
+    }
+
+    /// Close a settled auction bid, returning the rent to whoever paid it
+    pub fn close_auction_bid(ctx: Context<CloseAuctionBid>, bid_id: u64) -> Result<()> {
+        let bid = ctx.accounts.bid.load()?;
+        require!(bid.settled(), ErrorCode::BidNotSettled);
+
+        msg!("Auction bid {} closed, rent returned to {}", bid_id, ctx.accounts.rent_payer.key());
+        Ok(())
+    }
+
+    /// Create a swap offer: deposit token A and ask for an amount of token B
+    pub fn make_offer(
+        ctx: Context<MakeOffer>,
+        offer_id: u64,                  // Maker-chosen identifier, allows several open offers
+        token_a_amount: u64,            // Token A deposited into the offer vault
+        token_b_wanted: u64,            // Token B the maker expects in return
+        taker: Option<Pubkey>,          // Restrict the offer to one counterparty (None = anyone)
+        expires_at: i64,                // Unix timestamp after which fills stop (0 = never)
+        partially_fillable: bool,       // Allow takers to fill the offer in pieces
+    ) -> Result<()> {
+        require!(token_a_amount > 0, ErrorCode::InvalidTokenAmount);
+        require!(token_b_wanted > 0, ErrorCode::InvalidTokenAmount);
+
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(
+            expires_at == 0 || expires_at > current_time,
+            ErrorCode::InvalidOfferExpiry
+        );
+
+        let offer = &mut ctx.accounts.offer;
+        offer.maker = ctx.accounts.maker.key();
+        offer.offer_id = offer_id;
+        offer.token_mint_a = ctx.accounts.token_mint_a.key();
+        offer.token_mint_b = ctx.accounts.token_mint_b.key();
+        offer.token_a_amount = token_a_amount;
+        offer.token_b_wanted = token_b_wanted;
+        offer.taker = taker;
+        offer.bump = ctx.bumps.offer;
+        offer.token_a_remaining = token_a_amount;
+        offer.token_b_received = 0;
+        offer.expires_at = expires_at;
+        offer.partially_fillable = partially_fillable;
+        offer.version = Offer::VERSION;
+
+        // Transfer maker's token A to the offer vault
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.maker_token_account_a.to_account_info(),
+                to: ctx.accounts.offer_vault.to_account_info(),
+                authority: ctx.accounts.maker.to_account_info(),
+            },
+        );
+        token::transfer(transfer_ctx, token_a_amount)?;
+
+        msg!("Offer {} created: {} token A for {} token B", offer_id, token_a_amount, token_b_wanted);
+        Ok(())
+    }
+
+    /// Complete a swap offer atomically (taker pays token B, receives all remaining token A)
+    pub fn take_offer(ctx: Context<TakeOffer>) -> Result<()> {
+        let token_a_fill = ctx.accounts.offer.token_a_remaining;
+        process_offer_fill(ctx.accounts, token_a_fill)
+    }
+
+    /// Fill part of a partially fillable offer at the offer's fixed rate
+    pub fn fill_offer(
+        ctx: Context<TakeOffer>,
+        token_a_fill: u64,              // How much token A the taker receives
+    ) -> Result<()> {
+        let offer = &ctx.accounts.offer;
+        require!(
+            offer.partially_fillable || token_a_fill == offer.token_a_remaining,
+            ErrorCode::PartialFillsDisabled
+        );
+        process_offer_fill(ctx.accounts, token_a_fill)
+    }
+
+    /// Cancel a swap offer and return the deposit to the maker (maker only)
+    pub fn cancel_offer(ctx: Context<CancelOffer>) -> Result<()> {
+        let offer = &ctx.accounts.offer;
+
+        let offer_id_bytes = offer.offer_id.to_le_bytes();
+        let seeds = &[
+            b"offer",
+            offer.maker.as_ref(),
+            offer_id_bytes.as_ref(),
+            &[offer.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        // Return remaining token A to the maker
+        let vault_balance = ctx.accounts.offer_vault.amount;
+        if vault_balance > 0 {
+            let return_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.offer_vault.to_account_info(),
+                    to: ctx.accounts.maker_token_account_a.to_account_info(),
+                    authority: ctx.accounts.offer.to_account_info(),
+                },
+                signer,
+            );
+            token::transfer(return_ctx, vault_balance)?;
+        }
+
+        let close_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.offer_vault.to_account_info(),
+                destination: ctx.accounts.maker.to_account_info(),
+                authority: ctx.accounts.offer.to_account_info(),
+            },
+            signer,
+        );
+        token::close_account(close_ctx)?;
+
+        msg!("Offer {} cancelled, {} tokens returned to maker", offer.offer_id, vault_balance);
+        Ok(())
+    }
+
+    /// Lock a service payment for a payee under a named arbiter, a third party to both
+    pub fn create_service_escrow(
+        ctx: Context<CreateServiceEscrow>,
+        escrow_id: u64,                 // Payer-chosen identifier, allows several open escrows
+        amount: u64,                    // Payment locked in the escrow vault
+        release_deadline: i64,          // Unix timestamp after which funds auto-release to the payee
+    ) -> Result<()> {
+        require!(amount > 0, ErrorCode::InvalidTokenAmount);
+
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(release_deadline > current_time, ErrorCode::InvalidReleaseDeadline);
+        let arbiter = ctx.accounts.arbiter.key();
+        require!(
+            arbiter != ctx.accounts.payer.key() && arbiter != ctx.accounts.payee.key(),
+            ErrorCode::InvalidArbiter
+        );
+
+        let escrow = &mut ctx.accounts.service_escrow;
+        escrow.payer = ctx.accounts.payer.key();
+        escrow.payee = ctx.accounts.payee.key();
+        escrow.arbiter = ctx.accounts.arbiter.key();
+        escrow.mint = ctx.accounts.mint.key();
+        escrow.escrow_id = escrow_id;
+        escrow.amount = amount;
+        escrow.release_deadline = release_deadline;
+        escrow.status = ServiceEscrowStatus::Funded;
+        escrow.bump = ctx.bumps.service_escrow;
+        escrow.version = ServiceEscrow::VERSION;
+
+        // Transfer payer's funds to the escrow vault
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.payer_token_account.to_account_info(),
+                to: ctx.accounts.escrow_vault.to_account_info(),
+                authority: ctx.accounts.payer.to_account_info(),
+            },
+        );
+        token::transfer(transfer_ctx, amount)?;
+
+        msg!("Service escrow {} funded with {} for {}, arbiter {}",
+            escrow_id, amount, escrow.payee, escrow.arbiter);
+        Ok(())
+    }
+
+    /// Release escrowed funds to the payee (payer only)
+    pub fn release_service_escrow(ctx: Context<SettleServiceEscrow>) -> Result<()> {
+        let escrow = &ctx.accounts.service_escrow;
+        require!(ctx.accounts.authority.key() == escrow.payer, ErrorCode::UnauthorizedEscrowParty);
+        require!(escrow.status == ServiceEscrowStatus::Funded, ErrorCode::InvalidEscrowStatus);
+
+        let payee_amount = escrow.amount;
+        process_service_settlement(ctx.accounts, payee_amount)
+    }
+
+    /// Refund escrowed funds to the payer (payee only)
+    pub fn refund_service_escrow(ctx: Context<SettleServiceEscrow>) -> Result<()> {
+        let escrow = &ctx.accounts.service_escrow;
+        require!(ctx.accounts.authority.key() == escrow.payee, ErrorCode::UnauthorizedEscrowParty);
+        require!(escrow.status == ServiceEscrowStatus::Funded, ErrorCode::InvalidEscrowStatus);
+
+        process_service_settlement(ctx.accounts, 0)
+    }
+
+    /// Freeze the escrow until the arbiter resolves it (payer or payee, before the deadline)
+    pub fn raise_dispute(ctx: Context<RaiseDispute>) -> Result<()> {
+        let escrow = &mut ctx.accounts.service_escrow;
+        let raised_by = ctx.accounts.authority.key();
+        require!(
+            raised_by == escrow.payer || raised_by == escrow.payee,
+            ErrorCode::UnauthorizedEscrowParty
+        );
+        require!(escrow.status == ServiceEscrowStatus::Funded, ErrorCode::InvalidEscrowStatus);
+
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(current_time <= escrow.release_deadline, ErrorCode::ReleaseDeadlinePassed);
+
+        escrow.status = ServiceEscrowStatus::Disputed;
+
+        msg!("Service escrow {} disputed by {}", escrow.escrow_id, raised_by);
+        Ok(())
+    }
+
+    /// Split disputed funds between payee and payer (arbiter only)
+    pub fn resolve_dispute(
+        ctx: Context<SettleServiceEscrow>,
+        payee_share_bps: u16,           // Payee's share in basis points, remainder goes to payer
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.service_escrow;
+        require!(ctx.accounts.authority.key() == escrow.arbiter, ErrorCode::UnauthorizedEscrowParty);
+        require!(escrow.status == ServiceEscrowStatus::Disputed, ErrorCode::InvalidEscrowStatus);
+        require!(payee_share_bps <= 10000, ErrorCode::InvalidSplitBps);
+
+        let payee_amount = (escrow.amount as u128)
+            .checked_mul(payee_share_bps as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(10000)
+            .ok_or(ErrorCode::MathOverflow)? as u64;
+
+        process_service_settlement(ctx.accounts, payee_amount)
+    }
+
+    /// Release funds to the payee once the deadline passes undisputed (anyone can call)
+    pub fn claim_expired_service_escrow(ctx: Context<SettleServiceEscrow>) -> Result<()> {
+        let escrow = &ctx.accounts.service_escrow;
+        require!(escrow.status == ServiceEscrowStatus::Funded, ErrorCode::InvalidEscrowStatus);
+
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(current_time > escrow.release_deadline, ErrorCode::ReleaseDeadlineNotReached);
+
+        let payee_amount = escrow.amount;
+        process_service_settlement(ctx.accounts, payee_amount)
+    }
+
+    // DENY LIST
+    // The program has one deny list, at `["deny_list"]`, so no choice a seller makes takes
+    // a sale outside it. It is kept by a compliance authority, one entry account per
+    // denied wallet, so a purchase, ticket, deposit or bid proves a wallet is not denied
+    // by passing the entry's empty address.
+
+    /// Create the deny list, once, when the program is deployed; its creator becomes the
+    /// admin. `authority` denies and allows wallets; `required` screens every sale,
+    /// whatever its seller chose.
+    pub fn create_deny_list(
+        ctx: Context<CreateDenyList>,
+        authority: Pubkey,
+        required: bool,
+    ) -> Result<()> {
+        let deny_list = &mut ctx.accounts.deny_list;
+        deny_list.admin = ctx.accounts.admin.key();
+        deny_list.authority = authority;
+        deny_list.denied_count = 0;
+        deny_list.required = required;
+        deny_list.bump = ctx.bumps.deny_list;
+        deny_list.version = DenyList::VERSION;
+
+        emit!(DenyListConfigured {
+            deny_list: deny_list.key(),
+            admin: deny_list.admin,
+            authority,
+            required,
+        });
+        msg!("Deny list created by {}, kept by {} (required: {})",
+            deny_list.admin, authority, required);
+        Ok(())
+    }
+
+    /// Hand the deny list to another compliance authority or change whether it is
+    /// required (admin only)
+    pub fn update_deny_list(
+        ctx: Context<UpdateDenyList>,
+        authority: Pubkey,
+        required: bool,
+    ) -> Result<()> {
+        let deny_list = &mut ctx.accounts.deny_list;
+        deny_list.authority = authority;
+        deny_list.required = required;
+
+        emit!(DenyListConfigured {
+            deny_list: deny_list.key(),
+            admin: deny_list.admin,
+            authority,
+            required,
+        });
+        msg!("Deny list kept by {} (required: {})", authority, required);
+        Ok(())
+    }
+
+    /// Bar `wallet` from buying in sales screened against the deny list (compliance
+    /// authority only, paying the entry's rent)
+    pub fn deny_wallet(ctx: Context<DenyWallet>, wallet: Pubkey) -> Result<()> {
+        let deny_list = &mut ctx.accounts.deny_list;
+        deny_list.denied_count = deny_list.denied_count
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let entry = &mut ctx.accounts.denied_wallet;
+        entry.deny_list = deny_list.key();
+        entry.wallet = wallet;
+        entry.denied_by = ctx.accounts.authority.key();
+        entry.denied_at = Clock::get()?.unix_timestamp;
+        entry.bump = ctx.bumps.denied_wallet;
+        entry.version = DeniedWallet::VERSION;
+
+        emit!(WalletDenied {
+            deny_list: deny_list.key(),
+            wallet,
+            authority: entry.denied_by,
+        });
+        msg!("Wallet {} denied", wallet);
+        Ok(())
+    }
+
+    /// Lift a wallet's denial (compliance authority only). Closes its entry, returning
+    /// the rent to the authority.
+    pub fn allow_wallet(ctx: Context<AllowWallet>) -> Result<()> {
+        let deny_list = &mut ctx.accounts.deny_list;
+        deny_list.denied_count = deny_list.denied_count
+            .checked_sub(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let wallet = ctx.accounts.denied_wallet.wallet;
+
+        emit!(WalletAllowed {
+            deny_list: deny_list.key(),
+            wallet,
+            authority: ctx.accounts.authority.key(),
+        });
+        msg!("Wallet {} allowed", wallet);
+        Ok(())
+    }
+
+    /// Price a purchase of `token_amount` tokens the way `buy_tokens` would (read-only).
+    /// Returned as return data: simulate the transaction to read the quote.
+    pub fn quote_purchase(ctx: Context<QuotePurchase>, token_amount: u64) -> Result<PurchaseQuote> {
+        let tokens_purchased = ctx.accounts.tokens_purchased()?;
+        ctx.accounts.priced_sale()?.quote_purchase(token_amount, tokens_purchased)
+    }
+
+    /// Quote the largest purchase `payment` (fee included) can cover, within the
+    /// buyer's allowance and the per-transaction limit (read-only, via return data)
+    pub fn quote_exact_in(ctx: Context<QuotePurchase>, payment: u64) -> Result<PurchaseQuote> {
+        let tokens_purchased = ctx.accounts.tokens_purchased()?;
+        let sale = ctx.accounts.priced_sale()?;
+
+        let mut token_amount = sale.tokens_for_payment(payment)?
+            .min(sale.allowance(tokens_purchased));
+        if sale.max_tokens_per_tx > 0 {
+            token_amount = token_amount.min(sale.max_tokens_per_tx);
+        }
+        sale.quote_purchase(token_amount, tokens_purchased)
+    }
+
+    /// Convert a Borsh token sale account to the zero-copy layout (anyone can call, payer covers rent)
+    pub fn migrate_token_sale(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_to_zero_copy::<TokenSale>(ctx.accounts)
+    }
+
+    /// Convert a Borsh buyer account to the zero-copy layout (anyone can call, payer covers rent)
+    pub fn migrate_buyer_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_to_zero_copy::<BuyerAccount>(ctx.accounts)
+    }
+
+    /// Upgrade an offer to the current layout (anyone can call, payer covers rent)
+    pub fn migrate_offer(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account::<Offer>(ctx.accounts)
+    }
+
+    /// Upgrade a service escrow to the current layout (anyone can call, payer covers rent)
+    pub fn migrate_service_escrow(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account::<ServiceEscrow>(ctx.accounts)
+    }
+}
+
+/// Accounts a purchase moves funds between. `buy_tokens` buys for the payer itself,
+/// `buy_tokens_for` for a recipient whose tracker and token account it names.
+struct Purchase<'a, 'info> {
+    payer: &'a Signer<'info>,
+    recipient: Pubkey,
+    token_sale: &'a AccountLoader<'info, TokenSale>,
+    buyer_account: &'a AccountLoader<'info, BuyerAccount>,
+    /// The payer's own tracker when it buys for someone else, whose cooldown it shares
+    payer_account: Option<&'a AccountLoader<'info, BuyerAccount>>,
+    payer_payment_account: &'a Account<'info, TokenAccount>,
+    seller_payment_account: &'a Account<'info, TokenAccount>,
+    platform_fee_account: &'a Account<'info, TokenAccount>,
+    recipient_token_account: &'a Account<'info, TokenAccount>,
+    token_vault: &'a Account<'info, TokenAccount>,
+    proceeds_vault: Option<&'a Account<'info, TokenAccount>>,
+    receipt_mint: Option<&'a Account<'info, Mint>>,
+    deny_list: &'a AccountInfo<'info>,
+    /// Deny-list entries of the wallets in the purchase, which exist only if denied
+    deny_entries: &'a [&'a AccountInfo<'info>],
+    token_program: &'a Program<'info, Token>,
+    /// The purchase hook program followed by its accounts, when the sale has a hook
+    hook_accounts: &'a [AccountInfo<'info>],
+}
+
+/// Sell `token_amount` tokens to `accounts.recipient`, paid from the payer's account.
+/// Caps, cooldowns and milestone votes follow the recipient's tracker.
+fn process_purchase(accounts: Purchase, token_amount: u64) -> Result<()> {
+    let clock = Clock::get()?;
+    let current_time = clock.unix_timestamp;
+
+    // An announced price change takes effect with the first purchase once it is due
+    accounts.token_sale.load_mut()?.apply_due_price_change(current_time);
+
+    // Get sale data and perform security checks. Both accounts are read in place and
+    // released before the transfers, which pass the sale to the token program.
+    let sale = accounts.token_sale.load()?;
+    let buyer_account = accounts.buyer_account.load()?;
+    // Buying for oneself through `buy_tokens_for` names the same tracker twice
+    let payer_account = accounts.payer_account
+        .filter(|payer_account| payer_account.key() != accounts.buyer_account.key());
+    
+    // Basic sale status checks
+    require!(sale.is_active(), ErrorCode::SaleNotActive);
+    require!(!sale.is_paused(), ErrorCode::SalePaused);
+    require!(!sale.is_lottery(), ErrorCode::LotterySale);
+    require!(!sale.is_overflow(), ErrorCode::OverflowSale);
+    require!(!sale.is_auction(), ErrorCode::AuctionSale);
+    require!(token_amount > 0, ErrorCode::InvalidTokenAmount);
+    require!(token_amount <= sale.tokens_available, ErrorCode::InsufficientTokens);
+    
+    // Time-based validation
+    require!(current_time >= sale.sale_start_time, ErrorCode::SaleNotStarted);
+    require!(current_time <= sale.sale_end_time, ErrorCode::SaleEnded);
+
+    // Wallets on the deny list cannot buy where the sale screens or the list is required
+    screen_wallets(&sale, accounts.deny_list, accounts.deny_entries)?;
+    
+    // Per-buyer limit, payment amount (blended across price tiers) and platform fee,
+    // priced exactly as the quote instructions report them
+    let quote = sale.quote_purchase(token_amount, buyer_account.tokens_purchased)?;
+    let platform_fee = quote.platform_fee;
+    let seller_payment = quote.seller_net;
+    let purchase_hook = sale.purchase_hook();
+
+    // Anti-bot throttles (each one is disabled when set to 0)
+    if sale.max_tokens_per_tx > 0 {
+        require!(
+            token_amount <= sale.max_tokens_per_tx,
+            ErrorCode::ExceedsTransactionLimit
+        );
+    }
+
+    if sale.min_purchase_interval > 0 {
+        // A payer buying for someone else waits out its own cooldown as well
+        let payer_last_purchase_time = match payer_account {
+            Some(payer_account) => payer_account.load()?.last_purchase_time,
+            None => 0,
+        };
+        for last_purchase_time in [buyer_account.last_purchase_time, payer_last_purchase_time] {
+            if last_purchase_time > 0 {
+                let next_allowed_time = last_purchase_time
+                    .checked_add(sale.min_purchase_interval)
+                    .ok_or(ErrorCode::MathOverflow)?;
+                require!(current_time >= next_allowed_time, ErrorCode::PurchaseCooldownActive);
+            }
+        }
+    }
+
+    // The per-slot counter resets whenever a purchase lands in a new slot
+    let sold_earlier_in_slot = if sale.throttle_slot == clock.slot {
+        sale.tokens_sold_in_slot
+    } else {
+        0
+    };
+    let tokens_sold_in_slot = sold_earlier_in_slot
+        .checked_add(token_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    if sale.max_tokens_per_slot > 0 {
+        require!(
+            tokens_sold_in_slot <= sale.max_tokens_per_slot,
+            ErrorCode::ExceedsSlotLimit
+        );
+    }
+
+    // Transfer payment from buyer to seller, or into the proceeds vault
+    // when the sale releases funds by milestone
+    let escrows_proceeds = sale.milestone_count > 0;
+    let locks_tokens = sale.is_locked();
+    let (seller, token_mint, bump) = (sale.seller, sale.token_mint, sale.bump);
+    let platform_fee_recipient = sale.platform_fee_recipient;
+    drop(sale);
+    drop(buyer_account);
+
+    if seller_payment > 0 {
+        let payment_destination = if escrows_proceeds {
+            accounts.proceeds_vault
+                .ok_or(ErrorCode::ProceedsVaultRequired)?
+                .to_account_info()
+        } else {
+            accounts.seller_payment_account.to_account_info()
+        };
+
+        let payment_transfer_ctx = CpiContext::new(
+            accounts.token_program.to_account_info(),
+            Transfer {
+                from: accounts.payer_payment_account.to_account_info(),
+                to: payment_destination,
+                authority: accounts.payer.to_account_info(),
+            },
+        );
+        token::transfer(payment_transfer_ctx, seller_payment)?;
+    }
+
+    // Transfer platform fee if applicable
+    if platform_fee > 0 {
+        let fee_transfer_ctx = CpiContext::new(
+            accounts.token_program.to_account_info(),
+            Transfer {
+                from: accounts.payer_payment_account.to_account_info(),
+                to: accounts.platform_fee_account.to_account_info(),
+                authority: accounts.payer.to_account_info(),
+            },
+        );
+        token::transfer(fee_transfer_ctx, platform_fee)?;
+    }
+
+    // Transfer tokens from vault to buyer, or mint receipts for them while they stay locked
+    let seeds = &[
+        b"token_sale",
+        seller.as_ref(),
+        token_mint.as_ref(),
+        &[bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    if locks_tokens {
+        let mint_ctx = CpiContext::new_with_signer(
+            accounts.token_program.to_account_info(),
+            MintTo {
+                mint: accounts.receipt_mint
+                    .ok_or(ErrorCode::ReceiptMintRequired)?
+                    .to_account_info(),
+                to: accounts.recipient_token_account.to_account_info(),
+                authority: accounts.token_sale.to_account_info(),
+            },
+            signer,
+        );
+        token::mint_to(mint_ctx, token_amount)?;
+    } else {
+        let token_transfer_ctx = CpiContext::new_with_signer(
+            accounts.token_program.to_account_info(),
+            Transfer {
+                from: accounts.token_vault.to_account_info(),
+                to: accounts.recipient_token_account.to_account_info(),
+                authority: accounts.token_sale.to_account_info(),
+            },
+            signer,
+        );
+        token::transfer(token_transfer_ctx, token_amount)?;
+    }
+
+    // Update sale state
+    let mut sale = accounts.token_sale.load_mut()?;
+    sale.tokens_available = sale.tokens_available
+        .checked_sub(token_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    sale.throttle_slot = clock.slot;
+    sale.tokens_sold_in_slot = tokens_sold_in_slot;
+    if escrows_proceeds {
+        sale.proceeds_escrowed = sale.proceeds_escrowed
+            .checked_add(seller_payment)
+            .ok_or(ErrorCode::MathOverflow)?;
+        sale.milestone_vote_supply = sale.milestone_vote_supply
+            .checked_add(token_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+    if let Some(new_end_time) = sale.soft_close_end_time(current_time) {
+        emit!(SaleSoftCloseExtended {
+            sale: accounts.token_sale.key(),
+            buyer: accounts.recipient,
+            previous_end_time: sale.sale_end_time,
+            new_end_time,
+        });
+        msg!("Soft close: sale extended from {} to {}", sale.sale_end_time, new_end_time);
+        sale.sale_end_time = new_end_time;
+    }
+
+    // Update buyer tracking
+    let mut buyer_account = accounts.buyer_account.load_mut()?;
+    buyer_account.tokens_purchased = buyer_account.tokens_purchased
+        .checked_add(token_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    buyer_account.last_purchase_time = current_time;
+    if escrows_proceeds {
+        buyer_account.payment_escrowed = buyer_account.payment_escrowed
+            .checked_add(seller_payment)
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+    drop(sale);
+    drop(buyer_account);
+    if let Some(payer_account) = payer_account {
+        payer_account.load_mut()?.last_purchase_time = current_time;
+    }
+
+    if let Some(hook_program) = purchase_hook {
+        invoke_purchase_hook(
+            hook_program,
+            accounts.hook_accounts,
+            PurchaseHookArgs {
+                sale: accounts.token_sale.key(),
+                buyer: accounts.recipient,
+                token_amount,
+                cost: quote.gross_cost,
+            },
+        )?;
+    }
+
+    emit!(TokensPurchased {
+        sale: accounts.token_sale.key(),
+        buyer: accounts.recipient,
+        token_amount,
+        seller_payment,
+        platform_fee,
+        platform_fee_recipient,
+        timestamp: current_time,
+    });
+
+    if accounts.payer.key() != accounts.recipient {
+        emit!(PurchasedOnBehalf {
+            sale: accounts.token_sale.key(),
+            payer: accounts.payer.key(),
+            recipient: accounts.recipient,
+            token_amount,
+            payment: seller_payment + platform_fee,
+        });
+    }
+
+    msg!("Sold {} tokens for {} payment (fee: {})", token_amount, seller_payment, platform_fee);
+    Ok(())
+}
+
+/// The deny list at `info`, the `["deny_list"]` address, if it has been created
+fn load_deny_list(info: &AccountInfo) -> Result<Option<DenyList>> {
+    if info.owner != &crate::ID {
+        return Ok(None);
+    }
+    Ok(Some(DenyList::try_deserialize(&mut &info.try_borrow_data()?[..])?))
+}
+
+/// Fail with `WalletDenied` if the sale screens buyers, by its seller's choice or the
+/// deny list's requirement, and any of `entries` exists. Only this program can create
+/// accounts at those addresses, so an entry it does not own means the wallet is clear.
+fn screen_wallets(sale: &TokenSale, deny_list: &AccountInfo, entries: &[&AccountInfo]) -> Result<()> {
+    if let Some(deny_list) = load_deny_list(deny_list)? {
+        if deny_list.required || sale.screens_wallets() {
+            for entry in entries {
+                require!(entry.owner != &crate::ID, ErrorCode::WalletDenied);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Call a sale's purchase hook. `hook_accounts` starts with the hook program; the rest
+/// are passed on with their writability but never as signers, so the hook cannot act
+/// with the buyer's signature.
+fn invoke_purchase_hook<'info>(
+    hook_program: Pubkey,
+    hook_accounts: &[AccountInfo<'info>],
+    args: PurchaseHookArgs,
+) -> Result<()> {
+    let (program, accounts) = hook_accounts
+        .split_first()
+        .ok_or(ErrorCode::PurchaseHookAccountsMissing)?;
+    require_keys_eq!(program.key(), hook_program, ErrorCode::PurchaseHookAccountsMissing);
+
+    let mut data = PURCHASE_HOOK_DISCRIMINATOR.to_vec();
+    args.serialize(&mut data)?;
+    let instruction = anchor_lang::solana_program::instruction::Instruction {
+        program_id: hook_program,
+        accounts: accounts
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: account.key(),
+                is_signer: false,
+                is_writable: account.is_writable,
+            })
+            .collect(),
+        data,
+    };
+    anchor_lang::solana_program::program::invoke(&instruction, hook_accounts)?;
+    Ok(())
+}
+
+/// Settle a fill against an offer: taker pays pro-rata token B, vault releases token A.
+/// Closes the offer and its vault once nothing remains.
+fn process_offer_fill(accounts: &mut TakeOffer, token_a_fill: u64) -> Result<()> {
+    let offer = &accounts.offer;
+    require!(token_a_fill > 0, ErrorCode::InvalidTokenAmount);
+    require!(token_a_fill <= offer.token_a_remaining, ErrorCode::InsufficientTokens);
+
+    if let Some(taker) = offer.taker {
+        require!(taker == accounts.taker.key(), ErrorCode::UnauthorizedTaker);
+    }
+
+    if offer.expires_at > 0 {
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(current_time <= offer.expires_at, ErrorCode::OfferExpired);
+    }
+
+    let token_b_payment = offer.quote_fill(token_a_fill)?;
+
+    // Transfer token B from taker to maker
+    let payment_transfer_ctx = CpiContext::new(
+        accounts.token_program.to_account_info(),
+        Transfer {
+            from: accounts.taker_token_account_b.to_account_info(),
+            to: accounts.maker_token_account_b.to_account_info(),
+            authority: accounts.taker.to_account_info(),
+        },
+    );
+    token::transfer(payment_transfer_ctx, token_b_payment)?;
+
+    // Release token A from the vault to the taker
+    let offer_id_bytes = offer.offer_id.to_le_bytes();
+    let seeds = &[
+        b"offer",
+        offer.maker.as_ref(),
+        offer_id_bytes.as_ref(),
+        &[offer.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let release_ctx = CpiContext::new_with_signer(
+        accounts.token_program.to_account_info(),
+        Transfer {
+            from: accounts.offer_vault.to_account_info(),
+            to: accounts.taker_token_account_a.to_account_info(),
+            authority: accounts.offer.to_account_info(),
+        },
+        signer,
+    );
+    token::transfer(release_ctx, token_a_fill)?;
+
+    let fully_filled = offer.token_a_remaining == token_a_fill;
+    if fully_filled {
+        let close_ctx = CpiContext::new_with_signer(
+            accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: accounts.offer_vault.to_account_info(),
+                destination: accounts.maker.to_account_info(),
+                authority: accounts.offer.to_account_info(),
+            },
+            signer,
+        );
+        token::close_account(close_ctx)?;
+    }
+
+    // Update offer state
+    let offer = &mut accounts.offer;
+    offer.token_a_remaining -= token_a_fill;
+    offer.token_b_received = offer.token_b_received
+        .checked_add(token_b_payment)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    msg!("Offer {} filled: {} token A for {} token B, {} remaining",
+        offer.offer_id, token_a_fill, token_b_payment, offer.token_a_remaining);
+
+    if fully_filled {
+        accounts.offer.close(accounts.maker.to_account_info())?;
+    }
+    Ok(())
+}
+
+/// Pay out a service escrow: `payee_amount` to the payee, the rest back to the payer.
+/// The vault is closed here; the escrow account itself is closed by its `close` constraint.
+fn process_service_settlement(accounts: &mut SettleServiceEscrow, payee_amount: u64) -> Result<()> {
+    let escrow = &accounts.service_escrow;
+    let payer_amount = escrow.amount
+        .checked_sub(payee_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let escrow_id_bytes = escrow.escrow_id.to_le_bytes();
+    let seeds = &[
+        b"service_escrow",
+        escrow.payer.as_ref(),
+        escrow_id_bytes.as_ref(),
+        &[escrow.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    if payee_amount > 0 {
+        let payee_transfer_ctx = CpiContext::new_with_signer(
+            accounts.token_program.to_account_info(),
+            Transfer {
+                from: accounts.escrow_vault.to_account_info(),
+                to: accounts.payee_token_account.to_account_info(),
+                authority: accounts.service_escrow.to_account_info(),
+            },
+            signer,
+        );
+        token::transfer(payee_transfer_ctx, payee_amount)?;
+    }
+
+    if payer_amount > 0 {
+        let payer_transfer_ctx = CpiContext::new_with_signer(
+            accounts.token_program.to_account_info(),
+            Transfer {
+                from: accounts.escrow_vault.to_account_info(),
+                to: accounts.payer_token_account.to_account_info(),
+                authority: accounts.service_escrow.to_account_info(),
+            },
+            signer,
+        );
+        token::transfer(payer_transfer_ctx, payer_amount)?;
+    }
+
+    let close_ctx = CpiContext::new_with_signer(
+        accounts.token_program.to_account_info(),
+        CloseAccount {
+            account: accounts.escrow_vault.to_account_info(),
+            destination: accounts.payer.to_account_info(),
+            authority: accounts.service_escrow.to_account_info(),
+        },
+        signer,
+    );
+    token::close_account(close_ctx)?;
+
+    msg!("Service escrow {} settled: {} to payee, {} to payer",
+        escrow.escrow_id, payee_amount, payer_amount);
+    Ok(())
+}
+
+// Current layouts of the Borsh accounts, migrated by `token_sale_migration`
+versioned!(Offer, 1);
+versioned!(ServiceEscrow, 1);
+versioned!(DenyList, 1);
+versioned!(DeniedWallet, 1);
+
+/// Account structure for token sale state.
+///
+/// Zero-copy with a fixed layout, so purchases read and update it in place: flags are
+/// `u8` (0 or 1), price tiers and milestones fill fixed arrays up to their counts and an
+/// unset approver is the default pubkey. Read those through the accessors below.
+/// Discriminator: sha256("account:TokenSaleV2")[..8].
+#[account(zero_copy, discriminator = [104, 178, 2, 63, 172, 231, 91, 146])]
+pub struct TokenSale {
+    pub seller: Pubkey,              // Who created the sale (32 bytes)
+    pub token_mint: Pubkey,          // Token being sold (32 bytes)
+    pub payment_mint: Pubkey,        // Payment token (32 bytes)
+    pub platform_fee_recipient: Pubkey, // Where platform fees go (32 bytes)
+    pub milestone_approver: Pubkey,  // Who can unlock milestones, default = buyer vote only (32 bytes)
+    pub price_per_token: u64,        // Price in payment token lamports (8 bytes)
+    pub total_tokens: u64,           // Original token amount (8 bytes)
+    pub tokens_available: u64,       // Tokens left to sell (8 bytes)
+    pub sale_start_time: i64,        // Unix timestamp when sale starts (8 bytes)
+    pub sale_end_time: i64,          // Unix timestamp when sale ends (8 bytes)
+    pub max_tokens_per_buyer: u64,   // Maximum tokens one buyer can purchase (8 bytes)
+    pub max_tokens_per_tx: u64,      // Maximum tokens per purchase, 0 = no limit (8 bytes)
+    pub min_purchase_interval: i64,  // Seconds between purchases by one buyer, 0 = no limit (8 bytes)
+    pub max_tokens_per_slot: u64,    // Maximum tokens sold per slot, 0 = no limit (8 bytes)
+    pub throttle_slot: u64,          // Slot of the most recent purchase (8 bytes)
+    pub tokens_sold_in_slot: u64,    // Tokens sold during throttle_slot (8 bytes)
+    pub milestone_votes: u64,        // Tokens voting to unlock the current milestone (8 bytes)
+    pub milestone_vote_supply: u64,  // Tokens sold with escrowed proceeds (8 bytes)
+    pub proceeds_escrowed: u64,      // Seller proceeds paid into the proceeds vault (8 bytes)
+    pub proceeds_released: u64,      // Proceeds released to the seller so far (8 bytes)
+    pub price_tiers: [PriceTier; MAX_PRICE_TIERS], // Volume price breakpoints (5 * 16 = 80 bytes)
+    pub milestone_deadlines: [i64; MAX_MILESTONES], // Approval deadlines, missed = refunds open (5 * 8 = 40 bytes)
+    pub milestone_release_bps: [u16; MAX_MILESTONES], // Share of proceeds released per milestone (5 * 2 = 10 bytes)
+    pub platform_fee_bps: u16,       // Platform fee in basis points (2 bytes)
+    pub price_tier_count: u8,        // Price tiers in use, 0 = flat price (1 byte)
+    pub milestone_count: u8,         // Milestones in use, 0 = pay seller directly (1 byte)
+    pub current_milestone: u8,       // Index of the next milestone to release (1 byte)
+    pub is_active: u8,               // Sale status (1 byte)
+    pub is_paused: u8,               // Emergency pause status (1 byte)
+    pub milestone_approved: u8,      // Current milestone unlocked, awaiting release (1 byte)
+    pub milestones_failed: u8,       // A deadline was missed, proceeds are refundable (1 byte)
+    pub bump: u8,                    // PDA bump seed (1 byte)
+    pub version: u8,                 // Account layout version (1 byte)
+    pub is_lottery: u8,              // Allocated by lottery instead of buy_tokens (1 byte)
+    pub is_overflow: u8,             // Allocated pro rata to deposits instead of buy_tokens (1 byte)
+    pub is_auction: u8,              // Sold by uniform-price batch auction instead of buy_tokens (1 byte)
+    // Version 3
+    pub pending_price_per_token: u64, // Announced price change, 0 = none (8 bytes)
+    pub price_change_time: i64,      // When the announced price takes effect (8 bytes)
+    // Version 4
+    pub soft_close_window: i64,      // Final seconds in which purchases extend the sale, 0 = off (8 bytes)
+    pub soft_close_extension: i64,   // Seconds each such purchase adds to the end time (8 bytes)
+    pub soft_close_ceiling: i64,     // Latest end time the soft close may reach (8 bytes)
+    // Version 5
+    pub total_deposits: u64,         // Payment deposited into an overflow sale (8 bytes)
+    pub deposits_claimed: u64,       // Overflow deposits already settled by claims (8 bytes)
+    // Version 6
+    pub purchase_hook: Pubkey,       // Program invoked after each purchase, default = none (32 bytes)
+    // Version 7
+    pub receipt_mint: Pubkey,        // Receipts paid out while tokens are locked, default = not locked (32 bytes)
+    pub unlock_time: i64,            // When receipts start to redeem for tokens (8 bytes)
+    // Version 8
+    pub screens_wallets: u8,         // Buyers are screened against the deny list (1 byte)
+    pub padding: [u8; 7],            // Keeps the size a multiple of 8 (7 bytes)
+    // Version 9
+    pub min_sale_duration: i64,      // Seconds before the seller may close early, 0 = MIN_SALE_DURATION (8 bytes)
+    pub max_sale_duration: i64,      // Longest run from start to end, 0 = MAX_SALE_DURATION (8 bytes)
+}
+
+impl TokenSale {
+    pub const INIT_SPACE: usize = std::mem::size_of::<TokenSale>(); // 576 bytes
+
+    pub fn is_active(&self) -> bool {
+        self.is_active != 0
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.is_paused != 0
+    }
+
+    pub fn milestone_approved(&self) -> bool {
+        self.milestone_approved != 0
+    }
+
+    pub fn milestones_failed(&self) -> bool {
+        self.milestones_failed != 0
+    }
+
+    pub fn is_lottery(&self) -> bool {
+        self.is_lottery != 0
+    }
+
+    pub fn is_overflow(&self) -> bool {
+        self.is_overflow != 0
+    }
+
+    pub fn is_auction(&self) -> bool {
+        self.is_auction != 0
+    }
+
+    pub fn screens_wallets(&self) -> bool {
+        self.screens_wallets != 0
+    }
+
+    /// Who can unlock milestones (None = buyer vote only)
+    pub fn milestone_approver(&self) -> Option<Pubkey> {
+        (self.milestone_approver != Pubkey::default()).then_some(self.milestone_approver)
+    }
+
+    /// Whether purchases pay out receipts for tokens held until `unlock_time`
+    pub fn is_locked(&self) -> bool {
+        self.receipt_mint != Pubkey::default()
+    }
+
+    /// Mint purchases pay out: the receipt mint while tokens are locked, else the token
+    pub fn payout_mint(&self) -> Pubkey {
+        if self.is_locked() {
+            self.receipt_mint
+        } else {
+            self.token_mint
+        }
+    }
+
+    /// Program invoked after each purchase (None = no hook)
+    pub fn purchase_hook(&self) -> Option<Pubkey> {
+        (self.purchase_hook != Pubkey::default()).then_some(self.purchase_hook)
+    }
+
+    /// Price tiers in use, ordered by threshold
+    pub fn price_tiers(&self) -> &[PriceTier] {
+        &self.price_tiers[..self.price_tier_count as usize]
+    }
+
+    /// Replace the price tiers; the caller checks there are at most `MAX_PRICE_TIERS`
+    pub fn set_price_tiers(&mut self, price_tiers: &[PriceTier]) {
+        self.price_tiers = [PriceTier::default(); MAX_PRICE_TIERS];
+        self.price_tiers[..price_tiers.len()].copy_from_slice(price_tiers);
+        self.price_tier_count = price_tiers.len() as u8;
+    }
+
+    /// Milestone at `index`, if configured
+    pub fn milestone(&self, index: u8) -> Option<Milestone> {
+        (index < self.milestone_count).then(|| Milestone {
+            release_bps: self.milestone_release_bps[index as usize],
+            deadline: self.milestone_deadlines[index as usize],
+        })
+    }
+
+    /// Proceeds release schedule, empty = pay seller directly
+    pub fn milestones(&self) -> impl Iterator<Item = Milestone> + '_ {
+        (0..self.milestone_count).filter_map(|index| self.milestone(index))
+    }
+
+    /// Replace the milestones; the caller checks there are at most `MAX_MILESTONES`
+    pub fn set_milestones(&mut self, milestones: &[Milestone]) {
+        self.milestone_deadlines = [0; MAX_MILESTONES];
+        self.milestone_release_bps = [0; MAX_MILESTONES];
+        for (index, milestone) in milestones.iter().enumerate() {
+            self.milestone_deadlines[index] = milestone.deadline;
+            self.milestone_release_bps[index] = milestone.release_bps;
+        }
+        self.milestone_count = milestones.len() as u8;
+    }
+
+    /// Seconds the sale must run before the seller may close it early, unless it sold out
+    pub fn min_sale_duration(&self) -> i64 {
+        if self.min_sale_duration > 0 {
+            self.min_sale_duration
+        } else {
+            MIN_SALE_DURATION
+        }
+    }
+
+    /// Longest the sale may run from start to end once extended
+    pub fn max_sale_duration(&self) -> i64 {
+        if self.max_sale_duration > 0 {
+            self.max_sale_duration
+        } else {
+            MAX_SALE_DURATION
+        }
+    }
+
+    /// Latest the sale can end without the seller extending it, soft close included
+    pub fn latest_end_time(&self) -> i64 {
+        self.sale_end_time.max(self.soft_close_ceiling)
+    }
+
+    /// New end time after a purchase at `current_time`, if the soft close moves it:
+    /// purchases in the last `soft_close_window` seconds push the end out by
+    /// `soft_close_extension`, never past `soft_close_ceiling`
+    pub fn soft_close_end_time(&self, current_time: i64) -> Option<i64> {
+        if self.soft_close_window == 0
+            || current_time <= self.sale_end_time.saturating_sub(self.soft_close_window)
+        {
+            return None;
+        }
+        let new_end_time = self.sale_end_time
+            .saturating_add(self.soft_close_extension)
+            .min(self.soft_close_ceiling);
+        (new_end_time > self.sale_end_time).then_some(new_end_time)
+    }
+
+    /// Pro-rata share of an overflow sale earned by `deposit`, `deposit * total_tokens /
+    /// total_deposits`, capped at what the deposit pays for. Rounds down, so the shares of
+    /// all deposits never add up to more than the supply.
+    pub fn overflow_share(&self, deposit: u64) -> Result<u64> {
+        if self.total_deposits == 0 {
+            return Ok(0);
+        }
+        let pro_rata = (deposit as u128)
+            .checked_mul(self.total_tokens as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            / self.total_deposits as u128;
+        let affordable = deposit
+            .checked_div(self.price_per_token)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok((pro_rata as u64).min(affordable))
+    }
+
+    /// Tokens a buyer's overflow deposit is allocated: their share, within the per-buyer limit
+    pub fn overflow_allocation(&self, deposit: u64) -> Result<u64> {
+        let share = self.overflow_share(deposit)?;
+        Ok(if self.max_tokens_per_buyer > 0 {
+            share.min(self.max_tokens_per_buyer)
+        } else {
+            share
+        })
+    }
+
+    /// Tokens the overflow deposits not yet claimed may still be allocated. Nothing is
+    /// owed while the sale runs, since a cancelled sale refunds every deposit.
+    pub fn overflow_reserved(&self, current_time: i64) -> Result<u64> {
+        if !self.is_overflow() || current_time <= self.sale_end_time {
+            return Ok(0);
+        }
+        let unclaimed = self.total_deposits
+            .checked_sub(self.deposits_claimed)
+            .ok_or(ErrorCode::MathOverflow)?;
+        // The shares of the unclaimed deposits add up to at most the share of their sum
+        Ok(self.overflow_share(unclaimed)?.min(self.tokens_available))
+    }
+
+    /// Make an announced price change the current price once its time has come
+    pub fn apply_due_price_change(&mut self, current_time: i64) {
+        if self.pending_price_per_token > 0 && current_time >= self.price_change_time {
+            self.price_per_token = self.pending_price_per_token;
+            self.pending_price_per_token = 0;
+            self.price_change_time = 0;
+        }
+    }
+
+    /// Ensure the current milestone can still be approved: the sale is over, a milestone
+    /// is pending, it has not been unlocked yet and its deadline has not passed
+    pub fn require_pending_milestone(&self, current_time: i64) -> Result<()> {
+        require!(!self.milestones_failed(), ErrorCode::MilestonesFailed);
+        require!(
+            current_time > self.sale_end_time || self.tokens_available == 0 || !self.is_active(),
+            ErrorCode::SaleNotEnded
+        );
+
+        let milestone = self.milestone(self.current_milestone)
+            .ok_or(ErrorCode::NoPendingMilestone)?;
+        require!(!self.milestone_approved(), ErrorCode::MilestoneAlreadyApproved);
+        require!(current_time <= milestone.deadline, ErrorCode::MilestoneDeadlinePassed);
+        Ok(())
+    }
+
+    /// Tokens sold so far, used as the cursor into the price tiers
+    pub fn tokens_sold(&self) -> Result<u64> {
+        self.total_tokens
+            .checked_sub(self.tokens_available)
+            .ok_or(ErrorCode::MathOverflow.into())
+    }
+
+    /// Gross cost of buying `token_amount` tokens at the current point of the sale.
+    /// Purchases crossing a tier boundary are charged each tier's price for the
+    /// portion that falls inside it; anything past the last tier uses `price_per_token`.
+    pub fn quote_cost(&self, token_amount: u64) -> Result<u64> {
+        let mut position = self.tokens_sold()?;
+        let mut remaining = token_amount;
+        let mut cost: u64 = 0;
+
+        for tier in self.price_tiers() {
+            if remaining == 0 {
+                break;
+            }
+            if position >= tier.tokens_sold_threshold {
+                continue;
+            }
+
+            let tokens_in_tier = remaining.min(tier.tokens_sold_threshold - position);
+            let tier_cost = tokens_in_tier
+                .checked_mul(tier.price_per_token)
+                .ok_or(ErrorCode::MathOverflow)?;
+            cost = cost.checked_add(tier_cost).ok_or(ErrorCode::MathOverflow)?;
+
+            position += tokens_in_tier;
+            remaining -= tokens_in_tier;
+        }
+
+        let base_cost = remaining
+            .checked_mul(self.price_per_token)
+            .ok_or(ErrorCode::MathOverflow)?;
+        cost.checked_add(base_cost).ok_or(ErrorCode::MathOverflow.into())
+    }
+
+    /// Most tokens `payment` buys at the current point of the sale, walking the price
+    /// tiers like `quote_cost`. Ignores supply and purchase limits.
+    pub fn tokens_for_payment(&self, payment: u64) -> Result<u64> {
+        let mut position = self.tokens_sold()?;
+        let mut budget = payment;
+        let mut tokens: u64 = 0;
+
+        for tier in self.price_tiers() {
+            if position >= tier.tokens_sold_threshold {
+                continue;
+            }
+
+            let tokens_in_tier = tier.tokens_sold_threshold - position;
+            let affordable = budget
+                .checked_div(tier.price_per_token)
+                .ok_or(ErrorCode::MathOverflow)?;
+            if affordable < tokens_in_tier {
+                return tokens.checked_add(affordable).ok_or(ErrorCode::MathOverflow.into());
+            }
+
+            tokens = tokens.checked_add(tokens_in_tier).ok_or(ErrorCode::MathOverflow)?;
+            budget -= tokens_in_tier * tier.price_per_token;
+            position += tokens_in_tier;
+        }
+
+        let base_tokens = budget
+            .checked_div(self.price_per_token)
+            .ok_or(ErrorCode::MathOverflow)?;
+        tokens.checked_add(base_tokens).ok_or(ErrorCode::MathOverflow.into())
+    }
+
+    /// Tokens a buyer who already bought `tokens_purchased` may still buy
+    pub fn allowance(&self, tokens_purchased: u64) -> u64 {
+        if self.max_tokens_per_buyer > 0 {
+            self.tokens_available
+                .min(self.max_tokens_per_buyer.saturating_sub(tokens_purchased))
+        } else {
+            self.tokens_available
+        }
+    }
+
+    /// Price a purchase of `token_amount` by a buyer who already bought `tokens_purchased`.
+    /// This is the pricing `buy_tokens` charges; sale window, pause and throttles are
+    /// checked by `buy_tokens` alone.
+    pub fn quote_purchase(&self, token_amount: u64, tokens_purchased: u64) -> Result<PurchaseQuote> {
+        require!(token_amount > 0, ErrorCode::InvalidTokenAmount);
+        require!(token_amount <= self.tokens_available, ErrorCode::InsufficientTokens);
+
+        // Check per-buyer purchase limit if set
+        if self.max_tokens_per_buyer > 0 {
+            let total_after_purchase = tokens_purchased
+                .checked_add(token_amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+            require!(
+                total_after_purchase <= self.max_tokens_per_buyer,
+                ErrorCode::ExceedsPurchaseLimit
+            );
+        }
+
+        let gross_cost = self.quote_cost(token_amount)?;
+        let platform_fee = if self.platform_fee_bps > 0 {
+            gross_cost
+                .checked_mul(self.platform_fee_bps as u64)
+                .ok_or(ErrorCode::MathOverflow)?
+                .checked_div(10000)
+                .ok_or(ErrorCode::MathOverflow)?
+        } else {
+            0
+        };
+        let seller_net = gross_cost
+            .checked_sub(platform_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(PurchaseQuote {
+            token_amount,
+            gross_cost,
+            platform_fee,
+            seller_net,
+            remaining_allowance: self.allowance(tokens_purchased) - token_amount,
+        })
+    }
+}
+
+/// Price breakdown of a purchase, returned by `quote_purchase` and `quote_exact_in`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PurchaseQuote {
+    pub token_amount: u64,           // Tokens bought (8 bytes)
+    pub gross_cost: u64,             // Total paid by the buyer, fee included (8 bytes)
+    pub platform_fee: u64,           // Part of the cost sent to the fee recipient (8 bytes)
+    pub seller_net: u64,             // Part of the cost sent to the seller or proceeds vault (8 bytes)
+    pub remaining_allowance: u64,    // Tokens the buyer may still buy afterwards (8 bytes)
+}
+
+/// What `buy_tokens` sends a sale's purchase hook, Borsh-encoded after
+/// [`PURCHASE_HOOK_DISCRIMINATOR`]. An Anchor hook program receives it as the arguments
+/// of an `on_purchase` instruction.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PurchaseHookArgs {
+    pub sale: Pubkey,                // Sale purchased from (32 bytes)
+    pub buyer: Pubkey,               // Wallet credited with the tokens (32 bytes)
+    pub token_amount: u64,           // Tokens bought (8 bytes)
+    pub cost: u64,                   // Total paid, fee included (8 bytes)
+}
+
+/// Instruction discriminator of a purchase hook call: sha256("global:on_purchase")[..8]
+pub const PURCHASE_HOOK_DISCRIMINATOR: [u8; 8] = [176, 230, 190, 109, 31, 50, 164, 231];
+
+/// Maximum number of price tiers stored on a sale
+pub const MAX_PRICE_TIERS: usize = 5;
+
+/// Largest price change a live sale may announce, in basis points of the current price
+pub const MAX_PRICE_CHANGE_BPS: u16 = 1000; // 10%
+
+/// Seconds between announcing a live sale's price change and it taking effect
+pub const PRICE_CHANGE_DELAY: i64 = 24 * 60 * 60;
+
+/// Longest a sale may run from start to end once extended, unless the seller sets its own
+pub const MAX_SALE_DURATION: i64 = 90 * 24 * 60 * 60;
+
+/// Seconds a sale must run before the seller may close it early, unless it sold out or
+/// the seller sets its own
+pub const MIN_SALE_DURATION: i64 = 24 * 60 * 60;
+
+/// Seconds after a lottery sale ends that the designated revealer has to reveal the seed
+/// before anyone may fall back to a slot hash draw
+pub const LOTTERY_REVEAL_PERIOD: i64 = 24 * 60 * 60;
+
+/// Slots between requesting a slot hash draw and the slot whose hash seeds it
+pub const LOTTERY_DRAW_DELAY_SLOTS: u64 = 10;
+
+/// Slots the SlotHashes sysvar keeps; a draw slot older than this can no longer be drawn
+pub const MAX_SLOT_HASH_AGE: u64 = 512;
+
+/// Maximum number of bid prices on an auction's ladder
+pub const MAX_AUCTION_PRICE_LEVELS: usize = 32;
+
+/// Price applied to sales until cumulative tokens sold reaches the threshold
+#[zero_copy]
+#[derive(AnchorSerialize, AnchorDeserialize, Default)]
+pub struct PriceTier {
+    pub tokens_sold_threshold: u64,  // Tier applies while tokens_sold is below this (8 bytes)
+    pub price_per_token: u64,        // Price in payment token lamports (8 bytes)
+}
+
+impl PriceTier {
+    pub const INIT_SPACE: usize = 8 + 8; // 16 bytes
+}
+
+/// Maximum number of milestones stored on a sale
+pub const MAX_MILESTONES: usize = 5;
+
+/// Tranche of escrowed proceeds unlocked by approval before a deadline
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct Milestone {
+    pub release_bps: u16,            // Share of proceeds released (2 bytes)
+    pub deadline: i64,               // Approval deadline, missed = refunds open (8 bytes)
+}
+
+impl Milestone {
+    pub const INIT_SPACE: usize = 2 + 8; // 10 bytes
+}
+
+/// Account to track individual buyer purchases.
+///
+/// Zero-copy like `TokenSale`, with `refund_claimed` stored as a `u8` flag.
+/// Discriminator: sha256("account:BuyerAccountV2")[..8].
+#[account(zero_copy, discriminator = [235, 153, 11, 44, 234, 6, 0, 58])]
+pub struct BuyerAccount {
+    pub buyer: Pubkey,              // Buyer's public key (32 bytes)
+    pub token_sale: Pubkey,         // Associated token sale (32 bytes)
+    pub tokens_purchased: u64,      // Total tokens purchased (8 bytes)
+    pub last_purchase_time: i64,    // Unix timestamp of the latest purchase (8 bytes)
+    pub payment_escrowed: u64,      // Seller proceeds paid into the proceeds vault (8 bytes)
+    pub bump: u8,                   // PDA bump seed (1 byte)
+    pub last_voted_milestone: u8,   // Last milestone voted on, as index + 1 (1 byte)
+    pub refund_claimed: u8,         // Milestone refund already paid (1 byte)
+    pub version: u8,                // Account layout version (1 byte)
+    pub overflow_claimed: u8,       // Overflow allocation and refund already paid (1 byte)
+    pub padding: [u8; 3],           // Keeps the size a multiple of 8 (3 bytes)
+    // Version 3
+    pub overflow_deposit: u64,      // Payment deposited into an overflow sale (8 bytes)
+    // Version 4
+    pub rent_payer: Pubkey,         // Who paid the rent, refunded on close; default = buyer (32 bytes)
+}
+
+impl BuyerAccount {
+    pub const INIT_SPACE: usize = std::mem::size_of::<BuyerAccount>(); // 136 bytes
+
+    /// Where the rent goes when the tracker is closed. Trackers from before version 4
+    /// were always paid for by the buyer.
+    pub fn rent_recipient(&self) -> Pubkey {
+        if self.rent_payer == Pubkey::default() {
+            self.buyer
+        } else {
+            self.rent_payer
+        }
+    }
+
+    /// Whether the buyer may still claim anything through this tracker: an overflow
+    /// deposit not yet settled, or a share of proceeds still held for milestones
+    pub fn has_pending_claim(&self, sale: &TokenSale) -> bool {
+        let overflow_pending = self.overflow_deposit > 0 && !self.overflow_claimed();
+        let refund_pending = self.payment_escrowed > 0
+            && !self.refund_claimed()
+            && sale.proceeds_released < sale.proceeds_escrowed;
+        overflow_pending || refund_pending
+    }
+
+    pub fn refund_claimed(&self) -> bool {
+        self.refund_claimed != 0
+    }
+
+    pub fn overflow_claimed(&self) -> bool {
+        self.overflow_claimed != 0
+    }
+}
+
+impl ZeroCopyVersioned for TokenSale {
+    const VERSION: u8 = 9;
+    type Legacy = legacy::TokenSale;
+    const LEGACY_SPACE: usize = 8 + legacy::TokenSale::INIT_SPACE;
+
+    fn from_legacy(legacy: legacy::TokenSale) -> Self {
+        let mut sale: Self = bytemuck::Zeroable::zeroed();
+        sale.seller = legacy.seller;
+        sale.token_mint = legacy.token_mint;
+        sale.payment_mint = legacy.payment_mint;
+        sale.platform_fee_recipient = legacy.platform_fee_recipient;
+        sale.milestone_approver = legacy.milestone_approver.unwrap_or_default();
+        sale.price_per_token = legacy.price_per_token;
+        sale.total_tokens = legacy.total_tokens;
+        sale.tokens_available = legacy.tokens_available;
+        sale.sale_start_time = legacy.sale_start_time;
+        sale.sale_end_time = legacy.sale_end_time;
+        sale.max_tokens_per_buyer = legacy.max_tokens_per_buyer;
+        sale.max_tokens_per_tx = legacy.max_tokens_per_tx;
+        sale.min_purchase_interval = legacy.min_purchase_interval;
+        sale.max_tokens_per_slot = legacy.max_tokens_per_slot;
+        sale.throttle_slot = legacy.throttle_slot;
+        sale.tokens_sold_in_slot = legacy.tokens_sold_in_slot;
+        sale.milestone_votes = legacy.milestone_votes;
+        sale.milestone_vote_supply = legacy.milestone_vote_supply;
+        sale.proceeds_escrowed = legacy.proceeds_escrowed;
+        sale.proceeds_released = legacy.proceeds_released;
+        sale.set_price_tiers(&legacy.price_tiers);
+        sale.set_milestones(&legacy.milestones);
+        sale.platform_fee_bps = legacy.platform_fee_bps;
+        sale.current_milestone = legacy.current_milestone;
+        sale.is_active = legacy.is_active as u8;
+        sale.is_paused = legacy.is_paused as u8;
+        sale.milestone_approved = legacy.milestone_approved as u8;
+        sale.milestones_failed = legacy.milestones_failed as u8;
+        sale.bump = legacy.bump;
+        sale.version = Self::VERSION;
+        sale
+    }
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+}
+
+impl ZeroCopyVersioned for BuyerAccount {
+    const VERSION: u8 = 4;
+    type Legacy = legacy::BuyerAccount;
+    const LEGACY_SPACE: usize = 8 + legacy::BuyerAccount::INIT_SPACE;
+
+    fn from_legacy(legacy: legacy::BuyerAccount) -> Self {
+        let mut buyer_account: Self = bytemuck::Zeroable::zeroed();
+        buyer_account.buyer = legacy.buyer;
+        buyer_account.token_sale = legacy.token_sale;
+        buyer_account.tokens_purchased = legacy.tokens_purchased;
+        buyer_account.last_purchase_time = legacy.last_purchase_time;
+        buyer_account.payment_escrowed = legacy.payment_escrowed;
+        buyer_account.bump = legacy.bump;
+        buyer_account.last_voted_milestone = legacy.last_voted_milestone;
+        buyer_account.refund_claimed = legacy.refund_claimed as u8;
+        buyer_account.version = Self::VERSION;
+        buyer_account
+    }
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+}
+
+/// Lottery allocation of a sale, created by `configure_lottery`.
+///
+/// Ticket `index` wins when `(multiplier * index + offset) % tickets < winners`. The
+/// multiplier is coprime with `tickets`, so that map is a permutation of the ticket
+/// indices and exactly `winners` tickets win. Both are derived from `seed`, which is
+/// recorded once drawn so anyone can recompute every ticket's result.
+#[account(zero_copy)]
+pub struct Lottery {
+    pub token_sale: Pubkey,          // Sale being allocated (32 bytes)
+    pub revealer: Pubkey,            // Who reveals the seed, default = slot hash draw only (32 bytes)
+    pub seed_commitment: [u8; 32],   // sha256 of the revealer's seed (32 bytes)
+    pub seed: [u8; 32],              // Randomness the draw used (32 bytes)
+    pub ticket_tokens: u64,          // Tokens allocated to each winning ticket (8 bytes)
+    pub tickets: u64,                // Tickets registered (8 bytes)
+    pub winners: u64,                // Winning tickets, as many as the supply covers (8 bytes)
+    pub multiplier: u64,             // Permutation multiplier, coprime with tickets (8 bytes)
+    pub offset: u64,                 // Permutation offset (8 bytes)
+    pub draw_slot: u64,              // Slot whose hash seeds a slot hash draw, 0 = not requested (8 bytes)
+    pub is_drawn: u8,                // Winners are fixed (1 byte)
+    pub bump: u8,                    // PDA bump seed (1 byte)
+    pub version: u8,                 // Account layout version (1 byte)
+    pub padding: [u8; 5],            // Keeps the size a multiple of 8 (5 bytes)
+}
+
+impl Lottery {
+    pub const INIT_SPACE: usize = std::mem::size_of::<Lottery>(); // 184 bytes
+    pub const VERSION: u8 = 1;
+
+    pub fn is_drawn(&self) -> bool {
+        self.is_drawn != 0
+    }
+
+    /// Whether a commitment was made, so the revealer gets the first chance to draw
+    pub fn has_revealer(&self) -> bool {
+        self.revealer != Pubkey::default()
+    }
+
+    /// Fix the winners from `seed`, with `tokens_available` to allocate
+    pub fn draw(&mut self, seed: [u8; 32], tokens_available: u64) {
+        self.seed = seed;
+        self.winners = self.tickets.min(tokens_available / self.ticket_tokens);
+        if self.tickets > 0 {
+            let word = |at: usize| u64::from_le_bytes(seed[at..at + 8].try_into().unwrap());
+            let mut multiplier = word(0) % self.tickets;
+            while gcd(multiplier, self.tickets) != 1 {
+                multiplier = (multiplier + 1) % self.tickets;
+            }
+            self.multiplier = multiplier;
+            self.offset = word(8) % self.tickets;
+        }
+        self.is_drawn = 1;
+    }
+
+    /// Whether ticket `index` won (always false before the draw)
+    pub fn is_winner(&self, index: u64) -> bool {
+        if !self.is_drawn() || index >= self.tickets {
+            return false;
+        }
+        let rank = (self.multiplier as u128 * index as u128 + self.offset as u128)
+            % self.tickets as u128;
+        rank < self.winners as u128
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// A buyer's lottery registration, one per buyer and sale
+#[account(zero_copy)]
+pub struct LotteryTicket {
+    pub buyer: Pubkey,              // Ticket holder (32 bytes)
+    pub token_sale: Pubkey,         // Associated token sale (32 bytes)
+    pub index: u64,                 // Registration order, input to the draw (8 bytes)
+    pub deposit: u64,               // Payment held until the ticket is claimed (8 bytes)
+    pub claimed: u8,                // Tokens or refund already paid out (1 byte)
+    pub bump: u8,                   // PDA bump seed (1 byte)
+    pub version: u8,                // Account layout version (1 byte)
+    pub padding: [u8; 5],           // Keeps the size a multiple of 8 (5 bytes)
+    pub rent_payer: Pubkey,         // Who paid the rent, refunded on close (32 bytes)
+}
+
+impl LotteryTicket {
+    pub const INIT_SPACE: usize = std::mem::size_of::<LotteryTicket>(); // 120 bytes
+    pub const VERSION: u8 = 1;
+
+    pub fn claimed(&self) -> bool {
+        self.claimed != 0
+    }
+}
+
+/// Hash of `slot` in the SlotHashes sysvar, if it is still among the recent slots
+fn recent_slot_hash(slot_hashes: &AccountInfo, slot: u64) -> Result<Option<[u8; 32]>> {
+    let data = slot_hashes.try_borrow_data()?;
+    require!(data.len() >= 8, ErrorCode::InvalidSlotHashes);
+    let count = u64::from_le_bytes(data[..8].try_into().unwrap()) as usize;
+
+    // Entries are (slot, hash) pairs, newest first
+    Ok(data[8..]
+        .chunks_exact(40)
+        .take(count)
+        .find(|entry| u64::from_le_bytes(entry[..8].try_into().unwrap()) == slot)
+        .map(|entry| entry[8..].try_into().unwrap()))
+}
+
+/// Record the draw and reserve the winners' tokens, so `cancel_sale` only returns the rest
+fn finish_lottery_draw(
+    token_sale: &AccountLoader<TokenSale>,
+    lottery: &AccountLoader<Lottery>,
+    seed: [u8; 32],
+) -> Result<()> {
+    let mut sale = token_sale.load_mut()?;
+    let mut lottery = lottery.load_mut()?;
+    lottery.draw(seed, sale.tokens_available);
+
+    let reserved = lottery.winners
+        .checked_mul(lottery.ticket_tokens)
+        .ok_or(ErrorCode::MathOverflow)?;
+    sale.tokens_available = sale.tokens_available
+        .checked_sub(reserved)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    emit!(LotteryDrawn {
+        sale: token_sale.key(),
+        seed,
+        tickets: lottery.tickets,
+        winners: lottery.winners,
+        multiplier: lottery.multiplier,
+        offset: lottery.offset,
+    });
+    msg!("Lottery drawn: {} of {} tickets win", lottery.winners, lottery.tickets);
+    Ok(())
+}
+
+/// Uniform-price batch auction of a sale, created by `configure_auction`.
+///
+/// Bids are separate `AuctionBid` accounts; this one only keeps the tokens bid at each
+/// price level, which is all `clear_auction` needs to find the clearing price.
+#[account(zero_copy)]
+pub struct Auction {
+    pub token_sale: Pubkey,          // Sale being auctioned (32 bytes)
+    pub min_price: u64,              // Lowest bid price, the seller's reserve (8 bytes)
+    pub tick_size: u64,              // Step between bid prices (8 bytes)
+    pub demand: [u64; MAX_AUCTION_PRICE_LEVELS], // Tokens bid at each price level (32 * 8 = 256 bytes)
+    pub bids: u64,                   // Bids submitted (8 bytes)
+    pub bids_settled: u64,           // Bids paid out by settle_auction_bids (8 bytes)
+    pub clearing_price: u64,         // Price every winning bid pays (8 bytes)
+    pub marginal_supply: u64,        // Tokens left for bids at the clearing level (8 bytes)
+    pub marginal_demand: u64,        // Tokens bid at the clearing level (8 bytes)
+    pub tokens_reserved: u64,        // Tokens set aside for winning bids when cleared (8 bytes)
+    pub tokens_filled: u64,          // Tokens delivered to winning bids so far (8 bytes)
+    pub price_levels: u8,            // Prices on the ladder (1 byte)
+    pub clearing_level: u8,          // Ladder index of the clearing price (1 byte)
+    pub is_cleared: u8,              // Clearing price is fixed (1 byte)
+    pub bump: u8,                    // PDA bump seed (1 byte)
+    pub version: u8,                 // Account layout version (1 byte)
+    pub is_sealed: u8,               // Bids are committed as hashes and revealed after the sale (1 byte)
+    pub forfeit_bps: u16,            // Share of an unrevealed sealed bid's collateral paid to the seller (2 bytes)
+    pub collateral: u64,             // Deposit committed with each sealed bid (8 bytes)
+    pub reveal_period: i64,          // Seconds after the sale ends for revealing sealed bids (8 bytes)
+    pub bids_revealed: u64,          // Sealed bids revealed (8 bytes)
+}
+
+impl Auction {
+    pub const INIT_SPACE: usize = std::mem::size_of::<Auction>(); // 392 bytes
+    pub const VERSION: u8 = 1;
+
+    pub fn is_cleared(&self) -> bool {
+        self.is_cleared != 0
+    }
+
+    pub fn is_sealed(&self) -> bool {
+        self.is_sealed != 0
+    }
+
+    /// Bid price at ladder index `level`
+    pub fn level_price(&self, level: u8) -> u64 {
+        self.min_price + self.tick_size * level as u64
+    }
+
+    /// Ladder index of `price`, if it is one of the auction's bid prices
+    pub fn price_level(&self, price: u64) -> Option<u8> {
+        let above_min = price.checked_sub(self.min_price)?;
+        let level = if self.tick_size == 0 {
+            (above_min == 0).then_some(0)?
+        } else {
+            (above_min % self.tick_size == 0).then_some(above_min / self.tick_size)?
+        };
+        (level < self.price_levels as u64).then_some(level as u8)
+    }
+
+    /// Fix the clearing price for `supply` tokens, walking the levels from the top until
+    /// the demand covers the supply. If it never does, every bid wins at the lowest
+    /// price bid.
+    pub fn clear(&mut self, supply: u64) {
+        let mut sold: u64 = 0;
+        for level in (0..self.price_levels).rev() {
+            let demand = self.demand[level as usize];
+            if demand == 0 {
+                continue;
+            }
+            self.clearing_level = level;
+            self.marginal_demand = demand;
+            if sold.saturating_add(demand) >= supply {
+                self.marginal_supply = supply - sold;
+                sold = supply;
+                break;
+            }
+            self.marginal_supply = demand;
+            sold += demand;
+        }
+        self.clearing_price = self.level_price(self.clearing_level);
+        self.tokens_reserved = sold;
+        self.is_cleared = 1;
+    }
+
+    /// Tokens a bid for `quantity` at ladder index `level` wins: all of it above the
+    /// clearing price, a pro-rata share of the marginal supply at it (rounded down) and
+    /// none below it or before clearing
+    pub fn fill(&self, level: u8, quantity: u64) -> u64 {
+        if !self.is_cleared() || level < self.clearing_level {
+            0
+        } else if level > self.clearing_level || self.marginal_supply >= self.marginal_demand {
+            quantity
+        } else {
+            (quantity as u128 * self.marginal_supply as u128 / self.marginal_demand as u128) as u64
+        }
+    }
+}
+
+/// A bid in a sale's auction, with its full deposit held in the bid vault until settled
+#[account(zero_copy)]
+pub struct AuctionBid {
+    pub bidder: Pubkey,             // Who placed the bid (32 bytes)
+    pub token_sale: Pubkey,         // Associated token sale (32 bytes)
+    pub bid_id: u64,                // Bidder-chosen identifier (8 bytes)
+    pub quantity: u64,              // Tokens wanted (8 bytes)
+    pub max_price: u64,             // Highest price per token (8 bytes)
+    pub deposit: u64,               // quantity * max_price, held until settled (8 bytes)
+    pub price_level: u8,            // Ladder index of max_price (1 byte)
+    pub settled: u8,                // Fill and refund already paid out (1 byte)
+    pub bump: u8,                   // PDA bump seed (1 byte)
+    pub version: u8,                // Account layout version (1 byte)
+    pub sealed: u8,                 // Committed as a hash; quantity and price are unknown until revealed (1 byte)
+    pub revealed: u8,               // Sealed bid has been revealed (1 byte)
+    pub padding: [u8; 2],           // Keeps the size a multiple of 8 (2 bytes)
+    pub commitment: [u8; 32],       // sha256(max_price LE, quantity LE, salt, bidder) of a sealed bid (32 bytes)
+    pub rent_payer: Pubkey,         // Who paid the rent, refunded on close (32 bytes)
+}
+
+impl AuctionBid {
+    pub const INIT_SPACE: usize = std::mem::size_of::<AuctionBid>(); // 168 bytes
+    pub const VERSION: u8 = 1;
+
+    pub fn settled(&self) -> bool {
+        self.settled != 0
+    }
+
+    /// Sealed and never revealed: it takes no part in clearing
+    pub fn unrevealed(&self) -> bool {
+        self.sealed != 0 && self.revealed == 0
+    }
+
+    /// Commitment a sealed bid has to be revealed against
+    pub fn commitment_of(max_price: u64, quantity: u64, salt: &[u8; 32], bidder: &Pubkey) -> [u8; 32] {
+        anchor_lang::solana_program::hash::hashv(&[
+            &max_price.to_le_bytes(),
+            &quantity.to_le_bytes(),
+            salt,
+            bidder.as_ref(),
+        ])
+        .to_bytes()
+    }
+}
+
+/// Borsh layouts of the accounts that moved to zero-copy, kept so the migration
+/// instructions can read accounts created before the switch
+pub mod legacy {
+    use super::*;
+
+    /// `TokenSale` at layout versions 0 and 1
+    #[account]
+    #[derive(Default)]
+    pub struct TokenSale {
+        pub seller: Pubkey,
+        pub token_mint: Pubkey,
+        pub payment_mint: Pubkey,
+        pub price_per_token: u64,
+        pub total_tokens: u64,
+        pub tokens_available: u64,
+        pub sale_start_time: i64,
+        pub sale_end_time: i64,
+        pub max_tokens_per_buyer: u64,
+        pub platform_fee_bps: u16,
+        pub platform_fee_recipient: Pubkey,
+        pub is_active: bool,
+        pub is_paused: bool,
+        pub bump: u8,
+        pub max_tokens_per_tx: u64,
+        pub min_purchase_interval: i64,
+        pub max_tokens_per_slot: u64,
+        pub throttle_slot: u64,
+        pub tokens_sold_in_slot: u64,
+        pub price_tiers: Vec<PriceTier>,
+        pub milestones: Vec<Milestone>,
+        pub milestone_approver: Option<Pubkey>,
+        pub current_milestone: u8,
+        pub milestone_approved: bool,
+        pub milestone_votes: u64,
+        pub milestone_vote_supply: u64,
+        pub milestones_failed: bool,
+        pub proceeds_escrowed: u64,
+        pub proceeds_released: u64,
+        pub version: u8,
+    }
+
+    impl TokenSale {
+        pub const INIT_SPACE: usize = 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 2 + 32 + 1 + 1 + 1 + 8 + 8 + 8 + 8 + 8
+            + 4 + MAX_PRICE_TIERS * PriceTier::INIT_SPACE
+            + 4 + MAX_MILESTONES * Milestone::INIT_SPACE + 33 + 1 + 1 + 8 + 8 + 1 + 8 + 8 + 1; // 432 bytes
+    }
+
+    /// `BuyerAccount` at layout versions 0 and 1
+    #[account]
+    #[derive(Default)]
+    pub struct BuyerAccount {
+        pub buyer: Pubkey,
+        pub token_sale: Pubkey,
+        pub tokens_purchased: u64,
+        pub bump: u8,
+        pub last_purchase_time: i64,
+        pub payment_escrowed: u64,
+        pub last_voted_milestone: u8,
+        pub refund_claimed: bool,
+        pub version: u8,
+    }
+
+    impl BuyerAccount {
+        pub const INIT_SPACE: usize = 32 + 32 + 8 + 1 + 8 + 8 + 1 + 1 + 1; // 92 bytes
+    }
+}
+
+/// Two-party swap offer: maker's token A held in escrow against token B
+#[account]
+#[derive(Default)]
+pub struct Offer {
+    pub maker: Pubkey,               // Who created the offer (32 bytes)
+    pub offer_id: u64,               // Maker-chosen identifier (8 bytes)
+    pub token_mint_a: Pubkey,        // Token deposited by the maker (32 bytes)
+    pub token_mint_b: Pubkey,        // Token requested from the taker (32 bytes)
+    pub token_a_amount: u64,         // Token A held in the offer vault (8 bytes)
+    pub token_b_wanted: u64,         // Token B the taker must pay (8 bytes)
+    pub taker: Option<Pubkey>,       // Optional designated taker (1 + 32 = 33 bytes)
+    pub bump: u8,                    // PDA bump seed (1 byte)
+    pub token_a_remaining: u64,      // Token A not yet filled (8 bytes)
+    pub token_b_received: u64,       // Token B paid to the maker so far (8 bytes)
+    pub expires_at: i64,             // Unix timestamp after which fills stop, 0 = never (8 bytes)
+    pub partially_fillable: bool,    // Whether takers may fill in pieces (1 byte)
+    pub version: u8,                 // Account layout version (1 byte)
+}
+
+impl Offer {
+    pub const INIT_SPACE: usize = 32 + 8 + 32 + 32 + 8 + 8 + 33 + 1 + 8 + 8 + 8 + 1 + 1; // 180 bytes
+
+    /// Token B owed for filling `token_a_fill` more token A at the offer's fixed rate.
+    /// Computed from the cumulative fill and rounded up, so rounding always favours the
+    /// maker and the payments sum to exactly `token_b_wanted` once the offer is filled.
+    pub fn quote_fill(&self, token_a_fill: u64) -> Result<u64> {
+        let filled_before = self.token_a_amount
+            .checked_sub(self.token_a_remaining)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let filled_after = filled_before
+            .checked_add(token_a_fill)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let owed_after = (filled_after as u128)
+            .checked_mul(self.token_b_wanted as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            .div_ceil(self.token_a_amount as u128);
+        let owed_after = u64::try_from(owed_after).map_err(|_| ErrorCode::MathOverflow)?;
+
+        owed_after
+            .checked_sub(self.token_b_received)
+            .ok_or(ErrorCode::MathOverflow.into())
+    }
+}
+
+/// Service payment held for a payee, with an arbiter for disputes
+#[account]
+#[derive(Default)]
+pub struct ServiceEscrow {
+    pub payer: Pubkey,               // Who funded the escrow (32 bytes)
+    pub payee: Pubkey,               // Who is paid on release (32 bytes)
+    pub arbiter: Pubkey,             // Who resolves disputes (32 bytes)
+    pub mint: Pubkey,                // Payment token (32 bytes)
+    pub escrow_id: u64,              // Payer-chosen identifier (8 bytes)
+    pub amount: u64,                 // Funds locked in the vault (8 bytes)
+    pub release_deadline: i64,       // Auto-release timestamp (8 bytes)
+    pub status: ServiceEscrowStatus, // Funded or Disputed (1 byte)
+    pub bump: u8,                    // PDA bump seed (1 byte)
+    pub version: u8,                 // Account layout version (1 byte)
+}
+
+impl ServiceEscrow {
+    pub const INIT_SPACE: usize = 32 + 32 + 32 + 32 + 8 + 8 + 8 + 1 + 1 + 1; // 155 bytes
+}
+
+/// Service escrow lifecycle (settled escrows are closed)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ServiceEscrowStatus {
+    #[default]
+    Funded,       // Awaiting release, refund or dispute
+    Disputed,     // Only the arbiter can settle
+}
+
+/// Wallets the compliance authority has barred from buying, for every sale of the
+/// program. Each denied wallet has a `DeniedWallet` entry.
+#[account]
+#[derive(Default)]
+pub struct DenyList {
+    pub admin: Pubkey,               // Creator, who hands the list on and sets `required` (32 bytes)
+    pub authority: Pubkey,           // Compliance authority that denies and allows wallets (32 bytes)
+    pub denied_count: u64,           // Wallets currently denied (8 bytes)
+    pub required: bool,              // Every sale screens its buyers (1 byte)
+    pub bump: u8,                    // PDA bump seed (1 byte)
+    pub version: u8,                 // Account layout version (1 byte)
+}
+
+impl DenyList {
+    pub const INIT_SPACE: usize = 32 + 32 + 8 + 1 + 1 + 1; // 75 bytes
+}
+
+/// A wallet on a deny list; closing the entry lifts the denial
+#[account]
+#[derive(Default)]
+pub struct DeniedWallet {
+    pub deny_list: Pubkey,           // List the wallet is on (32 bytes)
+    pub wallet: Pubkey,              // Wallet barred from buying (32 bytes)
+    pub denied_by: Pubkey,           // Compliance authority that denied it (32 bytes)
+    pub denied_at: i64,              // Unix timestamp of the denial (8 bytes)
+    pub bump: u8,                    // PDA bump seed (1 byte)
+    pub version: u8,                 // Account layout version (1 byte)
+}
+
+impl DeniedWallet {
+    pub const INIT_SPACE: usize = 32 + 32 + 32 + 8 + 1 + 1; // 106 bytes
+}
+
+/// Account validation for initializing a sale
+#[derive(Accounts)]
+pub struct InitializeSale<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + TokenSale::INIT_SPACE,
+        seeds = [b"token_sale", seller.key().as_ref(), token_mint.key().as_ref()],
+        bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    pub token_mint: Account<'info, Mint>,
+    pub payment_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = seller_token_account.mint == token_mint.key(),
+        constraint = seller_token_account.owner == seller.key()
+    )]
+    pub seller_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = seller,
+        token::mint = token_mint,
+        token::authority = token_sale,
+        seeds = [b"token_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub token_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Account validation for creating buyer tracking account
+#[derive(Accounts)]
+pub struct CreateBuyerAccount<'info> {
+    pub buyer: Signer<'info>,
+
+    /// Covers the tracker's rent and gets it back when it is closed: the buyer or a sponsor
+    #[account(mut)]
+    pub rent_payer: Signer<'info>,
+
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        init,
+        payer = rent_payer,
+        space = 8 + BuyerAccount::INIT_SPACE,
+        seeds = [b"buyer", buyer.key().as_ref(), token_sale.key().as_ref()],
+        bump
+    )]
+    pub buyer_account: AccountLoader<'info, BuyerAccount>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Account validation for creating a buyer account on someone else's behalf
+#[derive(Accounts)]
+pub struct CreateBuyerAccountFor<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Only used as the tracker's owner and PDA seed
+    pub recipient: UncheckedAccount<'info>,
+
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + BuyerAccount::INIT_SPACE,
+        seeds = [b"buyer", recipient.key().as_ref(), token_sale.key().as_ref()],
+        bump
+    )]
+    pub buyer_account: AccountLoader<'info, BuyerAccount>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Account validation for closing a buyer account
+#[derive(Accounts)]
+pub struct CloseBuyerAccount<'info> {
+    pub buyer: Signer<'info>,
+
+    /// CHECK: Only receives the rent, must be whoever paid it
+    #[account(
+        mut,
+        constraint = rent_payer.key() == buyer_account.load()?.rent_recipient()
+    )]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        mut,
+        close = rent_payer,
+        seeds = [b"buyer", buyer.key().as_ref(), token_sale.key().as_ref()],
+        bump = buyer_account.load()?.bump
+    )]
+    pub buyer_account: AccountLoader<'info, BuyerAccount>,
+}
+
+/// Account validation for buying tokens
+#[derive(Accounts)]
+pub struct BuyTokens<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        mut,
+        seeds = [b"buyer", buyer.key().as_ref(), token_sale.key().as_ref()],
+        bump = buyer_account.load()?.bump
+    )]
+    pub buyer_account: AccountLoader<'info, BuyerAccount>,
+
+    #[account(
+        mut,
+        constraint = buyer_payment_account.mint == token_sale.load()?.payment_mint,
+        constraint = buyer_payment_account.owner == buyer.key()
+    )]
+    pub buyer_payment_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = seller_payment_account.mint == token_sale.load()?.payment_mint,
+        constraint = seller_payment_account.owner == token_sale.load()?.seller
+    )]
+    pub seller_payment_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = platform_fee_account.mint == token_sale.load()?.payment_mint,
+        constraint = platform_fee_account.owner == token_sale.load()?.platform_fee_recipient
+    )]
+    pub platform_fee_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = buyer_token_account.mint == token_sale.load()?.payout_mint(),
+        constraint = buyer_token_account.owner == buyer.key()
+    )]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"token_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub token_vault: Account<'info, TokenAccount>,
+
+    // Only required when the sale releases proceeds by milestone
+    #[account(
+        mut,
+        seeds = [b"proceeds_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub proceeds_vault: Option<Account<'info, TokenAccount>>,
+
+    // Only required when the sale locks tokens and pays out receipts
+    #[account(
+        mut,
+        seeds = [b"receipt_mint", token_sale.key().as_ref()],
+        bump
+    )]
+    pub receipt_mint: Option<Account<'info, Mint>>,
+
+    /// CHECK: The program's deny list, read once it has been created
+    #[account(
+        seeds = [b"deny_list"],
+        bump
+    )]
+    pub deny_list: UncheckedAccount<'info>,
+
+    /// CHECK: The buyer's deny-list entry, which only exists while the buyer is denied
+    #[account(
+        seeds = [b"denied", deny_list.key().as_ref(), buyer.key().as_ref()],
+        bump
+    )]
+    pub denied_buyer: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Account validation for buying tokens on behalf of a recipient
+#[derive(Accounts)]
+pub struct BuyTokensFor<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Only used as the tracker's owner and PDA seed
+    pub recipient: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        mut,
+        seeds = [b"buyer", recipient.key().as_ref(), token_sale.key().as_ref()],
+        bump = buyer_account.load()?.bump
+    )]
+    pub buyer_account: AccountLoader<'info, BuyerAccount>,
+
+    // The payer's own tracker, whose cooldown every purchase it makes restarts
+    #[account(
+        mut,
+        seeds = [b"buyer", payer.key().as_ref(), token_sale.key().as_ref()],
+        bump = payer_account.load()?.bump
+    )]
+    pub payer_account: AccountLoader<'info, BuyerAccount>,
+
+    #[account(
+        mut,
+        constraint = payer_payment_account.mint == token_sale.load()?.payment_mint,
+        constraint = payer_payment_account.owner == payer.key()
+    )]
+    pub payer_payment_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = seller_payment_account.mint == token_sale.load()?.payment_mint,
+        constraint = seller_payment_account.owner == token_sale.load()?.seller
+    )]
+    pub seller_payment_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = platform_fee_account.mint == token_sale.load()?.payment_mint,
+        constraint = platform_fee_account.owner == token_sale.load()?.platform_fee_recipient
+    )]
+    pub platform_fee_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = recipient_token_account.mint == token_sale.load()?.payout_mint(),
+        constraint = recipient_token_account.owner == recipient.key()
+    )]
+    pub recipient_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"token_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub token_vault: Account<'info, TokenAccount>,
+
+    // Only required when the sale releases proceeds by milestone
+    #[account(
+        mut,
+        seeds = [b"proceeds_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub proceeds_vault: Option<Account<'info, TokenAccount>>,
+
+    // Only required when the sale locks tokens and pays out receipts
+    #[account(
+        mut,
+        seeds = [b"receipt_mint", token_sale.key().as_ref()],
+        bump
+    )]
+    pub receipt_mint: Option<Account<'info, Mint>>,
+
+    /// CHECK: The program's deny list, read once it has been created
+    #[account(
+        seeds = [b"deny_list"],
+        bump
+    )]
+    pub deny_list: UncheckedAccount<'info>,
+
+    /// CHECK: The payer's deny-list entry, which only exists while the payer is denied
+    #[account(
+        seeds = [b"denied", deny_list.key().as_ref(), payer.key().as_ref()],
+        bump
+    )]
+    pub denied_payer: UncheckedAccount<'info>,
+
+    /// CHECK: The recipient's deny-list entry, which only exists while the recipient is denied
+    #[account(
+        seeds = [b"denied", deny_list.key().as_ref(), recipient.key().as_ref()],
+        bump
+    )]
+    pub denied_recipient: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Account validation for cancelling a sale
+#[derive(Accounts)]
+pub struct CancelSale<'info> {
+    #[account(
+        mut,
+        constraint = seller.key() == token_sale.load()?.seller
+    )]
+    pub seller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        mut,
+        constraint = seller_token_account.mint == token_sale.load()?.token_mint,
+        constraint = seller_token_account.owner == seller.key()
+    )]
+    pub seller_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"token_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub token_vault: Account<'info, TokenAccount>,
+
+    // Only required to cancel an auction sale after it ends
+    #[account(
+        seeds = [b"auction", token_sale.key().as_ref()],
+        bump = auction.load()?.bump
+    )]
+    pub auction: Option<AccountLoader<'info, Auction>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Account validation for toggling pause
+#[derive(Accounts)]
+pub struct TogglePause<'info> {
+    #[account(
+        mut,
+        constraint = seller.key() == token_sale.load()?.seller
+    )]
+    pub seller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+}
+
+/// Account validation for changing when a sale ends: extending it, closing it early or
+/// configuring its soft close
+#[derive(Accounts)]
+pub struct ChangeSaleWindow<'info> {
+    #[account(
+        constraint = seller.key() == token_sale.load()?.seller
+    )]
+    pub seller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+}
+
+/// Account validation for updating sale parameters
+#[derive(Accounts)]
+pub struct UpdateSaleParams<'info> {
+    #[account(
+        mut,
+        constraint = seller.key() == token_sale.load()?.seller
+    )]
+    pub seller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+}
+
+/// Account validation for configuring purchase throttles
+#[derive(Accounts)]
+pub struct SetPurchaseThrottles<'info> {
+    #[account(
+        mut,
+        constraint = seller.key() == token_sale.load()?.seller
+    )]
+    pub seller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+}
+
+/// Account validation for setting a purchase hook
+#[derive(Accounts)]
+pub struct SetPurchaseHook<'info> {
+    #[account(
+        constraint = seller.key() == token_sale.load()?.seller
+    )]
+    pub seller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    /// CHECK: Any executable program other than this one; purchases invoke it
+    pub hook_program: UncheckedAccount<'info>,
+}
+
+/// Account validation for turning wallet screening on or off
+#[derive(Accounts)]
+pub struct SetWalletScreening<'info> {
+    #[account(
+        constraint = seller.key() == token_sale.load()?.seller
+    )]
+    pub seller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    /// CHECK: The program's deny list, read once it has been created
+    #[account(
+        seeds = [b"deny_list"],
+        bump
+    )]
+    pub deny_list: UncheckedAccount<'info>,
+}
+
+/// Account validation for setting price tiers
+#[derive(Accounts)]
+pub struct SetPriceTiers<'info> {
+    #[account(
+        mut,
+        constraint = seller.key() == token_sale.load()?.seller
+    )]
+    pub seller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+}
+
+/// Account validation for configuring milestones
+#[derive(Accounts)]
+pub struct ConfigureMilestones<'info> {
+    #[account(
+        mut,
+        constraint = seller.key() == token_sale.load()?.seller
+    )]
+    pub seller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        constraint = payment_mint.key() == token_sale.load()?.payment_mint
+    )]
+    pub payment_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = seller,
+        token::mint = payment_mint,
+        token::authority = token_sale,
+        seeds = [b"proceeds_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub proceeds_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Account validation for approving a milestone
+#[derive(Accounts)]
+pub struct ApproveMilestone<'info> {
+    pub approver: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+}
+
+/// Account validation for voting on a milestone
+#[derive(Accounts)]
+pub struct VoteMilestone<'info> {
+    pub buyer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        mut,
+        seeds = [b"buyer", buyer.key().as_ref(), token_sale.key().as_ref()],
+        bump = buyer_account.load()?.bump
+    )]
+    pub buyer_account: AccountLoader<'info, BuyerAccount>,
+}
+
+/// Account validation for releasing a milestone tranche
+#[derive(Accounts)]
+pub struct ReleaseMilestone<'info> {
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        mut,
+        constraint = seller_payment_account.mint == token_sale.load()?.payment_mint,
+        constraint = seller_payment_account.owner == token_sale.load()?.seller
+    )]
+    pub seller_payment_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"proceeds_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub proceeds_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Account validation for claiming a milestone refund
+#[derive(Accounts)]
+pub struct ClaimMilestoneRefund<'info> {
+    pub buyer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        mut,
+        seeds = [b"buyer", buyer.key().as_ref(), token_sale.key().as_ref()],
+        bump = buyer_account.load()?.bump
+    )]
+    pub buyer_account: AccountLoader<'info, BuyerAccount>,
+
+    #[account(
+        mut,
+        constraint = buyer_payment_account.mint == token_sale.load()?.payment_mint,
+        constraint = buyer_payment_account.owner == buyer.key()
+    )]
+    pub buyer_payment_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"proceeds_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub proceeds_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Account validation for locking a sale's tokens behind receipts
+#[derive(Accounts)]
+pub struct ConfigureTokenLock<'info> {
+    #[account(
+        mut,
+        constraint = seller.key() == token_sale.load()?.seller
+    )]
+    pub seller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        constraint = token_mint.key() == token_sale.load()?.token_mint
+    )]
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = seller,
+        mint::decimals = token_mint.decimals,
+        mint::authority = token_sale,
+        seeds = [b"receipt_mint", token_sale.key().as_ref()],
+        bump
+    )]
+    pub receipt_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Account validation for redeeming allocation receipts
+#[derive(Accounts)]
+pub struct RedeemReceipts<'info> {
+    pub holder: Signer<'info>,
+
+    #[account(
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        mut,
+        seeds = [b"receipt_mint", token_sale.key().as_ref()],
+        bump
+    )]
+    pub receipt_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = holder_receipt_account.mint == receipt_mint.key(),
+        constraint = holder_receipt_account.owner == holder.key()
+    )]
+    pub holder_receipt_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = holder_token_account.mint == token_sale.load()?.token_mint
+    )]
+    pub holder_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"token_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub token_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Account validation for switching a sale to lottery allocation
+#[derive(Accounts)]
+pub struct ConfigureLottery<'info> {
+    #[account(
+        mut,
+        constraint = seller.key() == token_sale.load()?.seller
+    )]
+    pub seller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + Lottery::INIT_SPACE,
+        seeds = [b"lottery", token_sale.key().as_ref()],
+        bump
+    )]
+    pub lottery: AccountLoader<'info, Lottery>,
+
+    #[account(
+        constraint = payment_mint.key() == token_sale.load()?.payment_mint
+    )]
+    pub payment_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = seller,
+        token::mint = payment_mint,
+        token::authority = token_sale,
+        seeds = [b"lottery_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub lottery_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Account validation for registering a lottery ticket
+#[derive(Accounts)]
+pub struct RegisterLotteryTicket<'info> {
+    pub buyer: Signer<'info>,
+
+    /// Covers the ticket's rent: the buyer or a sponsor
+    #[account(mut)]
+    pub rent_payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        mut,
+        seeds = [b"lottery", token_sale.key().as_ref()],
+        bump = lottery.load()?.bump
+    )]
+    pub lottery: AccountLoader<'info, Lottery>,
+
+    #[account(
+        init,
+        payer = rent_payer,
+        space = 8 + LotteryTicket::INIT_SPACE,
+        seeds = [b"lottery_ticket", buyer.key().as_ref(), token_sale.key().as_ref()],
+        bump
+    )]
+    pub ticket: AccountLoader<'info, LotteryTicket>,
+
+    #[account(
+        mut,
+        constraint = buyer_payment_account.mint == token_sale.load()?.payment_mint,
+        constraint = buyer_payment_account.owner == buyer.key()
+    )]
+    pub buyer_payment_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"lottery_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub lottery_vault: Account<'info, TokenAccount>,
+
+    /// CHECK: The program's deny list, read once it has been created
+    #[account(
+        seeds = [b"deny_list"],
+        bump
+    )]
+    pub deny_list: UncheckedAccount<'info>,
+
+    /// CHECK: The buyer's deny-list entry, which only exists while the buyer is denied
+    #[account(
+        seeds = [b"denied", deny_list.key().as_ref(), buyer.key().as_ref()],
+        bump
+    )]
+    pub denied_buyer: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Account validation for revealing a lottery seed
+#[derive(Accounts)]
+pub struct RevealLotterySeed<'info> {
+    #[account(
+        constraint = revealer.key() == lottery.load()?.revealer
+    )]
+    pub revealer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        mut,
+        seeds = [b"lottery", token_sale.key().as_ref()],
+        bump = lottery.load()?.bump
+    )]
+    pub lottery: AccountLoader<'info, Lottery>,
+}
+
+/// Account validation for requesting and running a slot hash lottery draw
+#[derive(Accounts)]
+pub struct DrawLottery<'info> {
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        mut,
+        seeds = [b"lottery", token_sale.key().as_ref()],
+        bump = lottery.load()?.bump
+    )]
+    pub lottery: AccountLoader<'info, Lottery>,
+
+    /// CHECK: the SlotHashes sysvar, parsed by `recent_slot_hash`
+    #[account(address = anchor_lang::solana_program::sysvar::slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+}
+
+/// Account validation for closing a lottery ticket
+#[derive(Accounts)]
+pub struct CloseLotteryTicket<'info> {
+    pub buyer: Signer<'info>,
+
+    /// CHECK: Only receives the rent, must be whoever paid it
+    #[account(
+        mut,
+        constraint = rent_payer.key() == ticket.load()?.rent_payer
+    )]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        mut,
+        close = rent_payer,
+        seeds = [b"lottery_ticket", buyer.key().as_ref(), token_sale.key().as_ref()],
+        bump = ticket.load()?.bump
+    )]
+    pub ticket: AccountLoader<'info, LotteryTicket>,
+}
+
+/// Account validation for claiming a lottery ticket
+#[derive(Accounts)]
+pub struct ClaimLotteryTicket<'info> {
+    pub buyer: Signer<'info>,
+
+    #[account(
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        seeds = [b"lottery", token_sale.key().as_ref()],
+        bump = lottery.load()?.bump
+    )]
+    pub lottery: AccountLoader<'info, Lottery>,
+
+    #[account(
+        mut,
+        seeds = [b"lottery_ticket", buyer.key().as_ref(), token_sale.key().as_ref()],
+        bump = ticket.load()?.bump
+    )]
+    pub ticket: AccountLoader<'info, LotteryTicket>,
+
+    #[account(
+        mut,
+        seeds = [b"lottery_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub lottery_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"token_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub token_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = buyer_payment_account.mint == token_sale.load()?.payment_mint,
+        constraint = buyer_payment_account.owner == buyer.key()
+    )]
+    pub buyer_payment_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = buyer_token_account.mint == token_sale.load()?.token_mint,
+        constraint = buyer_token_account.owner == buyer.key()
+    )]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = seller_payment_account.mint == token_sale.load()?.payment_mint,
+        constraint = seller_payment_account.owner == token_sale.load()?.seller
+    )]
+    pub seller_payment_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = platform_fee_account.mint == token_sale.load()?.payment_mint,
+        constraint = platform_fee_account.owner == token_sale.load()?.platform_fee_recipient
+    )]
+    pub platform_fee_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Account validation for switching a sale to pro-rata overflow allocation
+#[derive(Accounts)]
+pub struct ConfigureOverflow<'info> {
+    #[account(
+        mut,
+        constraint = seller.key() == token_sale.load()?.seller
+    )]
+    pub seller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        constraint = payment_mint.key() == token_sale.load()?.payment_mint
+    )]
+    pub payment_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = seller,
+        token::mint = payment_mint,
+        token::authority = token_sale,
+        seeds = [b"deposit_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub deposit_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Account validation for depositing into an overflow sale
+#[derive(Accounts)]
+pub struct DepositOverflow<'info> {
+    pub buyer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        mut,
+        seeds = [b"buyer", buyer.key().as_ref(), token_sale.key().as_ref()],
+        bump = buyer_account.load()?.bump
+    )]
+    pub buyer_account: AccountLoader<'info, BuyerAccount>,
+
+    #[account(
+        mut,
+        constraint = buyer_payment_account.mint == token_sale.load()?.payment_mint,
+        constraint = buyer_payment_account.owner == buyer.key()
+    )]
+    pub buyer_payment_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"deposit_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub deposit_vault: Account<'info, TokenAccount>,
+
+    /// CHECK: The program's deny list, read once it has been created
+    #[account(
+        seeds = [b"deny_list"],
+        bump
+    )]
+    pub deny_list: UncheckedAccount<'info>,
+
+    /// CHECK: The buyer's deny-list entry, which only exists while the buyer is denied
+    #[account(
+        seeds = [b"denied", deny_list.key().as_ref(), buyer.key().as_ref()],
+        bump
+    )]
+    pub denied_buyer: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Account validation for claiming an overflow allocation and refund
+#[derive(Accounts)]
+pub struct ClaimOverflow<'info> {
+    pub buyer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        mut,
+        seeds = [b"buyer", buyer.key().as_ref(), token_sale.key().as_ref()],
+        bump = buyer_account.load()?.bump
+    )]
+    pub buyer_account: AccountLoader<'info, BuyerAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"deposit_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub deposit_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"token_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub token_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = buyer_payment_account.mint == token_sale.load()?.payment_mint,
+        constraint = buyer_payment_account.owner == buyer.key()
+    )]
+    pub buyer_payment_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = buyer_token_account.mint == token_sale.load()?.token_mint,
+        constraint = buyer_token_account.owner == buyer.key()
+    )]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = seller_payment_account.mint == token_sale.load()?.payment_mint,
+        constraint = seller_payment_account.owner == token_sale.load()?.seller
+    )]
+    pub seller_payment_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = platform_fee_account.mint == token_sale.load()?.payment_mint,
+        constraint = platform_fee_account.owner == token_sale.load()?.platform_fee_recipient
+    )]
+    pub platform_fee_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Account validation for switching a sale to a batch auction
+#[derive(Accounts)]
+pub struct ConfigureAuction<'info> {
+    #[account(
+        mut,
+        constraint = seller.key() == token_sale.load()?.seller
+    )]
+    pub seller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + Auction::INIT_SPACE,
+        seeds = [b"auction", token_sale.key().as_ref()],
+        bump
+    )]
+    pub auction: AccountLoader<'info, Auction>,
+
+    #[account(
+        constraint = payment_mint.key() == token_sale.load()?.payment_mint
+    )]
+    pub payment_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = seller,
+        token::mint = payment_mint,
+        token::authority = token_sale,
+        seeds = [b"bid_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub bid_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Account validation for submitting an auction bid
+#[derive(Accounts)]
+#[instruction(bid_id: u64)]
+pub struct SubmitBid<'info> {
+    pub bidder: Signer<'info>,
+
+    /// Covers the bid's rent: the bidder or a sponsor
+    #[account(mut)]
+    pub rent_payer: Signer<'info>,
+
+    #[account(
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        mut,
+        seeds = [b"auction", token_sale.key().as_ref()],
+        bump = auction.load()?.bump
+    )]
+    pub auction: AccountLoader<'info, Auction>,
+
+    #[account(
+        init,
+        payer = rent_payer,
+        space = 8 + AuctionBid::INIT_SPACE,
+        seeds = [b"auction_bid", token_sale.key().as_ref(), bidder.key().as_ref(), &bid_id.to_le_bytes()],
+        bump
+    )]
+    pub bid: AccountLoader<'info, AuctionBid>,
+
+    #[account(
+        mut,
+        constraint = bidder_payment_account.mint == token_sale.load()?.payment_mint,
+        constraint = bidder_payment_account.owner == bidder.key()
+    )]
+    pub bidder_payment_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"bid_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub bid_vault: Account<'info, TokenAccount>,
+
+    /// CHECK: The program's deny list, read once it has been created
+    #[account(
+        seeds = [b"deny_list"],
+        bump
+    )]
+    pub deny_list: UncheckedAccount<'info>,
+
+    /// CHECK: The bidder's deny-list entry, which only exists while the bidder is denied
+    #[account(
+        seeds = [b"denied", deny_list.key().as_ref(), bidder.key().as_ref()],
+        bump
+    )]
+    pub denied_bidder: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Account validation for closing an auction bid
+#[derive(Accounts)]
+#[instruction(bid_id: u64)]
+pub struct CloseAuctionBid<'info> {
+    pub bidder: Signer<'info>,
+
+    /// CHECK: Only receives the rent, must be whoever paid it
+    #[account(
+        mut,
+        constraint = rent_payer.key() == bid.load()?.rent_payer
+    )]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        mut,
+        close = rent_payer,
+        seeds = [b"auction_bid", token_sale.key().as_ref(), bidder.key().as_ref(), &bid_id.to_le_bytes()],
+        bump = bid.load()?.bump
+    )]
+    pub bid: AccountLoader<'info, AuctionBid>,
+}
+
+/// Account validation for sealing an auction's bids
+#[derive(Accounts)]
+pub struct ConfigureSealedBids<'info> {
+    #[account(
+        constraint = seller.key() == token_sale.load()?.seller
+    )]
+    pub seller: Signer<'info>,
+
+    #[account(
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        mut,
+        seeds = [b"auction", token_sale.key().as_ref()],
+        bump = auction.load()?.bump
+    )]
+    pub auction: AccountLoader<'info, Auction>,
+}
+
+/// Account validation for revealing a sealed auction bid
+#[derive(Accounts)]
+#[instruction(bid_id: u64)]
+pub struct RevealSealedBid<'info> {
+    pub bidder: Signer<'info>,
+
+    #[account(
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        mut,
+        seeds = [b"auction", token_sale.key().as_ref()],
+        bump = auction.load()?.bump
+    )]
+    pub auction: AccountLoader<'info, Auction>,
+
+    #[account(
+        mut,
+        seeds = [b"auction_bid", token_sale.key().as_ref(), bidder.key().as_ref(), &bid_id.to_le_bytes()],
+        bump = bid.load()?.bump
+    )]
+    pub bid: AccountLoader<'info, AuctionBid>,
+
+    #[account(
+        mut,
+        constraint = bidder_payment_account.mint == token_sale.load()?.payment_mint,
+        constraint = bidder_payment_account.owner == bidder.key()
+    )]
+    pub bidder_payment_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"bid_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub bid_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Account validation for clearing an auction
+#[derive(Accounts)]
+pub struct ClearAuction<'info> {
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        mut,
+        seeds = [b"auction", token_sale.key().as_ref()],
+        bump = auction.load()?.bump
+    )]
+    pub auction: AccountLoader<'info, Auction>,
+}
+
+/// Account validation for settling auction bids; the bids come as remaining accounts
+#[derive(Accounts)]
+pub struct SettleAuctionBids<'info> {
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        mut,
+        seeds = [b"auction", token_sale.key().as_ref()],
+        bump = auction.load()?.bump
+    )]
+    pub auction: AccountLoader<'info, Auction>,
+
+    #[account(
+        mut,
+        seeds = [b"bid_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub bid_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"token_vault", token_sale.key().as_ref()],
+        bump
+    )]
+    pub token_vault: Account<'info, TokenAccount>,
 
     #[account(
         mut,
-        seeds = [b"token_sale", token_sale.seller.as_ref(), token_sale.token_mint.as_ref()],
-        bump = token_sale.bump
+        constraint = seller_payment_account.mint == token_sale.load()?.payment_mint,
+        constraint = seller_payment_account.owner == token_sale.load()?.seller
     )]
-    pub token_sale: Account<'info, TokenSale>,
+    pub seller_payment_account: Account<'info, TokenAccount>,
 
     #[account(
         mut,
-        constraint = seller_token_account.mint == token_sale.token_mint,
-        constraint = seller_token_account.owner == seller.key()
+        constraint = seller_token_account.mint == token_sale.load()?.token_mint,
+        constraint = seller_token_account.owner == token_sale.load()?.seller
     )]
     pub seller_token_account: Account<'info, TokenAccount>,
 
     #[account(
         mut,
-        seeds = [b"token_vault", token_sale.key().as_ref()],
+        constraint = platform_fee_account.mint == token_sale.load()?.payment_mint,
+        constraint = platform_fee_account.owner == token_sale.load()?.platform_fee_recipient
+    )]
+    pub platform_fee_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Account validation for creating a swap offer
+#[derive(Accounts)]
+#[instruction(offer_id: u64)]
+pub struct MakeOffer<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(
+        init,
+        payer = maker,
+        space = 8 + Offer::INIT_SPACE,
+        seeds = [b"offer", maker.key().as_ref(), &offer_id.to_le_bytes()],
         bump
     )]
-    pub token_vault: Account<'info, TokenAccount>,
+    pub offer: Account<'info, Offer>,
+
+    pub token_mint_a: Account<'info, Mint>,
+    pub token_mint_b: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = maker_token_account_a.mint == token_mint_a.key(),
+        constraint = maker_token_account_a.owner == maker.key()
+    )]
+    pub maker_token_account_a: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = maker,
+        token::mint = token_mint_a,
+        token::authority = offer,
+        seeds = [b"offer_vault", offer.key().as_ref()],
+        bump
+    )]
+    pub offer_vault: Account<'info, TokenAccount>,
 
     pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
 }
 
-/// Account validation for toggling pause
+/// Account validation for taking or filling a swap offer
 #[derive(Accounts)]
-pub struct TogglePause<'info> {
+pub struct TakeOffer<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    /// CHECK: Offer maker, receives the rent of the closed accounts
     #[account(
         mut,
-        constraint = seller.key() == token_sale.seller
+        constraint = maker.key() == offer.maker
     )]
-    pub seller: Signer<'info>,
+    pub maker: AccountInfo<'info>,
 
     #[account(
         mut,
-        seeds = [b"token_sale", token_sale.seller.as_ref(), token_sale.token_mint.as_ref()],
-        bump = token_sale.bump
+        seeds = [b"offer", offer.maker.as_ref(), &offer.offer_id.to_le_bytes()],
+        bump = offer.bump
     )]
-    pub token_sale: Account<'info, TokenSale>,
+    pub offer: Account<'info, Offer>,
+
+    #[account(
+        mut,
+        constraint = taker_token_account_a.mint == offer.token_mint_a,
+        constraint = taker_token_account_a.owner == taker.key()
+    )]
+    pub taker_token_account_a: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = taker_token_account_b.mint == offer.token_mint_b,
+        constraint = taker_token_account_b.owner == taker.key()
+    )]
+    pub taker_token_account_b: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = maker_token_account_b.mint == offer.token_mint_b,
+        constraint = maker_token_account_b.owner == offer.maker
+    )]
+    pub maker_token_account_b: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"offer_vault", offer.key().as_ref()],
+        bump
+    )]
+    pub offer_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
 }
 
-/// Account validation for updating sale parameters
+/// Account validation for cancelling a swap offer
 #[derive(Accounts)]
-pub struct UpdateSaleParams<'info> {
+pub struct CancelOffer<'info> {
     #[account(
         mut,
-        constraint = seller.key() == token_sale.seller
+        constraint = maker.key() == offer.maker
     )]
-    pub seller: Signer<'info>,
+    pub maker: Signer<'info>,
+
+    #[account(
+        mut,
+        close = maker,
+        seeds = [b"offer", offer.maker.as_ref(), &offer.offer_id.to_le_bytes()],
+        bump = offer.bump
+    )]
+    pub offer: Account<'info, Offer>,
+
+    #[account(
+        mut,
+        constraint = maker_token_account_a.mint == offer.token_mint_a,
+        constraint = maker_token_account_a.owner == maker.key()
+    )]
+    pub maker_token_account_a: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"offer_vault", offer.key().as_ref()],
+        bump
+    )]
+    pub offer_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Account validation for creating a service escrow
+#[derive(Accounts)]
+#[instruction(escrow_id: u64)]
+pub struct CreateServiceEscrow<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Payee only receives funds, validated when token accounts are passed
+    pub payee: AccountInfo<'info>,
+
+    /// CHECK: Arbiter only signs later dispute resolutions
+    pub arbiter: AccountInfo<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ServiceEscrow::INIT_SPACE,
+        seeds = [b"service_escrow", payer.key().as_ref(), &escrow_id.to_le_bytes()],
+        bump
+    )]
+    pub service_escrow: Account<'info, ServiceEscrow>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = payer_token_account.mint == mint.key(),
+        constraint = payer_token_account.owner == payer.key()
+    )]
+    pub payer_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = payer,
+        token::mint = mint,
+        token::authority = service_escrow,
+        seeds = [b"service_vault", service_escrow.key().as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+/// Account validation for releasing, refunding or resolving a service escrow
+#[derive(Accounts)]
+pub struct SettleServiceEscrow<'info> {
+    pub authority: Signer<'info>,
+
+    /// CHECK: Escrow payer, receives the rent of the closed accounts
+    #[account(
+        mut,
+        constraint = payer.key() == service_escrow.payer
+    )]
+    pub payer: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        close = payer,
+        seeds = [b"service_escrow", service_escrow.payer.as_ref(), &service_escrow.escrow_id.to_le_bytes()],
+        bump = service_escrow.bump
+    )]
+    pub service_escrow: Account<'info, ServiceEscrow>,
+
+    #[account(
+        mut,
+        constraint = payer_token_account.mint == service_escrow.mint,
+        constraint = payer_token_account.owner == service_escrow.payer
+    )]
+    pub payer_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = payee_token_account.mint == service_escrow.mint,
+        constraint = payee_token_account.owner == service_escrow.payee
+    )]
+    pub payee_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"service_vault", service_escrow.key().as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Account validation for raising a dispute
+#[derive(Accounts)]
+pub struct RaiseDispute<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"service_escrow", service_escrow.payer.as_ref(), &service_escrow.escrow_id.to_le_bytes()],
+        bump = service_escrow.bump
+    )]
+    pub service_escrow: Account<'info, ServiceEscrow>,
+}
+
+/// Account validation for read-only purchase quotes
+#[derive(Accounts)]
+pub struct QuotePurchase<'info> {
+    #[account(
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    /// The buyer's tracker, if they have one, so the per-buyer limit is applied
+    #[account(
+        seeds = [b"buyer", buyer_account.load()?.buyer.as_ref(), token_sale.key().as_ref()],
+        bump = buyer_account.load()?.bump
+    )]
+    pub buyer_account: Option<AccountLoader<'info, BuyerAccount>>,
+}
+
+impl QuotePurchase<'_> {
+    /// Tokens the buyer already bought, 0 without a tracker
+    fn tokens_purchased(&self) -> Result<u64> {
+        match &self.buyer_account {
+            Some(buyer_account) => Ok(buyer_account.load()?.tokens_purchased),
+            None => Ok(0),
+        }
+    }
+
+    /// A copy of the sale priced as the next purchase would be, with any announced
+    /// price change that is due already applied
+    fn priced_sale(&self) -> Result<TokenSale> {
+        let mut sale = *self.token_sale.load()?;
+        sale.apply_due_price_change(Clock::get()?.unix_timestamp);
+        Ok(sale)
+    }
+}
+
+/// Account validation for creating the deny list
+#[derive(Accounts)]
+pub struct CreateDenyList<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + DenyList::INIT_SPACE,
+        seeds = [b"deny_list"],
+        bump
+    )]
+    pub deny_list: Account<'info, DenyList>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Account validation for updating the deny list
+#[derive(Accounts)]
+pub struct UpdateDenyList<'info> {
+    #[account(
+        constraint = admin.key() == deny_list.admin
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"deny_list"],
+        bump = deny_list.bump
+    )]
+    pub deny_list: Account<'info, DenyList>,
+}
+
+/// Account validation for denying a wallet
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct DenyWallet<'info> {
+    #[account(
+        mut,
+        constraint = authority.key() == deny_list.authority
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"deny_list"],
+        bump = deny_list.bump
+    )]
+    pub deny_list: Account<'info, DenyList>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + DeniedWallet::INIT_SPACE,
+        seeds = [b"denied", deny_list.key().as_ref(), wallet.as_ref()],
+        bump
+    )]
+    pub denied_wallet: Account<'info, DeniedWallet>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Account validation for lifting a wallet's denial
+#[derive(Accounts)]
+pub struct AllowWallet<'info> {
+    #[account(
+        mut,
+        constraint = authority.key() == deny_list.authority
+    )]
+    pub authority: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [b"token_sale", token_sale.seller.as_ref(), token_sale.token_mint.as_ref()],
-        bump = token_sale.bump
+        seeds = [b"deny_list"],
+        bump = deny_list.bump
     )]
-    pub token_sale: Account<'info, TokenSale>,
+    pub deny_list: Account<'info, DenyList>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"denied", deny_list.key().as_ref(), denied_wallet.wallet.as_ref()],
+        bump = denied_wallet.bump
+    )]
+    pub denied_wallet: Account<'info, DeniedWallet>,
+}
+
+/// Account validation for migrating any program account to its current layout
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: owner and discriminator are verified against the target type in the handler
+    #[account(mut)]
+    pub account: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrationAccounts<'info> for MigrateAccount<'info> {
+    fn account(&self) -> &AccountInfo<'info> {
+        &self.account
+    }
+
+    fn payer(&self) -> AccountInfo<'info> {
+        self.payer.to_account_info()
+    }
+
+    fn system_program(&self) -> AccountInfo<'info> {
+        self.system_program.to_account_info()
+    }
+
+    fn already_migrated(&self) -> Error {
+        ErrorCode::AccountAlreadyMigrated.into()
+    }
+}
+
+// EVENTS
+
+#[event]
+pub struct SaleInitialized {
+    pub sale: Pubkey,
+    pub seller: Pubkey,
+    pub token_mint: Pubkey,
+    pub payment_mint: Pubkey,
+    pub price_per_token: u64,
+    pub total_tokens: u64,
+    pub sale_start_time: i64,
+    pub sale_end_time: i64,
+    pub platform_fee_bps: u16,
+    pub platform_fee_recipient: Pubkey,
+}
+
+#[event]
+pub struct TokensPurchased {
+    pub sale: Pubkey,
+    pub buyer: Pubkey,
+    pub token_amount: u64,
+    pub seller_payment: u64,
+    pub platform_fee: u64,
+    pub platform_fee_recipient: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PurchasedOnBehalf {
+    pub sale: Pubkey,
+    pub payer: Pubkey,
+    pub recipient: Pubkey,
+    pub token_amount: u64,
+    pub payment: u64,
+}
+
+#[event]
+pub struct ReceiptsRedeemed {
+    pub sale: Pubkey,
+    pub holder: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct PurchaseHookSet {
+    pub sale: Pubkey,
+    pub hook_program: Pubkey,
+}
+
+#[event]
+pub struct WalletScreeningSet {
+    pub sale: Pubkey,
+    pub enabled: bool,
+}
+
+#[event]
+pub struct DenyListConfigured {
+    pub deny_list: Pubkey,
+    pub admin: Pubkey,
+    pub authority: Pubkey,
+    pub required: bool,
+}
+
+#[event]
+pub struct WalletDenied {
+    pub deny_list: Pubkey,
+    pub wallet: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct WalletAllowed {
+    pub deny_list: Pubkey,
+    pub wallet: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct BuyerAccountClosed {
+    pub sale: Pubkey,
+    pub buyer: Pubkey,
+    pub rent_payer: Pubkey,
+}
+
+#[event]
+pub struct SaleCancelled {
+    pub sale: Pubkey,
+    pub tokens_returned: u64,
+}
+
+#[event]
+pub struct PriceChangeScheduled {
+    pub sale: Pubkey,
+    pub current_price: u64,
+    pub new_price: u64,
+    pub effective_time: i64,
+}
+
+#[event]
+pub struct SaleExtended {
+    pub sale: Pubkey,
+    pub previous_end_time: i64,
+    pub new_end_time: i64,
+}
+
+#[event]
+pub struct SaleSoftCloseExtended {
+    pub sale: Pubkey,
+    pub buyer: Pubkey,
+    pub previous_end_time: i64,
+    pub new_end_time: i64,
+}
+
+#[event]
+pub struct SaleClosedEarly {
+    pub sale: Pubkey,
+    pub previous_end_time: i64,
+    pub new_end_time: i64,
+    pub tokens_available: u64,
+}
+
+#[event]
+pub struct LotteryTicketRegistered {
+    pub sale: Pubkey,
+    pub buyer: Pubkey,
+    pub index: u64,
+    pub deposit: u64,
+}
+
+#[event]
+pub struct LotteryDrawn {
+    pub sale: Pubkey,
+    pub seed: [u8; 32],
+    pub tickets: u64,
+    pub winners: u64,
+    pub multiplier: u64,
+    pub offset: u64,
+}
+
+#[event]
+pub struct LotteryTicketClaimed {
+    pub sale: Pubkey,
+    pub buyer: Pubkey,
+    pub index: u64,
+    pub won: bool,
+    pub token_amount: u64,
+    pub refund: u64,
+}
+
+#[event]
+pub struct OverflowDeposited {
+    pub sale: Pubkey,
+    pub buyer: Pubkey,
+    pub amount: u64,
+    pub total_deposits: u64,
+}
+
+#[event]
+pub struct OverflowClaimed {
+    pub sale: Pubkey,
+    pub buyer: Pubkey,
+    pub deposit: u64,
+    pub token_amount: u64,
+    pub refund: u64,
+}
+
+#[event]
+pub struct AuctionBidSubmitted {
+    pub sale: Pubkey,
+    pub bidder: Pubkey,
+    pub bid_id: u64,
+    pub quantity: u64,
+    pub max_price: u64,
+    pub deposit: u64,
+}
+
+#[event]
+pub struct AuctionCleared {
+    pub sale: Pubkey,
+    pub clearing_price: u64,
+    pub tokens_sold: u64,
+    pub bids: u64,
+}
+
+#[event]
+pub struct AuctionBidSettled {
+    pub sale: Pubkey,
+    pub bidder: Pubkey,
+    pub bid_id: u64,
+    pub filled: u64,
+    pub cost: u64,
+    pub forfeited: u64,
+    pub refund: u64,
+}
+
+#[event]
+pub struct SealedBidCommitted {
+    pub sale: Pubkey,
+    pub bidder: Pubkey,
+    pub bid_id: u64,
+    pub collateral: u64,
+}
+
+#[event]
+pub struct SealedBidRevealed {
+    pub sale: Pubkey,
+    pub bidder: Pubkey,
+    pub bid_id: u64,
+    pub quantity: u64,
+    pub max_price: u64,
+    pub deposit: u64,
 }
 
 /// Custom error codes
@@ -530,4 +5590,176 @@ pub enum ErrorCode {
     InvalidPlatformFee,
     #[msg("Sale has already started, cannot modify parameters")]
     SaleAlreadyStarted,
+    #[msg("Purchase exceeds per-transaction limit")]
+    ExceedsTransactionLimit,
+    #[msg("Buyer must wait before purchasing again")]
+    PurchaseCooldownActive,
+    #[msg("Purchase exceeds per-slot limit")]
+    ExceedsSlotLimit,
+    #[msg("Invalid throttle configuration: interval cannot be negative")]
+    InvalidThrottleConfig,
+    #[msg("Too many price tiers: maximum 5 allowed")]
+    TooManyPriceTiers,
+    #[msg("Invalid price tiers: thresholds must increase and not exceed total tokens")]
+    InvalidPriceTiers,
+    #[msg("Offer is reserved for a different taker")]
+    UnauthorizedTaker,
+    #[msg("Offer has expired")]
+    OfferExpired,
+    #[msg("Offer does not allow partial fills")]
+    PartialFillsDisabled,
+    #[msg("Invalid offer expiry: must be zero or in the future")]
+    InvalidOfferExpiry,
+    #[msg("Invalid release deadline: must be in the future")]
+    InvalidReleaseDeadline,
+    #[msg("Signer is not allowed to act on this escrow")]
+    UnauthorizedEscrowParty,
+    #[msg("Invalid escrow status for this operation")]
+    InvalidEscrowStatus,
+    #[msg("Release deadline has passed")]
+    ReleaseDeadlinePassed,
+    #[msg("Release deadline has not been reached")]
+    ReleaseDeadlineNotReached,
+    #[msg("Invalid split: must be 10000 basis points or less")]
+    InvalidSplitBps,
+    #[msg("Milestones have already been configured")]
+    MilestonesAlreadyConfigured,
+    #[msg("Too many milestones: maximum 5 allowed")]
+    TooManyMilestones,
+    #[msg("Invalid milestones: deadlines must follow the sale end in order and shares must sum to 10000 bps")]
+    InvalidMilestones,
+    #[msg("Proceeds vault must be provided for milestone sales")]
+    ProceedsVaultRequired,
+    #[msg("Signer is not the milestone approver")]
+    UnauthorizedMilestoneApprover,
+    #[msg("Sale has not ended yet")]
+    SaleNotEnded,
+    #[msg("No pending milestone")]
+    NoPendingMilestone,
+    #[msg("Milestone is already approved")]
+    MilestoneAlreadyApproved,
+    #[msg("Milestone is not approved")]
+    MilestoneNotApproved,
+    #[msg("Milestone deadline has passed")]
+    MilestoneDeadlinePassed,
+    #[msg("Milestone deadline has not been missed")]
+    MilestoneDeadlineNotMissed,
+    #[msg("Milestones have failed, proceeds are refundable")]
+    MilestonesFailed,
+    #[msg("Buyer has already voted on this milestone")]
+    AlreadyVoted,
+    #[msg("Refund has already been claimed")]
+    RefundAlreadyClaimed,
+    #[msg("Account is already at the current version")]
+    AccountAlreadyMigrated,
+    #[msg("Price change exceeds the maximum allowed for a live sale")]
+    PriceChangeTooLarge,
+    #[msg("Sale would run longer than the maximum duration")]
+    SaleDurationTooLong,
+    #[msg("Sale can only close early after the minimum duration or once sold out")]
+    EarlyCloseNotAllowed,
+    #[msg("Invalid soft close: window and extension must be positive and the ceiling after the sale end")]
+    InvalidSoftClose,
+    #[msg("Lottery sales cannot use price tiers or milestones")]
+    LotteryIncompatible,
+    #[msg("Lottery sales are bought by registering a ticket")]
+    LotterySale,
+    #[msg("Invalid lottery: a seed commitment needs a revealer and the reverse")]
+    InvalidLotteryConfig,
+    #[msg("Lottery has already been drawn")]
+    LotteryAlreadyDrawn,
+    #[msg("Revealed seed does not match the commitment")]
+    LotterySeedMismatch,
+    #[msg("The revealer may still reveal the lottery seed")]
+    LotteryRevealPending,
+    #[msg("A lottery draw is already requested")]
+    LotteryDrawRequested,
+    #[msg("The lottery draw slot has not been reached")]
+    LotteryDrawSlotNotReached,
+    #[msg("The lottery draw slot hash has expired; request a new draw")]
+    LotteryDrawSlotExpired,
+    #[msg("Lottery ticket already claimed")]
+    LotteryTicketClaimed,
+    #[msg("Lottery has not been drawn")]
+    LotteryNotDrawn,
+    #[msg("Invalid SlotHashes sysvar data")]
+    InvalidSlotHashes,
+    #[msg("Overflow sales cannot use price tiers, milestones, a lottery or live price changes")]
+    OverflowIncompatible,
+    #[msg("Overflow sales are bought by depositing")]
+    OverflowSale,
+    #[msg("Sale is not an overflow sale")]
+    NotOverflowSale,
+    #[msg("No overflow deposit to claim")]
+    NoOverflowDeposit,
+    #[msg("Overflow deposit already claimed")]
+    OverflowAlreadyClaimed,
+    #[msg("Auction sales cannot use price tiers, milestones, a per-buyer cap, a lottery or overflow deposits")]
+    AuctionIncompatible,
+    #[msg("Auction sales are bought by bidding")]
+    AuctionSale,
+    #[msg("Sale is not an auction")]
+    NotAuctionSale,
+    #[msg("Invalid auction: needs 1 to 32 price levels, a tick size and a payable top price")]
+    InvalidAuctionConfig,
+    #[msg("Bid price is not on the auction's price ladder")]
+    InvalidBidPrice,
+    #[msg("Auction has already been cleared")]
+    AuctionAlreadyCleared,
+    #[msg("Auction has not been cleared")]
+    AuctionNotCleared,
+    #[msg("Bid already settled")]
+    BidAlreadySettled,
+    #[msg("Settlement accounts must be (bid, payment account, token account) triples of this sale's bidders")]
+    InvalidSettlementAccounts,
+    #[msg("Invalid sealed bids: needs collateral, a reveal period up to the maximum sale duration and 1 to 10000 bps forfeited")]
+    InvalidSealedBidConfig,
+    #[msg("Auction bids are sealed: commit a hash instead")]
+    SealedAuction,
+    #[msg("Auction bids are not sealed")]
+    NotSealedAuction,
+    #[msg("Reveal period has ended")]
+    RevealPeriodEnded,
+    #[msg("Sealed bids are still being revealed")]
+    RevealPeriodActive,
+    #[msg("Bid is not sealed or already revealed")]
+    BidAlreadyRevealed,
+    #[msg("Revealed bid does not match its commitment")]
+    SealedBidMismatch,
+    #[msg("Buyer account still has an unsettled deposit or refund")]
+    BuyerAccountInUse,
+    #[msg("Sale already has a purchase hook")]
+    PurchaseHookAlreadySet,
+    #[msg("Purchase hook must be an executable program other than this one")]
+    InvalidPurchaseHook,
+    #[msg("Purchase hook program must lead the remaining accounts")]
+    PurchaseHookAccountsMissing,
+    #[msg("Token lock cannot be combined with lottery, overflow or auction allocation")]
+    TokenLockIncompatible,
+    #[msg("Unlock time must not precede the end of the sale")]
+    InvalidUnlockTime,
+    #[msg("Sale does not lock tokens behind receipts")]
+    NotLockedSale,
+    #[msg("Tokens are still locked")]
+    TokensLocked,
+    #[msg("Receipt mint must be provided for sales that lock tokens")]
+    ReceiptMintRequired,
+    #[msg("The deny list requires screening buyers")]
+    DenyListRequired,
+    #[msg("Wallet is on the deny list")]
+    WalletDenied,
+    #[msg("Sale durations must not be negative and the minimum must not exceed the maximum")]
+    InvalidSaleDuration,
+    #[msg("Auction account must be provided to cancel an auction sale after it ends")]
+    AuctionRequired,
+    #[msg("Lottery ticket has not been claimed")]
+    LotteryTicketNotClaimed,
+    #[msg("Bid has not been settled")]
+    BidNotSettled,
+    #[msg("Arbiter must be neither the payer nor the payee")]
+    InvalidArbiter,
+    #[msg("The lottery reveal period is over; the draw falls back to a slot hash")]
+    LotteryRevealExpired,
+    #[msg("Soft close only applies to fixed-price sales, not lotteries, overflow sales or auctions")]
+    SoftCloseIncompatible,
 }
\ No newline at end of file
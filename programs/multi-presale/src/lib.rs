@@ -1,5 +1,7 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+pub use token_sale_migration::{Versioned, ZeroCopyVersioned};
+use token_sale_migration::{migrate_account, migrate_to_zero_copy, versioned, MigrationAccounts};
 
 // Multi-project presale platform program ID
 declare_id!("11111111111111111111111111111112");
@@ -28,6 +30,7 @@ pub mod multi_presale {
         platform.min_project_duration = min_project_duration;
         platform.max_project_duration = max_project_duration;
         platform.bump = ctx.bumps.platform_account;
+        platform.version = PlatformAccount::VERSION;
 
         emit!(PlatformInitialized {
             authority: platform.authority,
@@ -82,6 +85,7 @@ pub mod multi_presale {
         project.approved_by = None;
         project.approved_at = None;
         project.bump = ctx.bumps.project_account;
+        project.version = ProjectAccount::VERSION;
 
         emit!(ProjectCreated {
             project_id,
@@ -148,6 +152,7 @@ pub mod multi_presale {
         sale_config.payment_mint = ctx.accounts.payment_mint.key();
         sale_config.is_active = true;
         sale_config.bump = ctx.bumps.sale_configuration;
+        sale_config.version = SaleConfiguration::VERSION;
 
         emit!(SaleConfigured {
             project_id: sale_config.project_id,
@@ -221,14 +226,14 @@ pub mod multi_presale {
         require!(end_time > start_time, ErrorCode::InvalidEndTime);
         require!(start_time > Clock::get()?.unix_timestamp, ErrorCode::InvalidStartTime);
         
-        let sale_round = &mut ctx.accounts.sale_round;
+        let mut sale_round = ctx.accounts.sale_round.load_init()?;
         let project = &ctx.accounts.project_account;
 
         // Verify project has vault initialized
         require!(project.token_vault.is_some(), ErrorCode::ProjectVaultNotInitialized);
 
         sale_round.project_id = project.id;
-        sale_round.sale_type = sale_type;
+        sale_round.sale_type = sale_type as u8;
         sale_round.round_number = round_number;
         sale_round.token_price = token_price;
         sale_round.total_tokens = total_tokens;
@@ -236,11 +241,12 @@ pub mod multi_presale {
         sale_round.max_tokens_per_buyer = max_tokens_per_buyer;
         sale_round.start_time = start_time;
         sale_round.end_time = end_time;
-        sale_round.is_active = true;
-        sale_round.whitelist_required = whitelist_required;
+        sale_round.is_active = 1;
+        sale_round.whitelist_required = whitelist_required as u8;
         sale_round.min_purchase = min_purchase;
         sale_round.max_total_raise = max_total_raise;
         sale_round.bump = ctx.bumps.sale_round;
+        sale_round.version = SaleRound::VERSION;
 
         emit!(SaleRoundCreated {
             project_id: project.id,
@@ -268,6 +274,7 @@ pub mod multi_presale {
         treasury.total_volume = 0;
         treasury.fee_percentage = fee_percentage;
         treasury.bump = ctx.bumps.platform_treasury;
+        treasury.version = PlatformTreasury::VERSION;
 
         Ok(())
     }
@@ -276,6 +283,7 @@ pub mod multi_presale {
     pub fn advance_project_status(ctx: Context<AdvanceProjectStatus>) -> Result<()> {
         let project = &mut ctx.accounts.project_account;
         let current_time = Clock::get()?.unix_timestamp;
+        let old_status = project.status;
 
         // Auto-advance based on conditions
         match project.status {
@@ -299,8 +307,8 @@ pub mod multi_presale {
 
         emit!(ProjectStatusChanged {
             project_id: project.id,
-            old_status: project.status.clone(),
-            new_status: project.status.clone(),
+            old_status,
+            new_status: project.status,
             timestamp: current_time,
         });
 
@@ -314,7 +322,8 @@ pub mod multi_presale {
         addresses: Vec<Pubkey>,
     ) -> Result<()> {
         let whitelist = &mut ctx.accounts.project_whitelist;
-        
+        whitelist.version = ProjectWhitelist::VERSION;
+
         require!(
             addresses.len() <= 100, // Limit batch size
             ErrorCode::TooManyAddresses
@@ -334,6 +343,81 @@ pub mod multi_presale {
         Ok(())
     }
 
+    /// Create the platform's deny list (admin only). `authority` denies and allows
+    /// wallets; `required` screens the buyers of every sale, whatever its seller chose.
+    pub fn create_deny_list(
+        ctx: Context<CreateDenyList>,
+        authority: Pubkey,
+        required: bool,
+    ) -> Result<()> {
+        let deny_list = &mut ctx.accounts.deny_list;
+        deny_list.authority = authority;
+        deny_list.denied_count = 0;
+        deny_list.required = required;
+        deny_list.bump = ctx.bumps.deny_list;
+        deny_list.version = DenyList::VERSION;
+
+        emit!(DenyListConfigured { authority, required });
+        msg!("Deny list created, kept by {} (required: {})", authority, required);
+        Ok(())
+    }
+
+    /// Hand the deny list to another compliance authority or change whether it is
+    /// required (admin only)
+    pub fn update_deny_list(
+        ctx: Context<UpdateDenyList>,
+        authority: Pubkey,
+        required: bool,
+    ) -> Result<()> {
+        let deny_list = &mut ctx.accounts.deny_list;
+        deny_list.authority = authority;
+        deny_list.required = required;
+
+        emit!(DenyListConfigured { authority, required });
+        msg!("Deny list kept by {} (required: {})", authority, required);
+        Ok(())
+    }
+
+    /// Bar `wallet` from buying in screened sales (compliance authority only, paying the
+    /// entry's rent)
+    pub fn deny_wallet(ctx: Context<DenyWallet>, wallet: Pubkey) -> Result<()> {
+        let deny_list = &mut ctx.accounts.deny_list;
+        deny_list.denied_count = deny_list.denied_count
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let entry = &mut ctx.accounts.denied_wallet;
+        entry.wallet = wallet;
+        entry.denied_by = ctx.accounts.authority.key();
+        entry.denied_at = Clock::get()?.unix_timestamp;
+        entry.bump = ctx.bumps.denied_wallet;
+        entry.version = DeniedWallet::VERSION;
+
+        emit!(WalletDenied {
+            wallet,
+            authority: entry.denied_by,
+        });
+        msg!("Wallet {} denied", wallet);
+        Ok(())
+    }
+
+    /// Lift a wallet's denial (compliance authority only). Closes its entry, returning
+    /// the rent to the authority.
+    pub fn allow_wallet(ctx: Context<AllowWallet>) -> Result<()> {
+        let deny_list = &mut ctx.accounts.deny_list;
+        deny_list.denied_count = deny_list.denied_count
+            .checked_sub(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let wallet = ctx.accounts.denied_wallet.wallet;
+
+        emit!(WalletAllowed {
+            wallet,
+            authority: ctx.accounts.authority.key(),
+        });
+        msg!("Wallet {} allowed", wallet);
+        Ok(())
+    }
+
     /// LEGACY ESCROW FUNCTIONS (to be replaced with Phase 2 multi-project functions)
     /// Initialize a new token sale with production security features
     pub fn initialize_sale(
@@ -356,7 +440,8 @@ pub mod multi_presale {
         let current_time = Clock::get()?.unix_timestamp;
         require!(sale_end_time > current_time, ErrorCode::SaleEndTimeInPast);
 
-        let sale = &mut ctx.accounts.token_sale;
+        let sale_key = ctx.accounts.token_sale.key();
+        let mut sale = ctx.accounts.token_sale.load_init()?;
         
         // Initialize sale account with security features
         sale.seller = ctx.accounts.seller.key();
@@ -370,9 +455,11 @@ pub mod multi_presale {
         sale.max_tokens_per_buyer = max_tokens_per_buyer;
         sale.platform_fee_bps = platform_fee_bps;
         sale.platform_fee_recipient = platform_fee_recipient;
-        sale.is_active = true;
-        sale.is_paused = false;
+        sale.is_active = 1;
+        sale.is_paused = 0;
         sale.bump = ctx.bumps.token_sale;
+        sale.version = TokenSale::VERSION;
+        drop(sale);
 
         // Transfer seller's tokens to the program's vault
         let transfer_ctx = CpiContext::new(
@@ -385,6 +472,19 @@ pub mod multi_presale {
         );
         token::transfer(transfer_ctx, total_tokens)?;
 
+        emit!(SaleInitialized {
+            sale: sale_key,
+            seller: ctx.accounts.seller.key(),
+            token_mint: ctx.accounts.token_mint.key(),
+            payment_mint: ctx.accounts.payment_mint.key(),
+            price_per_token,
+            total_tokens,
+            sale_start_time,
+            sale_end_time,
+            platform_fee_bps,
+            platform_fee_recipient,
+        });
+
         msg!("Token sale initialized: {} tokens at {} lamports each, from {} to {}", 
             total_tokens, price_per_token, sale_start_time, sale_end_time);
         Ok(())
@@ -392,13 +492,47 @@ pub mod multi_presale {
 
     /// Create a buyer tracking account (first-time buyers only)
     pub fn create_buyer_account(ctx: Context<CreateBuyerAccount>) -> Result<()> {
-        let buyer_account = &mut ctx.accounts.buyer_account;
+        let mut buyer_account = ctx.accounts.buyer_account.load_init()?;
         buyer_account.buyer = ctx.accounts.buyer.key();
         buyer_account.token_sale = ctx.accounts.token_sale.key();
         buyer_account.tokens_purchased = 0;
         buyer_account.bump = ctx.bumps.buyer_account;
+        buyer_account.rent_payer = ctx.accounts.rent_payer.key();
+        buyer_account.version = BuyerAccount::VERSION;
         
-        msg!("Buyer account created for {}", ctx.accounts.buyer.key());
+        msg!("Buyer account created for {}, rent paid by {}",
+            ctx.accounts.buyer.key(), ctx.accounts.rent_payer.key());
+        Ok(())
+    }
+
+    /// Close a purchase tracker once the sale is over, returning the rent to whoever paid it
+    pub fn close_buyer_account(ctx: Context<CloseBuyerAccount>) -> Result<()> {
+        let sale = ctx.accounts.token_sale.load()?;
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(current_time > sale.sale_end_time || !sale.is_active(), ErrorCode::SaleNotEnded);
+
+        msg!("Buyer account of {} closed, rent returned to {}",
+            ctx.accounts.buyer.key(), ctx.accounts.rent_payer.key());
+        Ok(())
+    }
+
+    /// Screen the sale's buyers against the platform's deny list, or stop screening
+    /// (seller only). Sellers cannot stop while the platform requires its deny list.
+    pub fn set_wallet_screening(ctx: Context<SetWalletScreening>, enabled: bool) -> Result<()> {
+        if !enabled {
+            let required = load_deny_list(&ctx.accounts.deny_list)?
+                .is_some_and(|deny_list| deny_list.required);
+            require!(!required, ErrorCode::DenyListRequired);
+        }
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
+        sale.screens_wallets = enabled as u8;
+
+        emit!(WalletScreeningSet {
+            sale: ctx.accounts.token_sale.key(),
+            enabled,
+        });
+        msg!("Wallet screening {}", if enabled { "enabled" } else { "disabled" });
         Ok(())
     }
 
@@ -407,12 +541,13 @@ pub mod multi_presale {
         ctx: Context<BuyTokens>,
         token_amount: u64,           // How many tokens to buy
     ) -> Result<()> {
-        // Get sale data and perform security checks
-        let sale = &ctx.accounts.token_sale;
+        // Get sale data and perform security checks. The sale is read in place and
+        // released before the transfers, which pass it to the token program.
+        let sale = ctx.accounts.token_sale.load()?;
         
         // Basic sale status checks
-        require!(sale.is_active, ErrorCode::SaleNotActive);
-        require!(!sale.is_paused, ErrorCode::SalePaused);
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
+        require!(!sale.is_paused(), ErrorCode::SalePaused);
         require!(token_amount > 0, ErrorCode::InvalidTokenAmount);
         require!(token_amount <= sale.tokens_available, ErrorCode::InsufficientTokens);
         
@@ -420,37 +555,25 @@ pub mod multi_presale {
         let current_time = Clock::get()?.unix_timestamp;
         require!(current_time >= sale.sale_start_time, ErrorCode::SaleNotStarted);
         require!(current_time <= sale.sale_end_time, ErrorCode::SaleEnded);
-        
-        // Check per-buyer purchase limit if set
-        if sale.max_tokens_per_buyer > 0 {
-            let current_purchased = ctx.accounts.buyer_account.tokens_purchased;
-            let total_after_purchase = current_purchased
-                .checked_add(token_amount)
-                .ok_or(ErrorCode::MathOverflow)?;
-            require!(
-                total_after_purchase <= sale.max_tokens_per_buyer,
-                ErrorCode::ExceedsPurchaseLimit
-            );
-        }
 
-        // Calculate payment amount and platform fee
-        let gross_payment = token_amount
-            .checked_mul(sale.price_per_token)
-            .ok_or(ErrorCode::MathOverflow)?;
-            
-        let platform_fee = if sale.platform_fee_bps > 0 {
-            gross_payment
-                .checked_mul(sale.platform_fee_bps as u64)
-                .ok_or(ErrorCode::MathOverflow)?
-                .checked_div(10000)
-                .ok_or(ErrorCode::MathOverflow)?
-        } else {
-            0
-        };
+        // Wallets on the platform's deny list cannot buy where the sale or platform screens
+        if let Some(deny_list) = load_deny_list(&ctx.accounts.deny_list)? {
+            if deny_list.required || sale.screens_wallets() {
+                require!(
+                    ctx.accounts.denied_buyer.owner != &crate::ID,
+                    ErrorCode::WalletDenied
+                );
+            }
+        }
         
-        let seller_payment = gross_payment
-            .checked_sub(platform_fee)
-            .ok_or(ErrorCode::MathOverflow)?;
+        // Per-buyer limit, payment amount and platform fee, priced exactly as the
+        // quote instructions report them
+        let quote = sale.quote_purchase(token_amount, ctx.accounts.buyer_account.load()?.tokens_purchased)?;
+        let platform_fee = quote.platform_fee;
+        let seller_payment = quote.seller_net;
+        let (seller, token_mint, bump) = (sale.seller, sale.token_mint, sale.bump);
+        let platform_fee_recipient = sale.platform_fee_recipient;
+        drop(sale);
 
         // Transfer payment from buyer to seller
         if seller_payment > 0 {
@@ -481,9 +604,9 @@ pub mod multi_presale {
         // Transfer tokens from vault to buyer
         let seeds = &[
             b"token_sale",
-            sale.seller.as_ref(),
-            sale.token_mint.as_ref(),
-            &[sale.bump],
+            seller.as_ref(),
+            token_mint.as_ref(),
+            &[bump],
         ];
         let signer = &[&seeds[..]];
 
@@ -499,33 +622,46 @@ pub mod multi_presale {
         token::transfer(token_transfer_ctx, token_amount)?;
 
         // Update sale state
-        let sale = &mut ctx.accounts.token_sale;
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
         sale.tokens_available = sale.tokens_available
             .checked_sub(token_amount)
             .ok_or(ErrorCode::MathOverflow)?;
 
         // Update buyer tracking
-        let buyer_account = &mut ctx.accounts.buyer_account;
+        let mut buyer_account = ctx.accounts.buyer_account.load_mut()?;
         buyer_account.tokens_purchased = buyer_account.tokens_purchased
             .checked_add(token_amount)
             .ok_or(ErrorCode::MathOverflow)?;
 
+        emit!(TokensPurchased {
+            sale: ctx.accounts.token_sale.key(),
+            buyer: ctx.accounts.buyer.key(),
+            token_amount,
+            seller_payment,
+            platform_fee,
+            platform_fee_recipient,
+            timestamp: current_time,
+        });
+
         msg!("Sold {} tokens for {} payment (fee: {})", token_amount, seller_payment, platform_fee);
         Ok(())
     }
 
     /// Cancel sale and return unsold tokens to seller
     pub fn cancel_sale(ctx: Context<CancelSale>) -> Result<()> {
-        let sale = &ctx.accounts.token_sale;
-        require!(sale.is_active, ErrorCode::SaleNotActive);
+        let sale = ctx.accounts.token_sale.load()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
+        let tokens_returned = sale.tokens_available;
+        let (seller, token_mint, bump) = (sale.seller, sale.token_mint, sale.bump);
+        drop(sale);
 
         // Return remaining tokens to seller
-        if sale.tokens_available > 0 {
+        if tokens_returned > 0 {
             let seeds = &[
                 b"token_sale",
-                sale.seller.as_ref(),
-                sale.token_mint.as_ref(),
-                &[sale.bump],
+                seller.as_ref(),
+                token_mint.as_ref(),
+                &[bump],
             ];
             let signer = &[&seeds[..]];
 
@@ -538,29 +674,54 @@ pub mod multi_presale {
                 },
                 signer,
             );
-            token::transfer(return_ctx, sale.tokens_available)?;
+            token::transfer(return_ctx, tokens_returned)?;
         }
 
         // Update sale state
-        let sale = &mut ctx.accounts.token_sale;
-        sale.is_active = false;
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        sale.is_active = 0;
         sale.tokens_available = 0;
 
+        emit!(SaleCancelled {
+            sale: ctx.accounts.token_sale.key(),
+            tokens_returned,
+        });
+
         msg!("Sale cancelled, tokens returned to seller");
         Ok(())
     }
 
     /// Emergency pause/unpause functionality (seller only)
     pub fn toggle_pause(ctx: Context<TogglePause>) -> Result<()> {
-        let sale = &mut ctx.accounts.token_sale;
-        require!(sale.is_active, ErrorCode::SaleNotActive);
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
         
-        sale.is_paused = !sale.is_paused;
+        sale.is_paused = !sale.is_paused() as u8;
         
-        msg!("Sale pause status changed to: {}", sale.is_paused);
+        msg!("Sale pause status changed to: {}", sale.is_paused());
         Ok(())
     }
 
+    /// Price a purchase of `token_amount` tokens the way `buy_tokens` would (read-only).
+    /// Returned as return data: simulate the transaction to read the quote.
+    pub fn quote_purchase(ctx: Context<QuotePurchase>, token_amount: u64) -> Result<PurchaseQuote> {
+        let tokens_purchased = ctx.accounts.tokens_purchased()?;
+        ctx.accounts.token_sale.load()?.quote_purchase(token_amount, tokens_purchased)
+    }
+
+    /// Quote the largest purchase `payment` (fee included) can cover within the
+    /// buyer's allowance (read-only, via return data)
+    pub fn quote_exact_in(ctx: Context<QuotePurchase>, payment: u64) -> Result<PurchaseQuote> {
+        let tokens_purchased = ctx.accounts.tokens_purchased()?;
+        let sale = ctx.accounts.token_sale.load()?;
+
+        let token_amount = payment
+            .checked_div(sale.price_per_token)
+            .ok_or(ErrorCode::MathOverflow)?
+            .min(sale.allowance(tokens_purchased));
+        sale.quote_purchase(token_amount, tokens_purchased)
+    }
+
     /// Update sale parameters (seller only, before sale starts)
     pub fn update_sale_params(
         ctx: Context<UpdateSaleParams>,
@@ -569,8 +730,8 @@ pub mod multi_presale {
         new_sale_end_time: Option<i64>,
         new_max_tokens_per_buyer: Option<u64>,
     ) -> Result<()> {
-        let sale = &mut ctx.accounts.token_sale;
-        require!(sale.is_active, ErrorCode::SaleNotActive);
+        let mut sale = ctx.accounts.token_sale.load_mut()?;
+        require!(sale.is_active(), ErrorCode::SaleNotActive);
         
         let current_time = Clock::get()?.unix_timestamp;
         require!(current_time < sale.sale_start_time, ErrorCode::SaleAlreadyStarted);
@@ -601,15 +762,83 @@ pub mod multi_presale {
         msg!("Sale parameters updated");
         Ok(())
     }
+
+    // ACCOUNT MIGRATIONS
+    // `RoundBuyerAccount` is zero-copy from the start: nothing created it in the Borsh
+    // layout, so it has no migration.
+
+    /// Upgrade the platform account to the current layout (anyone can call, payer covers rent)
+    pub fn migrate_platform(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account::<PlatformAccount>(ctx.accounts)
+    }
+
+    /// Upgrade a project to the current layout (anyone can call, payer covers rent)
+    pub fn migrate_project(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account::<ProjectAccount>(ctx.accounts)
+    }
+
+    /// Upgrade a sale tier configuration to the current layout (anyone can call, payer covers rent)
+    pub fn migrate_sale_configuration(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account::<SaleConfiguration>(ctx.accounts)
+    }
+
+    /// Convert a Borsh sale round to the zero-copy layout (anyone can call, payer covers rent)
+    pub fn migrate_sale_round(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_to_zero_copy::<SaleRound>(ctx.accounts)
+    }
+
+    /// Upgrade the platform treasury to the current layout (anyone can call, payer covers rent)
+    pub fn migrate_platform_treasury(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account::<PlatformTreasury>(ctx.accounts)
+    }
+
+    /// Upgrade a project whitelist to the current layout (anyone can call, payer covers rent)
+    pub fn migrate_whitelist(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_account::<ProjectWhitelist>(ctx.accounts)
+    }
+
+    /// Convert a Borsh token sale account to the zero-copy layout (anyone can call, payer covers rent)
+    pub fn migrate_token_sale(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_to_zero_copy::<TokenSale>(ctx.accounts)
+    }
+
+    /// Convert a Borsh buyer account to the zero-copy layout, or grow one from an older
+    /// version (anyone can call, payer covers rent)
+    pub fn migrate_buyer_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        migrate_to_zero_copy::<BuyerAccount>(ctx.accounts)
+    }
 }
 
-/// Account structure for token sale state
-#[account]
-#[derive(Default)]
+/// The platform's deny list at `info`, the `["deny_list"]` address, if it has been
+/// created. Entries live at `["denied", wallet]`, so only a denied wallet's entry is
+/// owned by this program.
+fn load_deny_list(info: &AccountInfo) -> Result<Option<DenyList>> {
+    if info.owner != &crate::ID {
+        return Ok(None);
+    }
+    Ok(Some(DenyList::try_deserialize(&mut &info.try_borrow_data()?[..])?))
+}
+
+// Current layouts of the Borsh accounts, migrated by `token_sale_migration`
+versioned!(PlatformAccount, 1);
+versioned!(ProjectAccount, 1);
+versioned!(SaleConfiguration, 1);
+versioned!(PlatformTreasury, 1);
+versioned!(ProjectWhitelist, 1);
+versioned!(DenyList, 1);
+versioned!(DeniedWallet, 1);
+
+/// Account structure for token sale state.
+///
+/// Zero-copy with a fixed layout, so purchases read and update it in place; flags are
+/// `u8` (0 or 1), read through the accessors below.
+/// Discriminator: sha256("account:TokenSaleV2")[..8].
+#[account(zero_copy, discriminator = [104, 178, 2, 63, 172, 231, 91, 146])]
 pub struct TokenSale {
     pub seller: Pubkey,              // Who created the sale (32 bytes)
     pub token_mint: Pubkey,          // Token being sold (32 bytes)
     pub payment_mint: Pubkey,        // Payment token (32 bytes)
+    pub platform_fee_recipient: Pubkey, // Where platform fees go (32 bytes)
     pub price_per_token: u64,        // Price in payment token lamports (8 bytes)
     pub total_tokens: u64,           // Original token amount (8 bytes)
     pub tokens_available: u64,       // Tokens left to sell (8 bytes)
@@ -617,28 +846,177 @@ pub struct TokenSale {
     pub sale_end_time: i64,          // Unix timestamp when sale ends (8 bytes)
     pub max_tokens_per_buyer: u64,   // Maximum tokens one buyer can purchase (8 bytes)
     pub platform_fee_bps: u16,       // Platform fee in basis points (2 bytes)
-    pub platform_fee_recipient: Pubkey, // Where platform fees go (32 bytes)
-    pub is_active: bool,             // Sale status (1 byte)
-    pub is_paused: bool,             // Emergency pause status (1 byte)
+    pub is_active: u8,               // Sale status (1 byte)
+    pub is_paused: u8,               // Emergency pause status (1 byte)
     pub bump: u8,                    // PDA bump seed (1 byte)
+    pub version: u8,                 // Account layout version (1 byte)
+    pub screens_wallets: u8,         // Buyers are screened against the deny list, 0 on older sales (1 byte)
+    pub padding: [u8; 1],            // Keeps the size a multiple of 8 (1 byte)
 }
 
 impl TokenSale {
-    pub const INIT_SPACE: usize = 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 2 + 32 + 1 + 1 + 1; // 181 bytes
+    pub const INIT_SPACE: usize = std::mem::size_of::<TokenSale>(); // 184 bytes
+
+    pub fn is_active(&self) -> bool {
+        self.is_active != 0
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.is_paused != 0
+    }
+
+    pub fn screens_wallets(&self) -> bool {
+        self.screens_wallets != 0
+    }
+
+    /// Tokens a buyer who already bought `tokens_purchased` may still buy
+    pub fn allowance(&self, tokens_purchased: u64) -> u64 {
+        if self.max_tokens_per_buyer > 0 {
+            self.tokens_available
+                .min(self.max_tokens_per_buyer.saturating_sub(tokens_purchased))
+        } else {
+            self.tokens_available
+        }
+    }
+
+    /// Price a purchase of `token_amount` by a buyer who already bought `tokens_purchased`.
+    /// This is the pricing `buy_tokens` charges; sale window and pause are checked by
+    /// `buy_tokens` alone.
+    pub fn quote_purchase(&self, token_amount: u64, tokens_purchased: u64) -> Result<PurchaseQuote> {
+        require!(token_amount > 0, ErrorCode::InvalidTokenAmount);
+        require!(token_amount <= self.tokens_available, ErrorCode::InsufficientTokens);
+
+        // Check per-buyer purchase limit if set
+        if self.max_tokens_per_buyer > 0 {
+            let total_after_purchase = tokens_purchased
+                .checked_add(token_amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+            require!(
+                total_after_purchase <= self.max_tokens_per_buyer,
+                ErrorCode::ExceedsPurchaseLimit
+            );
+        }
+
+        let gross_cost = token_amount
+            .checked_mul(self.price_per_token)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let platform_fee = if self.platform_fee_bps > 0 {
+            gross_cost
+                .checked_mul(self.platform_fee_bps as u64)
+                .ok_or(ErrorCode::MathOverflow)?
+                .checked_div(10000)
+                .ok_or(ErrorCode::MathOverflow)?
+        } else {
+            0
+        };
+        let seller_net = gross_cost
+            .checked_sub(platform_fee)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(PurchaseQuote {
+            token_amount,
+            gross_cost,
+            platform_fee,
+            seller_net,
+            remaining_allowance: self.allowance(tokens_purchased) - token_amount,
+        })
+    }
 }
 
-/// Account to track individual buyer purchases
-#[account]
-#[derive(Default)]
+/// Price breakdown of a purchase, returned by `quote_purchase` and `quote_exact_in`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PurchaseQuote {
+    pub token_amount: u64,           // Tokens bought (8 bytes)
+    pub gross_cost: u64,             // Total paid by the buyer, fee included (8 bytes)
+    pub platform_fee: u64,           // Part of the cost sent to the fee recipient (8 bytes)
+    pub seller_net: u64,             // Part of the cost sent to the seller (8 bytes)
+    pub remaining_allowance: u64,    // Tokens the buyer may still buy afterwards (8 bytes)
+}
+
+/// Account to track individual buyer purchases, zero-copy like `TokenSale`.
+/// Discriminator: sha256("account:BuyerAccountV2")[..8].
+#[account(zero_copy, discriminator = [235, 153, 11, 44, 234, 6, 0, 58])]
 pub struct BuyerAccount {
     pub buyer: Pubkey,              // Buyer's public key (32 bytes)
     pub token_sale: Pubkey,         // Associated token sale (32 bytes)
     pub tokens_purchased: u64,      // Total tokens purchased (8 bytes)
     pub bump: u8,                   // PDA bump seed (1 byte)
+    pub version: u8,                // Account layout version (1 byte)
+    pub padding: [u8; 6],           // Keeps the size a multiple of 8 (6 bytes)
+    // Version 3
+    pub rent_payer: Pubkey,         // Who paid the rent, refunded on close; default = buyer (32 bytes)
 }
 
 impl BuyerAccount {
-    pub const INIT_SPACE: usize = 32 + 32 + 8 + 1; // 73 bytes
+    pub const INIT_SPACE: usize = std::mem::size_of::<BuyerAccount>(); // 112 bytes
+
+    /// Where the rent goes when the tracker is closed. Trackers from before version 3
+    /// were always paid for by the buyer.
+    pub fn rent_recipient(&self) -> Pubkey {
+        if self.rent_payer == Pubkey::default() {
+            self.buyer
+        } else {
+            self.rent_payer
+        }
+    }
+}
+
+impl ZeroCopyVersioned for TokenSale {
+    const VERSION: u8 = 2;
+    type Legacy = legacy::TokenSale;
+    const LEGACY_SPACE: usize = 8 + legacy::TokenSale::INIT_SPACE;
+
+    fn from_legacy(legacy: legacy::TokenSale) -> Self {
+        let mut sale: Self = bytemuck::Zeroable::zeroed();
+        sale.seller = legacy.seller;
+        sale.token_mint = legacy.token_mint;
+        sale.payment_mint = legacy.payment_mint;
+        sale.platform_fee_recipient = legacy.platform_fee_recipient;
+        sale.price_per_token = legacy.price_per_token;
+        sale.total_tokens = legacy.total_tokens;
+        sale.tokens_available = legacy.tokens_available;
+        sale.sale_start_time = legacy.sale_start_time;
+        sale.sale_end_time = legacy.sale_end_time;
+        sale.max_tokens_per_buyer = legacy.max_tokens_per_buyer;
+        sale.platform_fee_bps = legacy.platform_fee_bps;
+        sale.is_active = legacy.is_active as u8;
+        sale.is_paused = legacy.is_paused as u8;
+        sale.bump = legacy.bump;
+        sale.version = Self::VERSION;
+        sale
+    }
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+}
+
+impl ZeroCopyVersioned for BuyerAccount {
+    const VERSION: u8 = 3;
+    type Legacy = legacy::BuyerAccount;
+    const LEGACY_SPACE: usize = 8 + legacy::BuyerAccount::INIT_SPACE;
+
+    fn from_legacy(legacy: legacy::BuyerAccount) -> Self {
+        let mut buyer_account: Self = bytemuck::Zeroable::zeroed();
+        buyer_account.buyer = legacy.buyer;
+        buyer_account.token_sale = legacy.token_sale;
+        buyer_account.tokens_purchased = legacy.tokens_purchased;
+        buyer_account.bump = legacy.bump;
+        buyer_account.version = Self::VERSION;
+        buyer_account
+    }
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
 }
 
 /// Account validation for initializing a sale
@@ -654,7 +1032,7 @@ pub struct InitializeSale<'info> {
         seeds = [b"token_sale", seller.key().as_ref(), token_mint.key().as_ref()],
         bump
     )]
-    pub token_sale: Account<'info, TokenSale>,
+    pub token_sale: AccountLoader<'info, TokenSale>,
 
     pub token_mint: Account<'info, Mint>,
     pub payment_mint: Account<'info, Mint>,
@@ -684,23 +1062,49 @@ pub struct InitializeSale<'info> {
 /// Account validation for creating buyer tracking account
 #[derive(Accounts)]
 pub struct CreateBuyerAccount<'info> {
-    #[account(mut)]
     pub buyer: Signer<'info>,
 
-    pub token_sale: Account<'info, TokenSale>,
+    /// Covers the tracker's rent: the buyer or a sponsor
+    #[account(mut)]
+    pub rent_payer: Signer<'info>,
+
+    pub token_sale: AccountLoader<'info, TokenSale>,
 
     #[account(
         init,
-        payer = buyer,
+        payer = rent_payer,
         space = 8 + BuyerAccount::INIT_SPACE,
         seeds = [b"buyer", buyer.key().as_ref(), token_sale.key().as_ref()],
         bump
     )]
-    pub buyer_account: Account<'info, BuyerAccount>,
+    pub buyer_account: AccountLoader<'info, BuyerAccount>,
 
     pub system_program: Program<'info, System>,
 }
 
+/// Account validation for closing a buyer account
+#[derive(Accounts)]
+pub struct CloseBuyerAccount<'info> {
+    pub buyer: Signer<'info>,
+
+    /// CHECK: Only receives the rent, must be whoever paid it
+    #[account(
+        mut,
+        constraint = rent_payer.key() == buyer_account.load()?.rent_recipient()
+    )]
+    pub rent_payer: UncheckedAccount<'info>,
+
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    #[account(
+        mut,
+        close = rent_payer,
+        seeds = [b"buyer", buyer.key().as_ref(), token_sale.key().as_ref()],
+        bump = buyer_account.load()?.bump
+    )]
+    pub buyer_account: AccountLoader<'info, BuyerAccount>,
+}
+
 /// Account validation for buying tokens
 #[derive(Accounts)]
 pub struct BuyTokens<'info> {
@@ -709,42 +1113,42 @@ pub struct BuyTokens<'info> {
 
     #[account(
         mut,
-        seeds = [b"token_sale", token_sale.seller.as_ref(), token_sale.token_mint.as_ref()],
-        bump = token_sale.bump
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
     )]
-    pub token_sale: Account<'info, TokenSale>,
+    pub token_sale: AccountLoader<'info, TokenSale>,
 
     #[account(
         mut,
         seeds = [b"buyer", buyer.key().as_ref(), token_sale.key().as_ref()],
-        bump = buyer_account.bump
+        bump = buyer_account.load()?.bump
     )]
-    pub buyer_account: Account<'info, BuyerAccount>,
+    pub buyer_account: AccountLoader<'info, BuyerAccount>,
 
     #[account(
         mut,
-        constraint = buyer_payment_account.mint == token_sale.payment_mint,
+        constraint = buyer_payment_account.mint == token_sale.load()?.payment_mint,
         constraint = buyer_payment_account.owner == buyer.key()
     )]
     pub buyer_payment_account: Account<'info, TokenAccount>,
 
     #[account(
         mut,
-        constraint = seller_payment_account.mint == token_sale.payment_mint,
-        constraint = seller_payment_account.owner == token_sale.seller
+        constraint = seller_payment_account.mint == token_sale.load()?.payment_mint,
+        constraint = seller_payment_account.owner == token_sale.load()?.seller
     )]
     pub seller_payment_account: Account<'info, TokenAccount>,
 
     #[account(
         mut,
-        constraint = platform_fee_account.mint == token_sale.payment_mint,
-        constraint = platform_fee_account.owner == token_sale.platform_fee_recipient
+        constraint = platform_fee_account.mint == token_sale.load()?.payment_mint,
+        constraint = platform_fee_account.owner == token_sale.load()?.platform_fee_recipient
     )]
     pub platform_fee_account: Account<'info, TokenAccount>,
 
     #[account(
         mut,
-        constraint = buyer_token_account.mint == token_sale.token_mint,
+        constraint = buyer_token_account.mint == token_sale.load()?.token_mint,
         constraint = buyer_token_account.owner == buyer.key()
     )]
     pub buyer_token_account: Account<'info, TokenAccount>,
@@ -756,28 +1160,65 @@ pub struct BuyTokens<'info> {
     )]
     pub token_vault: Account<'info, TokenAccount>,
 
+    /// CHECK: The platform's deny list, read if it has been created
+    #[account(
+        seeds = [b"deny_list"],
+        bump
+    )]
+    pub deny_list: UncheckedAccount<'info>,
+
+    /// CHECK: The buyer's deny-list entry, which only exists while the buyer is denied
+    #[account(
+        seeds = [b"denied", buyer.key().as_ref()],
+        bump
+    )]
+    pub denied_buyer: UncheckedAccount<'info>,
+
     pub token_program: Program<'info, Token>,
 }
 
+/// Account validation for turning wallet screening on or off
+#[derive(Accounts)]
+pub struct SetWalletScreening<'info> {
+    #[account(
+        constraint = seller.key() == token_sale.load()?.seller @ ErrorCode::UnauthorizedAccess
+    )]
+    pub seller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    /// CHECK: The platform's deny list, read if it has been created
+    #[account(
+        seeds = [b"deny_list"],
+        bump
+    )]
+    pub deny_list: UncheckedAccount<'info>,
+}
+
 /// Account validation for cancelling a sale
 #[derive(Accounts)]
 pub struct CancelSale<'info> {
     #[account(
         mut,
-        constraint = seller.key() == token_sale.seller
+        constraint = seller.key() == token_sale.load()?.seller
     )]
     pub seller: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [b"token_sale", token_sale.seller.as_ref(), token_sale.token_mint.as_ref()],
-        bump = token_sale.bump
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
     )]
-    pub token_sale: Account<'info, TokenSale>,
+    pub token_sale: AccountLoader<'info, TokenSale>,
 
     #[account(
         mut,
-        constraint = seller_token_account.mint == token_sale.token_mint,
+        constraint = seller_token_account.mint == token_sale.load()?.token_mint,
         constraint = seller_token_account.owner == seller.key()
     )]
     pub seller_token_account: Account<'info, TokenAccount>,
@@ -792,21 +1233,48 @@ pub struct CancelSale<'info> {
     pub token_program: Program<'info, Token>,
 }
 
+/// Account validation for read-only purchase quotes
+#[derive(Accounts)]
+pub struct QuotePurchase<'info> {
+    #[account(
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
+    )]
+    pub token_sale: AccountLoader<'info, TokenSale>,
+
+    /// The buyer's tracker, if they have one, so the per-buyer limit is applied
+    #[account(
+        seeds = [b"buyer", buyer_account.load()?.buyer.as_ref(), token_sale.key().as_ref()],
+        bump = buyer_account.load()?.bump
+    )]
+    pub buyer_account: Option<AccountLoader<'info, BuyerAccount>>,
+}
+
+impl QuotePurchase<'_> {
+    /// Tokens the buyer already bought, 0 without a tracker
+    fn tokens_purchased(&self) -> Result<u64> {
+        match &self.buyer_account {
+            Some(buyer_account) => Ok(buyer_account.load()?.tokens_purchased),
+            None => Ok(0),
+        }
+    }
+}
+
 /// Account validation for toggling pause
 #[derive(Accounts)]
 pub struct TogglePause<'info> {
     #[account(
         mut,
-        constraint = seller.key() == token_sale.seller
+        constraint = seller.key() == token_sale.load()?.seller
     )]
     pub seller: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [b"token_sale", token_sale.seller.as_ref(), token_sale.token_mint.as_ref()],
-        bump = token_sale.bump
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
     )]
-    pub token_sale: Account<'info, TokenSale>,
+    pub token_sale: AccountLoader<'info, TokenSale>,
 }
 
 /// Account validation for updating sale parameters
@@ -814,16 +1282,16 @@ pub struct TogglePause<'info> {
 pub struct UpdateSaleParams<'info> {
     #[account(
         mut,
-        constraint = seller.key() == token_sale.seller
+        constraint = seller.key() == token_sale.load()?.seller
     )]
     pub seller: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [b"token_sale", token_sale.seller.as_ref(), token_sale.token_mint.as_ref()],
-        bump = token_sale.bump
+        seeds = [b"token_sale", token_sale.load()?.seller.as_ref(), token_sale.load()?.token_mint.as_ref()],
+        bump = token_sale.load()?.bump
     )]
-    pub token_sale: Account<'info, TokenSale>,
+    pub token_sale: AccountLoader<'info, TokenSale>,
 }
 
 /// Custom error codes
@@ -893,6 +1361,14 @@ pub enum ErrorCode {
     InvalidRoundNumber,
     #[msg("Sale round not active")]
     SaleRoundNotActive,
+    #[msg("Account is already at the current version")]
+    AccountAlreadyMigrated,
+    #[msg("The platform requires screening buyers against its deny list")]
+    DenyListRequired,
+    #[msg("Wallet is on the platform's deny list")]
+    WalletDenied,
+    #[msg("Sale has not ended yet")]
+    SaleNotEnded,
 }
 
 // NEW MULTI-PROJECT PLATFORM DATA STRUCTURES
@@ -909,10 +1385,11 @@ pub struct PlatformAccount {
     pub min_project_duration: i64,      // Minimum sale duration in seconds (8 bytes)
     pub max_project_duration: i64,      // Maximum sale duration in seconds (8 bytes)
     pub bump: u8,                       // PDA bump seed (1 byte)
+    pub version: u8,                    // Account layout version (1 byte)
 }
 
 impl PlatformAccount {
-    pub const INIT_SPACE: usize = 32 + 32 + 2 + 8 + 1 + 8 + 8 + 1; // 92 bytes
+    pub const INIT_SPACE: usize = 32 + 32 + 2 + 8 + 1 + 8 + 8 + 1 + 1; // 93 bytes
 }
 
 /// Individual project information and metadata
@@ -940,10 +1417,11 @@ pub struct ProjectAccount {
     pub token_vault: Option<Pubkey>,    // Project-specific token vault (1 + 32 = 33 bytes)
     pub vault_bump: Option<u8>,         // Vault PDA bump (1 + 1 = 2 bytes)
     pub bump: u8,                       // PDA bump seed (1 byte)
+    pub version: u8,                    // Account layout version (1 byte)
 }
 
 impl ProjectAccount {
-    pub const INIT_SPACE: usize = 8 + 32 + 54 + 504 + 104 + 104 + 1 + 244 + 32 + 54 + 14 + 1 + 1 + 8 + 8 + 1 + 33 + 9 + 33 + 2 + 1; // ~1235 bytes
+    pub const INIT_SPACE: usize = 8 + 32 + 54 + 504 + 104 + 104 + 1 + 244 + 32 + 54 + 14 + 1 + 1 + 8 + 8 + 1 + 33 + 9 + 33 + 2 + 1 + 1; // ~1236 bytes
 }
 
 /// Sale configuration for different tiers - PHASE 2
@@ -966,10 +1444,11 @@ pub struct SaleConfiguration {
     pub payment_mint: Pubkey,          // Payment token mint (32 bytes)
     pub is_active: bool,               // Sale is active (1 byte)
     pub bump: u8,                      // PDA bump seed (1 byte)
+    pub version: u8,                   // Account layout version (1 byte)
 }
 
 impl SaleConfiguration {
-    pub const INIT_SPACE: usize = 8 + 1 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1 + 1 + 2 + 32 + 1 + 1; // 104 bytes
+    pub const INIT_SPACE: usize = 8 + 1 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1 + 1 + 2 + 32 + 1 + 1 + 1; // 105 bytes
 }
 
 /// Project categories for filtering and organization
@@ -1024,27 +1503,81 @@ impl Default for ApprovalStatus {
 }
 
 /// Enhanced sale configuration with sale rounds - COMPLETION PHASE
-#[account]
-#[derive(Default)]
+///
+/// Zero-copy with a fixed layout; `sale_type` and the flags are stored as `u8`, read
+/// through the accessors below. Discriminator: sha256("account:SaleRoundV2")[..8].
+#[account(zero_copy, discriminator = [210, 52, 80, 27, 210, 70, 251, 229])]
 pub struct SaleRound {
     pub project_id: u64,                    // Which project this belongs to (8 bytes)
-    pub sale_type: SaleType,                // Seed, Private, Public (1 byte)
-    pub round_number: u8,                   // 1, 2, 3, etc. (1 byte)
     pub token_price: u64,                   // Price per token in payment currency (8 bytes)
     pub total_tokens: u64,                  // Total tokens allocated for this round (8 bytes)
     pub tokens_sold: u64,                   // Tokens sold so far (8 bytes)
     pub max_tokens_per_buyer: u64,          // Purchase limit per buyer (8 bytes)
     pub start_time: i64,                    // When this round starts (8 bytes)
     pub end_time: i64,                      // When this round ends (8 bytes)
-    pub is_active: bool,                    // Round status (1 byte)
-    pub whitelist_required: bool,           // Whether this round requires whitelist (1 byte)
     pub min_purchase: u64,                  // Minimum purchase amount (8 bytes)
     pub max_total_raise: u64,               // Maximum total raise for this round (8 bytes)
+    pub sale_type: u8,                      // Seed, Private, Public as `SaleType as u8` (1 byte)
+    pub round_number: u8,                   // 1, 2, 3, etc. (1 byte)
+    pub is_active: u8,                      // Round status (1 byte)
+    pub whitelist_required: u8,             // Whether this round requires whitelist (1 byte)
     pub bump: u8,                           // PDA bump (1 byte)
+    pub version: u8,                        // Account layout version (1 byte)
+    pub padding: [u8; 2],                   // Keeps the size a multiple of 8 (2 bytes)
 }
 
 impl SaleRound {
-    pub const INIT_SPACE: usize = 8 + 1 + 1 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1 + 8 + 8 + 1; // 89 bytes
+    pub const INIT_SPACE: usize = std::mem::size_of::<SaleRound>(); // 80 bytes
+
+    pub fn sale_type(&self) -> SaleType {
+        match self.sale_type {
+            0 => SaleType::Seed,
+            1 => SaleType::Private,
+            _ => SaleType::Public,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.is_active != 0
+    }
+
+    pub fn whitelist_required(&self) -> bool {
+        self.whitelist_required != 0
+    }
+}
+
+impl ZeroCopyVersioned for SaleRound {
+    const VERSION: u8 = 2;
+    type Legacy = legacy::SaleRound;
+    const LEGACY_SPACE: usize = 8 + legacy::SaleRound::INIT_SPACE;
+
+    fn from_legacy(legacy: legacy::SaleRound) -> Self {
+        let mut round: Self = bytemuck::Zeroable::zeroed();
+        round.project_id = legacy.project_id;
+        round.token_price = legacy.token_price;
+        round.total_tokens = legacy.total_tokens;
+        round.tokens_sold = legacy.tokens_sold;
+        round.max_tokens_per_buyer = legacy.max_tokens_per_buyer;
+        round.start_time = legacy.start_time;
+        round.end_time = legacy.end_time;
+        round.min_purchase = legacy.min_purchase;
+        round.max_total_raise = legacy.max_total_raise;
+        round.sale_type = legacy.sale_type as u8;
+        round.round_number = legacy.round_number;
+        round.is_active = legacy.is_active as u8;
+        round.whitelist_required = legacy.whitelist_required as u8;
+        round.bump = legacy.bump;
+        round.version = Self::VERSION;
+        round
+    }
+
+    fn version(&self) -> u8 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
 }
 
 /// Platform treasury management - COMPLETION PHASE
@@ -1058,27 +1591,29 @@ pub struct PlatformTreasury {
     pub total_volume: u64,                  // Total trading volume across all projects (8 bytes)
     pub fee_percentage: u16,                // Platform fee in basis points (100 = 1%) (2 bytes)
     pub bump: u8,                           // PDA bump (1 byte)
+    pub version: u8,                        // Account layout version (1 byte)
 }
 
 impl PlatformTreasury {
-    pub const INIT_SPACE: usize = 32 + 32 + 8 + 8 + 8 + 2 + 1; // 91 bytes
+    pub const INIT_SPACE: usize = 32 + 32 + 8 + 8 + 8 + 2 + 1 + 1; // 92 bytes
 }
 
 /// Round-specific buyer tracking - COMPLETION PHASE
-#[account]
-#[derive(Default)]
+#[account(zero_copy)]
 pub struct RoundBuyerAccount {
     pub buyer: Pubkey,                      // Buyer's public key (32 bytes)
     pub project_id: u64,                    // Associated project (8 bytes)
-    pub round_number: u8,                   // Which round (1 byte)
     pub tokens_purchased: u64,              // Tokens purchased in this round (8 bytes)
     pub total_paid: u64,                    // Total amount paid in this round (8 bytes)
     pub purchase_timestamp: i64,            // When the purchase was made (8 bytes)
+    pub round_number: u8,                   // Which round (1 byte)
     pub bump: u8,                           // PDA bump (1 byte)
+    pub version: u8,                        // Account layout version (1 byte)
+    pub padding: [u8; 5],                   // Keeps the size a multiple of 8 (5 bytes)
 }
 
 impl RoundBuyerAccount {
-    pub const INIT_SPACE: usize = 32 + 8 + 1 + 8 + 8 + 8 + 1; // 66 bytes
+    pub const INIT_SPACE: usize = std::mem::size_of::<RoundBuyerAccount>(); // 72 bytes
 }
 
 /// Project whitelist management - COMPLETION PHASE
@@ -1091,11 +1626,43 @@ pub struct ProjectWhitelist {
     pub current_entries: u16,              // Current number of entries (2 bytes)
     pub is_active: bool,                   // Whether whitelist is active (1 byte)
     pub bump: u8,                          // PDA bump (1 byte)
+    pub version: u8,                       // Account layout version (1 byte)
     // Note: Actual addresses would be stored in a separate vector account for scalability
 }
 
 impl ProjectWhitelist {
-    pub const INIT_SPACE: usize = 8 + 1 + 2 + 2 + 1 + 1; // 15 bytes
+    pub const INIT_SPACE: usize = 8 + 1 + 2 + 2 + 1 + 1 + 1; // 16 bytes
+}
+
+/// Wallets the platform's compliance authority has barred from buying. Each denied
+/// wallet has a `DeniedWallet` entry.
+#[account]
+#[derive(Default)]
+pub struct DenyList {
+    pub authority: Pubkey,                 // Compliance authority that denies and allows wallets (32 bytes)
+    pub denied_count: u64,                 // Wallets currently denied (8 bytes)
+    pub required: bool,                    // Every sale screens its buyers (1 byte)
+    pub bump: u8,                          // PDA bump (1 byte)
+    pub version: u8,                       // Account layout version (1 byte)
+}
+
+impl DenyList {
+    pub const INIT_SPACE: usize = 32 + 8 + 1 + 1 + 1; // 43 bytes
+}
+
+/// A wallet on the deny list; closing the entry lifts the denial
+#[account]
+#[derive(Default)]
+pub struct DeniedWallet {
+    pub wallet: Pubkey,                    // Wallet barred from buying (32 bytes)
+    pub denied_by: Pubkey,                 // Compliance authority that denied it (32 bytes)
+    pub denied_at: i64,                    // Unix timestamp of the denial (8 bytes)
+    pub bump: u8,                          // PDA bump (1 byte)
+    pub version: u8,                       // Account layout version (1 byte)
+}
+
+impl DeniedWallet {
+    pub const INIT_SPACE: usize = 32 + 32 + 8 + 1 + 1; // 74 bytes
 }
 
 /// Enhanced project status tracking
@@ -1131,6 +1698,77 @@ impl Default for SaleType {
     }
 }
 
+/// Borsh layouts of the accounts that moved to zero-copy, kept so the migration
+/// instructions can read accounts created before the switch
+pub mod legacy {
+    use super::*;
+
+    /// `TokenSale` at layout versions 0 and 1
+    #[account]
+    #[derive(Default)]
+    pub struct TokenSale {
+        pub seller: Pubkey,
+        pub token_mint: Pubkey,
+        pub payment_mint: Pubkey,
+        pub price_per_token: u64,
+        pub total_tokens: u64,
+        pub tokens_available: u64,
+        pub sale_start_time: i64,
+        pub sale_end_time: i64,
+        pub max_tokens_per_buyer: u64,
+        pub platform_fee_bps: u16,
+        pub platform_fee_recipient: Pubkey,
+        pub is_active: bool,
+        pub is_paused: bool,
+        pub bump: u8,
+        pub version: u8,
+    }
+
+    impl TokenSale {
+        pub const INIT_SPACE: usize = 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 2 + 32 + 1 + 1 + 1 + 1; // 182 bytes
+    }
+
+    /// `BuyerAccount` at layout versions 0 and 1
+    #[account]
+    #[derive(Default)]
+    pub struct BuyerAccount {
+        pub buyer: Pubkey,
+        pub token_sale: Pubkey,
+        pub tokens_purchased: u64,
+        pub bump: u8,
+        pub version: u8,
+    }
+
+    impl BuyerAccount {
+        pub const INIT_SPACE: usize = 32 + 32 + 8 + 1 + 1; // 74 bytes
+    }
+
+    /// `SaleRound` at layout versions 0 and 1
+    #[account]
+    #[derive(Default)]
+    pub struct SaleRound {
+        pub project_id: u64,
+        pub sale_type: SaleType,
+        pub round_number: u8,
+        pub token_price: u64,
+        pub total_tokens: u64,
+        pub tokens_sold: u64,
+        pub max_tokens_per_buyer: u64,
+        pub start_time: i64,
+        pub end_time: i64,
+        pub is_active: bool,
+        pub whitelist_required: bool,
+        pub min_purchase: u64,
+        pub max_total_raise: u64,
+        pub bump: u8,
+        pub version: u8,
+    }
+
+    impl SaleRound {
+        pub const INIT_SPACE: usize = 8 + 1 + 1 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1 + 8 + 8 + 1 + 1; // 90 bytes
+    }
+}
+
 // NEW ACCOUNT VALIDATION CONTEXTS
 
 /// Initialize platform account
@@ -1285,7 +1923,7 @@ pub struct CreateSaleRound<'info> {
         seeds = [b"sale_round", project_account.key().as_ref(), &round_number.to_le_bytes()],
         bump
     )]
-    pub sale_round: Account<'info, SaleRound>,
+    pub sale_round: AccountLoader<'info, SaleRound>,
 
     pub system_program: Program<'info, System>,
 }
@@ -1359,6 +1997,140 @@ pub struct AddToWhitelist<'info> {
     pub system_program: Program<'info, System>,
 }
 
+/// Account validation for creating the platform's deny list
+#[derive(Accounts)]
+pub struct CreateDenyList<'info> {
+    #[account(
+        mut,
+        constraint = admin.key() == platform_account.authority @ ErrorCode::UnauthorizedAccess
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump = platform_account.bump
+    )]
+    pub platform_account: Account<'info, PlatformAccount>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + DenyList::INIT_SPACE,
+        seeds = [b"deny_list"],
+        bump
+    )]
+    pub deny_list: Account<'info, DenyList>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Account validation for updating the platform's deny list
+#[derive(Accounts)]
+pub struct UpdateDenyList<'info> {
+    #[account(
+        constraint = admin.key() == platform_account.authority @ ErrorCode::UnauthorizedAccess
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"platform"],
+        bump = platform_account.bump
+    )]
+    pub platform_account: Account<'info, PlatformAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"deny_list"],
+        bump = deny_list.bump
+    )]
+    pub deny_list: Account<'info, DenyList>,
+}
+
+/// Account validation for denying a wallet
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct DenyWallet<'info> {
+    #[account(
+        mut,
+        constraint = authority.key() == deny_list.authority @ ErrorCode::UnauthorizedAccess
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"deny_list"],
+        bump = deny_list.bump
+    )]
+    pub deny_list: Account<'info, DenyList>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + DeniedWallet::INIT_SPACE,
+        seeds = [b"denied", wallet.as_ref()],
+        bump
+    )]
+    pub denied_wallet: Account<'info, DeniedWallet>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Account validation for lifting a wallet's denial
+#[derive(Accounts)]
+pub struct AllowWallet<'info> {
+    #[account(
+        mut,
+        constraint = authority.key() == deny_list.authority @ ErrorCode::UnauthorizedAccess
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"deny_list"],
+        bump = deny_list.bump
+    )]
+    pub deny_list: Account<'info, DenyList>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"denied", denied_wallet.wallet.as_ref()],
+        bump = denied_wallet.bump
+    )]
+    pub denied_wallet: Account<'info, DeniedWallet>,
+}
+
+/// Account validation for migrating any program account to its current layout
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: owner and discriminator are verified against the target type in the handler
+    #[account(mut)]
+    pub account: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MigrationAccounts<'info> for MigrateAccount<'info> {
+    fn account(&self) -> &AccountInfo<'info> {
+        &self.account
+    }
+
+    fn payer(&self) -> AccountInfo<'info> {
+        self.payer.to_account_info()
+    }
+
+    fn system_program(&self) -> AccountInfo<'info> {
+        self.system_program.to_account_info()
+    }
+
+    fn already_migrated(&self) -> Error {
+        ErrorCode::AccountAlreadyMigrated.into()
+    }
+}
+
 // NEW EVENTS
 
 #[event]
@@ -1409,4 +2181,59 @@ pub struct ProjectStatusChanged {
     pub old_status: ProjectStatus,
     pub new_status: ProjectStatus,
     pub timestamp: i64,
-}
\ No newline at end of file
+}
+
+#[event]
+pub struct SaleInitialized {
+    pub sale: Pubkey,
+    pub seller: Pubkey,
+    pub token_mint: Pubkey,
+    pub payment_mint: Pubkey,
+    pub price_per_token: u64,
+    pub total_tokens: u64,
+    pub sale_start_time: i64,
+    pub sale_end_time: i64,
+    pub platform_fee_bps: u16,
+    pub platform_fee_recipient: Pubkey,
+}
+
+#[event]
+pub struct TokensPurchased {
+    pub sale: Pubkey,
+    pub buyer: Pubkey,
+    pub token_amount: u64,
+    pub seller_payment: u64,
+    pub platform_fee: u64,
+    pub platform_fee_recipient: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct WalletScreeningSet {
+    pub sale: Pubkey,
+    pub enabled: bool,
+}
+
+#[event]
+pub struct DenyListConfigured {
+    pub authority: Pubkey,
+    pub required: bool,
+}
+
+#[event]
+pub struct WalletDenied {
+    pub wallet: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct WalletAllowed {
+    pub wallet: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct SaleCancelled {
+    pub sale: Pubkey,
+    pub tokens_returned: u64,
+}
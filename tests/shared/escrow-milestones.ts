@@ -0,0 +1,371 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import {
+  PublicKey,
+  Keypair,
+  SystemProgram,
+  LAMPORTS_PER_SOL
+} from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  createAccount,
+  mintTo,
+  getAccount,
+} from "@solana/spl-token";
+import { assert } from "chai";
+
+describe("Custom Escrow - Milestone Tests", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Escrow as Program<any>;
+
+  const TOKENS_TO_SELL = 1000;
+
+  let seller: Keypair;
+  let approver: Keypair;
+  let buyers: Keypair[];
+  let paymentMint: PublicKey;
+  let sellerPaymentAccount: PublicKey;
+  let platformFeeAccount: PublicKey;
+  let buyerPaymentAccounts: PublicKey[];
+  let saleStartTime: number;
+  let saleEndTime: number;
+
+  // Sale "approved" releases by approver and buyer vote, sale "missed" lets its deadline pass
+  const sales: { [name: string]: { tokenMint: PublicKey; tokenSale: PublicKey; tokenVault: PublicKey; proceedsVault: PublicKey } } = {};
+  const buyerTokenAccounts: { [name: string]: PublicKey[] } = {};
+
+  const buyerAccountPDA = (name: string, buyer: Keypair) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("buyer"), buyer.publicKey.toBuffer(), sales[name].tokenSale.toBuffer()],
+      program.programId
+    )[0];
+
+  const milestone = (releaseBps: number, deadline: number) => ({
+    releaseBps,
+    deadline: new anchor.BN(deadline),
+  });
+
+  const configureMilestones = (name: string, milestones: any[], milestoneApprover: PublicKey | null) =>
+    program.methods
+      .configureMilestones(milestones, milestoneApprover)
+      .accounts({
+        seller: seller.publicKey,
+        tokenSale: sales[name].tokenSale,
+        paymentMint: paymentMint,
+        proceedsVault: sales[name].proceedsVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      })
+      .signers([seller])
+      .rpc();
+
+  const buy = (name: string, index: number, amount: number) =>
+    program.methods
+      .buyTokens(new anchor.BN(amount))
+      .accounts({
+        buyer: buyers[index].publicKey,
+        tokenSale: sales[name].tokenSale,
+        buyerAccount: buyerAccountPDA(name, buyers[index]),
+        buyerPaymentAccount: buyerPaymentAccounts[index],
+        sellerPaymentAccount: sellerPaymentAccount,
+        platformFeeAccount: platformFeeAccount,
+        buyerTokenAccount: buyerTokenAccounts[name][index],
+        tokenVault: sales[name].tokenVault,
+        proceedsVault: sales[name].proceedsVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([buyers[index]])
+      .rpc();
+
+  const approve = (signer: Keypair) =>
+    program.methods
+      .approveMilestone()
+      .accounts({
+        approver: signer.publicKey,
+        tokenSale: sales.approved.tokenSale,
+      })
+      .signers([signer])
+      .rpc();
+
+  const vote = (index: number) =>
+    program.methods
+      .voteMilestone()
+      .accounts({
+        buyer: buyers[index].publicKey,
+        tokenSale: sales.approved.tokenSale,
+        buyerAccount: buyerAccountPDA("approved", buyers[index]),
+      })
+      .signers([buyers[index]])
+      .rpc();
+
+  const release = () =>
+    program.methods
+      .releaseMilestone()
+      .accounts({
+        tokenSale: sales.approved.tokenSale,
+        sellerPaymentAccount: sellerPaymentAccount,
+        proceedsVault: sales.approved.proceedsVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+  const claimRefund = () =>
+    program.methods
+      .claimMilestoneRefund()
+      .accounts({
+        buyer: buyers[0].publicKey,
+        tokenSale: sales.missed.tokenSale,
+        buyerAccount: buyerAccountPDA("missed", buyers[0]),
+        buyerPaymentAccount: buyerPaymentAccounts[0],
+        proceedsVault: sales.missed.proceedsVault,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([buyers[0]])
+      .rpc();
+
+  const waitUntil = async (time: number) => {
+    const delay = time * 1000 - Date.now();
+    if (delay > 0) {
+      await new Promise(resolve => setTimeout(resolve, delay));
+    }
+  };
+
+  before(async () => {
+    seller = Keypair.generate();
+    approver = Keypair.generate();
+    buyers = [Keypair.generate(), Keypair.generate()];
+
+    await Promise.all(
+      [seller, approver, ...buyers].map(wallet =>
+        provider.connection.requestAirdrop(wallet.publicKey, 2 * LAMPORTS_PER_SOL)
+      )
+    );
+    await new Promise(resolve => setTimeout(resolve, 2000));
+
+    paymentMint = await createMint(provider.connection, seller, seller.publicKey, null, 6);
+    sellerPaymentAccount = await createAccount(provider.connection, seller, paymentMint, seller.publicKey);
+    platformFeeAccount = await createAccount(provider.connection, approver, paymentMint, approver.publicKey);
+    buyerPaymentAccounts = [];
+    for (const buyer of buyers) {
+      const account = await createAccount(provider.connection, buyer, paymentMint, buyer.publicKey);
+      await mintTo(provider.connection, seller, paymentMint, account, seller, 1000);
+      buyerPaymentAccounts.push(account);
+    }
+
+    // Milestones can only be configured before the start, so both sales open shortly
+    const currentTime = Math.floor(Date.now() / 1000);
+    saleStartTime = currentTime + 15;
+    saleEndTime = currentTime + 25;
+
+    for (const name of ["approved", "missed"]) {
+      const tokenMint = await createMint(provider.connection, seller, seller.publicKey, null, 6);
+      const sellerTokenAccount = await createAccount(provider.connection, seller, tokenMint, seller.publicKey);
+      await mintTo(provider.connection, seller, tokenMint, sellerTokenAccount, seller, TOKENS_TO_SELL);
+
+      const [tokenSale] = PublicKey.findProgramAddressSync(
+        [Buffer.from("token_sale"), seller.publicKey.toBuffer(), tokenMint.toBuffer()],
+        program.programId
+      );
+      const [tokenVault] = PublicKey.findProgramAddressSync(
+        [Buffer.from("token_vault"), tokenSale.toBuffer()],
+        program.programId
+      );
+      const [proceedsVault] = PublicKey.findProgramAddressSync(
+        [Buffer.from("proceeds_vault"), tokenSale.toBuffer()],
+        program.programId
+      );
+      sales[name] = { tokenMint, tokenSale, tokenVault, proceedsVault };
+
+      await program.methods
+        .initializeSale(
+          new anchor.BN(1),
+          new anchor.BN(TOKENS_TO_SELL),
+          new anchor.BN(saleStartTime),
+          new anchor.BN(saleEndTime),
+          new anchor.BN(0),
+          0, // No platform fee, so every payment lands in the proceeds vault
+          approver.publicKey
+        )
+        .accounts({
+          seller: seller.publicKey,
+          tokenSale: tokenSale,
+          tokenMint: tokenMint,
+          paymentMint: paymentMint,
+          sellerTokenAccount: sellerTokenAccount,
+          tokenVault: tokenVault,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .signers([seller])
+        .rpc();
+
+      buyerTokenAccounts[name] = [];
+      for (const buyer of buyers) {
+        buyerTokenAccounts[name].push(
+          await createAccount(provider.connection, buyer, tokenMint, buyer.publicKey)
+        );
+        await program.methods
+          .createBuyerAccount()
+          .accounts({
+            buyer: buyer.publicKey,
+            tokenSale: tokenSale,
+            buyerAccount: buyerAccountPDA(name, buyer),
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([buyer])
+          .rpc();
+      }
+    }
+  });
+
+  describe("Milestone Configuration Tests", () => {
+    it("Fails when shares don't cover all proceeds or deadlines are out of order", async () => {
+      const invalidSchedules = [
+        [],
+        [milestone(5000, saleEndTime + 60)],
+        [milestone(5000, saleEndTime + 60), milestone(5000, saleEndTime + 30)],
+        [milestone(10000, saleEndTime)],
+      ];
+      for (const milestones of invalidSchedules) {
+        try {
+          await configureMilestones("approved", milestones, approver.publicKey);
+          assert.fail("Should fail with an invalid milestone schedule");
+        } catch (error: any) {
+          assert(error.message.includes("InvalidMilestones"));
+        }
+      }
+    });
+
+    it("Fails with more than the maximum number of milestones", async () => {
+      try {
+        await configureMilestones(
+          "approved",
+          [1, 2, 3, 4, 5, 6].map(step => milestone(step == 6 ? 5000 : 1000, saleEndTime + step * 10)),
+          approver.publicKey
+        );
+        assert.fail("Should fail with too many milestones");
+      } catch (error: any) {
+        assert(error.message.includes("TooManyMilestones"));
+      }
+    });
+
+    it("Successfully configures milestones before the sale starts", async () => {
+      await configureMilestones(
+        "approved",
+        [milestone(6000, saleEndTime + 300), milestone(4000, saleEndTime + 600)],
+        approver.publicKey
+      );
+      await configureMilestones("missed", [milestone(10000, saleEndTime + 15)], null);
+
+      const saleAccount = await program.account.tokenSale.fetch(sales.approved.tokenSale);
+      assert.equal(saleAccount.milestones.length, 2);
+      assert.equal(saleAccount.milestones[0].releaseBps, 6000);
+      assert.equal(saleAccount.milestoneApprover.toString(), approver.publicKey.toString());
+    });
+  });
+
+  describe("Milestone Release Tests", () => {
+    it("Holds purchase payments in the proceeds vault", async () => {
+      await waitUntil(saleStartTime + 1);
+
+      await buy("approved", 0, 30);
+      await buy("approved", 1, 70);
+      await buy("missed", 0, 50);
+
+      const proceedsVault = await getAccount(provider.connection, sales.approved.proceedsVault);
+      const sellerAccount = await getAccount(provider.connection, sellerPaymentAccount);
+      assert.equal(Number(proceedsVault.amount), 100);
+      assert.equal(Number(sellerAccount.amount), 0);
+    });
+
+    it("Fails to approve before the sale ends", async () => {
+      try {
+        await approve(approver);
+        assert.fail("Should wait for the sale to end");
+      } catch (error: any) {
+        assert(error.message.includes("SaleNotEnded"));
+      }
+    });
+
+    it("Fails when anyone but the approver approves", async () => {
+      await waitUntil(saleEndTime + 1);
+
+      try {
+        await approve(seller);
+        assert.fail("Should only allow the designated approver");
+      } catch (error: any) {
+        assert(error.message.includes("UnauthorizedMilestoneApprover"));
+      }
+    });
+
+    it("Fails to release a milestone that isn't approved", async () => {
+      try {
+        await release();
+        assert.fail("Should require approval before release");
+      } catch (error: any) {
+        assert(error.message.includes("MilestoneNotApproved"));
+      }
+    });
+
+    it("Counts each buyer's vote once", async () => {
+      // 30 of 100 tokens is short of a majority
+      await vote(0);
+
+      try {
+        await vote(0);
+        assert.fail("Should prevent voting twice");
+      } catch (error: any) {
+        assert(error.message.includes("AlreadyVoted"));
+      }
+
+      const saleAccount = await program.account.tokenSale.fetch(sales.approved.tokenSale);
+      assert.equal(saleAccount.milestoneVotes.toNumber(), 30);
+      assert.equal(saleAccount.milestoneApproved, false);
+    });
+
+    it("Successfully releases the approved tranche to the seller", async () => {
+      await approve(approver);
+      await release();
+
+      const sellerAccount = await getAccount(provider.connection, sellerPaymentAccount);
+      assert.equal(Number(sellerAccount.amount), 60);
+
+      const saleAccount = await program.account.tokenSale.fetch(sales.approved.tokenSale);
+      assert.equal(saleAccount.currentMilestone, 1);
+      assert.equal(saleAccount.proceedsReleased.toNumber(), 60);
+      assert.equal(saleAccount.milestoneVotes.toNumber(), 0);
+    });
+  });
+
+  describe("Milestone Refund Tests", () => {
+    it("Fails to refund before the deadline is missed", async () => {
+      try {
+        await claimRefund();
+        assert.fail("Should wait for the deadline");
+      } catch (error: any) {
+        assert(error.message.includes("MilestoneDeadlineNotMissed"));
+      }
+    });
+
+    it("Refunds unreleased proceeds once after a missed deadline", async () => {
+      await waitUntil(saleEndTime + 17);
+      const balanceBefore = await getAccount(provider.connection, buyerPaymentAccounts[0]);
+
+      await claimRefund();
+
+      const balanceAfter = await getAccount(provider.connection, buyerPaymentAccounts[0]);
+      assert.equal(Number(balanceAfter.amount) - Number(balanceBefore.amount), 50);
+
+      try {
+        await claimRefund();
+        assert.fail("Should prevent claiming twice");
+      } catch (error: any) {
+        assert(error.message.includes("RefundAlreadyClaimed"));
+      }
+    });
+  });
+});
@@ -0,0 +1,176 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import {
+  PublicKey,
+  Keypair,
+  SystemProgram,
+  LAMPORTS_PER_SOL
+} from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  createAccount,
+  mintTo,
+  getAccount,
+} from "@solana/spl-token";
+import { assert } from "chai";
+
+describe("Custom Escrow - Service Escrow Tests", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Escrow as Program<any>;
+
+  const ESCROW_AMOUNT = 100 * Math.pow(10, 6); // 100 USDC
+
+  let payer: Keypair;
+  let payee: Keypair;
+  let arbiter: Keypair;
+  let paymentMint: PublicKey;
+  let payerTokenAccount: PublicKey;
+  let payeeTokenAccount: PublicKey;
+
+  const escrowPDAs = (escrowId: number) => {
+    const [serviceEscrowPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("service_escrow"), payer.publicKey.toBuffer(), new anchor.BN(escrowId).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [escrowVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("service_vault"), serviceEscrowPDA.toBuffer()],
+      program.programId
+    );
+    return { serviceEscrowPDA, escrowVaultPDA };
+  };
+
+  const createEscrow = (escrowId: number) => {
+    const { serviceEscrowPDA, escrowVaultPDA } = escrowPDAs(escrowId);
+    const currentTime = Math.floor(Date.now() / 1000);
+    return program.methods
+      .createServiceEscrow(
+        new anchor.BN(escrowId),
+        new anchor.BN(ESCROW_AMOUNT),
+        new anchor.BN(currentTime + 7 * 24 * 3600) // Auto-release in a week
+      )
+      .accounts({
+        payer: payer.publicKey,
+        payee: payee.publicKey,
+        arbiter: arbiter.publicKey,
+        serviceEscrow: serviceEscrowPDA,
+        mint: paymentMint,
+        payerTokenAccount: payerTokenAccount,
+        escrowVault: escrowVaultPDA,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      })
+      .signers([payer])
+      .rpc();
+  };
+
+  const settleAccounts = (escrowId: number, authority: PublicKey) => {
+    const { serviceEscrowPDA, escrowVaultPDA } = escrowPDAs(escrowId);
+    return {
+      authority: authority,
+      payer: payer.publicKey,
+      serviceEscrow: serviceEscrowPDA,
+      payerTokenAccount: payerTokenAccount,
+      payeeTokenAccount: payeeTokenAccount,
+      escrowVault: escrowVaultPDA,
+      tokenProgram: TOKEN_PROGRAM_ID,
+    };
+  };
+
+  before(async () => {
+    payer = Keypair.generate();
+    payee = Keypair.generate();
+    arbiter = Keypair.generate();
+
+    await Promise.all([
+      provider.connection.requestAirdrop(payer.publicKey, 2 * LAMPORTS_PER_SOL),
+      provider.connection.requestAirdrop(payee.publicKey, 2 * LAMPORTS_PER_SOL),
+      provider.connection.requestAirdrop(arbiter.publicKey, 2 * LAMPORTS_PER_SOL),
+    ]);
+    await new Promise(resolve => setTimeout(resolve, 2000));
+
+    paymentMint = await createMint(provider.connection, payer, payer.publicKey, null, 6);
+    payerTokenAccount = await createAccount(provider.connection, payer, paymentMint, payer.publicKey);
+    payeeTokenAccount = await createAccount(provider.connection, payee, paymentMint, payee.publicKey);
+    await mintTo(provider.connection, payer, paymentMint, payerTokenAccount, payer, 3 * ESCROW_AMOUNT);
+  });
+
+  it("Successfully funds and releases an escrow to the payee", async () => {
+    const { serviceEscrowPDA } = escrowPDAs(1);
+    await createEscrow(1);
+
+    const escrowAccount = await program.account.serviceEscrow.fetch(serviceEscrowPDA);
+    assert.equal(escrowAccount.arbiter.toString(), arbiter.publicKey.toString());
+    assert.equal(escrowAccount.amount.toNumber(), ESCROW_AMOUNT);
+
+    const payeeBefore = await getAccount(provider.connection, payeeTokenAccount);
+
+    await program.methods
+      .releaseServiceEscrow()
+      .accounts(settleAccounts(1, payer.publicKey))
+      .signers([payer])
+      .rpc();
+
+    const payeeAfter = await getAccount(provider.connection, payeeTokenAccount);
+    assert.equal(Number(payeeAfter.amount) - Number(payeeBefore.amount), ESCROW_AMOUNT);
+    assert.isNull(await provider.connection.getAccountInfo(serviceEscrowPDA));
+  });
+
+  it("Fails when the payee tries to release funds to themselves", async () => {
+    await createEscrow(2);
+
+    try {
+      await program.methods
+        .releaseServiceEscrow()
+        .accounts(settleAccounts(2, payee.publicKey))
+        .signers([payee])
+        .rpc();
+
+      assert.fail("Should prevent payee from releasing");
+    } catch (error: any) {
+      assert(error.message.includes("UnauthorizedEscrowParty"));
+    }
+  });
+
+  it("Lets only the arbiter split a disputed escrow", async () => {
+    const { serviceEscrowPDA } = escrowPDAs(2);
+
+    await program.methods
+      .raiseDispute()
+      .accounts({
+        authority: payee.publicKey,
+        serviceEscrow: serviceEscrowPDA,
+      })
+      .signers([payee])
+      .rpc();
+
+    // The payer can no longer release once disputed
+    try {
+      await program.methods
+        .releaseServiceEscrow()
+        .accounts(settleAccounts(2, payer.publicKey))
+        .signers([payer])
+        .rpc();
+
+      assert.fail("Should block release while disputed");
+    } catch (error: any) {
+      assert(error.message.includes("InvalidEscrowStatus"));
+    }
+
+    const payerBefore = await getAccount(provider.connection, payerTokenAccount);
+    const payeeBefore = await getAccount(provider.connection, payeeTokenAccount);
+
+    await program.methods
+      .resolveDispute(7000) // 70% to payee
+      .accounts(settleAccounts(2, arbiter.publicKey))
+      .signers([arbiter])
+      .rpc();
+
+    const payerAfter = await getAccount(provider.connection, payerTokenAccount);
+    const payeeAfter = await getAccount(provider.connection, payeeTokenAccount);
+    assert.equal(Number(payeeAfter.amount) - Number(payeeBefore.amount), ESCROW_AMOUNT * 0.7);
+    assert.equal(Number(payerAfter.amount) - Number(payerBefore.amount), ESCROW_AMOUNT * 0.3);
+  });
+});
@@ -0,0 +1,257 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import {
+  PublicKey,
+  Keypair,
+  SystemProgram,
+  LAMPORTS_PER_SOL
+} from "@solana/web3.js";
+import {
+  TOKEN_PROGRAM_ID,
+  createMint,
+  createAccount,
+  mintTo,
+  getAccount,
+} from "@solana/spl-token";
+import { assert } from "chai";
+
+describe("Custom Escrow - Swap Offer Tests", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  const program = anchor.workspace.Escrow as Program<any>;
+
+  const TOKEN_A_AMOUNT = 500 * Math.pow(10, 6); // 500 token A offered
+  const TOKEN_B_WANTED = 250 * Math.pow(10, 6); // 250 token B requested
+
+  let maker: Keypair;
+  let taker: Keypair;
+  let outsider: Keypair;
+  let tokenMintA: PublicKey;
+  let tokenMintB: PublicKey;
+  let makerTokenAccountA: PublicKey;
+  let makerTokenAccountB: PublicKey;
+  let takerTokenAccountA: PublicKey;
+  let takerTokenAccountB: PublicKey;
+  let outsiderTokenAccountA: PublicKey;
+  let outsiderTokenAccountB: PublicKey;
+
+  const offerPDAs = (offerId: number) => {
+    const [offerPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("offer"), maker.publicKey.toBuffer(), new anchor.BN(offerId).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const [offerVaultPDA] = PublicKey.findProgramAddressSync(
+      [Buffer.from("offer_vault"), offerPDA.toBuffer()],
+      program.programId
+    );
+    return { offerPDA, offerVaultPDA };
+  };
+
+  const makeOffer = (
+    offerId: number,
+    designatedTaker: PublicKey | null,
+    expiresAt: number = 0,
+    partiallyFillable: boolean = false
+  ) => {
+    const { offerPDA, offerVaultPDA } = offerPDAs(offerId);
+    return program.methods
+      .makeOffer(
+        new anchor.BN(offerId),
+        new anchor.BN(TOKEN_A_AMOUNT),
+        new anchor.BN(TOKEN_B_WANTED),
+        designatedTaker,
+        new anchor.BN(expiresAt),
+        partiallyFillable
+      )
+      .accounts({
+        maker: maker.publicKey,
+        offer: offerPDA,
+        tokenMintA: tokenMintA,
+        tokenMintB: tokenMintB,
+        makerTokenAccountA: makerTokenAccountA,
+        offerVault: offerVaultPDA,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      })
+      .signers([maker])
+      .rpc();
+  };
+
+  before(async () => {
+    maker = Keypair.generate();
+    taker = Keypair.generate();
+    outsider = Keypair.generate();
+
+    await Promise.all([
+      provider.connection.requestAirdrop(maker.publicKey, 2 * LAMPORTS_PER_SOL),
+      provider.connection.requestAirdrop(taker.publicKey, 2 * LAMPORTS_PER_SOL),
+      provider.connection.requestAirdrop(outsider.publicKey, 2 * LAMPORTS_PER_SOL),
+    ]);
+    await new Promise(resolve => setTimeout(resolve, 2000));
+
+    tokenMintA = await createMint(provider.connection, maker, maker.publicKey, null, 6);
+    tokenMintB = await createMint(provider.connection, taker, taker.publicKey, null, 6);
+
+    makerTokenAccountA = await createAccount(provider.connection, maker, tokenMintA, maker.publicKey);
+    makerTokenAccountB = await createAccount(provider.connection, maker, tokenMintB, maker.publicKey);
+    takerTokenAccountA = await createAccount(provider.connection, taker, tokenMintA, taker.publicKey);
+    takerTokenAccountB = await createAccount(provider.connection, taker, tokenMintB, taker.publicKey);
+    outsiderTokenAccountA = await createAccount(provider.connection, outsider, tokenMintA, outsider.publicKey);
+    outsiderTokenAccountB = await createAccount(provider.connection, outsider, tokenMintB, outsider.publicKey);
+
+    await mintTo(provider.connection, maker, tokenMintA, makerTokenAccountA, maker, 3 * TOKEN_A_AMOUNT);
+    await mintTo(provider.connection, taker, tokenMintB, takerTokenAccountB, taker, 3 * TOKEN_B_WANTED);
+    await mintTo(provider.connection, taker, tokenMintB, outsiderTokenAccountB, taker, 3 * TOKEN_B_WANTED);
+  });
+
+  it("Successfully makes an offer and escrows token A", async () => {
+    const { offerPDA, offerVaultPDA } = offerPDAs(1);
+    await makeOffer(1, null);
+
+    const offerAccount = await program.account.offer.fetch(offerPDA);
+    assert.equal(offerAccount.maker.toString(), maker.publicKey.toString());
+    assert.equal(offerAccount.tokenAAmount.toNumber(), TOKEN_A_AMOUNT);
+    assert.equal(offerAccount.tokenBWanted.toNumber(), TOKEN_B_WANTED);
+    assert.isNull(offerAccount.taker);
+    assert.equal(offerAccount.tokenARemaining.toNumber(), TOKEN_A_AMOUNT);
+    assert.equal(offerAccount.partiallyFillable, false);
+
+    const vaultAccount = await getAccount(provider.connection, offerVaultPDA);
+    assert.equal(Number(vaultAccount.amount), TOKEN_A_AMOUNT);
+  });
+
+  it("Successfully takes an open offer", async () => {
+    const { offerPDA, offerVaultPDA } = offerPDAs(1);
+    const makerBBefore = await getAccount(provider.connection, makerTokenAccountB);
+    const takerABefore = await getAccount(provider.connection, takerTokenAccountA);
+
+    await program.methods
+      .takeOffer()
+      .accounts({
+        taker: taker.publicKey,
+        maker: maker.publicKey,
+        offer: offerPDA,
+        takerTokenAccountA: takerTokenAccountA,
+        takerTokenAccountB: takerTokenAccountB,
+        makerTokenAccountB: makerTokenAccountB,
+        offerVault: offerVaultPDA,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([taker])
+      .rpc();
+
+    const makerBAfter = await getAccount(provider.connection, makerTokenAccountB);
+    const takerAAfter = await getAccount(provider.connection, takerTokenAccountA);
+    assert.equal(Number(makerBAfter.amount) - Number(makerBBefore.amount), TOKEN_B_WANTED);
+    assert.equal(Number(takerAAfter.amount) - Number(takerABefore.amount), TOKEN_A_AMOUNT);
+
+    // Offer and vault are closed after a full swap
+    assert.isNull(await provider.connection.getAccountInfo(offerPDA));
+    assert.isNull(await provider.connection.getAccountInfo(offerVaultPDA));
+  });
+
+  it("Fails when a non-designated taker takes a private offer", async () => {
+    const { offerPDA, offerVaultPDA } = offerPDAs(2);
+    await makeOffer(2, taker.publicKey);
+
+    try {
+      await program.methods
+        .takeOffer()
+        .accounts({
+          taker: outsider.publicKey,
+          maker: maker.publicKey,
+          offer: offerPDA,
+          takerTokenAccountA: outsiderTokenAccountA,
+          takerTokenAccountB: outsiderTokenAccountB,
+          makerTokenAccountB: makerTokenAccountB,
+          offerVault: offerVaultPDA,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([outsider])
+        .rpc();
+
+      assert.fail("Should reject a non-designated taker");
+    } catch (error: any) {
+      assert(error.message.includes("UnauthorizedTaker"));
+    }
+  });
+
+  it("Successfully cancels an offer and returns the deposit", async () => {
+    const { offerPDA, offerVaultPDA } = offerPDAs(2);
+    const makerABefore = await getAccount(provider.connection, makerTokenAccountA);
+
+    await program.methods
+      .cancelOffer()
+      .accounts({
+        maker: maker.publicKey,
+        offer: offerPDA,
+        makerTokenAccountA: makerTokenAccountA,
+        offerVault: offerVaultPDA,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .signers([maker])
+      .rpc();
+
+    const makerAAfter = await getAccount(provider.connection, makerTokenAccountA);
+    assert.equal(Number(makerAAfter.amount) - Number(makerABefore.amount), TOKEN_A_AMOUNT);
+    assert.isNull(await provider.connection.getAccountInfo(offerPDA));
+  });
+
+  describe("Partial Fill Tests", () => {
+    const fillOffer = (offerId: number, amount: number) => {
+      const { offerPDA, offerVaultPDA } = offerPDAs(offerId);
+      return program.methods
+        .fillOffer(new anchor.BN(amount))
+        .accounts({
+          taker: taker.publicKey,
+          maker: maker.publicKey,
+          offer: offerPDA,
+          takerTokenAccountA: takerTokenAccountA,
+          takerTokenAccountB: takerTokenAccountB,
+          makerTokenAccountB: makerTokenAccountB,
+          offerVault: offerVaultPDA,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([taker])
+        .rpc();
+    };
+
+    it("Fails to partially fill an all-or-nothing offer", async () => {
+      await makeOffer(3, null);
+
+      try {
+        await fillOffer(3, TOKEN_A_AMOUNT / 2);
+        assert.fail("Should reject partial fill");
+      } catch (error: any) {
+        assert(error.message.includes("PartialFillsDisabled"));
+      }
+    });
+
+    it("Fills an offer in pieces with pro-rata pricing", async () => {
+      const currentTime = Math.floor(Date.now() / 1000);
+      const { offerPDA } = offerPDAs(4);
+      await makeOffer(4, null, currentTime + 3600, true);
+
+      const makerBBefore = await getAccount(provider.connection, makerTokenAccountB);
+
+      // Fill a third, then the rest
+      const firstFill = Math.floor(TOKEN_A_AMOUNT / 3);
+      await fillOffer(4, firstFill);
+
+      const offerAccount = await program.account.offer.fetch(offerPDA);
+      assert.equal(offerAccount.tokenARemaining.toNumber(), TOKEN_A_AMOUNT - firstFill);
+      assert.equal(
+        offerAccount.tokenBReceived.toNumber(),
+        Math.ceil(firstFill * TOKEN_B_WANTED / TOKEN_A_AMOUNT)
+      );
+
+      await fillOffer(4, TOKEN_A_AMOUNT - firstFill);
+
+      // The maker receives exactly the requested amount across all fills
+      const makerBAfter = await getAccount(provider.connection, makerTokenAccountB);
+      assert.equal(Number(makerBAfter.amount) - Number(makerBBefore.amount), TOKEN_B_WANTED);
+      assert.isNull(await provider.connection.getAccountInfo(offerPDA));
+    });
+  });
+});
@@ -609,6 +609,286 @@ describe("Custom Escrow - Production Unit Tests", () => {
     });
   });
 
+  describe("Purchase Throttle Tests", () => {
+    let throttledSeller: Keypair;
+    let throttledSalePDA: PublicKey;
+    let throttledVaultPDA: PublicKey;
+    let throttledBuyerAccountPDA: PublicKey;
+    let throttledSellerPaymentAccount: PublicKey;
+
+    before(async () => {
+      throttledSeller = Keypair.generate();
+      await provider.connection.requestAirdrop(throttledSeller.publicKey, 2 * LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+
+      const throttledSellerTokenAccount = await createAccount(provider.connection, throttledSeller, tokenMint, throttledSeller.publicKey);
+      throttledSellerPaymentAccount = await createAccount(provider.connection, throttledSeller, paymentMint, throttledSeller.publicKey);
+      await mintTo(provider.connection, seller, tokenMint, throttledSellerTokenAccount, seller, TOKENS_TO_SELL);
+
+      [throttledSalePDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("token_sale"), throttledSeller.publicKey.toBuffer(), tokenMint.toBuffer()],
+        program.programId
+      );
+
+      [throttledVaultPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("token_vault"), throttledSalePDA.toBuffer()],
+        program.programId
+      );
+
+      [throttledBuyerAccountPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("buyer"), buyer.publicKey.toBuffer(), throttledSalePDA.toBuffer()],
+        program.programId
+      );
+
+      const currentTime = Math.floor(Date.now() / 1000);
+
+      await program.methods
+        .initializeSale(
+          new anchor.BN(PRICE_PER_TOKEN),
+          new anchor.BN(TOKENS_TO_SELL),
+          new anchor.BN(currentTime - 100),
+          new anchor.BN(currentTime + 3600),
+          new anchor.BN(0),
+          PLATFORM_FEE_BPS,
+          platformFeeRecipient.publicKey
+        )
+        .accounts({
+          seller: throttledSeller.publicKey,
+          tokenSale: throttledSalePDA,
+          tokenMint: tokenMint,
+          paymentMint: paymentMint,
+          sellerTokenAccount: throttledSellerTokenAccount,
+          tokenVault: throttledVaultPDA,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .signers([throttledSeller])
+        .rpc();
+
+      await program.methods
+        .createBuyerAccount()
+        .accounts({
+          buyer: buyer.publicKey,
+          tokenSale: throttledSalePDA,
+          buyerAccount: throttledBuyerAccountPDA,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+    });
+
+    const buyThrottled = (amount: number) =>
+      program.methods
+        .buyTokens(new anchor.BN(amount))
+        .accounts({
+          buyer: buyer.publicKey,
+          tokenSale: throttledSalePDA,
+          buyerAccount: throttledBuyerAccountPDA,
+          buyerPaymentAccount: buyerPaymentAccount,
+          sellerPaymentAccount: throttledSellerPaymentAccount,
+          platformFeeAccount: platformFeeAccount,
+          buyerTokenAccount: buyerTokenAccount,
+          tokenVault: throttledVaultPDA,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+    it("Successfully configures throttles (seller only)", async () => {
+      await program.methods
+        .setPurchaseThrottles(
+          new anchor.BN(10 * Math.pow(10, 6)), // 10 tokens per transaction
+          new anchor.BN(3600),                  // 1 hour between purchases
+          null
+        )
+        .accounts({
+          seller: throttledSeller.publicKey,
+          tokenSale: throttledSalePDA,
+        })
+        .signers([throttledSeller])
+        .rpc();
+
+      const saleAccount = await program.account.tokenSale.fetch(throttledSalePDA);
+      assert.equal(saleAccount.maxTokensPerTx.toNumber(), 10 * Math.pow(10, 6));
+      assert.equal(saleAccount.minPurchaseInterval.toNumber(), 3600);
+      assert.equal(saleAccount.maxTokensPerSlot.toNumber(), 0);
+    });
+
+    it("Fails to buy more than the per-transaction limit", async () => {
+      try {
+        await buyThrottled(11 * Math.pow(10, 6));
+        assert.fail("Should fail when exceeding per-transaction limit");
+      } catch (error: any) {
+        assert(error.message.includes("ExceedsTransactionLimit"));
+      }
+    });
+
+    it("Fails to buy again before the cooldown elapses", async () => {
+      await buyThrottled(5 * Math.pow(10, 6));
+
+      const buyerAccount = await program.account.buyerAccount.fetch(throttledBuyerAccountPDA);
+      assert.isAbove(buyerAccount.lastPurchaseTime.toNumber(), 0);
+
+      try {
+        await buyThrottled(5 * Math.pow(10, 6));
+        assert.fail("Should fail during purchase cooldown");
+      } catch (error: any) {
+        assert(error.message.includes("PurchaseCooldownActive"));
+      }
+    });
+  });
+
+  describe("Price Tier Tests", () => {
+    const TIERED_BASE_PRICE = 3;
+    let tieredSeller: Keypair;
+    let tieredSalePDA: PublicKey;
+    let tieredVaultPDA: PublicKey;
+    let tieredBuyerAccountPDA: PublicKey;
+    let tieredSellerPaymentAccount: PublicKey;
+
+    before(async () => {
+      tieredSeller = Keypair.generate();
+      await provider.connection.requestAirdrop(tieredSeller.publicKey, 2 * LAMPORTS_PER_SOL);
+      await new Promise(resolve => setTimeout(resolve, 1000));
+
+      const tieredSellerTokenAccount = await createAccount(provider.connection, tieredSeller, tokenMint, tieredSeller.publicKey);
+      tieredSellerPaymentAccount = await createAccount(provider.connection, tieredSeller, paymentMint, tieredSeller.publicKey);
+      await mintTo(provider.connection, seller, tokenMint, tieredSellerTokenAccount, seller, 1000);
+
+      [tieredSalePDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("token_sale"), tieredSeller.publicKey.toBuffer(), tokenMint.toBuffer()],
+        program.programId
+      );
+
+      [tieredVaultPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("token_vault"), tieredSalePDA.toBuffer()],
+        program.programId
+      );
+
+      [tieredBuyerAccountPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("buyer"), buyer.publicKey.toBuffer(), tieredSalePDA.toBuffer()],
+        program.programId
+      );
+
+      // Tiers can only be set before the start, so the sale opens a few seconds from now
+      const currentTime = Math.floor(Date.now() / 1000);
+
+      await program.methods
+        .initializeSale(
+          new anchor.BN(TIERED_BASE_PRICE),
+          new anchor.BN(1000),
+          new anchor.BN(currentTime + 10),
+          new anchor.BN(currentTime + 3600),
+          new anchor.BN(0),
+          PLATFORM_FEE_BPS,
+          platformFeeRecipient.publicKey
+        )
+        .accounts({
+          seller: tieredSeller.publicKey,
+          tokenSale: tieredSalePDA,
+          tokenMint: tokenMint,
+          paymentMint: paymentMint,
+          sellerTokenAccount: tieredSellerTokenAccount,
+          tokenVault: tieredVaultPDA,
+          systemProgram: SystemProgram.programId,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .signers([tieredSeller])
+        .rpc();
+
+      await program.methods
+        .createBuyerAccount()
+        .accounts({
+          buyer: buyer.publicKey,
+          tokenSale: tieredSalePDA,
+          buyerAccount: tieredBuyerAccountPDA,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([buyer])
+        .rpc();
+    });
+
+    const tier = (tokensSoldThreshold: number, pricePerToken: number) => ({
+      tokensSoldThreshold: new anchor.BN(tokensSoldThreshold),
+      pricePerToken: new anchor.BN(pricePerToken),
+    });
+
+    const setTiers = (priceTiers: any[]) =>
+      program.methods
+        .setPriceTiers(priceTiers)
+        .accounts({
+          seller: tieredSeller.publicKey,
+          tokenSale: tieredSalePDA,
+        })
+        .signers([tieredSeller])
+        .rpc();
+
+    it("Fails with thresholds out of order or beyond the sale", async () => {
+      for (const priceTiers of [[tier(200, 1), tier(100, 2)], [tier(1001, 1)]]) {
+        try {
+          await setTiers(priceTiers);
+          assert.fail("Should fail with invalid tier thresholds");
+        } catch (error: any) {
+          assert(error.message.includes("InvalidPriceTiers"));
+        }
+      }
+    });
+
+    it("Fails with more than the maximum number of tiers", async () => {
+      try {
+        await setTiers([1, 2, 3, 4, 5, 6].map(step => tier(step * 100, 1)));
+        assert.fail("Should fail with too many tiers");
+      } catch (error: any) {
+        assert(error.message.includes("TooManyPriceTiers"));
+      }
+    });
+
+    it("Successfully sets tiers before the sale starts", async () => {
+      await setTiers([tier(100, 1), tier(300, 2)]);
+
+      const saleAccount = await program.account.tokenSale.fetch(tieredSalePDA);
+      assert.equal(saleAccount.priceTiers.length, 2);
+      assert.equal(saleAccount.priceTiers[1].tokensSoldThreshold.toNumber(), 300);
+      assert.equal(saleAccount.priceTiers[1].pricePerToken.toNumber(), 2);
+    });
+
+    it("Charges a blended cost for purchases crossing a tier boundary", async () => {
+      await new Promise(resolve => setTimeout(resolve, 11000));
+      const balanceBefore = (await getAccount(provider.connection, buyerPaymentAccount)).amount;
+
+      // 100 tokens at 1 and 50 at 2
+      await program.methods
+        .buyTokens(new anchor.BN(150))
+        .accounts({
+          buyer: buyer.publicKey,
+          tokenSale: tieredSalePDA,
+          buyerAccount: tieredBuyerAccountPDA,
+          buyerPaymentAccount: buyerPaymentAccount,
+          sellerPaymentAccount: tieredSellerPaymentAccount,
+          platformFeeAccount: platformFeeAccount,
+          buyerTokenAccount: buyerTokenAccount,
+          tokenVault: tieredVaultPDA,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      const balanceAfter = (await getAccount(provider.connection, buyerPaymentAccount)).amount;
+      assert.equal(Number(balanceBefore - balanceAfter), 200);
+    });
+
+    it("Fails to change tiers once the sale has started", async () => {
+      try {
+        await setTiers([]);
+        assert.fail("Should fail after the sale starts");
+      } catch (error: any) {
+        assert(error.message.includes("SaleAlreadyStarted"));
+      }
+    });
+  });
+
   describe("Pause Functionality Tests", () => {
     it("Prevents purchases when sale is paused", async () => {
       // First pause the original sale